@@ -0,0 +1,263 @@
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::plonk::Error;
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::circuit::{Region, Layouter};
+use crate::host::{
+    ForeignInst,
+    ExternalHostCallEntryTable,
+    ExternalHostCallEntry,
+};
+use crate::host::ForeignInst::{
+    Blake2bNew,
+    Blake2bPush,
+    Blake2bFinalize,
+    Blake2sNew,
+    Blake2sPush,
+    Blake2sFinalize,
+};
+use crate::host::blake2::{
+    Blake2Variant,
+    STATE_WORDS,
+    BLOCK_WORDS,
+};
+use crate::circuits::blake2::Blake2Chip;
+use crate::circuits::bitwise::BitwiseWordConfig;
+
+use crate::circuits::host::{
+    HostOpSelector,
+    HostOpConfig,
+};
+
+use crate::utils::Limb;
+use crate::utils::field_to_u64;
+
+/* The calling convention will be (one call per compressed block)
+ * Blake2xNew: the digest length for the first block of a hash else 0
+ * Blake2xNew: t, the number of bytes hashed so far including this block
+ * Blake2xNew: 1 if this is the last block else 0
+ * Blake2xPush: BLOCK_WORDS words of the zero padded block
+ * Blake2xFinalize: STATE_WORDS words of the state after the compression
+ *
+ * The digest is the first digest length bytes of the state after the last block.
+ */
+const NEW_SIZE:usize = 3;
+const CHUNK_SIZE:usize = NEW_SIZE + BLOCK_WORDS + STATE_WORDS;
+/* A blake2b block takes about 8.4k lookup lines (84 per G function) next to 4.8k lines of
+ * the common gate and a blake2s block about 4.3k lookup lines, thus 128 blocks take ~1.1M rows
+ * which also hold the 196609 lines of the bitwise table.
+ */
+const TOTAL_CONSTRUCTIONS:usize = 128;
+
+fn blake2_opcodes(word_bits: usize) -> [ForeignInst; 3] {
+    match word_bits {
+        64 => [Blake2bNew, Blake2bPush, Blake2bFinalize],
+        32 => [Blake2sNew, Blake2sPush, Blake2sFinalize],
+        _ => unreachable!(),
+    }
+}
+
+fn blake2_words(words: &[u64], op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    words.iter().map(|x| ExternalHostCallEntry {
+        op: op as usize,
+        value: *x,
+        is_ret: false,
+    }).collect()
+}
+
+fn blake2_to_host_call_table(variant: &Blake2Variant, inputs: &Vec<(Vec<u8>, usize)>) -> ExternalHostCallEntryTable {
+    let [new, push, finalize] = blake2_opcodes(variant.word_bits);
+    let mut r = vec![];
+    for (message, digest_len) in inputs.iter() {
+        let mut h = variant.init(*digest_len);
+        for (i, (m, t, last)) in variant.blocks(message).iter().enumerate() {
+            let init = if i == 0 { *digest_len as u64 } else { 0 };
+            r.push(blake2_words(&[init, *t, *last as u64], new));
+            r.push(blake2_words(m, push));
+            variant.compress(&mut h, m, *t, *last);
+            r.push(blake2_words(&h, finalize));
+        }
+    }
+    ExternalHostCallEntryTable(r.into_iter().flatten().collect())
+}
+
+/* Reject a block whose opcodes are not those of the calling convention
+ * or whose digest length or last flag are out of range, the circuit can not prove it.
+ */
+fn check_blake2_block(
+    variant: &Blake2Variant,
+    opcodes: &[Fr],
+    group: &[((Fr, Fr), Fr)],
+) -> Result<(), Error> {
+    for (i, ((_, opcode), _)) in group.iter().enumerate() {
+        let expected = if i < NEW_SIZE { opcodes[0] } else if i < NEW_SIZE + BLOCK_WORDS { opcodes[1] } else { opcodes[2] };
+        if *opcode != expected {
+            return Err(Error::Synthesis);
+        }
+    }
+    let ((init, _), _) = group[0];
+    let ((last, _), _) = group[2];
+    if field_to_u64(&init) as usize > variant.max_digest_len() || field_to_u64(&last) > 1 {
+        return Err(Error::Synthesis);
+    }
+    Ok(())
+}
+
+fn assign_blake2_block(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for ((operand, opcode), index) in group.iter() {
+        let limb = config.assign_one_line(
+            region, offset, *operand, *opcode, *index,
+            *operand,
+            Fr::zero(),
+            enable
+        )?;
+        r.push(limb);
+    }
+    Ok(r)
+}
+
+impl<const WORD_BITS: usize> HostOpSelector for Blake2Chip<Fr, WORD_BITS> {
+    type Config = BitwiseWordConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        Blake2Chip::<Fr, WORD_BITS>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        Blake2Chip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = blake2_opcodes(WORD_BITS)
+            .iter()
+            .map(|op| Fr::from(*op as u64))
+            .collect();
+
+        let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+
+        let selected_entries = entries.filter(|((_operand, opcode), _index)| {
+            opcodes.contains(opcode)
+        }).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        if selected_entries.len() % CHUNK_SIZE != 0 {
+            return Err(Error::Synthesis);
+        }
+        let total_used_instructions = selected_entries.len()/CHUNK_SIZE;
+        if total_used_instructions > TOTAL_CONSTRUCTIONS {
+            return Err(Error::Synthesis);
+        }
+
+        let variant = Blake2Variant::from_word_bits(WORD_BITS);
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            check_blake2_block(variant, &opcodes, group)?;
+            let mut limbs = assign_blake2_block(region, &mut offset, config, group, true)?;
+            r.append(&mut limbs);
+        }
+
+        let default_table = blake2_to_host_call_table(variant, &vec![(vec![], variant.max_digest_len())]);
+
+        let default_entries:Vec<((Fr, Fr), Fr)> = default_table.0.into_iter().map(
+            |x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())
+        ).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
+            let mut limbs = assign_blake2_block(region, &mut offset, config, &default_entries, false)?;
+            r.append(&mut limbs);
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "blake2 hash region",
+            |mut region| {
+                let mut offset = 0;
+                let timer = start_timer!(|| "assign");
+                self.initialize(&mut region, &mut offset)?;
+                for arg_group in arg_cells.chunks_exact(CHUNK_SIZE).into_iter() {
+                    let args = arg_group.into_iter().map(|x| x.clone());
+                    let args = args.collect::<Vec<_>>();
+                    self.assign_compress(
+                        &mut region,
+                        &mut offset,
+                        &args[0],
+                        &args[1],
+                        &args[2],
+                        &args[NEW_SIZE..NEW_SIZE + BLOCK_WORDS].to_vec().try_into().unwrap(),
+                        &args[NEW_SIZE + BLOCK_WORDS..].to_vec().try_into().unwrap(),
+                    )?;
+                }
+                end_timer!(timer);
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use halo2_proofs::pairing::bn256::Fr;
+    use crate::host::blake2::{BLAKE2B, BLAKE2S};
+    use super::{blake2_opcodes, check_blake2_block};
+
+    #[test]
+    fn generate_blake2_input() {
+        let inputs = vec![(b"abc".to_vec(), 32), (vec![0u8; 200], 64)];
+        let table = super::blake2_to_host_call_table(&BLAKE2B, &inputs);
+        let file = File::create("blake2btest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+
+        let inputs = vec![(b"abc".to_vec(), 32), (vec![0u8; 100], 32)];
+        let table = super::blake2_to_host_call_table(&BLAKE2S, &inputs);
+        let file = File::create("blake2stest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+
+    #[test]
+    fn test_blake2_block_check() {
+        let opcodes = blake2_opcodes(64).map(|op| Fr::from(op as u64));
+        let table = super::blake2_to_host_call_table(&BLAKE2B, &vec![(b"abc".to_vec(), 64)]);
+        let group = table.0.iter().map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())).collect::<Vec<_>>();
+        assert!(check_blake2_block(&BLAKE2B, &opcodes, &group).is_ok());
+
+        // a digest length longer than the state
+        let mut long = group.clone();
+        long[0].0.0 = Fr::from(65u64);
+        assert!(check_blake2_block(&BLAKE2B, &opcodes, &long).is_err());
+
+        // a last flag that is not a bit
+        let mut last = group.clone();
+        last[2].0.0 = Fr::from(2u64);
+        assert!(check_blake2_block(&BLAKE2B, &opcodes, &last).is_err());
+
+        // the words of the block and of the result swapped
+        let mut swapped = group.clone();
+        swapped[super::NEW_SIZE].0.1 = opcodes[2];
+        assert!(check_blake2_block(&BLAKE2B, &opcodes, &swapped).is_err());
+    }
+}
@@ -5,35 +5,86 @@ use halo2_proofs::{
 };
 use halo2_proofs::arithmetic::FieldExt;
 
-//pub const BLS381FQ_SIZE: usize = 8;
-pub const BLS381G1_SIZE: usize = 17;
-pub const BLS381G2_SIZE: usize = 33;
-pub const BLS381GT_SIZE: usize = 96;
-const BLSPAIR_SIZE: usize = BLS381G1_SIZE + BLS381G2_SIZE + BLS381GT_SIZE;
-const BLSSUM_SIZE: usize = BLS381G1_SIZE * 3;
+const BLS381SCALAR_SIZE: usize = 5;
+const BLSHASHTOCURVE_SIZE: usize = Bls381::FQ_SIZE * 2 + Bls381::G1_SIZE;
 
 use crate::circuits::bls::{
-    Bls381PairChip,
-    Bls381SumChip,
+    Bls381,
+    Bls381MsmChip,
+    Bls381HashToCurveChip,
     Bls381ChipConfig,
+    BLS381SCALAR_LIMBS,
 };
+use crate::circuits::pairing::PairingCurve;
 
+use crate::adaptor::pairingadaptor::{assign_fq, assign_g1, PairingOpcodes};
 use crate::circuits::host::{HostOpSelector, HostOpConfig};
 
 use crate::host::ForeignInst;
-use crate::utils::Limb;
+use crate::utils::{Limb, field_to_u64};
 
-impl HostOpSelector for Bls381PairChip<Fr> {
+impl PairingOpcodes for Bls381 {
+    /// 8 * 54 bits cover the 381 bits of an Fq
+    const FQ_SIZE: usize = 8;
+
+    const PAIR_G1: ForeignInst = ForeignInst::BlspairG1;
+    const PAIR_G2: ForeignInst = ForeignInst::BlspairG2;
+    const PAIR_GT: ForeignInst = ForeignInst::BlspairG3;
+
+    const SUM_G1: ForeignInst = ForeignInst::BlsSumG1;
+    const SUM_RESULT: ForeignInst = ForeignInst::BlsSumResult;
+
+    const MULTI_PAIR_NEW: ForeignInst = ForeignInst::BlsMultiPairNew;
+    const MULTI_PAIR_G1: ForeignInst = ForeignInst::BlsMultiPairG1;
+    const MULTI_PAIR_G2: ForeignInst = ForeignInst::BlsMultiPairG2;
+    const MULTI_PAIR_GT: ForeignInst = ForeignInst::BlsMultiPairGt;
+
+    const SUM_G2: ForeignInst = ForeignInst::BlsSumG2;
+    const SUM_G2_RESULT: ForeignInst = ForeignInst::BlsSumG2Result;
+}
+
+/// assign the scalar (5) entries into scalar (3) limbs
+fn assign_scalar(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for i in 0..2 {
+        let limb = config.assign_merged_operands(
+            region,
+            offset,
+            vec![&group[2*i], &group[2*i+1]],
+            Fr::from_u128(1u128 << 54),
+            true
+        )?;
+        r.push(limb);
+    }
+    let ((operand, opcode), index) = group[4];
+    let limb = config.assign_one_line(region, offset, operand, opcode, index,
+       operand, Fr::zero(), true)?;
+    r.push(limb);
+    Ok(r)
+}
+
+/* The calling convention of a msm call will be
+ * BlsMsmNew: the number k > 0 of (point, scalar) pairs
+ * k * (BlsMsmG1: G1 (8 * 2 + 1), BlsMsmScalar: scalar (5))
+ * BlsMsmResult: G1 (8 * 2 + 1) of the sum of the scalar multiplications
+ */
+impl HostOpSelector for Bls381MsmChip<Fr> {
     type Config = Bls381ChipConfig;
     fn configure(
         meta: &mut ConstraintSystem<Fr>,
     ) -> Self::Config {
-        Bls381PairChip::<Fr>::configure(meta)
+        Bls381MsmChip::<Fr>::configure(meta)
     }
 
     fn construct(c: Self::Config) -> Self {
-        Bls381PairChip::construct(c)
+        Bls381MsmChip::construct(c)
     }
+
     fn assign(
         region: &mut Region<Fr>,
         shared_operands: &Vec<Fr>,
@@ -42,95 +93,88 @@ impl HostOpSelector for Bls381PairChip<Fr> {
         config: &HostOpConfig,
     ) -> Result<Vec<Limb<Fr>>, Error> {
         let opcodes: Vec<Fr> = vec![
-            Fr::from(ForeignInst::BlspairG1 as u64),
-            Fr::from(ForeignInst::BlspairG2 as u64),
-            Fr::from(ForeignInst::BlspairG3 as u64),
+            Fr::from(ForeignInst::BlsMsmNew as u64),
+            Fr::from(ForeignInst::BlsMsmG1 as u64),
+            Fr::from(ForeignInst::BlsMsmScalar as u64),
+            Fr::from(ForeignInst::BlsMsmResult as u64),
         ];
 
-
         let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
 
         let selected_entries = entries.filter(|((_operand, opcode), _index)| {
             opcodes.contains(opcode)
         }).collect::<Vec<((Fr, Fr), Fr)>>();
 
-        assert!(selected_entries.len() % BLSPAIR_SIZE == 0);
-
         let mut offset = 0;
         let mut r = vec![];
-
-        for group in selected_entries.chunks_exact(BLSPAIR_SIZE) {
-            for i in 0..8 {
-                let limb = config.assign_merged_operands(
-                    region,
-                    &mut offset,
-                    vec![&group[2*i], &group[2*i+1]],
-                    Fr::from_u128(1u128 << 54),
-                    true
-                )?;
-                r.push(limb);
-            }
-
-            let ((operand, opcode), index) = *group.get(16).clone().unwrap();
-
+        let mut pos = 0;
+
+        while pos < selected_entries.len() {
+            let ((operand, opcode), index) = selected_entries[pos];
+            assert!(opcode == Fr::from(ForeignInst::BlsMsmNew as u64));
+            let k = field_to_u64(&operand) as usize;
+            // an empty msm has no point to build the result from
+            assert!(k > 0);
             let limb = config.assign_one_line(region, &mut offset, operand, opcode, index,
                operand, Fr::zero(), true)?;
             r.push(limb);
+            pos += 1;
 
-            for i in 0..16 {
-                let limb = config.assign_merged_operands(
-                    region,
-                    &mut offset,
-                    vec![&group[2*i+17], &group[2*i+1+17]],
-                    Fr::from_u128(1u128 << 54),
-                    true
-                )?;
-                r.push(limb);
+            for _ in 0..k {
+                r.append(&mut assign_g1::<Bls381>(region, &mut offset, config, &selected_entries[pos..pos + Bls381::G1_SIZE])?);
+                pos += Bls381::G1_SIZE;
+                r.append(&mut assign_scalar(region, &mut offset, config, &selected_entries[pos..pos + BLS381SCALAR_SIZE])?);
+                pos += BLS381SCALAR_SIZE;
             }
 
-            let ((operand, opcode), index) = *group.get(49).clone().unwrap();
-
-            let limb = config.assign_one_line(region, &mut offset, operand, opcode, index,
-               operand, Fr::zero(), true)?;
-            r.push(limb);
-
-            for i in 0..48 {
-                let limb = config.assign_merged_operands(
-                    region,
-                    &mut offset,
-                    vec![&group[2*i+50], &group[2*i+1+50]],
-                    Fr::from_u128(1u128 << 54),
-                    true
-                )?;
-                r.push(limb);
-            }
+            let ((_, opcode), _) = selected_entries[pos];
+            assert!(opcode == Fr::from(ForeignInst::BlsMsmResult as u64));
+            r.append(&mut assign_g1::<Bls381>(region, &mut offset, config, &selected_entries[pos..pos + Bls381::G1_SIZE])?);
+            pos += Bls381::G1_SIZE;
         }
         Ok(r)
     }
+
     fn synthesize(
         &mut self,
         arg_cells: &Vec<Limb<Fr>>,
         layouter: &mut impl Layouter<Fr>,
     ) -> Result<(), Error> {
         self.range_chip.init_table(layouter)?;
-        let a = arg_cells[0..9].to_vec();
-        let b = arg_cells[9..26].to_vec();
-        let ab = arg_cells[26..74].to_vec();
-        self.load_bls381_pair_circuit(&a, &b, &ab, layouter)?;
+        // arg_cells format 1 + k * (G1_LIMBS + BLS381SCALAR_LIMBS) + G1_LIMBS
+        let mut calls = vec![];
+        let mut pos = 0;
+        while pos < arg_cells.len() {
+            let k = field_to_u64(&arg_cells[pos].value) as usize;
+            pos += 1;
+            let ls = arg_cells[pos..pos + k * (Bls381::G1_LIMBS + BLS381SCALAR_LIMBS)].to_vec();
+            pos += k * (Bls381::G1_LIMBS + BLS381SCALAR_LIMBS);
+            let msm = arg_cells[pos..pos + Bls381::G1_LIMBS].to_vec();
+            pos += Bls381::G1_LIMBS;
+            calls.push((ls, msm));
+        }
+        self.load_bls381_msm_circuit(&calls, layouter)?;
         Ok(())
     }
 }
 
-impl HostOpSelector for Bls381SumChip<Fr> {
+/* The calling convention of a hash to curve will be
+ * BlsHashToCurveU: u0 (8), u1 (8) of hash_to_field(msg, dst), both less than p
+ * BlsHashToCurveResult: G1 (8 * 2 + 1) of the hashed point
+ * The circuit proves the map of (u0, u1) to G1 only: hash_to_field is not constrained
+ * so the caller has to trust or prove that u0, u1 are the ones of msg (e.g. with the
+ * SHA256 host op for expand_message_xmd). Hashing to G2 is not supported.
+ */
+impl HostOpSelector for Bls381HashToCurveChip<Fr> {
     type Config = Bls381ChipConfig;
     fn configure(
         meta: &mut ConstraintSystem<Fr>,
     ) -> Self::Config {
-        Bls381SumChip::<Fr>::configure(meta)
+        Bls381HashToCurveChip::<Fr>::configure(meta)
     }
 
     fn construct(c: Self::Config) -> Self {
-        Bls381SumChip::construct(c)
+        Bls381HashToCurveChip::construct(c)
     }
 
     fn assign(
@@ -141,8 +185,8 @@ impl HostOpSelector for Bls381SumChip<Fr> {
         config: &HostOpConfig,
     ) -> Result<Vec<Limb<Fr>>, Error> {
         let opcodes: Vec<Fr> = vec![
-            Fr::from(ForeignInst::BlsSumG1 as u64),
-            Fr::from(ForeignInst::BlsSumResult as u64),
+            Fr::from(ForeignInst::BlsHashToCurveU as u64),
+            Fr::from(ForeignInst::BlsHashToCurveResult as u64),
         ];
 
         let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
@@ -151,26 +195,15 @@ impl HostOpSelector for Bls381SumChip<Fr> {
             opcodes.contains(opcode)
         }).collect::<Vec<((Fr, Fr), Fr)>>();
 
-        assert!(selected_entries.len() % BLSSUM_SIZE == 0);
+        assert!(selected_entries.len() % BLSHASHTOCURVE_SIZE == 0);
 
         let mut offset = 0;
         let mut r = vec![];
 
-        for group in selected_entries.chunks_exact(BLS381G1_SIZE) {
-            for i in 0..8 {
-                let limb = config.assign_merged_operands(
-                    region,
-                    &mut offset,
-                    vec![&group[2*i], &group[2*i+1]],
-                    Fr::from_u128(1u128 << 54),
-                    true
-                )?;
-                r.push(limb);
-            }
-            let ((operand, opcode), index) = *group.get(16).clone().unwrap();
-            let limb = config.assign_one_line(region, &mut offset, operand, opcode, index,
-               operand, Fr::zero(), true)?;
-            r.push(limb);
+        for group in selected_entries.chunks_exact(BLSHASHTOCURVE_SIZE) {
+            r.append(&mut assign_fq::<Bls381>(region, &mut offset, config, &group[0..Bls381::FQ_SIZE])?);
+            r.append(&mut assign_fq::<Bls381>(region, &mut offset, config, &group[Bls381::FQ_SIZE..Bls381::FQ_SIZE * 2])?);
+            r.append(&mut assign_g1::<Bls381>(region, &mut offset, config, &group[Bls381::FQ_SIZE * 2..])?);
         }
         Ok(r)
     }
@@ -181,11 +214,9 @@ impl HostOpSelector for Bls381SumChip<Fr> {
         layouter: &mut impl Layouter<Fr>,
     ) -> Result<(), Error> {
         self.range_chip.init_table(layouter)?;
-        let len = arg_cells.len();
-        let args = arg_cells[0..len - 9].to_vec();
-        let ret = arg_cells[len - 9..len].to_vec();
-        self.load_bls381_sum_circuit(&args, &ret, layouter)?;
+        // arg_cells format FQ_LIMBS + FQ_LIMBS + G1_LIMBS
+        let calls = arg_cells.chunks_exact(Bls381::FQ_LIMBS * 2 + Bls381::G1_LIMBS).map(|x| x.to_vec()).collect::<Vec<_>>();
+        self.load_bls381_hash_to_curve_circuit(&calls, layouter)?;
         Ok(())
     }
 }
-
@@ -5,159 +5,56 @@ use halo2_proofs::{
     plonk::{ConstraintSystem, Error},
 };
 
-//pub const BN256FQ_SIZE: usize = 5;
-pub const BN256G1_SIZE: usize = 11;
-pub const BN256G2_SIZE: usize = 21;
-pub const BN256GT_SIZE: usize = 60;
-
-const BN256PAIR_SIZE: usize = BN256G1_SIZE + BN256G2_SIZE + BN256GT_SIZE;
-const BN256SUM_SIZE: usize = BN256G1_SIZE * 3;
+const BN256SCALAR_SIZE: usize = 4;
 
 use crate::circuits::bn256::{
-    Bn256PairChip,
-    Bn256SumChip,
+    Bn256,
+    Bn256MsmChip,
     Bn256ChipConfig,
 };
+use crate::circuits::pairing::PairingCurve;
 
+use crate::adaptor::pairingadaptor::{assign_g1, PairingOpcodes};
 use crate::circuits::host::{HostOpSelector, HostOpConfig};
-use crate::utils::Limb;
+use crate::utils::{Limb, field_to_u64};
 
 use crate::host::ForeignInst;
 
-impl HostOpSelector for Bn256PairChip<Fr> {
-    type Config = Bn256ChipConfig;
-    fn configure(
-        meta: &mut ConstraintSystem<Fr>,
-    ) -> Self::Config {
-        Bn256PairChip::<Fr>::configure(meta)
-    }
-
-    fn construct(c: Self::Config) -> Self {
-        Bn256PairChip::construct(c)
-    }
-
-    fn assign(
-        region: &mut Region<Fr>,
-        shared_operands: &Vec<Fr>,
-        shared_opcodes: &Vec<Fr>,
-        shared_index: &Vec<Fr>,
-        config: &HostOpConfig,
-    ) -> Result<Vec<Limb<Fr>>, Error> {
-        let opcodes: Vec<Fr> = vec![
-            Fr::from(ForeignInst::Bn254PairG1 as u64),
-            Fr::from(ForeignInst::Bn254PairG2 as u64),
-            Fr::from(ForeignInst::Bn254PairG3 as u64),
-        ];
-
-        let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
-
-        let selected_entries = entries.filter(|((_operand, opcode), _index)| {
-            opcodes.contains(opcode)
-        }).collect::<Vec<((Fr, Fr), Fr)>>();
-
-        assert!(selected_entries.len() % BN256PAIR_SIZE == 0);
-
-        let mut offset = 0;
-        let mut r = vec![];
-
-        for group in selected_entries.chunks_exact(BN256PAIR_SIZE) {
-            // get g1_x and g1_y: ((1,1) (1,1) 1) * 2
-            for j in 0..2 {
-                for i in 0..2 {
-                    let p_01 = config.assign_merged_operands(
-                        region,
-                        &mut offset,
-                        vec![&group[5*j+2*i], &group[5*j+2*i+1]],
-                        Fr::from_u128(1u128 << 54),
-                        true
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = *group.get(5*j + 4).clone().unwrap();
-                let p_2 = config.assign_one_line(region, &mut offset, operand, opcode, index,
-                   operand, Fr::zero(), true)?;
-                r.push(p_2);
-
-            }
-
-            // whether g1 is zero or not
-            let ((operand, opcode), index) = *group.get(10).clone().unwrap();
+impl PairingOpcodes for Bn256 {
+    /// 5 * 54 bits cover the 254 bits of an Fq
+    const FQ_SIZE: usize = 5;
 
-            let g1zero = config.assign_one_line(region, &mut offset, operand, opcode, index,
-               operand, Fr::zero(), true)?;
-            r.push(g1zero);
-
-
-            for j in 0..4 {
-                for i in 0..2 {
-                    let p_01 = config.assign_merged_operands(
-                        region,
-                        &mut offset,
-                        vec![&group[5*j+2*i+11], &group[5*j+2*i+1+11]],
-                        Fr::from_u128(1u128 << 54),
-                        true
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = *group.get(5*j + 4 + 11).clone().unwrap();
-                let p_2 = config.assign_one_line(region, &mut offset, operand, opcode, index,
-                   operand, Fr::zero(), true)?;
-                r.push(p_2);
+    const PAIR_G1: ForeignInst = ForeignInst::Bn254PairG1;
+    const PAIR_G2: ForeignInst = ForeignInst::Bn254PairG2;
+    const PAIR_GT: ForeignInst = ForeignInst::Bn254PairG3;
 
-            }
+    const SUM_G1: ForeignInst = ForeignInst::Bn254SumG1;
+    const SUM_RESULT: ForeignInst = ForeignInst::Bn254SumResult;
 
-            // whether g2 is zero or not
-            let ((operand, opcode), index) = *group.get(31).clone().unwrap();
+    const MULTI_PAIR_NEW: ForeignInst = ForeignInst::Bn254MultiPairNew;
+    const MULTI_PAIR_G1: ForeignInst = ForeignInst::Bn254MultiPairG1;
+    const MULTI_PAIR_G2: ForeignInst = ForeignInst::Bn254MultiPairG2;
+    const MULTI_PAIR_GT: ForeignInst = ForeignInst::Bn254MultiPairGt;
 
-            let g2zero = config.assign_one_line(region, &mut offset, operand, opcode, index,
-               operand, Fr::zero(), true)?;
-            r.push(g2zero);
-
-            for j in 0..12 {
-                for i in 0..2 {
-                    let q = config.assign_merged_operands(
-                        region,
-                        &mut offset,
-                        vec![&group[5*j+2*i+32], &group[5*j+2*i+1+32]],
-                        Fr::from_u128(1u128 << 54),
-                        true
-                    )?;
-                    r.push(q);
-                }
-                let ((operand, opcode), index) = *group.get(5*j+4+32).clone().unwrap();
-                let q  = config.assign_one_line(region, &mut offset, operand, opcode, index,
-                   operand, Fr::zero(), true)?;
-                r.push(q);
-            }
-        }
-        Ok(r)
-    }
-
-    fn synthesize(
-        &mut self,
-        arg_cells: &Vec<Limb<Fr>>,
-        layouter: &mut impl Layouter<Fr>,
-    ) -> Result<(), Error> {
-        self.range_chip.init_table(layouter)?;
-        let a = arg_cells[0..7].to_vec();
-        let b = arg_cells[7..20].to_vec();
-        let ab = arg_cells[20..56].to_vec();
-        println!("ab is: {:?}", ab);
-        self.load_bn256_pair_circuit(&a, &b, &ab, layouter)?;
-        Ok(())
-    }
+    const SUM_G2: ForeignInst = ForeignInst::Bn254SumG2;
+    const SUM_G2_RESULT: ForeignInst = ForeignInst::Bn254SumG2Result;
 }
 
-impl HostOpSelector for Bn256SumChip<Fr> {
+/* The calling convention of a msm call will be
+ * Bn254MsmNew: the number k > 0 of (point, scalar) pairs
+ * k * (Bn254MsmG1: G1 (5 * 2 + 1), Bn254MsmScalar: 4 u64 of the scalar)
+ * Bn254MsmResult: G1 (5 * 2 + 1) of the sum of the scalar multiplications
+ */
+impl HostOpSelector for Bn256MsmChip<Fr> {
     type Config = Bn256ChipConfig;
     fn configure(
         meta: &mut ConstraintSystem<Fr>,
     ) -> Self::Config {
-        Bn256SumChip::<Fr>::configure(meta)
+        Bn256MsmChip::<Fr>::configure(meta)
     }
 
     fn construct(c: Self::Config) -> Self {
-        Bn256SumChip::construct(c)
+        Bn256MsmChip::construct(c)
     }
 
     fn assign(
@@ -168,8 +65,10 @@ impl HostOpSelector for Bn256SumChip<Fr> {
         config: &HostOpConfig,
     ) -> Result<Vec<Limb<Fr>>, Error> {
         let opcodes: Vec<Fr> = vec![
-            Fr::from(ForeignInst::Bn254SumG1 as u64),
-            Fr::from(ForeignInst::Bn254SumResult as u64),
+            Fr::from(ForeignInst::Bn254MsmNew as u64),
+            Fr::from(ForeignInst::Bn254MsmG1 as u64),
+            Fr::from(ForeignInst::Bn254MsmScalar as u64),
+            Fr::from(ForeignInst::Bn254MsmResult as u64),
         ];
 
         let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
@@ -178,38 +77,40 @@ impl HostOpSelector for Bn256SumChip<Fr> {
             opcodes.contains(opcode)
         }).collect::<Vec<((Fr, Fr), Fr)>>();
 
-        assert!(selected_entries.len() % BN256SUM_SIZE == 0);
-
         let mut offset = 0;
         let mut r = vec![];
-
-
-        for group in selected_entries.chunks_exact(BN256G1_SIZE) {
-            for j in 0..2 {
-                for i in 0..2 {
-                    let p_01 = config.assign_merged_operands(
-                        region,
-                        &mut offset,
-                        vec![&group[5*j+2*i], &group[5*j+2*i+1]],
-                        Fr::from_u128(1u128 << 54),
-                        true,
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = *group.get(5*j + 4).clone().unwrap();
-                let p_2 = config.assign_one_line(region, &mut offset, operand, opcode, index,
-                   operand, Fr::zero(), true)?;
-                r.push(p_2);
-
-            }
-
-            // whether g1 is zero or not
-            let ((operand, opcode), index) = *group.get(10).clone().unwrap();
+        let mut pos = 0;
+
+        while pos < selected_entries.len() {
+            let ((operand, opcode), index) = selected_entries[pos];
+            assert!(opcode == Fr::from(ForeignInst::Bn254MsmNew as u64));
+            let k = field_to_u64(&operand) as usize;
+            // an empty msm has no point to build the result from
+            assert!(k > 0);
             let limb = config.assign_one_line(region, &mut offset, operand, opcode, index,
                operand, Fr::zero(), true)?;
             r.push(limb);
+            pos += 1;
+
+            for _ in 0..k {
+                r.append(&mut assign_g1::<Bn256>(region, &mut offset, config, &selected_entries[pos..pos + Bn256::G1_SIZE])?);
+                pos += Bn256::G1_SIZE;
+                let scalar = config.assign_merged_operands(
+                    region,
+                    &mut offset,
+                    selected_entries[pos..pos + BN256SCALAR_SIZE].iter().collect(),
+                    Fr::from_u128(1u128 << 64),
+                    true,
+                )?;
+                r.push(scalar);
+                pos += BN256SCALAR_SIZE;
+            }
+
+            let ((_, opcode), _) = selected_entries[pos];
+            assert!(opcode == Fr::from(ForeignInst::Bn254MsmResult as u64));
+            r.append(&mut assign_g1::<Bn256>(region, &mut offset, config, &selected_entries[pos..pos + Bn256::G1_SIZE])?);
+            pos += Bn256::G1_SIZE;
         }
-        println!("r: {:?} with length {}", r, r.len());
         Ok(r)
     }
 
@@ -219,10 +120,19 @@ impl HostOpSelector for Bn256SumChip<Fr> {
         layouter: &mut impl Layouter<Fr>,
     ) -> Result<(), Error> {
         self.range_chip.init_table(layouter)?;
-        let len = arg_cells.len();
-        let args = arg_cells[0..len - 7].to_vec();
-        let ret = arg_cells[len - 7..len].to_vec();
-        self.load_bn256_sum_circuit(&args, &ret, layouter)?;
+        // arg_cells format 1 + k * (G1_LIMBS + 1) + G1_LIMBS
+        let mut calls = vec![];
+        let mut pos = 0;
+        while pos < arg_cells.len() {
+            let k = field_to_u64(&arg_cells[pos].value) as usize;
+            pos += 1;
+            let ls = arg_cells[pos..pos + k * (Bn256::G1_LIMBS + 1)].to_vec();
+            pos += k * (Bn256::G1_LIMBS + 1);
+            let msm = arg_cells[pos..pos + Bn256::G1_LIMBS].to_vec();
+            pos += Bn256::G1_LIMBS;
+            calls.push((ls, msm));
+        }
+        self.load_bn256_msm_circuit(&calls, layouter)?;
         Ok(())
     }
 }
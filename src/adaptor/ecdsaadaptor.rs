@@ -0,0 +1,327 @@
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
+    plonk::{ConstraintSystem, Error},
+};
+
+use crate::circuits::ecdsa::{EcdsaChip, EcdsaChipConfig};
+use crate::circuits::host::{HostOpSelector, HostOpConfig};
+use crate::host::ExternalHostCallEntry;
+use crate::host::ForeignInst::{
+    EcdsaPush,
+    EcdsaVerify,
+    EcdsaRecoverPush,
+    EcdsaRecoverResult,
+};
+use crate::host::ecdsa::{Point, Signature};
+use crate::utils::{field_to_u64, Limb};
+use num_bigint::BigUint;
+
+/* The calling convention will be
+ * EcdsaPush: 5 * 54 bits per value of vk.x, vk.y, r, s
+ * EcdsaVerify: 5 * 54 bits of the message hash z, the call fails if the signature is not valid
+ *
+ * and for ecrecover
+ * EcdsaRecoverPush: 5 * 54 bits per value of r, s, z followed by the recovery id v (0 or 1)
+ * EcdsaRecoverResult: 5 * 54 bits per value of vk.x, vk.y,
+ * the address is the last 20 bytes of keccak256(vk.x || vk.y) (see crate::host::ecdsa::address)
+ */
+const MERGE_SIZE: usize = 5;
+const CHUNK_SIZE: usize = (2 + 1 + 1 + 1) * MERGE_SIZE;
+const RECOVER_PUSH_SIZE: usize = 3 * MERGE_SIZE + 1;
+const RECOVER_CHUNK_SIZE: usize = RECOVER_PUSH_SIZE + 2 * MERGE_SIZE;
+
+fn ecdsa_to_host_call_table(inputs: &Vec<(Point, BigUint, Signature)>) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (vk, z, sig) in inputs.into_iter() {
+        r.push(crate::adaptor::bn_to_args(&vk.x, 5, 54, EcdsaPush));
+        r.push(crate::adaptor::bn_to_args(&vk.y, 5, 54, EcdsaPush));
+        r.push(crate::adaptor::bn_to_args(&sig.r, 5, 54, EcdsaPush));
+        r.push(crate::adaptor::bn_to_args(&sig.s, 5, 54, EcdsaPush));
+        r.push(crate::adaptor::bn_to_args(z, 5, 54, EcdsaVerify));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+fn ecrecover_to_host_call_table(inputs: &Vec<(BigUint, Signature, u64, Point)>) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (z, sig, v, vk) in inputs.into_iter() {
+        r.push(crate::adaptor::bn_to_args(&sig.r, 5, 54, EcdsaRecoverPush));
+        r.push(crate::adaptor::bn_to_args(&sig.s, 5, 54, EcdsaRecoverPush));
+        r.push(crate::adaptor::bn_to_args(z, 5, 54, EcdsaRecoverPush));
+        r.push(crate::adaptor::bn_to_args(&BigUint::from(*v), 1, 54, EcdsaRecoverPush));
+        r.push(crate::adaptor::bn_to_args(&vk.x, 5, 54, EcdsaRecoverResult));
+        r.push(crate::adaptor::bn_to_args(&vk.y, 5, 54, EcdsaRecoverResult));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+/* Reject a group whose opcodes are not those of the calling convention:
+ * push_size entries of opcodes[0] followed by opcodes[1].
+ */
+fn check_ecdsa_group(
+    opcodes: &[Fr],
+    push_size: usize,
+    group: &[((Fr, Fr), Fr)],
+) -> Result<(), Error> {
+    for (i, ((_, opcode), _)) in group.iter().enumerate() {
+        let expected = if i < push_size { opcodes[0] } else { opcodes[1] };
+        if *opcode != expected {
+            return Err(Error::Synthesis);
+        }
+    }
+    Ok(())
+}
+
+/* Reject a group whose recovery id is not a bit, the circuit can not prove it. */
+fn check_ecrecover_group(
+    opcodes: &[Fr],
+    group: &[((Fr, Fr), Fr)],
+) -> Result<(), Error> {
+    check_ecdsa_group(opcodes, RECOVER_PUSH_SIZE, group)?;
+    let ((v, _), _) = group[RECOVER_PUSH_SIZE - 1];
+    if field_to_u64(&v) > 1 {
+        return Err(Error::Synthesis);
+    }
+    Ok(())
+}
+
+/// each value ((1,1) (1,1) 1) is merged into 3 limbs of 108 bits
+fn assign_value(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    value: &[((Fr, Fr), Fr)],
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for i in 0..2 {
+        let limb = config.assign_merged_operands(
+            region,
+            offset,
+            vec![&value[2*i], &value[2*i+1]],
+            Fr::from_u128(1u128 << 54),
+            true,
+        )?;
+        r.push(limb);
+    }
+    let ((operand, opcode), index) = value[4];
+    let limb = config.assign_one_line(region, offset, operand, opcode, index,
+       operand, Fr::zero(), true)?;
+    r.push(limb);
+    Ok(r)
+}
+
+fn select_entries(
+    opcodes: &[Fr],
+    shared_operands: &Vec<Fr>,
+    shared_opcodes: &Vec<Fr>,
+    shared_index: &Vec<Fr>,
+) -> Vec<((Fr, Fr), Fr)> {
+    let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+    entries.filter(|((_operand, opcode), _index)| {
+        opcodes.contains(opcode)
+    }).collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+impl HostOpSelector for EcdsaChip<Fr> {
+    type Config = EcdsaChipConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        EcdsaChip::<Fr>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        EcdsaChip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = vec![
+            Fr::from(EcdsaPush as u64),
+            Fr::from(EcdsaVerify as u64),
+        ];
+
+        let selected_entries = select_entries(&opcodes, shared_operands, shared_opcodes, shared_index);
+
+        assert!(selected_entries.len() % CHUNK_SIZE == 0);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            check_ecdsa_group(&opcodes, CHUNK_SIZE - MERGE_SIZE, group)?;
+            for value in group.chunks_exact(MERGE_SIZE) {
+                r.append(&mut assign_value(region, &mut offset, config, value)?);
+            }
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.range_chip.init_table(layouter)?;
+        // arg_cells format (3 * 2) + 3 + 3 + 3
+        let calls = arg_cells.chunks_exact(15).map(|x| x.to_vec()).collect::<Vec<_>>();
+        self.load_ecdsa_circuit(&calls, layouter)?;
+        Ok(())
+    }
+}
+
+pub struct EcrecoverChip(pub EcdsaChip<Fr>);
+
+impl HostOpSelector for EcrecoverChip {
+    type Config = EcdsaChipConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        EcdsaChip::<Fr>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        EcrecoverChip(EcdsaChip::construct(c))
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = vec![
+            Fr::from(EcdsaRecoverPush as u64),
+            Fr::from(EcdsaRecoverResult as u64),
+        ];
+
+        let selected_entries = select_entries(&opcodes, shared_operands, shared_opcodes, shared_index);
+
+        assert!(selected_entries.len() % RECOVER_CHUNK_SIZE == 0);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(RECOVER_CHUNK_SIZE) {
+            check_ecrecover_group(&opcodes, group)?;
+            for value in group[0..3 * MERGE_SIZE].chunks_exact(MERGE_SIZE) {
+                r.append(&mut assign_value(region, &mut offset, config, value)?);
+            }
+            let ((operand, opcode), index) = group[RECOVER_PUSH_SIZE - 1];
+            let limb = config.assign_one_line(region, &mut offset, operand, opcode, index,
+               operand, Fr::zero(), true)?;
+            r.push(limb);
+            for value in group[RECOVER_PUSH_SIZE..].chunks_exact(MERGE_SIZE) {
+                r.append(&mut assign_value(region, &mut offset, config, value)?);
+            }
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.0.range_chip.init_table(layouter)?;
+        // arg_cells format 3 + 3 + 3 + 1 + (3 * 2), reordered to vk, r, s, z, v
+        let calls = arg_cells.chunks_exact(16).map(|x| {
+            x[10..16].iter().chain(x[0..10].iter()).cloned().collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+        self.0.load_ecrecover_circuit(&calls, layouter)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::ExternalHostCallEntryTable;
+    use crate::host::ecdsa::{public_key, recover, sign, verify, Point, Signature};
+    use crate::host::ForeignInst::{EcdsaPush, EcdsaVerify, EcdsaRecoverPush, EcdsaRecoverResult};
+    use crate::host::ExternalHostCallEntry;
+    use super::{check_ecdsa_group, check_ecrecover_group, ecdsa_to_host_call_table, ecrecover_to_host_call_table, CHUNK_SIZE, MERGE_SIZE, RECOVER_PUSH_SIZE};
+    use halo2_proofs::pairing::bn256::Fr;
+    use num_bigint::BigUint;
+    use std::fs::File;
+
+    fn signed() -> (Point, BigUint, Signature, u64) {
+        let sk = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef", 16).unwrap();
+        let vk = public_key(&sk);
+        let z = BigUint::parse_bytes(b"4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", 16).unwrap();
+        let k = BigUint::parse_bytes(b"fedcba0987654321fedcba0987654321", 16).unwrap();
+        let sig = sign(&sk, &z, &k);
+        let v = (0..2).find(|v| recover(&z, &sig, *v) == Some(vk.clone())).unwrap();
+        (vk, z, sig, v)
+    }
+
+    fn to_group(table: &Vec<ExternalHostCallEntry>) -> Vec<((Fr, Fr), Fr)> {
+        table.iter().map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())).collect()
+    }
+
+    /// the value of 5 entries of 54 bits
+    fn to_bn(entries: &[ExternalHostCallEntry]) -> BigUint {
+        entries.iter().rev().fold(BigUint::from(0u64), |acc, x| (acc << 54) + x.value)
+    }
+
+    #[test]
+    fn generate_ecdsa_input() {
+        let (vk, z, sig, _) = signed();
+        assert!(verify(&vk, &z, &sig));
+        let table = ecdsa_to_host_call_table(&vec![(vk, z, sig)]);
+        let file = File::create("ecdsa.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(table)).expect("can not write to file");
+    }
+
+    #[test]
+    fn test_ecdsa_group_check() {
+        let (vk, z, sig, _) = signed();
+        let table = ecdsa_to_host_call_table(&vec![(vk.clone(), z.clone(), sig.clone())]);
+        assert_eq!(table.len(), CHUNK_SIZE);
+        let values = table.chunks_exact(MERGE_SIZE).map(to_bn).collect::<Vec<_>>();
+        assert_eq!(values, vec![vk.x, vk.y, sig.r, sig.s, z]);
+
+        let opcodes = [EcdsaPush, EcdsaVerify].map(|op| Fr::from(op as u64));
+        let group = to_group(&table);
+        assert!(check_ecdsa_group(&opcodes, CHUNK_SIZE - MERGE_SIZE, &group).is_ok());
+
+        // the message hash pushed before the signature
+        let mut swapped = group.clone();
+        swapped[0].0.1 = opcodes[1];
+        assert!(check_ecdsa_group(&opcodes, CHUNK_SIZE - MERGE_SIZE, &swapped).is_err());
+    }
+
+    #[test]
+    fn test_ecrecover_group_check() {
+        let (vk, z, sig, v) = signed();
+        let table = ecrecover_to_host_call_table(&vec![(z.clone(), sig.clone(), v, vk.clone())]);
+        let r = to_bn(&table[0..MERGE_SIZE]);
+        let s = to_bn(&table[MERGE_SIZE..2 * MERGE_SIZE]);
+        let hash = to_bn(&table[2 * MERGE_SIZE..3 * MERGE_SIZE]);
+        let id = table[RECOVER_PUSH_SIZE - 1].value;
+        let x = to_bn(&table[RECOVER_PUSH_SIZE..RECOVER_PUSH_SIZE + MERGE_SIZE]);
+        let y = to_bn(&table[RECOVER_PUSH_SIZE + MERGE_SIZE..]);
+        assert_eq!(recover(&hash, &Signature { r, s }, id), Some(Point { x, y, infinity: false }));
+
+        let opcodes = [EcdsaRecoverPush, EcdsaRecoverResult].map(|op| Fr::from(op as u64));
+        let group = to_group(&table);
+        assert!(check_ecrecover_group(&opcodes, &group).is_ok());
+
+        // a recovery id that is not a bit
+        let mut id = group.clone();
+        id[RECOVER_PUSH_SIZE - 1].0.0 = Fr::from(2u64);
+        assert!(check_ecrecover_group(&opcodes, &id).is_err());
+
+        // the recovery id missing
+        let mut missing = group.clone();
+        missing[RECOVER_PUSH_SIZE - 1].0.1 = opcodes[1];
+        assert!(check_ecrecover_group(&opcodes, &missing).is_err());
+    }
+}
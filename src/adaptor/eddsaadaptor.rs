@@ -0,0 +1,317 @@
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::plonk::Error;
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::circuit::{Region, Layouter};
+use crate::host::ExternalHostCallEntry;
+
+use crate::host::ForeignInst::{
+    EddsaPush,
+    EddsaVerify,
+    EddsaCompressedPush,
+    EddsaCompressedVerify,
+};
+use crate::circuits::babyjub::Point as CircuitPoint;
+use crate::circuits::eddsa::EddsaChip;
+use crate::circuits::CommonGateConfig;
+
+use crate::circuits::host::{
+    HostOpSelector,
+    HostOpConfig,
+};
+
+use crate::host::eddsa::{sign, public_key, Signature};
+use crate::host::jubjub::Point;
+use crate::utils::{bn_to_field, Limb};
+use num_bigint::BigUint;
+
+/* The calling convention will be
+ * EddsaPush: 4 u64 per field element of vk.x, vk.y, R.x, R.y, s
+ * EddsaVerify: 4 u64 of the message, the call fails if the signature is not valid
+ */
+const MERGE_SIZE:usize = 4;
+const CHUNK_SIZE:usize = (2 + 2 + 1 + 1) * MERGE_SIZE;
+
+const TOTAL_CONSTRUCTIONS:usize = 16;
+
+fn eddsa_to_host_call_table(inputs: &Vec<(Point, Fr, Signature)>) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (vk, msg, sig) in inputs.into_iter() {
+        r.push(crate::adaptor::fr_to_args(vk.x, 4, 64, EddsaPush));
+        r.push(crate::adaptor::fr_to_args(vk.y, 4, 64, EddsaPush));
+        r.push(crate::adaptor::fr_to_args(sig.r.x, 4, 64, EddsaPush));
+        r.push(crate::adaptor::fr_to_args(sig.r.y, 4, 64, EddsaPush));
+        r.push(crate::adaptor::fr_to_args(bn_to_field::<Fr>(&sig.s), 4, 64, EddsaPush));
+        r.push(crate::adaptor::fr_to_args(*msg, 4, 64, EddsaVerify));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+/* The calling convention of compressed keys (see crate::host::jubjub::Point::compress) will be
+ * EddsaCompressedPush: 4 u64 of the compressed vk, 4 u64 of the compressed R, 4 u64 of s
+ * EddsaCompressedVerify: 4 u64 of the message, the call fails if the signature is not valid
+ * the first three u64 of a compressed point are merged and the last one holds the sign of x.
+ * Decompressing vk and R takes about 2.6k more lines per signature than the uncompressed calls.
+ */
+const COMPRESSED_CHUNK_SIZE:usize = (2 + 1 + 1) * MERGE_SIZE;
+
+fn compressed_to_args(bytes: &[u8; 32]) -> Vec<ExternalHostCallEntry> {
+    bytes.chunks_exact(8).map(|x| ExternalHostCallEntry {
+        op: EddsaCompressedPush as usize,
+        value: u64::from_le_bytes(x.try_into().unwrap()),
+        is_ret: false,
+    }).collect()
+}
+
+fn eddsa_compressed_to_host_call_table(inputs: &Vec<(Point, Fr, Signature)>) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (vk, msg, sig) in inputs.into_iter() {
+        r.push(compressed_to_args(&vk.compress()));
+        r.push(compressed_to_args(&sig.r.compress()));
+        r.push(crate::adaptor::fr_to_args(bn_to_field::<Fr>(&sig.s), 4, 64, EddsaCompressedPush));
+        r.push(crate::adaptor::fr_to_args(*msg, 4, 64, EddsaCompressedVerify));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+/// Assign the low, high limbs of vk and R then s and the message
+fn assign_compressed_signature(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for point in group[0..2 * MERGE_SIZE].chunks_exact(MERGE_SIZE) {
+        let low = config.assign_merged_operands(region, offset, point[0..MERGE_SIZE - 1].iter().collect(), Fr::from_u128(1u128 << 64), enable)?;
+        let ((operand, opcode), index) = point[MERGE_SIZE - 1];
+        let high = config.assign_one_line(region, offset, operand, opcode, index, operand, Fr::zero(), enable)?;
+        r.push(low);
+        r.push(high);
+    }
+    for subgroup in group[2 * MERGE_SIZE..].iter().collect::<Vec<_>>().chunks_exact(MERGE_SIZE) {
+        r.push(config.assign_merged_operands(region, offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), enable)?);
+    }
+    Ok(r)
+}
+
+/// A valid signature which fills the unused constructions
+fn default_signature() -> (Point, Fr, Signature) {
+    let sk = BigUint::from(1u64);
+    let msg = Fr::zero();
+    (public_key(&sk), msg, sign(&sk, &msg))
+}
+
+impl HostOpSelector for EddsaChip<Fr> {
+    type Config = CommonGateConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        EddsaChip::<Fr>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        EddsaChip::new(c)
+    }
+
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = vec![
+            Fr::from(EddsaPush as u64),
+            Fr::from(EddsaVerify as u64),
+        ];
+
+        let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+
+        let selected_entries = entries.filter(|((_operand, opcode), _index)| {
+            opcodes.contains(opcode)
+        }).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        assert!(selected_entries.len() % CHUNK_SIZE == 0);
+        let total_used_instructions = selected_entries.len()/(CHUNK_SIZE);
+        assert!(total_used_instructions <= TOTAL_CONSTRUCTIONS);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            let ((_, opcode), _) = group[CHUNK_SIZE - 1];
+            assert!(opcode == Fr::from(EddsaVerify as u64));
+            for subgroup in group.iter().collect::<Vec<_>>().chunks_exact(MERGE_SIZE) {
+                let limb = config.assign_merged_operands(region, &mut offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), true)?;
+                r.push(limb);
+            }
+        }
+
+        let default_table = eddsa_to_host_call_table(&vec![default_signature()]);
+
+        let default_entries:Vec<((Fr, Fr), Fr)> = default_table.into_iter().map(
+            |x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())
+        ).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
+            for subgroup in default_entries.iter().collect::<Vec<_>>().chunks_exact(MERGE_SIZE) {
+                let limb = config.assign_merged_operands(region, &mut offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), false)?;
+                r.push(limb);
+            }
+        }
+
+        Ok(r)
+    }
+
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "eddsa region",
+            |mut region| {
+                let mut offset = 0;
+                let timer = start_timer!(|| "assign");
+                let config = self.config.clone();
+                self.initialize(&config, &mut region, &mut offset)?;
+                // arg_cells format 2 + 2 + 1 + 1
+                for args in arg_cells.chunks_exact(6).into_iter() {
+                    self.assign_verify(
+                        &mut region,
+                        &mut offset,
+                        &CircuitPoint {
+                            x: args[0].clone(),
+                            y: args[1].clone(),
+                        },
+                        &CircuitPoint {
+                            x: args[2].clone(),
+                            y: args[3].clone(),
+                        },
+                        &args[4],
+                        &args[5],
+                    )?;
+                }
+                end_timer!(timer);
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+
+/// Host op selector of the EdDSA verification with compressed vk and R
+pub struct EddsaCompressedChip(pub EddsaChip<Fr>);
+
+impl HostOpSelector for EddsaCompressedChip {
+    type Config = CommonGateConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        EddsaChip::<Fr>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        EddsaCompressedChip(EddsaChip::new(c))
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = vec![
+            Fr::from(EddsaCompressedPush as u64),
+            Fr::from(EddsaCompressedVerify as u64),
+        ];
+
+        let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+
+        let selected_entries = entries.filter(|((_operand, opcode), _index)| {
+            opcodes.contains(opcode)
+        }).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        assert!(selected_entries.len() % COMPRESSED_CHUNK_SIZE == 0);
+        let total_used_instructions = selected_entries.len()/(COMPRESSED_CHUNK_SIZE);
+        assert!(total_used_instructions <= TOTAL_CONSTRUCTIONS);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(COMPRESSED_CHUNK_SIZE) {
+            let ((_, opcode), _) = group[COMPRESSED_CHUNK_SIZE - 1];
+            assert!(opcode == Fr::from(EddsaCompressedVerify as u64));
+            let mut limbs = assign_compressed_signature(region, &mut offset, config, group, true)?;
+            r.append(&mut limbs);
+        }
+
+        let default_table = eddsa_compressed_to_host_call_table(&vec![default_signature()]);
+
+        let default_entries:Vec<((Fr, Fr), Fr)> = default_table.into_iter().map(
+            |x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())
+        ).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
+            let mut limbs = assign_compressed_signature(region, &mut offset, config, &default_entries, false)?;
+            r.append(&mut limbs);
+        }
+
+        Ok(r)
+    }
+
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "eddsa compressed region",
+            |mut region| {
+                let mut offset = 0;
+                let timer = start_timer!(|| "assign");
+                let chip = &mut self.0;
+                let config = chip.config.clone();
+                chip.initialize(&config, &mut region, &mut offset)?;
+                // arg_cells format 2 + 2 + 1 + 1
+                for args in arg_cells.chunks_exact(6).into_iter() {
+                    let vk = chip.jubjub().decompress(&mut region, &mut offset, &args[0], &args[1])?;
+                    let r = chip.jubjub().decompress(&mut region, &mut offset, &args[2], &args[3])?;
+                    chip.assign_verify(&mut region, &mut offset, &vk, &r, &args[4], &args[5])?;
+                }
+                end_timer!(timer);
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::ExternalHostCallEntryTable;
+    use super::{eddsa_to_host_call_table, eddsa_compressed_to_host_call_table, default_signature};
+    use std::fs::File;
+
+    #[test]
+    fn generate_eddsa_input() {
+        let table = eddsa_to_host_call_table(&vec![default_signature()]);
+        let file = File::create("eddsa.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(table)).expect("can not write to file");
+    }
+
+    #[test]
+    fn generate_eddsa_compressed_input() {
+        let table = eddsa_compressed_to_host_call_table(&vec![default_signature()]);
+        let file = File::create("eddsa_compressed.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(table)).expect("can not write to file");
+    }
+}
@@ -19,6 +19,7 @@ use crate::circuits::poseidon::PoseidonChip;
 use crate::circuits::CommonGateConfig;
 use crate::circuits::LookupAssistChip;
 use crate::host::poseidon::gen_hasher;
+use crate::host::poseidon::RATE;
 
 use crate::circuits::host::{
     HostOpSelector,
@@ -34,6 +35,7 @@ impl LookupAssistConfig for () {
         _cs: &mut ConstraintSystem<F>,
         _col: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
         _hint: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
+        _shift: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
     ) {
         ()
     }
@@ -45,6 +47,7 @@ impl<F:FieldExt> LookupAssistChip<F> for () {
         _region: &mut Region<F>,
         _value: F,
         _sz: u64,
+        _shift: F,
     ) -> Result<(), Error> {
         Ok(())
     }
@@ -59,7 +62,7 @@ fn hash_cont(restart: bool) -> Vec<ExternalHostCallEntry> {
 
 }
 
-fn hash_to_host_call_table(inputs: [Fr; 8], result: Fr) -> ExternalHostCallEntryTable {
+fn hash_to_host_call_table(inputs: &Vec<Fr>, result: Fr) -> ExternalHostCallEntryTable {
     let mut r = vec![];
     r.push(hash_cont(true));
     for f in inputs.iter() {
@@ -69,8 +72,80 @@ fn hash_to_host_call_table(inputs: [Fr; 8], result: Fr) -> ExternalHostCallEntry
     ExternalHostCallEntryTable(r.into_iter().flatten().collect())
 }
 
+/* The calling convention will be
+ * PoseidonNew: restart + 2 * tag (see host::poseidon::encode_new)
+ *   restart is 1 if the sponge restarts else 0 (continue absorbing after the last squeeze)
+ *   tag is 0 for no domain separation, otherwise the restarted sponge starts with the
+ *   capacity element 2^64 + tag instead of 2^64 (the tag is ignored when not restarting)
+ * PoseidonPush: 4 u64 per field element, any number of field elements
+ * PoseidonFinalize: 4 u64 of the result
+ *
+ * The pushed elements are padded with one and then zeros up to a multiple of RATE,
+ * which is exactly what Poseidon::update followed by Poseidon::squeeze does.
+ * A call of n pushes spans n / RATE + 1 blocks chained through the sponge state.
+ * Every block takes the same rows: one line for PoseidonNew, RATE push slots and
+ * the result, the lines that are not part of the call are zero lines: PoseidonNew of
+ * all blocks but the first, the unused slots of the last block and PoseidonFinalize of
+ * all blocks but the last. Each block is passed to the chip as
+ * [(new, opcode), (data, opcode) x RATE, (result, opcode)] and the chip derives from the opcodes
+ * which slots are used and whether the block ends its call (see PoseidonChip::assign_call_block).
+ */
+const MERGE_SIZE:usize = 4;
+const BLOCK_SIZE:usize = 2 * (1 + RATE + 1);
 const TOTAL_CONSTRUCTIONS:usize = 2048;
 
+/* Returns the limbs of the blocks of one call and the number of blocks */
+fn assign_poseidon_call(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+    enable: bool,
+) -> Result<(Vec<Limb<Fr>>, usize), Error> {
+    assert!(group.len() >= 1 + MERGE_SIZE);
+    assert!((group.len() - 1) % MERGE_SIZE == 0);
+    let ((_, opcode), _) = group[0];
+    assert!(opcode == Fr::from(PoseidonNew as u64));
+
+    let subgroups = group[1..].chunks_exact(MERGE_SIZE).collect::<Vec<_>>();
+    let (finalize, pushes) = subgroups.split_last().unwrap();
+    let ((_, opcode), _) = finalize[0];
+    assert!(opcode == Fr::from(PoseidonFinalize as u64));
+
+    let blocks = pushes.len() / RATE + 1;
+    let unused = ((Fr::zero(), Fr::zero()), Fr::zero());
+    let unused_merged = vec![unused; MERGE_SIZE];
+    let mut r = vec![];
+    for b in 0..blocks {
+        let ((operand, opcode), index) = if b == 0 { group[0] } else { unused };
+        let (new, opcode) = config.assign_one_line_with_opcode(
+            region, offset, operand, opcode, index,
+            operand,
+            Fr::zero(),
+            enable
+        )?;
+        r.push(new);
+        r.push(opcode);
+
+        for i in b * RATE..(b + 1) * RATE {
+            let subgroup = pushes.get(i).map_or(&unused_merged[..], |x| *x);
+            let (limb, opcode) = config.assign_merged_operands_with_opcode(
+                region, offset, subgroup.iter().collect(), Fr::from_u128(1u128 << 64), enable
+            )?;
+            r.push(limb);
+            r.push(opcode);
+        }
+
+        let subgroup = if b == blocks - 1 { *finalize } else { &unused_merged[..] };
+        let (result, opcode) = config.assign_merged_operands_with_opcode(
+            region, offset, subgroup.iter().collect(), Fr::from_u128(1u128 << 64), enable
+        )?;
+        r.push(result);
+        r.push(opcode);
+    }
+    Ok((r, blocks))
+}
+
 impl HostOpSelector for PoseidonChip<Fr> {
     type Config = CommonGateConfig;
     fn configure(
@@ -103,55 +178,39 @@ impl HostOpSelector for PoseidonChip<Fr> {
             opcodes.contains(opcode)
         }).collect::<Vec<((Fr, Fr), Fr)>>();
 
-        let total_used_instructions = selected_entries.len()/(1+8*4+4);
+        // split the entries into calls, each of them starts with PoseidonNew
+        let mut groups: Vec<Vec<((Fr, Fr), Fr)>> = vec![];
+        for entry in selected_entries.into_iter() {
+            let ((_, opcode), _) = entry;
+            if opcode == Fr::from(PoseidonNew as u64) {
+                groups.push(vec![entry]);
+            } else {
+                groups.last_mut().expect("poseidon call must start with PoseidonNew").push(entry);
+            }
+        }
 
         let mut offset = 0;
         let mut r = vec![];
 
-        // TODO: Change 8 to RATE ?
-        for group in selected_entries.chunks_exact(1+8*4+4) {
-            let ((operand, opcode), index) = *group.get(0).clone().unwrap();
-            assert!(opcode.clone() == Fr::from(PoseidonNew as u64));
-
-            let limb = config.assign_one_line(
-                region, &mut offset, operand, opcode, index,
-                operand,
-                Fr::zero(),
-                true
-            )?;
-            r.push(limb);
-
-            for subgroup in group.clone().into_iter().skip(1).collect::<Vec<_>>().chunks_exact(4) {
-                let limb = config.assign_merged_operands(region, &mut offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), true)?;
-                r.push(limb);
-            }
+        let mut total_used_blocks = 0;
+        for group in groups.iter() {
+            let (mut limbs, blocks) = assign_poseidon_call(region, &mut offset, config, group, true)?;
+            r.append(&mut limbs);
+            total_used_blocks += blocks;
         }
 
-        let default_table = hash_to_host_call_table([
-                Fr::one(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()
-            ], gen_hasher().squeeze());
+        assert!(total_used_blocks <= TOTAL_CONSTRUCTIONS);
+
+        let default_table = hash_to_host_call_table(&vec![], gen_hasher().squeeze());
 
         //let entries = default_table.
         let default_entries:Vec<((Fr, Fr), Fr)> = default_table.0.into_iter().map(
             |x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())
         ).collect::<Vec<((Fr, Fr), Fr)>>();
 
-        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
-            let ((operand, opcode), index) = default_entries[0].clone();
-            assert!(opcode.clone() == Fr::from(PoseidonNew as u64));
-
-            let limb = config.assign_one_line(
-                region, &mut offset, operand, opcode, index,
-                operand,
-                Fr::zero(),
-                false
-            )?;
-            r.push(limb);
-
-            for subgroup in default_entries.clone().iter().skip(1).collect::<Vec<_>>().chunks_exact(4) {
-                let limb = config.assign_merged_operands(region, &mut offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), false)?;
-                r.push(limb);
-            }
+        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_blocks {
+            let (mut limbs, _) = assign_poseidon_call(region, &mut offset, config, &default_entries, false)?;
+            r.append(&mut limbs);
         }
 
         Ok(r)
@@ -171,17 +230,33 @@ impl HostOpSelector for PoseidonChip<Fr> {
                 let timer = start_timer!(|| "assign");
                 let config = self.config.clone();
                 self.initialize(&config, &mut region, &mut offset)?;
-                for arg_group in arg_cells.chunks_exact(10).into_iter() {
-                    let args = arg_group.into_iter().map(|x| x.clone());
-                    let args = args.collect::<Vec<_>>();
-                    self.assign_permute(
+                let opcodes = [
+                    Fr::from(PoseidonNew as u64),
+                    Fr::from(PoseidonPush as u64),
+                    Fr::from(PoseidonFinalize as u64),
+                ];
+                // the first block starts a call and a block starts a call if the last one ends its call
+                let mut start = Limb::new(None, Fr::one());
+                for args in arg_cells.chunks_exact(BLOCK_SIZE).into_iter() {
+                    let entries = args.chunks_exact(2).map(|x| {
+                        (x[0].clone(), x[1].clone())
+                    }).collect::<Vec<_>>();
+                    start = self.assign_call_block(
                         &mut region,
                         &mut offset,
-                        &args[1..9].to_vec().try_into().unwrap(),
-                        &args[0],
-                        &args[9],
+                        &start,
+                        &entries[0],
+                        &entries[1..1 + RATE],
+                        &entries[1 + RATE],
+                        opcodes,
                     )?;
                 }
+                // the last block ends its call
+                self.config.assign_line(&mut region, &mut (), &mut offset,
+                    [Some(start), None, None, None, None, None],
+                    [Some(Fr::one()), None, None, None, None, None, None, None, Some(-Fr::one())],
+                    0,
+                )?;
                 end_timer!(timer);
                 Ok(())
             },
@@ -215,7 +290,7 @@ mod tests {
 
     }
 
-    fn hash_to_host_call_table(inputs: Vec<[Fr; 8]>) -> ExternalHostCallEntryTable {
+    fn hash_to_host_call_table(inputs: Vec<Vec<Fr>>) -> ExternalHostCallEntryTable {
         let mut r = vec![];
         let mut start = true;
         let mut hasher = crate::host::poseidon::gen_hasher();
@@ -225,17 +300,34 @@ mod tests {
             for f in round.iter() {
                 r.push(crate::adaptor::fr_to_args(*f, 4, 64, PoseidonPush));
             }
-            let result = hasher.update_exact(&round);
+            hasher.update(&round);
+            let result = hasher.squeeze();
             r.push(crate::adaptor::fr_to_args(result, 4, 64, PoseidonFinalize));
         }
         ExternalHostCallEntryTable(r.into_iter().flatten().collect())
     }
 
+    fn hash_with_prefix_to_host_call_table(prefix: u64, inputs: Vec<Fr>) -> ExternalHostCallEntryTable {
+        let mut r = vec![];
+        let mut hasher = crate::host::poseidon::gen_hasher_with_prefix(prefix).unwrap();
+        r.push(vec![ExternalHostCallEntry {
+            op: PoseidonNew as usize,
+            value: crate::host::poseidon::encode_new(true, Some(prefix)).unwrap(),
+            is_ret: false,
+        }]);
+        for f in inputs.iter() {
+            r.push(crate::adaptor::fr_to_args(*f, 4, 64, PoseidonPush));
+        }
+        hasher.update(&inputs);
+        let result = hasher.squeeze();
+        r.push(crate::adaptor::fr_to_args(result, 4, 64, PoseidonFinalize));
+        ExternalHostCallEntryTable(r.into_iter().flatten().collect())
+    }
 
     #[test]
     fn generate_poseidon_input() {
         let table = hash_to_host_call_table(
-            vec![[Fr::one(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()]],
+            vec![vec![Fr::zero()]],
         );
         let file = File::create("poseidontest.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &table).expect("can not write to file");
@@ -245,12 +337,24 @@ mod tests {
     fn generate_poseidon_input_multi() {
         let table = hash_to_host_call_table(
             vec![
-                [Fr::one(); 8],
-                [Fr::one(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()],
+                vec![Fr::one(); 8],
+                vec![],
+                vec![Fr::one(); 11],
+                vec![Fr::one(); 20],
             ],
         );
         let file = File::create("poseidontest_multi.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &table).expect("can not write to file");
     }
 
+    #[test]
+    fn generate_poseidon_input_domain() {
+        let table = hash_with_prefix_to_host_call_table(
+            crate::host::poseidon::PREFIX_POINT,
+            vec![Fr::one(); 8],
+        );
+        let file = File::create("poseidontest_domain.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+
 }
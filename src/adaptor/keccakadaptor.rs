@@ -0,0 +1,205 @@
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::plonk::Error;
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::circuit::{Region, Layouter};
+use crate::host::{
+    ForeignInst,
+    ExternalHostCallEntryTable,
+    ExternalHostCallEntry,
+};
+use crate::host::ForeignInst::{
+    KeccakNew,
+    KeccakPush,
+    KeccakFinalize,
+};
+use crate::host::keccak256::{
+    LANES,
+    RATE_LANES,
+    DIGEST_LANES,
+    pad,
+    absorb,
+};
+use crate::circuits::keccak256::KeccakChip;
+use crate::circuits::CommonGateConfig;
+
+use crate::circuits::host::{
+    HostOpSelector,
+    HostOpConfig,
+};
+
+use crate::utils::Limb;
+
+/* The calling convention will be
+ * KeccakNew: 1 if the state restarts else 0 (continue with the next block of the same message)
+ * KeccakPush: RATE_LANES u64 lanes of one padded block (see host::keccak256::pad)
+ * KeccakFinalize: DIGEST_LANES u64 lanes of the state after the permutation
+ *
+ * The opcode absorbs blocks that are already padded, the circuit proves the permutations
+ * and not the padding: the pad10*1 bytes of the last block are absorbed like any other
+ * byte. Thus the result of the last block is the keccak256 hash of the message only if the
+ * guest pads it with host::keccak256::pad, which is the job of the guest library.
+ *
+ * Every bit operation of keccak-f takes one line of the common gate (see circuits::keccak256),
+ * a round takes 6400 lines (theta 3200, chi 3200) plus the bits set in its iota constant,
+ * thus a block takes 24 * 6400 + 86 lines for the permutation and about 4100 lines
+ * to reset the state, absorb the block and compose the digest, 157.8k lines in total.
+ * The TOTAL_CONSTRUCTIONS blocks take 2.5M rows which fit in the 2^22 rows of the circuit.
+ */
+const CHUNK_SIZE:usize = 1 + RATE_LANES + DIGEST_LANES;
+const TOTAL_CONSTRUCTIONS:usize = 16;
+
+fn keccak_cont(restart: bool) -> Vec<ExternalHostCallEntry> {
+    vec![ExternalHostCallEntry {
+        op: KeccakNew as usize,
+        value: if restart {1u64} else {0u64},
+        is_ret: false,
+    }]
+}
+
+fn keccak_lanes(lanes: &[u64], op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    lanes.iter().map(|x| ExternalHostCallEntry {
+        op: op as usize,
+        value: *x,
+        is_ret: false,
+    }).collect()
+}
+
+fn keccak_to_host_call_table(inputs: &Vec<Vec<u8>>) -> ExternalHostCallEntryTable {
+    let mut r = vec![];
+    for message in inputs.iter() {
+        let mut state = [0u64; LANES];
+        for (i, block) in pad(message).iter().enumerate() {
+            r.push(keccak_cont(i == 0));
+            r.push(keccak_lanes(block, KeccakPush));
+            let result = absorb(&mut state, block);
+            r.push(keccak_lanes(&result, KeccakFinalize));
+        }
+    }
+    ExternalHostCallEntryTable(r.into_iter().flatten().collect())
+}
+
+fn assign_keccak_block(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let ((_, opcode), _) = group[0].clone();
+    assert!(opcode == Fr::from(KeccakNew as u64));
+    let mut r = vec![];
+    for ((operand, opcode), index) in group.iter() {
+        let limb = config.assign_one_line(
+            region, offset, *operand, *opcode, *index,
+            *operand,
+            Fr::zero(),
+            enable
+        )?;
+        r.push(limb);
+    }
+    Ok(r)
+}
+
+impl HostOpSelector for KeccakChip<Fr> {
+    type Config = CommonGateConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        KeccakChip::<Fr>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        KeccakChip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = vec![
+            Fr::from(ForeignInst::KeccakNew as u64),
+            Fr::from(ForeignInst::KeccakPush as u64),
+            Fr::from(ForeignInst::KeccakFinalize as u64),
+        ];
+
+        let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+
+        let selected_entries = entries.filter(|((_operand, opcode), _index)| {
+            opcodes.contains(opcode)
+        }).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        assert!(selected_entries.len() % CHUNK_SIZE == 0);
+        let total_used_instructions = selected_entries.len()/CHUNK_SIZE;
+        assert!(total_used_instructions <= TOTAL_CONSTRUCTIONS);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            let mut limbs = assign_keccak_block(region, &mut offset, config, group, true)?;
+            r.append(&mut limbs);
+        }
+
+        let default_table = keccak_to_host_call_table(&vec![vec![]]);
+
+        let default_entries:Vec<((Fr, Fr), Fr)> = default_table.0.into_iter().map(
+            |x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())
+        ).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
+            let mut limbs = assign_keccak_block(region, &mut offset, config, &default_entries, false)?;
+            r.append(&mut limbs);
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "keccak hash region",
+            |mut region| {
+                let mut offset = 0;
+                let timer = start_timer!(|| "assign");
+                let config = self.config.clone();
+                self.initialize(&config, &mut region, &mut offset)?;
+                for arg_group in arg_cells.chunks_exact(CHUNK_SIZE).into_iter() {
+                    let args = arg_group.into_iter().map(|x| x.clone());
+                    let args = args.collect::<Vec<_>>();
+                    self.assign_permute(
+                        &mut region,
+                        &mut offset,
+                        &args[1..1+RATE_LANES].to_vec().try_into().unwrap(),
+                        &args[0],
+                        &args[1+RATE_LANES..].to_vec().try_into().unwrap(),
+                    )?;
+                }
+                end_timer!(timer);
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    #[test]
+    fn generate_keccak_input() {
+        let table = super::keccak_to_host_call_table(
+            &vec![b"abc".to_vec(), vec![0u8; 200]],
+        );
+        let file = File::create("keccaktest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+}
@@ -1,4 +1,4 @@
-//use ark_std::{end_timer, start_timer};
+use ark_std::{end_timer, start_timer};
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::plonk::Error;
 use halo2_proofs::pairing::bn256::Fr;
@@ -13,7 +13,9 @@ use crate::host::ForeignInst::{
     KVPairGet,
 };
 
-use crate::circuits::merkle::MerkleChip;
+use crate::circuits::merkle::{MerkleChip, MerkleConfig};
+use crate::circuits::poseidon::PoseidonChip;
+use crate::host::kvpair::MAX_LEAF_WORDS;
 use crate::circuits::CommonGateConfig;
 
 use crate::circuits::host::{
@@ -40,13 +42,24 @@ lazy_static::lazy_static! {
 
 /* The calling convention will be
  * KVPairAddress
- * KVPairSetRoot
- * KVPairSet / KVPairGet
+ * KVPairSet / KVPairGet: 4 u64 of root
+ * KVPairSet / KVPairGet: number of words of the value, between 1 and MAX_LEAF_WORDS
+ * KVPairSet / KVPairGet: 4 u64 per word of the value
+ *
+ * A call ends at the next KVPairAddress.
+ * Each word of the value is split into two 128 bits limbs so that
+ * they are exactly the field elements absorbed when hashing the leaf (see MerkleRecord::set).
+ * Every call returns the limbs of MAX_LEAF_WORDS words, the limbs of the missing words
+ * are zero limbs without any host row, MerkleChip::assign_leaf constrains them to be zero
+ * and hashes the first 2 * words limbs.
  */
 const MERGE_SIZE:usize = 4;
-// 0: set/get 1-4: root 5-8:address 9-12:value
-const CHUNK_SIZE:usize = 1 + 1 * MERGE_SIZE + 1*MERGE_SIZE; // should equal to 9
+const HALF_MERGE_SIZE:usize = 2;
+// 0: address 1-4: root 5: number of words 6-9: first word of value
+const MIN_CHUNK_SIZE:usize = 1 + 1 * MERGE_SIZE + 1 + 1*MERGE_SIZE; // should equal to 10
 const TOTAL_CONSTRUCTIONS:usize = 2;
+// address, root, number of words and two limbs per word
+const CALL_SIZE:usize = 3 + 2 * MAX_LEAF_WORDS;
 
 fn kvpair_new(address: u64) -> Vec<ExternalHostCallEntry> {
     vec![ExternalHostCallEntry {
@@ -56,30 +69,87 @@ fn kvpair_new(address: u64) -> Vec<ExternalHostCallEntry> {
     }]
 }
 
-fn kvpair_to_host_call_table<F:FieldExt>(inputs: &Vec<(u64, F, F, ForeignInst)>) -> Vec<ExternalHostCallEntry> {
+fn kvpair_value(value: &Vec<u64>, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    assert!(!value.is_empty() && value.len() % MERGE_SIZE == 0);
+    assert!(value.len() / MERGE_SIZE <= MAX_LEAF_WORDS);
+    let words = (value.len() / MERGE_SIZE) as u64;
+    std::iter::once(&words).chain(value.iter()).map(|x| ExternalHostCallEntry {
+        op: op as usize,
+        value: *x,
+        is_ret: false,
+    }).collect()
+}
+
+fn kvpair_to_host_call_table<F:FieldExt>(inputs: &Vec<(u64, F, Vec<u64>, ForeignInst)>) -> Vec<ExternalHostCallEntry> {
     let mut r = vec![];
     for (addr, root, value, op) in inputs.into_iter() {
         r.push(kvpair_new(*addr));
         r.push(crate::adaptor::fr_to_args(*root, 4, 64, *op));
-        r.push(crate::adaptor::fr_to_args(*value, 4, 64, *op));
+        r.push(kvpair_value(value, *op));
     }
     r.into_iter().flatten().collect::<Vec<_>>()
 }
 
+/* Returns the limbs of one call: address, root, number of words and
+ * two limbs per word for MAX_LEAF_WORDS words, the limbs of the missing words have no cell.
+ */
+fn assign_kvpair_call(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    assert!(group.len() >= MIN_CHUNK_SIZE);
+    assert!((group.len() - 2) % MERGE_SIZE == 0);
+    let ((operand, opcode), index) = group[0].clone();
+    assert!(opcode.clone() == Fr::from(KVPairAddress as u64));
+
+    let mut r = vec![];
+    let limb = config.assign_one_line(
+        region, offset, operand, opcode, index,
+        operand,
+        Fr::zero(),
+        enable
+    )?;
+    r.push(limb);
+
+    let root = group[1..1 + MERGE_SIZE].iter().collect::<Vec<_>>();
+    let limb = config.assign_merged_operands(region, offset, root, Fr::from_u128(1u128 << 64), enable)?;
+    r.push(limb);
+
+    let ((operand, opcode), index) = group[1 + MERGE_SIZE].clone();
+    let value = group[2 + MERGE_SIZE..].iter().collect::<Vec<_>>();
+    assert!(operand == Fr::from((value.len() / MERGE_SIZE) as u64));
+    assert!(value.len() / MERGE_SIZE <= MAX_LEAF_WORDS);
+    let limb = config.assign_one_line(
+        region, offset, operand, opcode, index,
+        operand,
+        Fr::zero(),
+        enable
+    )?;
+    r.push(limb);
+
+    for half in value.chunks_exact(HALF_MERGE_SIZE) {
+        let limb = config.assign_merged_operands(region, offset, half.to_vec(), Fr::from_u128(1u128 << 64), enable)?;
+        r.push(limb);
+    }
+    r.resize(CALL_SIZE, Limb::new(None, Fr::zero()));
+    Ok(r)
+}
+
 
 
 impl HostOpSelector for MerkleChip<Fr> {
-    type Config = CommonGateConfig;
+    type Config = (MerkleConfig, CommonGateConfig);
     fn configure(
-        _meta: &mut ConstraintSystem<Fr>,
+        meta: &mut ConstraintSystem<Fr>,
     ) -> Self::Config {
-        todo!()
-        //MerkleChip::<Fr>::configure(meta)
+        (MerkleChip::<Fr>::configure(meta), PoseidonChip::<Fr>::configure(meta))
     }
 
-    fn construct(_c: Self::Config) -> Self {
-        todo!()
-        //MerkleChip::new(c)
+    fn construct(c: Self::Config) -> Self {
+        MerkleChip::new(c.0, c.1)
     }
 
     fn assign(
@@ -102,30 +172,28 @@ impl HostOpSelector for MerkleChip<Fr> {
             opcodes.contains(opcode)
         }).collect::<Vec<((Fr, Fr), Fr)>>();
 
-        let total_used_instructions = selected_entries.len()/(CHUNK_SIZE);
+        // split the entries into calls, each of them starts with KVPairAddress
+        let mut groups: Vec<Vec<((Fr, Fr), Fr)>> = vec![];
+        for entry in selected_entries.into_iter() {
+            let ((_, opcode), _) = entry;
+            if opcode == Fr::from(KVPairAddress as u64) {
+                groups.push(vec![entry]);
+            } else {
+                groups.last_mut().expect("kvpair call must start with KVPairAddress").push(entry);
+            }
+        }
+
+        let total_used_instructions = groups.len();
 
         let mut offset = 0;
         let mut r = vec![];
 
-        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
-            let ((operand, opcode), index) = *group.get(0).clone().unwrap();
-            assert!(opcode.clone() == Fr::from(KVPairAddress as u64));
-
-            let limb = config.assign_one_line(
-                region, &mut offset, operand, opcode, index,
-                operand,
-                Fr::zero(),
-                true
-            )?;
-            r.push(limb);
-
-            for subgroup in group.clone().into_iter().skip(1).collect::<Vec<_>>().chunks_exact(MERGE_SIZE) {
-                let limb = config.assign_merged_operands(region, &mut offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), true)?;
-                r.push(limb);
-            }
+        for group in groups.iter() {
+            let mut limbs = assign_kvpair_call(region, &mut offset, config, group, true)?;
+            r.append(&mut limbs);
         }
 
-        let default_table = kvpair_to_host_call_table(&vec![(0u64, *DEFAULT_ROOT_HASH, Fr::zero(), KVPairGet)]);
+        let default_table = kvpair_to_host_call_table(&vec![(0u64, *DEFAULT_ROOT_HASH, vec![0u64; MERGE_SIZE], KVPairGet)]);
 
         //let entries = default_table.
         let default_entries:Vec<((Fr, Fr), Fr)> = default_table.into_iter().map(
@@ -133,21 +201,8 @@ impl HostOpSelector for MerkleChip<Fr> {
         ).collect::<Vec<((Fr, Fr), Fr)>>();
 
         for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
-            let ((operand, opcode), index) = default_entries[0].clone();
-            assert!(opcode.clone() == Fr::from(KVPairAddress as u64));
-
-            let limb = config.assign_one_line(
-                region, &mut offset, operand, opcode, index,
-                operand,
-                Fr::zero(),
-                false
-            )?;
-            r.push(limb);
-
-            for subgroup in default_entries.clone().iter().skip(1).collect::<Vec<_>>().chunks_exact(MERGE_SIZE) {
-                let limb = config.assign_merged_operands(region, &mut offset, subgroup.to_vec(), Fr::from_u128(1u128 << 64), false)?;
-                r.push(limb);
-            }
+            let mut limbs = assign_kvpair_call(region, &mut offset, config, &default_entries, false)?;
+            r.append(&mut limbs);
         }
 
         Ok(r)
@@ -161,35 +216,21 @@ impl HostOpSelector for MerkleChip<Fr> {
     ) -> Result<(), Error> {
         println!("total args is {}", arg_cells.len());
         layouter.assign_region(
-            || "poseidon hash region",
-            |mut _region| {
-                /*
+            || "merkle leaf region",
+            |mut region| {
                 let mut offset = 0;
                 let timer = start_timer!(|| "assign");
-                let config = self.config.clone();
-                self.initialize(&config, &mut region, &mut offset)?;
-                // arg_cells format 1 + 2 + 1 + 2
-                for arg_group in arg_cells.chunks_exact(5).into_iter() {
-                    let args = arg_group.into_iter().map(|x| x.clone());
-                    let args = args.collect::<Vec<_>>();
-                    self.assign_incremental_msm(
+                self.initialize(&mut region, &mut offset)?;
+                // arg_cells format: address, root, words, 2 * MAX_LEAF_WORDS limbs of value
+                for args in arg_cells.chunks_exact(CALL_SIZE).into_iter() {
+                    self.assign_leaf(
                         &mut region,
                         &mut offset,
-                        &CircuitPoint {
-                            x: args[1].clone(),
-                            y: args[2].clone(),
-                        },
+                        &args[3..],
                         &args[2],
-                        &args[0],
-                        &CircuitPoint {
-                            x: args[3].clone(),
-                            y: args[4].clone(),
-                        },
-
                     )?;
                 }
                 end_timer!(timer);
-                */
                 Ok(())
             },
         )?;
@@ -229,12 +270,12 @@ mod tests {
         let mut _mt = MongoMerkle::construct(TEST_ADDR, DEFAULT_ROOT_HASH_BYTES);
         let root_default = DEFAULT_ROOT_HASH.clone();
         let index = 2_u64.pow(20) - 1;
-        let data = 0x1000;
+        let data = vec![0x1000, 0, 0, 0];
         let root64_new = Fr::from_raw(NEW_ROOT_HASH64);
 
         let default_table = kvpair_to_host_call_table(&vec![
-            (index, root_default, Fr::zero(), KVPairGet),
-            (index, root64_new, Fr::from(data), KVPairSet)
+            (index, root_default, vec![0; 4], KVPairGet),
+            (index, root64_new, data, KVPairSet)
         ]);
         let file = File::create("kvpair.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table)).expect("can not write to file");
@@ -6,12 +6,22 @@ use num_bigint::BigUint;
 
 pub mod bls381adaptor;
 pub mod bn256adaptor;
+pub mod pairingadaptor;
 pub mod hashadaptor;
 pub mod msmadaptor;
+pub mod eddsaadaptor;
+pub mod ecdsaadaptor;
 pub mod merkleadaptor;
+pub mod keccakadaptor;
+pub mod blake2adaptor;
+pub mod sha256adaptor;
 
 pub fn fr_to_args<F:BaseExt>(f: F, nblimbs: usize, sz: usize, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
-    let mut bn = field_to_bn(&f);
+    bn_to_args(&field_to_bn(&f), nblimbs, sz, op)
+}
+
+pub fn bn_to_args(bn: &BigUint, nblimbs: usize, sz: usize, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    let mut bn = bn.clone();
     let mut ret = vec![];
     for _ in 0..nblimbs {
         let d: BigUint = BigUint::from(1u128 << sz);
@@ -36,11 +36,12 @@ fn msm_new(restart: bool) -> Vec<ExternalHostCallEntry> {
     }]
 }
 
-fn msm_to_host_call_table<F:FieldExt>(inputs: &Vec<(Point, F)>) -> Vec<ExternalHostCallEntry> {
+fn msm_to_host_call_table<F:FieldExt>(inputs: &Vec<(Point, F)>, subgroup_check: bool) -> Vec<ExternalHostCallEntry> {
     let mut r = vec![];
     let mut start = true;
     let mut result = Point::identity();
     for (p, c) in inputs.into_iter() {
+        assert!(!subgroup_check || p.is_in_subgroup());
         r.push(msm_new(start));
         r.push(crate::adaptor::fr_to_args(p.x, 4, 64, JubjubSumPush));
         r.push(crate::adaptor::fr_to_args(p.y, 4, 64, JubjubSumPush));
@@ -53,9 +54,11 @@ fn msm_to_host_call_table<F:FieldExt>(inputs: &Vec<(Point, F)>) -> Vec<ExternalH
     r.into_iter().flatten().collect::<Vec<_>>()
 }
 
+/// Host op selector of the jubjub msm. SUBGROUP_CHECK configures whether the
+/// pushed points are constrained to be in the prime order subgroup.
+pub struct AltJubMsmChip<const SUBGROUP_CHECK: bool = true>(pub AltJubChip<Fr>);
 
-
-impl HostOpSelector for AltJubChip<Fr> {
+impl<const SUBGROUP_CHECK: bool> HostOpSelector for AltJubMsmChip<SUBGROUP_CHECK> {
     type Config = CommonGateConfig;
     fn configure(
         meta: &mut ConstraintSystem<Fr>,
@@ -64,7 +67,11 @@ impl HostOpSelector for AltJubChip<Fr> {
     }
 
     fn construct(c: Self::Config) -> Self {
-        AltJubChip::new(c)
+        if SUBGROUP_CHECK {
+            AltJubMsmChip(AltJubChip::with_subgroup_check(c))
+        } else {
+            AltJubMsmChip(AltJubChip::new(c))
+        }
     }
 
 
@@ -110,7 +117,7 @@ impl HostOpSelector for AltJubChip<Fr> {
             }
         }
 
-        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())]);
+        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())], SUBGROUP_CHECK);
 
         //let entries = default_table.
         let default_entries:Vec<((Fr, Fr), Fr)> = default_table.into_iter().map(
@@ -146,28 +153,29 @@ impl HostOpSelector for AltJubChip<Fr> {
     ) -> Result<(), Error> {
         println!("total args is {}", arg_cells.len());
         layouter.assign_region(
-            || "poseidon hash region",
+            || "jubjub msm region",
             |mut region| {
                 let mut offset = 0;
                 let timer = start_timer!(|| "assign");
-                let config = self.config.clone();
-                self.initialize(&config, &mut region, &mut offset)?;
+                let chip = &mut self.0;
+                let config = chip.config.clone();
+                chip.initialize(&config, &mut region, &mut offset)?;
                 // arg_cells format 1 + 2 + 1 + 2
-                for arg_group in arg_cells.chunks_exact(5).into_iter() {
+                for arg_group in arg_cells.chunks_exact(6).into_iter() {
                     let args = arg_group.into_iter().map(|x| x.clone());
                     let args = args.collect::<Vec<_>>();
-                    self.assign_incremental_msm(
+                    chip.assign_incremental_msm(
                         &mut region,
                         &mut offset,
                         &CircuitPoint {
                             x: args[1].clone(),
                             y: args[2].clone(),
                         },
-                        &args[2],
+                        &args[3],
                         &args[0],
                         &CircuitPoint {
-                            x: args[3].clone(),
-                            y: args[4].clone(),
+                            x: args[4].clone(),
+                            y: args[5].clone(),
                         },
 
                     )?;
@@ -193,14 +201,14 @@ mod tests {
 
     #[test]
     fn generate_jubjub_msm_input() {
-        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())]);
+        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())], true);
         let file = File::create("jubjub.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table)).expect("can not write to file");
     }
 
     #[test]
     fn generate_jubjub_msm_input_multi() {
-        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())]);
+        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())], true);
         let file = File::create("jubjub_multi.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table)).expect("can not write to file");
     }
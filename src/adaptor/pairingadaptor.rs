@@ -0,0 +1,385 @@
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
+    plonk::{ConstraintSystem, Error},
+};
+
+use crate::circuits::pairing::{
+    G2SumChip,
+    MultiPairChip,
+    PairChip,
+    PairingChipConfig,
+    PairingCurve,
+    SumChip,
+};
+
+use crate::circuits::host::{HostOpSelector, HostOpConfig};
+use crate::host::ForeignInst;
+use crate::utils::{Limb, field_to_u64};
+
+/// The host call layout of a pairing curve, an Fq is FQ_SIZE entries of 54 bits
+/// which are merged two by two into the limbs of 108 bits of the chips.
+pub trait PairingOpcodes: PairingCurve {
+    const FQ_SIZE: usize;
+    const G1_SIZE: usize = Self::FQ_SIZE * 2 + 1;
+    const G2_SIZE: usize = Self::FQ_SIZE * 4 + 1;
+    const GT_SIZE: usize = Self::FQ_SIZE * 12;
+
+    const PAIR_G1: ForeignInst;
+    const PAIR_G2: ForeignInst;
+    const PAIR_GT: ForeignInst;
+
+    const SUM_G1: ForeignInst;
+    const SUM_RESULT: ForeignInst;
+
+    const MULTI_PAIR_NEW: ForeignInst;
+    const MULTI_PAIR_G1: ForeignInst;
+    const MULTI_PAIR_G2: ForeignInst;
+    const MULTI_PAIR_GT: ForeignInst;
+
+    const SUM_G2: ForeignInst;
+    const SUM_G2_RESULT: ForeignInst;
+}
+
+fn select_entries(
+    shared_operands: &Vec<Fr>,
+    shared_opcodes: &Vec<Fr>,
+    shared_index: &Vec<Fr>,
+    opcodes: Vec<ForeignInst>,
+) -> Vec<((Fr, Fr), Fr)> {
+    let opcodes: Vec<Fr> = opcodes.into_iter().map(|op| Fr::from(op as u64)).collect();
+
+    let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+
+    entries.filter(|((_operand, opcode), _index)| {
+        opcodes.contains(opcode)
+    }).collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+/// assign the Fq (FQ_SIZE) entries into Fq (FQ_LIMBS) limbs
+pub fn assign_fq<C: PairingOpcodes>(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for pair in group[0..C::FQ_SIZE].chunks(2) {
+        let limb = if pair.len() == 2 {
+            config.assign_merged_operands(
+                region,
+                offset,
+                vec![&pair[0], &pair[1]],
+                Fr::from_u128(1u128 << 54),
+                true,
+            )?
+        } else {
+            let ((operand, opcode), index) = pair[0];
+            config.assign_one_line(region, offset, operand, opcode, index,
+               operand, Fr::zero(), true)?
+        };
+        r.push(limb);
+    }
+    Ok(r)
+}
+
+/// assign the G1 (FQ_SIZE * 2 + 1) entries into G1 (FQ_LIMBS * 2 + 1) limbs
+pub fn assign_g1<C: PairingOpcodes>(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for j in 0..2 {
+        r.append(&mut assign_fq::<C>(region, offset, config, &group[C::FQ_SIZE * j..])?);
+    }
+
+    // whether g1 is zero or not
+    let ((operand, opcode), index) = group[C::FQ_SIZE * 2];
+    let limb = config.assign_one_line(region, offset, operand, opcode, index,
+       operand, Fr::zero(), true)?;
+    r.push(limb);
+    Ok(r)
+}
+
+/// assign the G2 (FQ_SIZE * 4 + 1) entries into G2 (FQ_LIMBS * 4 + 1) limbs
+pub fn assign_g2<C: PairingOpcodes>(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for j in 0..4 {
+        r.append(&mut assign_fq::<C>(region, offset, config, &group[C::FQ_SIZE * j..])?);
+    }
+
+    // whether g2 is zero or not
+    let ((operand, opcode), index) = group[C::FQ_SIZE * 4];
+    let limb = config.assign_one_line(region, offset, operand, opcode, index,
+       operand, Fr::zero(), true)?;
+    r.push(limb);
+    Ok(r)
+}
+
+/// assign the Gt (FQ_SIZE * 12) entries into Fq_12 (FQ_LIMBS * 12) limbs
+pub fn assign_gt<C: PairingOpcodes>(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for j in 0..12 {
+        r.append(&mut assign_fq::<C>(region, offset, config, &group[C::FQ_SIZE * j..])?);
+    }
+    Ok(r)
+}
+
+/* The calling convention of a pairing will be
+ * PAIR_G1: G1, PAIR_G2: G2, PAIR_GT: Fq_12 of the pairing
+ */
+impl<C: PairingOpcodes> HostOpSelector for PairChip<Fr, C> {
+    type Config = PairingChipConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        PairChip::<Fr, C>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        PairChip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let selected_entries = select_entries(shared_operands, shared_opcodes, shared_index, vec![
+            C::PAIR_G1,
+            C::PAIR_G2,
+            C::PAIR_GT,
+        ]);
+
+        let pair_size = C::G1_SIZE + C::G2_SIZE + C::GT_SIZE;
+        assert!(selected_entries.len() % pair_size == 0);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(pair_size) {
+            r.append(&mut assign_g1::<C>(region, &mut offset, config, &group[0..C::G1_SIZE])?);
+            r.append(&mut assign_g2::<C>(region, &mut offset, config, &group[C::G1_SIZE..C::G1_SIZE + C::G2_SIZE])?);
+            r.append(&mut assign_gt::<C>(region, &mut offset, config, &group[C::G1_SIZE + C::G2_SIZE..])?);
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.range_chip.init_table(layouter)?;
+        let a = arg_cells[0..C::G1_LIMBS].to_vec();
+        let b = arg_cells[C::G1_LIMBS..C::G1_LIMBS + C::G2_LIMBS].to_vec();
+        let ab = arg_cells[C::G1_LIMBS + C::G2_LIMBS..C::G1_LIMBS + C::G2_LIMBS + C::GT_LIMBS].to_vec();
+        self.load_pair_circuit(&a, &b, &ab, layouter)?;
+        Ok(())
+    }
+}
+
+/* The calling convention of a G1 sum will be
+ * k * SUM_G1: G1
+ * SUM_RESULT: G1 of the sum
+ */
+impl<C: PairingOpcodes> HostOpSelector for SumChip<Fr, C> {
+    type Config = PairingChipConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        SumChip::<Fr, C>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        SumChip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let selected_entries = select_entries(shared_operands, shared_opcodes, shared_index, vec![
+            C::SUM_G1,
+            C::SUM_RESULT,
+        ]);
+
+        assert!(selected_entries.len() % (C::G1_SIZE * 3) == 0);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(C::G1_SIZE) {
+            r.append(&mut assign_g1::<C>(region, &mut offset, config, group)?);
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.range_chip.init_table(layouter)?;
+        let len = arg_cells.len();
+        let args = arg_cells[0..len - C::G1_LIMBS].to_vec();
+        let ret = arg_cells[len - C::G1_LIMBS..len].to_vec();
+        self.load_sum_circuit(&args, &ret, layouter)?;
+        Ok(())
+    }
+}
+
+/* The calling convention of a multi pairing call will be
+ * MULTI_PAIR_NEW: the number k of (G1, G2) pairs
+ * k * (MULTI_PAIR_G1: G1, MULTI_PAIR_G2: G2)
+ * MULTI_PAIR_GT: Fq_12 of the product of the pairings,
+ * which is the one of Fq_12 when checking that the product equals identity
+ */
+impl<C: PairingOpcodes> HostOpSelector for MultiPairChip<Fr, C> {
+    type Config = PairingChipConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        MultiPairChip::<Fr, C>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        MultiPairChip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let selected_entries = select_entries(shared_operands, shared_opcodes, shared_index, vec![
+            C::MULTI_PAIR_NEW,
+            C::MULTI_PAIR_G1,
+            C::MULTI_PAIR_G2,
+            C::MULTI_PAIR_GT,
+        ]);
+
+        let mut offset = 0;
+        let mut r = vec![];
+        let mut pos = 0;
+
+        while pos < selected_entries.len() {
+            let ((operand, opcode), index) = selected_entries[pos];
+            assert!(opcode == Fr::from(C::MULTI_PAIR_NEW as u64));
+            let k = field_to_u64(&operand) as usize;
+            let limb = config.assign_one_line(region, &mut offset, operand, opcode, index,
+               operand, Fr::zero(), true)?;
+            r.push(limb);
+            pos += 1;
+
+            for _ in 0..k {
+                r.append(&mut assign_g1::<C>(region, &mut offset, config, &selected_entries[pos..pos + C::G1_SIZE])?);
+                pos += C::G1_SIZE;
+                r.append(&mut assign_g2::<C>(region, &mut offset, config, &selected_entries[pos..pos + C::G2_SIZE])?);
+                pos += C::G2_SIZE;
+            }
+
+            let ((_, opcode), _) = selected_entries[pos];
+            assert!(opcode == Fr::from(C::MULTI_PAIR_GT as u64));
+            r.append(&mut assign_gt::<C>(region, &mut offset, config, &selected_entries[pos..pos + C::GT_SIZE])?);
+            pos += C::GT_SIZE;
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.range_chip.init_table(layouter)?;
+        // arg_cells format 1 + k * (G1_LIMBS + G2_LIMBS) + GT_LIMBS
+        let pair_limbs = C::G1_LIMBS + C::G2_LIMBS;
+        let mut calls = vec![];
+        let mut pos = 0;
+        while pos < arg_cells.len() {
+            let k = field_to_u64(&arg_cells[pos].value) as usize;
+            pos += 1;
+            let ls = arg_cells[pos..pos + k * pair_limbs].to_vec();
+            pos += k * pair_limbs;
+            let gt = arg_cells[pos..pos + C::GT_LIMBS].to_vec();
+            pos += C::GT_LIMBS;
+            calls.push((ls, gt));
+        }
+        self.load_multi_pair_circuit(&calls, layouter)?;
+        Ok(())
+    }
+}
+
+/* The calling convention of a G2 sum will be
+ * k * SUM_G2: G2
+ * SUM_G2_RESULT: G2 of the sum
+ */
+impl<C: PairingOpcodes> HostOpSelector for G2SumChip<Fr, C> {
+    type Config = PairingChipConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        G2SumChip::<Fr, C>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        G2SumChip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let selected_entries = select_entries(shared_operands, shared_opcodes, shared_index, vec![
+            C::SUM_G2,
+            C::SUM_G2_RESULT,
+        ]);
+
+        assert!(selected_entries.len() % C::G2_SIZE == 0);
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(C::G2_SIZE) {
+            r.append(&mut assign_g2::<C>(region, &mut offset, config, group)?);
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.range_chip.init_table(layouter)?;
+        let len = arg_cells.len();
+        let args = arg_cells[0..len - C::G2_LIMBS].to_vec();
+        let ret = arg_cells[len - C::G2_LIMBS..len].to_vec();
+        self.load_g2_sum_circuit(&args, &ret, layouter)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::plonk::Error;
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::circuit::{Region, Layouter};
+use crate::host::{
+    ExternalHostCallEntryTable,
+    ExternalHostCallEntry,
+};
+use crate::host::ForeignInst::{
+    SHA256New,
+    SHA256Push,
+    SHA256Finalize,
+};
+use crate::host::sha256::{
+    blocks,
+    compress,
+    H0,
+    STATE_WORDS,
+    BLOCK_WORDS,
+};
+use crate::circuits::sha256::Sha256Chip;
+use crate::circuits::bitwise::BitwiseWordConfig;
+
+use crate::circuits::host::{
+    HostOpSelector,
+    HostOpConfig,
+};
+
+use crate::utils::Limb;
+use crate::utils::field_to_u64;
+
+/* The calling convention will be (one call per compressed block)
+ * SHA256New: 1 for the first block of a message else 0
+ * SHA256Push: BLOCK_WORDS big endian words of the padded block
+ * SHA256Finalize: STATE_WORDS words of the state after the compression
+ *
+ * The digest is the big endian bytes of the state after the last block.
+ */
+const NEW_SIZE:usize = 1;
+const CHUNK_SIZE:usize = NEW_SIZE + BLOCK_WORDS + STATE_WORDS;
+/* A block takes about 9.9k lookup lines next to 7k lines of the common gate,
+ * thus 100 blocks take ~1M rows which also hold the 196609 lines of the bitwise table.
+ */
+const TOTAL_CONSTRUCTIONS:usize = 100;
+
+fn sha256_words(words: &[u32], op: usize) -> Vec<ExternalHostCallEntry> {
+    words.iter().map(|x| ExternalHostCallEntry {
+        op,
+        value: *x as u64,
+        is_ret: false,
+    }).collect()
+}
+
+fn sha256_to_host_call_table(inputs: &Vec<Vec<u8>>) -> ExternalHostCallEntryTable {
+    let mut r = vec![];
+    for message in inputs.iter() {
+        let mut h = H0;
+        for (i, m) in blocks(message).iter().enumerate() {
+            r.push(sha256_words(&[(i == 0) as u32], SHA256New as usize));
+            r.push(sha256_words(m, SHA256Push as usize));
+            compress(&mut h, m);
+            r.push(sha256_words(&h, SHA256Finalize as usize));
+        }
+    }
+    ExternalHostCallEntryTable(r.into_iter().flatten().collect())
+}
+
+/* Reject a block whose opcodes are not those of the calling convention
+ * or whose new flag is not a bit, the circuit can not prove it.
+ */
+fn check_sha256_block(
+    opcodes: &[Fr],
+    group: &[((Fr, Fr), Fr)],
+) -> Result<(), Error> {
+    for (i, ((_, opcode), _)) in group.iter().enumerate() {
+        let expected = if i < NEW_SIZE { opcodes[0] } else if i < NEW_SIZE + BLOCK_WORDS { opcodes[1] } else { opcodes[2] };
+        if *opcode != expected {
+            return Err(Error::Synthesis);
+        }
+    }
+    let ((new, _), _) = group[0];
+    if field_to_u64(&new) > 1 {
+        return Err(Error::Synthesis);
+    }
+    Ok(())
+}
+
+fn assign_sha256_block(
+    region: &mut Region<Fr>,
+    offset: &mut usize,
+    config: &HostOpConfig,
+    group: &[((Fr, Fr), Fr)],
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for ((operand, opcode), index) in group.iter() {
+        let limb = config.assign_one_line(
+            region, offset, *operand, *opcode, *index,
+            *operand,
+            Fr::zero(),
+            enable
+        )?;
+        r.push(limb);
+    }
+    Ok(r)
+}
+
+impl HostOpSelector for Sha256Chip<Fr> {
+    type Config = BitwiseWordConfig;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+    ) -> Self::Config {
+        Sha256Chip::<Fr>::configure(meta)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        Sha256Chip::construct(c)
+    }
+
+    fn assign(
+        region: &mut Region<Fr>,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        shared_index: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes: Vec<Fr> = vec![
+            Fr::from(SHA256New as u64),
+            Fr::from(SHA256Push as u64),
+            Fr::from(SHA256Finalize as u64),
+        ];
+
+        let entries = shared_operands.clone().into_iter().zip(shared_opcodes.clone()).zip(shared_index.clone());
+
+        let selected_entries = entries.filter(|((_operand, opcode), _index)| {
+            opcodes.contains(opcode)
+        }).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        if selected_entries.len() % CHUNK_SIZE != 0 {
+            return Err(Error::Synthesis);
+        }
+        let total_used_instructions = selected_entries.len()/CHUNK_SIZE;
+        if total_used_instructions > TOTAL_CONSTRUCTIONS {
+            return Err(Error::Synthesis);
+        }
+
+        let mut offset = 0;
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            check_sha256_block(&opcodes, group)?;
+            let mut limbs = assign_sha256_block(region, &mut offset, config, group, true)?;
+            r.append(&mut limbs);
+        }
+
+        let default_table = sha256_to_host_call_table(&vec![vec![]]);
+
+        let default_entries:Vec<((Fr, Fr), Fr)> = default_table.0.into_iter().map(
+            |x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())
+        ).collect::<Vec<((Fr, Fr), Fr)>>();
+
+        for _ in 0..TOTAL_CONSTRUCTIONS - total_used_instructions {
+            let mut limbs = assign_sha256_block(region, &mut offset, config, &default_entries, false)?;
+            r.append(&mut limbs);
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "sha256 hash region",
+            |mut region| {
+                let mut offset = 0;
+                let timer = start_timer!(|| "assign");
+                self.initialize(&mut region, &mut offset)?;
+                for arg_group in arg_cells.chunks_exact(CHUNK_SIZE).into_iter() {
+                    let args = arg_group.into_iter().map(|x| x.clone());
+                    let args = args.collect::<Vec<_>>();
+                    self.assign_compress(
+                        &mut region,
+                        &mut offset,
+                        &args[0],
+                        &args[NEW_SIZE..NEW_SIZE + BLOCK_WORDS].to_vec().try_into().unwrap(),
+                        &args[NEW_SIZE + BLOCK_WORDS..].to_vec().try_into().unwrap(),
+                    )?;
+                }
+                end_timer!(timer);
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use halo2_proofs::pairing::bn256::Fr;
+    use crate::host::ForeignInst::{
+        SHA256New,
+        SHA256Push,
+        SHA256Finalize,
+    };
+    use super::check_sha256_block;
+
+    #[test]
+    fn generate_sha256_input() {
+        let inputs = vec![b"abc".to_vec(), vec![0u8; 200]];
+        let table = super::sha256_to_host_call_table(&inputs);
+        let file = File::create("sha256test.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+
+    #[test]
+    fn test_sha256_block_check() {
+        let opcodes = [SHA256New, SHA256Push, SHA256Finalize].map(|op| Fr::from(op as u64));
+        let table = super::sha256_to_host_call_table(&vec![b"abc".to_vec()]);
+        let group = table.0.iter().map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero())).collect::<Vec<_>>();
+        assert!(check_sha256_block(&opcodes, &group).is_ok());
+
+        // a new flag that is not a bit
+        let mut new = group.clone();
+        new[0].0.0 = Fr::from(2u64);
+        assert!(check_sha256_block(&opcodes, &new).is_err());
+
+        // the words of the block and of the result swapped
+        let mut swapped = group.clone();
+        swapped[super::NEW_SIZE].0.1 = opcodes[2];
+        assert!(check_sha256_block(&opcodes, &swapped).is_err());
+    }
+}
@@ -1,9 +1,12 @@
-/*
-use crate::utils::{
-    field_to_bn,
-    bn_to_field,
+use crate::utils::{bn_to_field, field_to_bn};
+use crate::host::jubjub::{
+    A_BIG,
+    D_BIG,
+    Q,
+    SUBGROUP_ORDER,
+    test_bit,
 };
-*/
+use num_bigint::BigUint;
 
 use crate::circuits::{
     CommonGateConfig,
@@ -21,6 +24,7 @@ use std::marker::PhantomData;
 
 pub struct AltJubChip<F:FieldExt> {
     pub config: CommonGateConfig,
+    subgroup_check: bool,
     state: JubState<F>,
     _marker: PhantomData<F>
 }
@@ -33,6 +37,66 @@ pub struct Point<F: FieldExt> {
     pub y: Limb<F>,
 }
 
+/// The number of 2 bits windows of the scalar in mul_scalar and mul_scalar_fixed_base,
+/// enough for the canonical representation of any element of F
+pub fn scalar_windows<F: FieldExt>() -> usize {
+    (field_to_bn(&-F::one()).bits() as usize + 1) / 2
+}
+
+/// The modulus of F, the bound of the scalar in mul_scalar
+pub fn scalar_modulus<F: FieldExt>() -> BigUint {
+    field_to_bn(&-F::one()) + 1u64
+}
+
+/// The multiples [0, 1, 2, 3] * 4^i * base of a fixed base for each 2 bits window i
+/// of the scalar (from the least significant window), see AltJubChip::fixed_base_table
+pub type FixedBaseTable<F> = Vec<[(F, F); 4]>;
+
+fn curve_a<F: FieldExt>() -> F {
+    bn_to_field(&A_BIG)
+}
+
+fn curve_d<F: FieldExt>() -> F {
+    bn_to_field(&D_BIG)
+}
+
+/// Affine addition on the values of the points (without constraints)
+fn add_value<F: FieldExt>(lhs: &(F, F), rhs: &(F, F)) -> (F, F) {
+    let x1x2 = lhs.0 * rhs.0;
+    let y1y2 = lhs.1 * rhs.1;
+    let lambda = curve_d::<F>() * x1x2 * y1y2;
+    let x3 = (lhs.0 * rhs.1 + lhs.1 * rhs.0) * (F::one() + lambda).invert().unwrap();
+    let y3 = (y1y2 - curve_a::<F>() * x1x2) * (F::one() - lambda).invert().unwrap();
+    (x3, y3)
+}
+
+/// Scalar multiplication on the values of the point (without constraints)
+fn mul_value<F: FieldExt>(p: &(F, F), n: &BigUint) -> (F, F) {
+    let mut r = (F::zero(), F::one());
+    let b = n.to_bytes_le();
+    for i in (0..n.bits() as usize).rev() {
+        r = add_value(&r, &r);
+        if test_bit(&b, i) {
+            r = add_value(&r, p);
+        }
+    }
+    r
+}
+
+/// (q - 1) / 2, a coordinate is negative if it is greater than it (see crate::host::jubjub::is_negative)
+fn half_modulus() -> BigUint {
+    (&*Q - 1u64) / 2u64
+}
+
+/// The x of the point with the given y and sign of x (without constraints)
+fn decompress_value<F: FieldExt>(y: F, sign: bool) -> Option<F> {
+    let yy = y * y;
+    let xx = (F::one() - yy) * (curve_a::<F>() - curve_d::<F>() * yy).invert().unwrap();
+    let x: F = Option::from(xx.sqrt())?;
+    let x = if (field_to_bn(&x) > half_modulus()) == sign { x } else { -x };
+    if (field_to_bn(&x) > half_modulus()) == sign { Some(x) } else { None }
+}
+
 pub struct JubState<F: FieldExt> {
     acc: Point<F>,
     default: Point<F>,
@@ -86,11 +150,20 @@ impl<F: FieldExt> AltJubChip<F> {
         };
         AltJubChip {
             config,
+            subgroup_check: false,
             state,
             _marker: PhantomData,
         }
     }
 
+    /// The chip also checks that the points of assign_incremental_msm are in the prime order subgroup
+    pub fn with_subgroup_check(config: CommonGateConfig) -> Self {
+        AltJubChip {
+            subgroup_check: true,
+            ..Self::new(config)
+        }
+    }
+
     pub fn initialize(
         &mut self,
         config: &CommonGateConfig,
@@ -110,6 +183,11 @@ impl<F: FieldExt> AltJubChip<F> {
         reset: &Limb<F>,
         result: &Point<F>,
     ) -> Result<(), Error> {
+        self.assert_on_curve(region, offset, point)?;
+        if self.subgroup_check {
+            self.assert_in_subgroup(region, offset, point)?;
+        }
+
         self.state.acc.x = self.config.select(
             region,
             &mut (),
@@ -170,7 +248,7 @@ impl<F: FieldExt> AltJubChip<F> {
     ) -> Result<Point<F>, Error> {
         /* lambda = dx1x2y1y2
          * x3 = (x1y2 + y1x2)/(1 + lambda)
-         * y3 = (y1y2 - x1x2)/(1 - lambda)
+         * y3 = (y1y2 - ax1x2)/(1 - lambda)
          */
         let x1x2 = lhs.x.value * rhs.x.value;
         let y1y2 = lhs.y.value * rhs.y.value;
@@ -185,7 +263,7 @@ impl<F: FieldExt> AltJubChip<F> {
             ],
             [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
             0
-        )?[4].clone();
+        )?[2].clone();
         let lambda2 = self.config.assign_line(region, &mut (), offset,
             [
                 Some(lhs.y.clone()),
@@ -197,19 +275,19 @@ impl<F: FieldExt> AltJubChip<F> {
             ],
             [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
             0
-        )?[4].clone();
+        )?[2].clone();
         let lambda = self.config.assign_line(region, &mut (), offset,
             [
                 Some(lambda1),
                 None,
                 None,
                 Some(lambda2),
-                Some(Limb::new(None, y1y2 * x1x2)),
+                Some(Limb::new(None, curve_d::<F>() * y1y2 * x1x2)),
                 None,
             ],
-            [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+            [None, None, None, None, Some(-F::one()), None, Some(curve_d()), None, None],
             0
-        )?[4].clone();
+        )?[2].clone();
 
         let x3_f = lhs.x.value * rhs.y.value + lhs.y.value * rhs.x.value;
         let x3s = self.config.assign_line(region, &mut (), offset,
@@ -221,7 +299,7 @@ impl<F: FieldExt> AltJubChip<F> {
                 Some(Limb::new(None, x3_f)),
                 None,
             ],
-            [None, None, None, None, Some(F::one()), None, Some(F::one()), Some(F::one()), None],
+            [None, None, None, None, Some(-F::one()), None, Some(F::one()), Some(F::one()), None],
             0
         )?[4].clone();
 
@@ -238,11 +316,11 @@ impl<F: FieldExt> AltJubChip<F> {
             ],
             [Some(F::one()), Some(-F::one()), None, None, None, None, Some(F::one()), None, None],
             0
-        )?[3].clone();
+        )?[0].clone();
 
 
 
-        let y3_f = lhs.y.value * rhs.y.value - lhs.x.value * rhs.x.value;
+        let y3_f = lhs.y.value * rhs.y.value - curve_a::<F>() * lhs.x.value * rhs.x.value;
         let y3s = self.config.assign_line(region, &mut (), offset,
             [
                 Some(lhs.y.clone()),
@@ -252,7 +330,7 @@ impl<F: FieldExt> AltJubChip<F> {
                 Some(Limb::new(None, y3_f)),
                 None,
             ],
-            [None, None, None, None, Some(-F::one()), None, Some(F::one()), Some(-F::one()), None],
+            [None, None, None, None, Some(-F::one()), None, Some(F::one()), Some(-curve_a::<F>()), None],
             0
         )?[4].clone();
 
@@ -269,19 +347,571 @@ impl<F: FieldExt> AltJubChip<F> {
             ],
             [Some(F::one()), Some(-F::one()), None, None, None, None, Some(-F::one()), None, None],
             0
-        )?[3].clone();
+        )?[0].clone();
         Ok(Point {x: x3, y: y3})
     }
 
+    /// constrain a * x^2 + y^2 = 1 + d * x^2 * y^2
+    pub fn assert_on_curve(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        point: &Point<F>,
+    ) -> Result<(), Error> {
+        let xx = self.config.assign_line(region, &mut (), offset,
+            [
+                Some(point.x.clone()),
+                None,
+                None,
+                Some(point.x.clone()),
+                Some(Limb::new(None, point.x.value * point.x.value)),
+                None,
+            ],
+            [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+            0
+        )?[2].clone();
+        let yy = self.config.assign_line(region, &mut (), offset,
+            [
+                Some(point.y.clone()),
+                None,
+                None,
+                Some(point.y.clone()),
+                Some(Limb::new(None, point.y.value * point.y.value)),
+                None,
+            ],
+            [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+            0
+        )?[2].clone();
+        self.config.assign_line(region, &mut (), offset,
+            [
+                Some(xx),
+                None,
+                None,
+                Some(yy),
+                None,
+                None,
+            ],
+            [Some(curve_a()), None, None, Some(F::one()), None, None, Some(-curve_d::<F>()), None, Some(-F::one())],
+            0
+        )?;
+        Ok(())
+    }
+
+    /// Cofactor cleared subgroup check: the point is 8 * q for a witness q on the curve,
+    /// since 8 * E is the subgroup of order SUBGROUP_ORDER.
+    pub fn assert_in_subgroup(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        point: &Point<F>,
+    ) -> Result<(), Error> {
+        let order = &*SUBGROUP_ORDER;
+        let inv = BigUint::from(8u64).modpow(&(order - 2u64), order);
+        let q = mul_value(&(point.x.value, point.y.value), &inv);
+        let q = self.config.assign_witness(region, &mut (), offset,
+            [
+                Some(Limb::new(None, q.0)),
+                Some(Limb::new(None, q.1)),
+                None,
+                None,
+                None,
+            ],
+            0
+        )?;
+        let mut q = Point { x: q[0].clone(), y: q[1].clone() };
+        self.assert_on_curve(region, offset, &q)?;
+        for _ in 0..3 {
+            q = self.add(region, offset, &q, &q)?;
+        }
+        region.constrain_equal(
+            point.x.cell.as_ref().unwrap().cell(),
+            q.x.cell.as_ref().unwrap().cell()
+        )?;
+        region.constrain_equal(
+            point.y.cell.as_ref().unwrap().cell(),
+            q.y.cell.as_ref().unwrap().cell()
+        )?;
+        Ok(())
+    }
+
+    /// constrain 0 <= value <= (q - 1) / 2 by checking that both value and
+    /// (q - 1) / 2 - value have 253 bits
+    fn assert_nonnegative(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        value: &Limb<F>,
+    ) -> Result<(), Error> {
+        let half = bn_to_field::<F>(&half_modulus());
+        let rem = self.config.assign_line(region, &mut (), offset,
+            [Some(value.clone()), None, None, None, Some(Limb::new(None, half - value.value)), None],
+            [Some(-F::one()), None, None, None, Some(-F::one()), None, None, None, Some(half)],
+            0
+        )?[1].clone();
+        for v in [value, &rem] {
+            let mut bits = vec![];
+            self.config.decompose_limb(region, &mut (), offset, v, &mut bits, 256)?;
+            // the three highest bits are zero
+            self.config.assign_line(region, &mut (), offset,
+                [Some(bits[0].clone()), Some(bits[1].clone()), Some(bits[2].clone()), None, None, None],
+                [Some(F::one()), Some(F::one()), Some(F::one()), None, None, None, None, None, None],
+                0
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The big endian bits (at most 255) are the ones of an integer less than the modulus:
+    /// eq is whether the bits so far are the ones of q - 1, a set bit where q - 1 has
+    /// a zero bit is then copied to zero (eq * bit = 0) and eq_next = eq * bit otherwise.
+    fn assert_canonical(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        bits: &[Limb<F>],
+    ) -> Result<(), Error> {
+        let max = (&*Q - 1u64).to_bytes_le();
+        let mut eq = self.config.assign_constant(region, &mut (), offset, &F::one())?;
+        let zero = self.config.assign_constant(region, &mut (), offset, &F::zero())?;
+        for (i, bit) in bits.iter().enumerate() {
+            let set = test_bit(&max, bits.len() - 1 - i);
+            let l = self.config.assign_line(region, &mut (), offset,
+                [Some(eq.clone()), None, None, Some(bit.clone()), Some(Limb::new(None, eq.value * bit.value)), None],
+                [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+                0
+            )?;
+            if set {
+                eq = l[2].clone();
+            } else {
+                region.constrain_equal(
+                    l[2].cell.as_ref().unwrap().cell(),
+                    zero.cell.as_ref().unwrap().cell()
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decompress the 32 bytes encoding of crate::host::jubjub::Point::compress given as
+    /// low (the merge of its first three u64) and high (its last u64 with the sign of x in the highest bit).
+    /// As in crate::host::jubjub::Point::decompress the encoding of y must be canonical (y < q),
+    /// see crate::adaptor::eddsaadaptor::EddsaCompressedChip for the host calls of compressed keys.
+    pub fn decompress(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        low: &Limb<F>,
+        high: &Limb<F>,
+    ) -> Result<Point<F>, Error> {
+        // the big endian bits of high then low, which also range check them
+        let mut bits = vec![];
+        self.config.decompose_limb(region, &mut (), offset, high, &mut bits, 64)?;
+        self.config.decompose_limb(region, &mut (), offset, low, &mut bits, 192)?;
+        let sign = bits[0].clone();
+        self.assert_canonical(region, offset, &bits[1..])?;
+
+        // y = low + (high - sign * 2^63) * 2^192
+        let shift = F::from_u128(1u128 << 96) * F::from_u128(1u128 << 96);
+        let sign_shift = F::from_u128(1u128 << 63) * shift;
+        let y = self.config.assign_line(region, &mut (), offset,
+            [
+                Some(low.clone()),
+                Some(high.clone()),
+                Some(sign.clone()),
+                None,
+                Some(Limb::new(None, low.value + high.value * shift - sign.value * sign_shift)),
+                None,
+            ],
+            [Some(F::one()), Some(shift), Some(-sign_shift), None, Some(-F::one()), None, None, None, None],
+            0
+        )?[3].clone();
+
+        let x = decompress_value(y.value, sign.value == F::one()).ok_or(Error::Synthesis)?;
+        let x = self.config.assign_witness(region, &mut (), offset,
+            [Some(Limb::new(None, x)), None, None, None, None],
+            0
+        )?[0].clone();
+        let point = Point { x, y };
+        self.assert_on_curve(region, offset, &point)?;
+
+        // sign -> x != 0: sign * x * inv = sign, otherwise x = 0 would pass below as (q - 1) / 2
+        let inv = point.x.value.invert().unwrap_or(F::zero());
+        let u = self.config.assign_line(region, &mut (), offset,
+            [
+                Some(point.x.clone()),
+                None,
+                None,
+                Some(Limb::new(None, inv)),
+                Some(Limb::new(None, point.x.value * inv)),
+                None,
+            ],
+            [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+            0
+        )?[2].clone();
+        self.config.assign_line(region, &mut (), offset,
+            [Some(sign.clone()), None, None, Some(u), None, None],
+            [Some(-F::one()), None, None, None, None, None, Some(F::one()), None, None],
+            0
+        )?;
+
+        // x - sign * ((q - 1) / 2 + 1) is in [0, (q - 1) / 2]
+        let half_one = bn_to_field::<F>(&half_modulus()) + F::one();
+        let t = self.config.assign_line(region, &mut (), offset,
+            [
+                Some(point.x.clone()),
+                Some(sign.clone()),
+                None,
+                None,
+                Some(Limb::new(None, point.x.value - sign.value * half_one)),
+                None,
+            ],
+            [Some(F::one()), Some(-half_one), None, None, Some(-F::one()), None, None, None, None],
+            0
+        )?[2].clone();
+        self.assert_nonnegative(region, offset, &t)?;
+        Ok(point)
+    }
+
+    /// The indicators of the 2 bits windows of the scalar in big endian. The windows are
+    /// constrained to be in [0, 4) and to represent an integer not greater than bound - 1,
+    /// with bound at most the modulus the decomposition of the scalar is then canonical.
+    fn scalar_windows(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        scalar: &Limb<F>,
+        bound: &BigUint,
+    ) -> Result<Vec<Vec<Limb<F>>>, Error> {
+        assert!(*bound <= scalar_modulus::<F>());
+        let size = scalar_windows::<F>();
+        let mut windows = vec![];
+        self.config.decompose_windows(region, &mut (), offset, scalar, &mut windows, size * 2, 2)?;
+        let max = bound - 1u64;
+        // eq: the windows so far are equal to the ones of max
+        let mut eq = self.config.assign_constant(region, &mut (), offset, &F::one())?;
+        let zero = self.config.assign_constant(region, &mut (), offset, &F::zero())?;
+        let mut indicators = vec![];
+        for (i, window) in windows.iter().enumerate() {
+            // exactly one indicator is set which also bounds the window (not looked up with `()`)
+            let e = self.config.window_indicators(region, &mut (), offset, window, 4)?;
+            let m = ((&max >> (2 * (size - 1 - i))) % 4u64).to_u64_digits().first().map_or(0, |x| *x) as usize;
+            // eq * e_j = 0 for j > m, the sum is copied to zero so that a scalar
+            // not less than bound fails in the prover instead of while assigning
+            for greater in e[m + 1..].chunks(2) {
+                let v = greater.iter().fold(F::zero(), |acc, e| acc + eq.value * e.value);
+                let l = self.config.assign_line(region, &mut (), offset,
+                    [Some(eq.clone()), greater.get(1).map(|_| eq.clone()), greater.get(1).cloned(), Some(greater[0].clone()), Some(Limb::new(None, v)), None],
+                    [None, None, None, None, Some(-F::one()), None, Some(F::one()), greater.get(1).map(|_| F::one()), None],
+                    0
+                )?;
+                region.constrain_equal(
+                    l.last().unwrap().cell.as_ref().unwrap().cell(),
+                    zero.cell.as_ref().unwrap().cell()
+                )?;
+            }
+            // eq_next = eq * e_m
+            eq = self.config.assign_line(region, &mut (), offset,
+                [Some(eq.clone()), None, None, Some(e[m].clone()), Some(Limb::new(None, eq.value * e[m].value)), None],
+                [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+                0
+            )?[2].clone();
+            indicators.push(e);
+        }
+        Ok(indicators)
+    }
+
+    /// lhs * rhs with windows of 2 bits: the multiples [0, 1, 2, 3] * rhs are computed once
+    /// and the one of the window is added after every two doublings.
     pub fn mul_scalar(
         &self,
-        _region: &mut Region<F>,
-        _offset: &mut usize,
-        _lhs: &Limb<F>,
-        _rhs: &Point<F>,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Point<F>,
     ) -> Result<Point<F>, Error> {
-        todo!()
+        let windows = self.scalar_windows(region, offset, lhs, &scalar_modulus::<F>())?;
+        let zero = self.config.assign_constant(region, &mut (), offset, &F::zero())?;
+        let one = self.config.assign_constant(region, &mut (), offset, &F::one())?;
+        let identity = Point { x: zero, y: one };
+        let double = self.add(region, offset, rhs, rhs)?;
+        let triple = self.add(region, offset, &double, rhs)?;
+        let multiples = [identity.clone(), rhs.clone(), double, triple];
+        let xs = multiples.iter().map(|p| p.x.clone()).collect::<Vec<_>>();
+        let ys = multiples.iter().map(|p| p.y.clone()).collect::<Vec<_>>();
+        let mut acc = identity;
+        for indicators in windows.iter() {
+            acc = self.add(region, offset, &acc, &acc)?;
+            acc = self.add(region, offset, &acc, &acc)?;
+            let x = self.config.select_by_indicators(region, &mut (), offset, indicators, &xs)?;
+            let y = self.config.select_by_indicators(region, &mut (), offset, indicators, &ys)?;
+            acc = self.add(region, offset, &acc, &Point { x, y })?;
+        }
+        Ok(acc)
+    }
+
+    /// Precompute the table of a fixed base for mul_scalar_fixed_base
+    pub fn fixed_base_table(base: &(F, F)) -> FixedBaseTable<F> {
+        let identity = (F::zero(), F::one());
+        let mut b = base.clone();
+        let mut table = vec![];
+        for _ in 0..scalar_windows::<F>() {
+            let double = add_value(&b, &b);
+            let triple = add_value(&double, &b);
+            table.push([identity, b, double, triple]);
+            b = add_value(&double, &double);
+        }
+        table
+    }
+
+    /// scalar * base where the multiples of the base are the constants of the table
+    /// and the scalar is constrained to be less than bound (at most the modulus).
+    /// The multiple of each 2 bits window is the sum of the table entries of the window
+    /// weighted by its indicators in one line per coordinate,
+    /// thus only one addition is needed per window.
+    pub fn mul_scalar_fixed_base(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        scalar: &Limb<F>,
+        table: &FixedBaseTable<F>,
+        bound: &BigUint,
+    ) -> Result<Point<F>, Error> {
+        assert!(table.len() == scalar_windows::<F>());
+        let windows = self.scalar_windows(region, offset, scalar, bound)?;
+        let mut acc: Option<Point<F>> = None;
+        for (i, e) in windows.iter().rev().enumerate() {
+            let mut coords = vec![];
+            for c in 0..2 {
+                let t = table[i].map(|p| if c == 0 { p.0 } else { p.1 });
+                let v = e.iter().zip(t.iter()).fold(F::zero(), |acc, (e, t)| acc + e.value * t);
+                let l = self.config.assign_line(region, &mut (), offset,
+                    [
+                        Some(e[0].clone()),
+                        Some(e[1].clone()),
+                        Some(e[2].clone()),
+                        Some(e[3].clone()),
+                        Some(Limb::new(None, v)),
+                        None,
+                    ],
+                    [
+                        Some(t[0]),
+                        Some(t[1]),
+                        Some(t[2]),
+                        Some(t[3]),
+                        Some(-F::one()),
+                        None,
+                        None,
+                        None,
+                        None,
+                    ],
+                    0
+                )?;
+                coords.push(l[4].clone());
+            }
+            let operand = Point { x: coords[0].clone(), y: coords[1].clone() };
+            acc = Some(match acc {
+                None => operand,
+                Some(acc) => self.add(region, offset, &acc, &operand)?,
+            });
+        }
+        Ok(acc.unwrap())
     }
 
 }
 
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+    use ff::Field;
+    use num_bigint::BigUint;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{
+            Circuit, ConstraintSystem, Error,
+        },
+    };
+
+    use super::{AltJubChip, Point, scalar_modulus};
+    use crate::circuits::CommonGateConfig;
+    use crate::host::jubjub;
+    use crate::host::eddsa::BASE;
+    use crate::utils::{bn_to_field, field_to_bn};
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        scalar: Fr,
+        point: (Fr, Fr),
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = CommonGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            AltJubChip::<Fr>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = AltJubChip::<Fr>::new(config.clone());
+            let expected = jubjub::Point { x: self.point.0, y: self.point.1 }
+                .mul_scalar(&field_to_bn(&self.scalar));
+            layouter.assign_region(
+                || "jubjub mul scalar",
+                |mut region| {
+                    let mut offset = 0;
+                    let scalar = config.assign_constant(&mut region, &mut (), &mut offset, &self.scalar)?;
+                    let point = Point {
+                        x: config.assign_constant(&mut region, &mut (), &mut offset, &self.point.0)?,
+                        y: config.assign_constant(&mut region, &mut (), &mut offset, &self.point.1)?,
+                    };
+                    let r = chip.mul_scalar(&mut region, &mut offset, &scalar, &point)?;
+                    assert_eq!((r.x.value, r.y.value), (expected.x, expected.y));
+
+                    let table = AltJubChip::<Fr>::fixed_base_table(&self.point);
+                    let r = chip.mul_scalar_fixed_base(&mut region, &mut offset, &scalar, &table, &scalar_modulus::<Fr>())?;
+                    assert_eq!((r.x.value, r.y.value), (expected.x, expected.y));
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct SubgroupCircuit {
+        point: (Fr, Fr),
+    }
+
+    impl Circuit<Fr> for SubgroupCircuit {
+        type Config = CommonGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            AltJubChip::<Fr>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = AltJubChip::<Fr>::with_subgroup_check(config.clone());
+            layouter.assign_region(
+                || "jubjub subgroup check",
+                |mut region| {
+                    let mut offset = 0;
+                    let point = Point {
+                        x: config.assign_constant(&mut region, &mut (), &mut offset, &self.point.0)?,
+                        y: config.assign_constant(&mut region, &mut (), &mut offset, &self.point.1)?,
+                    };
+                    chip.assert_on_curve(&mut region, &mut offset, &point)?;
+                    chip.assert_in_subgroup(&mut region, &mut offset, &point)?;
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_jubjub_subgroup_check() {
+        let point = (BASE.x, BASE.y);
+        let prover = MockProver::run(10, &SubgroupCircuit { point }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // (0, -1) is on the curve and has order 2
+        let point = (Fr::zero(), -Fr::one());
+        let prover = MockProver::run(10, &SubgroupCircuit { point }, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct DecompressCircuit {
+        encodings: Vec<[u8; 32]>,
+    }
+
+    impl Circuit<Fr> for DecompressCircuit {
+        type Config = CommonGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            AltJubChip::<Fr>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = AltJubChip::<Fr>::new(config.clone());
+            layouter.assign_region(
+                || "jubjub decompress",
+                |mut region| {
+                    let mut offset = 0;
+                    for bytes in self.encodings.iter() {
+                        let low = bn_to_field(&BigUint::from_bytes_le(&bytes[0..24]));
+                        let high = bn_to_field(&BigUint::from_bytes_le(&bytes[24..32]));
+                        let low = config.assign_constant(&mut region, &mut (), &mut offset, &low)?;
+                        let high = config.assign_constant(&mut region, &mut (), &mut offset, &high)?;
+                        let p = chip.decompress(&mut region, &mut offset, &low, &high)?;
+                        if let Some(point) = jubjub::Point::decompress(bytes) {
+                            assert_eq!((p.x.value, p.y.value), (point.x, point.y));
+                        }
+                    }
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_jubjub_decompress() {
+        let (x, y) = (BASE.x, BASE.y);
+        let encodings = [(x, y), (-x, y), (Fr::zero(), Fr::one())].iter()
+            .map(|(x, y)| jubjub::Point { x: *x, y: *y }.compress())
+            .collect();
+        let test_circuit = DecompressCircuit { encodings };
+        let prover = MockProver::run(13, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the identity with y + q instead of y
+        let mut y = (BigUint::from(1u64) + &*jubjub::Q).to_bytes_le();
+        y.resize(32, 0);
+        let test_circuit = DecompressCircuit { encodings: vec![y.try_into().unwrap()] };
+        let prover = MockProver::run(13, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_jubjub_mul_scalar() {
+        let point = (BASE.x, BASE.y);
+        let scalar = bn_to_field(&BigUint::parse_bytes(b"1902101563350775171813864964289368622061698554691074493911860015574812994359", 10).unwrap());
+        let test_circuit = TestCircuit { scalar, point };
+        let prover = MockProver::run(14, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the largest canonical scalar
+        let test_circuit = TestCircuit { scalar: -Fr::one(), point };
+        let prover = MockProver::run(14, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
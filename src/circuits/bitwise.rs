@@ -0,0 +1,673 @@
+use crate::utils::{
+    field_to_u64,
+    GateCell,
+    Limb,
+};
+use crate::circuits::CommonGateConfig;
+use crate::{
+    customized_circuits,
+    table_item,
+    item_count,
+    customized_circuits_expand,
+    constant_from,
+    value_for_assign,
+};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::Region,
+    plonk::{
+        Fixed, Advice, Column, ConstraintSystem,
+        Error, Expression, Selector, VirtualCells
+    },
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+/*
+ * Lookup table of bitwise operations over operands of BITS bits.
+ * Each line is (op, a, b, op(a, b)) and the first line is all zero so that
+ * a lookup with all its expressions multiplied by a disabled selector always succeeds.
+ * NOT is linear over BITS bits (mask - a) thus it does not need a table.
+ */
+customized_circuits!(BitwiseTableConfig, 1, 0, 4, 0,
+   | op   |  a   | b     | c
+);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitwiseOp {
+    Xor = 1,
+    And = 2,
+    Or = 3,
+}
+
+impl BitwiseOp {
+    pub fn eval(&self, a: u64, b: u64) -> u64 {
+        match self {
+            BitwiseOp::Xor => a ^ b,
+            BitwiseOp::And => a & b,
+            BitwiseOp::Or => a | b,
+        }
+    }
+
+    pub fn expr<F: FieldExt>(&self) -> Expression<F> {
+        constant_from!(*self as u64)
+    }
+}
+
+impl BitwiseTableConfig {
+    /// register a lookup [op, a, b, c] which requires c = op(a, b) for operands of BITS bits
+    pub fn register<F: FieldExt> (
+        &self,
+        cs: &mut ConstraintSystem<F>,
+        name: &'static str,
+        lookup: impl FnOnce(&mut VirtualCells<F>) -> [Expression<F>; 4],
+    ) {
+        cs.lookup_any(name, |meta| {
+            let table = [
+                self.get_expr(meta, BitwiseTableConfig::op()),
+                self.get_expr(meta, BitwiseTableConfig::a()),
+                self.get_expr(meta, BitwiseTableConfig::b()),
+                self.get_expr(meta, BitwiseTableConfig::c()),
+            ];
+            lookup(meta).into_iter().zip(table.into_iter()).collect()
+        });
+    }
+
+    /// register a lookup which requires value to have at most BITS bits when sel is set,
+    /// this is the line (And, value, value, value) of the table
+    pub fn register_range<F: FieldExt> (
+        &self,
+        cs: &mut ConstraintSystem<F>,
+        name: &'static str,
+        lookup: impl FnOnce(&mut VirtualCells<F>) -> (Expression<F>, Expression<F>),
+    ) {
+        self.register(cs, name, |meta| {
+            let (sel, value) = lookup(meta);
+            let value = value * sel.clone();
+            [BitwiseOp::And.expr() * sel, value.clone(), value.clone(), value]
+        });
+    }
+}
+
+/// The default operands are bytes, the table then has 3 * 2^16 + 1 = 196609 lines
+/// which fits in the k = 22 of the host circuits (k >= 18 is required).
+pub struct BitwiseTableChip<F:FieldExt, const BITS: usize = 8> {
+    config: BitwiseTableConfig,
+    _marker: PhantomData<F>
+}
+
+impl<F: FieldExt, const BITS: usize> BitwiseTableChip<F, BITS> {
+    pub fn new(config: BitwiseTableConfig) -> Self {
+        BitwiseTableChip {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<F>) -> BitwiseTableConfig {
+        let witness = [];
+        let fixed = [0; 4].map(|_| cs.fixed_column());
+        let selector =[];
+        BitwiseTableConfig { fixed, selector, witness }
+    }
+
+    /// the number of lines of the table (including the zero line)
+    pub fn table_size() -> usize {
+        3 * (1 << (2 * BITS)) + 1
+    }
+
+    /// fill the table, initialize needs to be called before using any registered lookup
+    pub fn initialize(
+        &self,
+        region: &mut Region<F>,
+    ) -> Result<(), Error> {
+        let mut offset = 0;
+        for cell in [BitwiseTableConfig::op(), BitwiseTableConfig::a(), BitwiseTableConfig::b(), BitwiseTableConfig::c()] {
+            self.config.assign_cell(region, offset, &cell, F::zero())?;
+        }
+        offset += 1;
+        for op in [BitwiseOp::Xor, BitwiseOp::And, BitwiseOp::Or] {
+            for a in 0..(1u64 << BITS) {
+                for b in 0..(1u64 << BITS) {
+                    self.config.assign_cell(region, offset, &BitwiseTableConfig::op(), F::from(op as u64))?;
+                    self.config.assign_cell(region, offset, &BitwiseTableConfig::a(), F::from(a))?;
+                    self.config.assign_cell(region, offset, &BitwiseTableConfig::b(), F::from(b))?;
+                    self.config.assign_cell(region, offset, &BitwiseTableConfig::c(), F::from(op.eval(a, b)))?;
+                    offset += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the mask of BITS bits for NOT (mask - a)
+    pub fn mask() -> u64 {
+        (1u64 << BITS) - 1
+    }
+}
+
+
+/*
+ * A line which looks up one bitwise operation (op, a, b, c) of bytes in the table,
+ * the operands are copied from the cells of the words. An unused line is all zero
+ * which is the zero line of the table.
+ */
+customized_circuits!(BitwiseLineConfig, 1, 3, 1, 0,
+   | a   |  b   | c    | op
+);
+
+/// A word of bytes in little endian
+pub type Word<F> = Vec<Limb<F>>;
+
+#[derive(Clone, Debug)]
+pub struct BitwiseWordConfig {
+    pub common: CommonGateConfig,
+    line: BitwiseLineConfig,
+    table: BitwiseTableConfig,
+}
+
+/*
+ * Operations over words of bytes for the ARX hash chips:
+ * the bitwise operations and the range checks are looked up byte by byte in the 8-bit table,
+ * the sums and the recompositions of the words are lines of the common gate.
+ * The lookup lines have their own offset thus they are laid out next to the lines of
+ * the common gate in the same region.
+ */
+pub struct BitwiseWordChip<F: FieldExt> {
+    pub config: BitwiseWordConfig,
+    line_offset: usize,
+    bytes: Vec<Limb<F>>,
+    _marker: PhantomData<F>
+}
+
+impl<F: FieldExt> BitwiseWordChip<F> {
+    pub fn new(config: BitwiseWordConfig) -> Self {
+        BitwiseWordChip {
+            config,
+            line_offset: 0,
+            bytes: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<F>) -> BitwiseWordConfig {
+        let table = BitwiseTableChip::<F, 8>::configure(cs);
+        let common = CommonGateConfig::configure(cs, &());
+        let witness = [0; 3].map(|_| cs.advice_column());
+        witness.map(|x| cs.enable_equality(x));
+        let fixed = [0; 1].map(|_| cs.fixed_column());
+        let selector = [];
+        let line = BitwiseLineConfig::new(witness, fixed, selector);
+        table.register(cs, "bitwise word byte", |meta| [
+            line.get_expr(meta, BitwiseLineConfig::op()),
+            line.get_expr(meta, BitwiseLineConfig::a()),
+            line.get_expr(meta, BitwiseLineConfig::b()),
+            line.get_expr(meta, BitwiseLineConfig::c()),
+        ]);
+        BitwiseWordConfig { common, line, table }
+    }
+
+    /// fill the table and assign the constant bytes, needs to be called before any operation
+    pub fn initialize(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        BitwiseTableChip::<F, 8>::new(self.config.table.clone()).initialize(region)?;
+        self.line_offset = 0;
+        self.bytes = vec![];
+        for v in 0..256u64 {
+            let byte = self.config.common.assign_constant(region, &mut (), offset, &F::from(v))?;
+            self.bytes.push(byte);
+        }
+        Ok(())
+    }
+
+    /// the constant word of the given number of bytes
+    pub fn constant_word(&self, v: u64, bytes: usize) -> Word<F> {
+        (0..bytes).map(|i| self.bytes[((v >> (8 * i)) & 0xff) as usize].clone()).collect()
+    }
+
+    fn assign_op(
+        &mut self,
+        region: &mut Region<F>,
+        op: BitwiseOp,
+        a: &Limb<F>,
+        b: &Limb<F>,
+        c: F,
+    ) -> Result<[Limb<F>; 3], Error> {
+        let offset = self.line_offset;
+        self.config.line.assign_cell(region, offset, &BitwiseLineConfig::op(), F::from(op as u64))?;
+        let a = self.config.line.bind_cell(region, offset, &BitwiseLineConfig::a(), a)?;
+        let b = self.config.line.bind_cell(region, offset, &BitwiseLineConfig::b(), b)?;
+        let c = self.config.line.assign_cell(region, offset, &BitwiseLineConfig::c(), c)?;
+        self.line_offset += 1;
+        Ok([a, b, c])
+    }
+
+    /// op(a, b) of two bytes
+    pub fn op(
+        &mut self,
+        region: &mut Region<F>,
+        op: BitwiseOp,
+        a: &Limb<F>,
+        b: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        let c = F::from(op.eval(field_to_u64(&a.value), field_to_u64(&b.value)));
+        Ok(self.assign_op(region, op, a, b, c)?[2].clone())
+    }
+
+    /// constrain value to be less than 2^bits (bits <= 8): value & mask = value,
+    /// a value without cell is assigned
+    pub fn assign_bits(
+        &mut self,
+        region: &mut Region<F>,
+        value: &Limb<F>,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        let mask = self.bytes[(1 << bits) - 1].clone();
+        let [a, _, c] = self.assign_op(region, BitwiseOp::And, value, &mask, value.value)?;
+        region.constrain_equal(a.get_the_cell().cell(), c.get_the_cell().cell())?;
+        Ok(a)
+    }
+
+    fn word_op(
+        &mut self,
+        region: &mut Region<F>,
+        op: BitwiseOp,
+        a: &Word<F>,
+        b: &Word<F>,
+    ) -> Result<Word<F>, Error> {
+        let mut r = vec![];
+        for (x, y) in a.iter().zip(b.iter()) {
+            r.push(self.op(region, op, x, y)?);
+        }
+        Ok(r)
+    }
+
+    pub fn xor(&mut self, region: &mut Region<F>, a: &Word<F>, b: &Word<F>) -> Result<Word<F>, Error> {
+        self.word_op(region, BitwiseOp::Xor, a, b)
+    }
+
+    pub fn and(&mut self, region: &mut Region<F>, a: &Word<F>, b: &Word<F>) -> Result<Word<F>, Error> {
+        self.word_op(region, BitwiseOp::And, a, b)
+    }
+
+    pub fn or(&mut self, region: &mut Region<F>, a: &Word<F>, b: &Word<F>) -> Result<Word<F>, Error> {
+        self.word_op(region, BitwiseOp::Or, a, b)
+    }
+
+    /// the value of a word
+    pub fn compose(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        word: &Word<F>,
+    ) -> Result<Limb<F>, Error> {
+        let bytes = word.iter()
+            .enumerate()
+            .map(|(i, byte)| (byte, F::from_u128(1u128 << (8 * i))))
+            .collect::<Vec<_>>();
+        self.config.common.sum_with_constant(region, &mut (), offset, bytes, None)
+    }
+
+    /// decompose a limb into a word of the given number of bytes (at most 8)
+    pub fn decompose(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        limb: &Limb<F>,
+        bytes: usize,
+    ) -> Result<Word<F>, Error> {
+        let v = field_to_u64(&limb.value);
+        let mut word = vec![];
+        for i in 0..bytes {
+            let byte = Limb::new(None, F::from((v >> (8 * i)) & 0xff));
+            word.push(self.assign_bits(region, &byte, 8)?);
+        }
+        let sum = self.compose(region, offset, &word)?;
+        region.constrain_equal(sum.get_the_cell().cell(), limb.get_the_cell().cell())?;
+        Ok(word)
+    }
+
+    /// the sum of the words modulo 2^(8 * bytes)
+    pub fn add(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        words: Vec<&Word<F>>,
+    ) -> Result<Word<F>, Error> {
+        let bytes = words[0].len();
+        let sum = words.iter().fold(0u128, |acc, word| {
+            word.iter().enumerate().fold(acc, |acc, (i, byte)| acc + ((field_to_u64(&byte.value) as u128) << (8 * i)))
+        });
+        let mut r = vec![];
+        for i in 0..bytes {
+            let byte = Limb::new(None, F::from(((sum >> (8 * i)) & 0xff) as u64));
+            r.push(self.assign_bits(region, &byte, 8)?);
+        }
+        // the carry is less than the number of words
+        let carry_bits = (usize::BITS - (words.len() - 1).leading_zeros()) as usize;
+        let carry = Limb::new(None, F::from((sum >> (8 * bytes)) as u64));
+        let carry = self.assign_bits(region, &carry, carry_bits)?;
+
+        let mut inputs = vec![];
+        for word in words.iter() {
+            for (i, byte) in word.iter().enumerate() {
+                inputs.push((byte, F::from_u128(1u128 << (8 * i))));
+            }
+        }
+        for (i, byte) in r.iter().enumerate() {
+            inputs.push((byte, -F::from_u128(1u128 << (8 * i))));
+        }
+        inputs.push((&carry, -F::from_u128(1u128 << (8 * bytes))));
+        let zero = self.config.common.sum_with_constant(region, &mut (), offset, inputs, None)?;
+        region.constrain_equal(zero.get_the_cell().cell(), self.bytes[0].get_the_cell().cell())?;
+        Ok(r)
+    }
+
+    /// split a byte at bit s: byte = h * 2^s + l
+    fn split(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        byte: &Limb<F>,
+        s: usize,
+    ) -> Result<(Limb<F>, Limb<F>), Error> {
+        let v = field_to_u64(&byte.value);
+        let h = self.assign_bits(region, &Limb::new(None, F::from(v >> s)), 8 - s)?;
+        let l = self.assign_bits(region, &Limb::new(None, F::from(v & ((1 << s) - 1))), s)?;
+        self.config.common.assign_line(region, &mut (), offset,
+            [Some(byte.clone()), Some(h.clone()), Some(l.clone()), None, None, None],
+            [Some(F::one()), Some(-F::from(1u64 << s)), Some(-F::one()), None, None, None, None, None, None],
+            0,
+        )?;
+        Ok((h, l))
+    }
+
+    /// Shift (or rotate) a word right by r bits, the byte j of the result is made of the high
+    /// bits of the byte j + r / 8 and the low bits of the next byte.
+    fn shift(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        word: &Word<F>,
+        r: usize,
+        rotate: bool,
+    ) -> Result<Word<F>, Error> {
+        let n = word.len();
+        let (q, s) = (r / 8, r % 8);
+        let source = |i: usize| if rotate { Some(i % n) } else if i < n { Some(i) } else { None };
+        if s == 0 {
+            return Ok((0..n).map(|j| {
+                source(j + q).map_or(self.bytes[0].clone(), |i| word[i].clone())
+            }).collect());
+        }
+        let mut parts = vec![None; n];
+        for i in (if rotate { 0 } else { q })..n {
+            parts[i] = Some(self.split(region, offset, &word[i], s)?);
+        }
+        let mut result = vec![];
+        for j in 0..n {
+            let h = source(j + q).map(|i| parts[i].clone().unwrap().0);
+            let l = source(j + q + 1).map(|i| parts[i].clone().unwrap().1);
+            let byte = match (h, l) {
+                (Some(h), Some(l)) => {
+                    let v = h.value + l.value * F::from(1u64 << (8 - s));
+                    self.config.common.assign_line(region, &mut (), offset,
+                        [Some(h), Some(l), None, None, Some(Limb::new(None, v)), None],
+                        [Some(F::one()), Some(F::from(1u64 << (8 - s))), None, None, Some(-F::one()), None, None, None, None],
+                        0,
+                    )?[2].clone()
+                },
+                (Some(h), None) => h,
+                _ => self.bytes[0].clone(),
+            };
+            result.push(byte);
+        }
+        Ok(result)
+    }
+
+    pub fn rotate_right(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        word: &Word<F>,
+        r: usize,
+    ) -> Result<Word<F>, Error> {
+        self.shift(region, offset, word, r, true)
+    }
+
+    pub fn shift_right(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        word: &Word<F>,
+        r: usize,
+    ) -> Result<Word<F>, Error> {
+        self.shift(region, offset, word, r, false)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+
+    use halo2_proofs::{
+        circuit::{Chip, Layouter, Region, SimpleFloorPlanner},
+        plonk::{
+            Advice, Circuit, Column, ConstraintSystem, Error, VirtualCells,
+            Expression
+        },
+        poly::Rotation,
+    };
+
+    use super::{
+        BitwiseOp,
+        BitwiseTableChip,
+        BitwiseTableConfig,
+        BitwiseWordChip,
+        BitwiseWordConfig,
+    };
+    use crate::value_for_assign;
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChipConfig {
+        limbs: [Column<Advice>; 4]
+    }
+
+    impl HelperChipConfig {
+        pub fn lookup_columns (&self, cs: &mut VirtualCells<Fr>) -> [Expression<Fr>; 4] {
+            self.limbs.map(|c| cs.query_advice(c, Rotation::cur()))
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChip {
+        config: HelperChipConfig
+    }
+
+    impl Chip<Fr> for HelperChip {
+        type Config = HelperChipConfig;
+        type Loaded = ();
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+
+        fn loaded(&self) -> &Self::Loaded {
+            &()
+        }
+    }
+
+    impl HelperChip {
+        fn new(config: HelperChipConfig) -> Self {
+            HelperChip{
+                config,
+            }
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> HelperChipConfig {
+            let limbs = [0; 4].map(|_| cs.advice_column());
+            HelperChipConfig {
+                limbs,
+            }
+        }
+
+        fn assign_op(
+            &self,
+            region: &mut Region<Fr>,
+            offset: &mut usize,
+            values: [u64; 4],
+        ) -> Result<(), Error> {
+            for i in 0..4 {
+                region.assign_advice(
+                    || format!("assign input"),
+                    self.config.limbs[i],
+                    *offset,
+                    || value_for_assign!(Fr::from(values[i]))
+                )?;
+            }
+            *offset = *offset + 1;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        ops: Vec<[u64; 4]>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        bitwiseconfig: BitwiseTableConfig,
+        helperconfig: HelperChipConfig,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let bitwiseconfig = BitwiseTableChip::<Fr, 4>::configure(meta);
+            let helperconfig = HelperChip::configure(meta);
+
+            bitwiseconfig.register(
+                meta,
+                "helper bitwise op",
+                |c| helperconfig.lookup_columns(c),
+            );
+
+            Self::Config {
+               bitwiseconfig,
+               helperconfig,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let bitwise_chip = BitwiseTableChip::<Fr, 4>::new(config.clone().bitwiseconfig);
+            let helper_chip = HelperChip::new(config.clone().helperconfig);
+            layouter.assign_region(
+                || "bitwise test",
+                |mut region| {
+                    bitwise_chip.initialize(&mut region)?;
+                    let mut offset = 0;
+                    for op in self.ops.iter() {
+                        helper_chip.assign_op(&mut region, &mut offset, *op)?;
+                    }
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bitwise_circuit() {
+        let ops = [BitwiseOp::Xor, BitwiseOp::And, BitwiseOp::Or].iter().map(|op| {
+            [*op as u64, 0b1010, 0b0110, op.eval(0b1010, 0b0110)]
+        }).collect();
+        let test_circuit = TestCircuit { ops };
+        let prover = MockProver::run(10, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_bitwise_circuit_fail() {
+        let test_circuit = TestCircuit { ops: vec![[BitwiseOp::Xor as u64, 0b1010, 0b0110, 0b1111]] };
+        let prover = MockProver::run(10, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    /// the words a and b with (a >>> r, a >> r, a + b, a ^ b) for r in 1..32
+    #[derive(Clone, Debug, Default)]
+    struct WordCircuit {
+        a: u32,
+        b: u32,
+    }
+
+    impl Circuit<Fr> for WordCircuit {
+        type Config = BitwiseWordConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            BitwiseWordChip::<Fr>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut chip = BitwiseWordChip::<Fr>::new(config.clone());
+            layouter.assign_region(
+                || "bitwise word test",
+                |mut region| {
+                    let mut offset = 0;
+                    chip.initialize(&mut region, &mut offset)?;
+                    let a = config.common.assign_constant(&mut region, &mut (), &mut offset, &Fr::from(self.a as u64))?;
+                    let b = config.common.assign_constant(&mut region, &mut (), &mut offset, &Fr::from(self.b as u64))?;
+                    let a = chip.decompose(&mut region, &mut offset, &a, 4)?;
+                    let b = chip.decompose(&mut region, &mut offset, &b, 4)?;
+                    for r in 1..32 {
+                        let rotated = chip.rotate_right(&mut region, &mut offset, &a, r)?;
+                        let shifted = chip.shift_right(&mut region, &mut offset, &a, r)?;
+                        let rotated = chip.compose(&mut region, &mut offset, &rotated)?;
+                        let shifted = chip.compose(&mut region, &mut offset, &shifted)?;
+                        assert_eq!(rotated.value, Fr::from(self.a.rotate_right(r as u32) as u64));
+                        assert_eq!(shifted.value, Fr::from((self.a >> r) as u64));
+                    }
+                    let sum = chip.add(&mut region, &mut offset, vec![&a, &b, &a])?;
+                    let sum = chip.compose(&mut region, &mut offset, &sum)?;
+                    assert_eq!(sum.value, Fr::from(self.a.wrapping_add(self.b).wrapping_add(self.a) as u64));
+                    let xor = chip.xor(&mut region, &a, &b)?;
+                    let xor = chip.compose(&mut region, &mut offset, &xor)?;
+                    assert_eq!(xor.value, Fr::from((self.a ^ self.b) as u64));
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bitwise_word_circuit() {
+        let test_circuit = WordCircuit { a: 0x9e37_79b9, b: 0xf00d_cafe };
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,419 @@
+use crate::host::blake2::{
+    Blake2Variant,
+    SIGMA,
+    STATE_WORDS,
+    BLOCK_WORDS,
+};
+use halo2_proofs::arithmetic::FieldExt;
+
+use crate::circuits::Limb;
+use crate::circuits::bitwise::{
+    BitwiseOp,
+    BitwiseWordChip,
+    BitwiseWordConfig,
+    Word,
+};
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::Region,
+    plonk::{
+        ConstraintSystem,
+        Error
+    },
+};
+
+/* Words are kept as little endian bytes (see BitwiseWordChip):
+ * xor: looked up byte by byte in the bitwise table
+ * rotate: the bytes are permuted and, for a rotation which is not a multiple of 8,
+ *      split at the rotation bit and recombined
+ * add: the sum of the bytes is the bytes of the result plus the carry times 2^WORD_BITS
+ */
+pub struct Blake2State<F: FieldExt> {
+    h: Vec<Word<F>>,
+}
+
+pub struct Blake2Chip<F:FieldExt, const WORD_BITS: usize> {
+    pub config: BitwiseWordConfig,
+    word_chip: BitwiseWordChip<F>,
+    blake2_state: Blake2State<F>,
+    round: u64,
+    _marker: PhantomData<F>
+}
+
+pub type Blake2bChip<F> = Blake2Chip<F, 64>;
+pub type Blake2sChip<F> = Blake2Chip<F, 32>;
+
+impl<F: FieldExt, const WORD_BITS: usize> Blake2Chip<F, WORD_BITS> {
+    const WORD_BYTES: usize = WORD_BITS / 8;
+
+    pub fn construct(config: BitwiseWordConfig) -> Self {
+        let state = Blake2State {
+            h: vec![],
+        };
+
+        Blake2Chip {
+            round: 0,
+            word_chip: BitwiseWordChip::new(config.clone()),
+            config,
+            blake2_state: state,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn variant() -> &'static Blake2Variant {
+        Blake2Variant::from_word_bits(WORD_BITS)
+    }
+
+    pub fn initialize(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        *offset = 0;
+        self.word_chip.initialize(region, offset)?;
+        self.blake2_state.h = vec![self.constant_word(0); STATE_WORDS];
+        Ok(())
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<F>) -> BitwiseWordConfig {
+        BitwiseWordChip::configure(cs)
+    }
+
+    fn constant_word(&self, v: u64) -> Word<F> {
+        self.word_chip.constant_word(v, Self::WORD_BYTES)
+    }
+
+    /// the sum of the words modulo 2^WORD_BITS
+    fn add(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        words: Vec<&Word<F>>,
+    ) -> Result<Word<F>, Error> {
+        self.word_chip.add(region, offset, words)
+    }
+
+    /// rotate right the xor of a and b by r bits
+    fn xor_rotate(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        a: &Word<F>,
+        b: &Word<F>,
+        r: u32,
+    ) -> Result<Word<F>, Error> {
+        let x = self.word_chip.xor(region, a, b)?;
+        self.word_chip.rotate_right(region, offset, &x, r as usize)
+    }
+
+    fn g(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        v: &mut Vec<Word<F>>,
+        [a, b, c, d]: [usize; 4],
+        x: &Word<F>,
+        y: &Word<F>,
+    ) -> Result<(), Error> {
+        let rotations = Self::variant().rotations;
+        v[a] = self.add(region, offset, vec![&v[a], &v[b], x])?;
+        v[d] = self.xor_rotate(region, offset, &v[d], &v[a], rotations[0])?;
+        v[c] = self.add(region, offset, vec![&v[c], &v[d]])?;
+        v[b] = self.xor_rotate(region, offset, &v[b], &v[c], rotations[1])?;
+        v[a] = self.add(region, offset, vec![&v[a], &v[b], y])?;
+        v[d] = self.xor_rotate(region, offset, &v[d], &v[a], rotations[2])?;
+        v[c] = self.add(region, offset, vec![&v[c], &v[d]])?;
+        v[b] = self.xor_rotate(region, offset, &v[b], &v[c], rotations[3])?;
+        Ok(())
+    }
+
+    /// Returns the flag of whether the state restarts and the initial state with the digest length
+    fn assign_init(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        init: &Limb<F>,
+    ) -> Result<(Limb<F>, Vec<Word<F>>), Error> {
+        // restart = init * inv and init * (1 - restart) = 0
+        let inv = init.value.invert().unwrap_or(F::zero());
+        let restart = init.value * inv;
+        let l = self.config.common.assign_line(region, &mut (), offset,
+            [Some(init.clone()), None, None, Some(Limb::new(None, inv)), Some(Limb::new(None, restart)), None],
+            [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+            0,
+        )?;
+        let restart = l[2].clone();
+        self.config.common.assign_line(region, &mut (), offset,
+            [Some(init.clone()), None, None, Some(restart.clone()), None, None],
+            [Some(F::one()), None, None, None, None, None, Some(-F::one()), None, None],
+            0,
+        )?;
+
+        // init - restart < max_digest_len, thus a restarted state has a digest length
+        // between 1 and max_digest_len and init is a byte
+        let variant = Self::variant();
+        let bound = variant.max_digest_len().trailing_zeros() as usize;
+        assert!(bound < 8);
+        let len = self.config.common.assign_line(region, &mut (), offset,
+            [Some(init.clone()), Some(restart.clone()), None, None, Some(Limb::new(None, init.value - restart.value)), None],
+            [Some(F::one()), Some(-F::one()), None, None, Some(-F::one()), None, None, None, None],
+            0,
+        )?[2].clone();
+        self.word_chip.assign_bits(region, &len, bound)?;
+
+        // the digest length is at most 64 thus it only changes the lowest byte of h[0]
+        let mut h = variant.iv.iter().map(|v| self.constant_word(*v)).collect::<Vec<_>>();
+        let param = self.constant_word(variant.iv[0] ^ 0x0101_0000);
+        h[0] = param.clone();
+        h[0][0] = self.word_chip.op(region, BitwiseOp::Xor, &param[0], init)?;
+        Ok((restart, h))
+    }
+
+    /// Compress one block of BLOCK_WORDS words.
+    /// init is the digest length when a new hash starts or 0 to continue with the current state,
+    /// t is the number of bytes hashed so far and last is 1 for the last block.
+    pub fn assign_compress(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        init: &Limb<F>,
+        t: &Limb<F>,
+        last: &Limb<F>,
+        values: &[Limb<F>; BLOCK_WORDS],
+        result: &[Limb<F>; STATE_WORDS],
+    ) -> Result<(), Error> {
+        let variant = Self::variant();
+        let (restart, init_h) = self.assign_init(region, offset, init)?;
+        let mut h = vec![];
+        for (word, init_word) in self.blake2_state.h.iter().zip(init_h.iter()) {
+            let mut w = vec![];
+            for (byte, init_byte) in word.iter().zip(init_word.iter()) {
+                w.push(self.config.common.select(region, &mut (), offset, &restart, byte, init_byte, self.round)?);
+            }
+            h.push(w);
+        }
+
+        // last * last - last = 0
+        self.config.common.assign_line(region, &mut (), offset,
+            [Some(last.clone()), Some(last.clone()), Some(last.clone()), None, None, None],
+            [Some(-F::one()), None, None, None, None, None, None, Some(F::one()), None],
+            0,
+        )?;
+        // the byte 0xff * last
+        let last_byte = self.config.common.assign_line(region, &mut (), offset,
+            [Some(last.clone()), None, None, None, Some(Limb::new(None, last.value * F::from(0xffu64))), None],
+            [Some(F::from(0xffu64)), None, None, None, Some(-F::one()), None, None, None, None],
+            0,
+        )?[1].clone();
+
+        let mut m = vec![];
+        for value in values.iter() {
+            m.push(self.word_chip.decompose(region, offset, value, Self::WORD_BYTES)?);
+        }
+        let t = self.word_chip.decompose(region, offset, t, 8)?;
+
+        let mut v = h.clone();
+        v.append(&mut variant.iv.iter().map(|x| self.constant_word(*x)).collect());
+        v[12] = self.word_chip.xor(region, &v[12], &t[0..Self::WORD_BYTES].to_vec())?;
+        if WORD_BITS < 64 {
+            v[13] = self.word_chip.xor(region, &v[13], &t[Self::WORD_BYTES..2 * Self::WORD_BYTES].to_vec())?;
+        }
+        v[14] = self.word_chip.xor(region, &v[14], &vec![last_byte; Self::WORD_BYTES])?;
+
+        for round in 0..variant.rounds {
+            let s = &SIGMA[round % 10];
+            self.g(region, offset, &mut v, [0, 4, 8, 12], &m[s[0]], &m[s[1]])?;
+            self.g(region, offset, &mut v, [1, 5, 9, 13], &m[s[2]], &m[s[3]])?;
+            self.g(region, offset, &mut v, [2, 6, 10, 14], &m[s[4]], &m[s[5]])?;
+            self.g(region, offset, &mut v, [3, 7, 11, 15], &m[s[6]], &m[s[7]])?;
+            self.g(region, offset, &mut v, [0, 5, 10, 15], &m[s[8]], &m[s[9]])?;
+            self.g(region, offset, &mut v, [1, 6, 11, 12], &m[s[10]], &m[s[11]])?;
+            self.g(region, offset, &mut v, [2, 7, 8, 13], &m[s[12]], &m[s[13]])?;
+            self.g(region, offset, &mut v, [3, 4, 9, 14], &m[s[14]], &m[s[15]])?;
+        }
+
+        for i in 0..STATE_WORDS {
+            let w = self.word_chip.xor(region, &h[i], &v[i])?;
+            h[i] = self.word_chip.xor(region, &w, &v[i + STATE_WORDS])?;
+            let word = self.word_chip.compose(region, offset, &h[i])?;
+            assert!(word.value == result[i].value);
+            region.constrain_equal(
+                result[i].cell.as_ref().unwrap().cell(),
+                word.cell.as_ref().unwrap().cell()
+            )?;
+        }
+        self.blake2_state.h = h;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+    use crate::value_for_assign;
+    use crate::circuits::bitwise::BitwiseWordConfig;
+    use crate::host::blake2::{BLAKE2B, BLAKE2S};
+
+    use halo2_proofs::{
+        circuit::{Chip, Layouter, Region, SimpleFloorPlanner},
+        plonk::{
+            Advice, Circuit, Column, ConstraintSystem, Error
+        },
+    };
+
+    use super::{
+        Blake2Chip,
+        Limb,
+    };
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChipConfig {
+        limb: Column<Advice>
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChip {
+        config: HelperChipConfig
+    }
+
+    impl Chip<Fr> for HelperChip {
+        type Config = HelperChipConfig;
+        type Loaded = ();
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+
+        fn loaded(&self) -> &Self::Loaded {
+            &()
+        }
+    }
+
+    impl HelperChip {
+        fn new(config: HelperChipConfig) -> Self {
+            HelperChip{
+                config,
+            }
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> HelperChipConfig {
+            let limb= cs.advice_column();
+            cs.enable_equality(limb);
+            HelperChipConfig {
+                limb,
+            }
+        }
+
+        fn assign_values(
+            &self,
+            region: &mut Region<Fr>,
+            offset: &mut usize,
+            values: &Vec<u64>,
+        ) -> Result<Vec<Limb<Fr>>, Error> {
+            let mut r = vec![];
+            for v in values.iter() {
+                let c = region.assign_advice(
+                    || format!("assign input"),
+                    self.config.limb,
+                    *offset,
+                    || value_for_assign!(Fr::from(*v))
+                )?;
+                *offset += 1;
+                r.push(Limb::new(Some(c), Fr::from(*v)));
+            }
+            Ok(r)
+        }
+    }
+
+    /// blocks of (init, t, last, message words, result words)
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit<const WORD_BITS: usize> {
+        blocks: Vec<(Vec<u64>, Vec<u64>, Vec<u64>)>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        blake2config: BitwiseWordConfig,
+        helperconfig: HelperChipConfig,
+    }
+
+    impl<const WORD_BITS: usize> Circuit<Fr> for TestCircuit<WORD_BITS> {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Self::Config {
+               blake2config: Blake2Chip::<Fr, WORD_BITS>::configure(meta),
+               helperconfig: HelperChip::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut blake2chip = Blake2Chip::<Fr, WORD_BITS>::construct(config.clone().blake2config);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            layouter.assign_region(
+                || "assign blake2 test",
+                |mut region| {
+                    let mut offset = 0;
+                    let mut blocks = vec![];
+                    for (params, inputs, result) in self.blocks.iter() {
+                        let params = helperchip.assign_values(&mut region, &mut offset, params)?;
+                        let inputs = helperchip.assign_values(&mut region, &mut offset, inputs)?;
+                        let result = helperchip.assign_values(&mut region, &mut offset, result)?;
+                        blocks.push((params, inputs, result));
+                    }
+                    offset = 0;
+                    blake2chip.initialize(&mut region, &mut offset)?;
+                    for (params, inputs, result) in blocks.into_iter() {
+                        blake2chip.assign_compress(
+                            &mut region,
+                            &mut offset,
+                            &params[0],
+                            &params[1],
+                            &params[2],
+                            &inputs.try_into().unwrap(),
+                            &result.try_into().unwrap(),
+                        )?;
+                    }
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    fn test_blocks(variant: &crate::host::blake2::Blake2Variant, message: &[u8], digest_len: usize) -> Vec<(Vec<u64>, Vec<u64>, Vec<u64>)> {
+        let mut h = variant.init(digest_len);
+        variant.blocks(message).into_iter().enumerate().map(|(i, (m, t, last))| {
+            variant.compress(&mut h, &m, t, last);
+            let init = if i == 0 { digest_len as u64 } else { 0 };
+            (vec![init, t, last as u64], m.to_vec(), h.to_vec())
+        }).collect()
+    }
+
+    #[test]
+    fn test_blake2b_circuit_00() {
+        let test_circuit = TestCircuit::<64> {blocks: test_blocks(&BLAKE2B, b"abc", 32)};
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_blake2s_circuit_00() {
+        let message = (0..100).map(|x| x as u8).collect::<Vec<_>>();
+        let test_circuit = TestCircuit::<32> {blocks: test_blocks(&BLAKE2S, &message, 32)};
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -1,437 +1,337 @@
-use ark_std::{end_timer, start_timer};
 use halo2_proofs::pairing::bn256::Fr;
 use halo2_proofs::{
-    arithmetic::{BaseExt, FieldExt},
-    circuit::{AssignedCell, Chip, Layouter, Region},
+    circuit::{AssignedCell, Layouter, Region},
     pairing::bls12_381::G1Affine,
-    plonk::{ConstraintSystem, Error},
+    plonk::Error,
 };
 use halo2ecc_s::circuit::base_chip::BaseChipOps;
-use halo2ecc_s::circuit::fq12::Fq12ChipOps;
 use std::cell::RefCell;
-use std::marker::PhantomData;
 use std::rc::Rc;
-use std::sync::Arc;
 
 use halo2_proofs::pairing::bls12_381::Fq as Bls381Fq;
-use halo2ecc_s::assign::{AssignedCondition, AssignedFq, Cell as ContextCell};
-use halo2ecc_s::assign::{AssignedFq12, AssignedG2Affine, AssignedPoint};
+use halo2_proofs::pairing::bls12_381::Fr as Bls381Fr;
+use halo2ecc_s::assign::{AssignedCondition, AssignedFq, AssignedPoint};
 use halo2ecc_s::circuit::ecc_chip::EccBaseIntegerChipWrapper;
-use halo2ecc_s::circuit::{ecc_chip::EccChipBaseOps, pairing_chip::PairingChipOps};
+use halo2ecc_s::circuit::ecc_chip::{EccChipBaseOps, EccChipScalarOps};
+use halo2ecc_s::circuit::integer_chip::IntegerChipOps;
 
-pub const BLS381FQ_SIZE: usize = 8;
-pub const BLS381G1_SIZE: usize = 17;
-pub const BLS381G2_SIZE: usize = 33;
+/// limbs of 108 bits of a scalar of bls12_381
+pub const BLS381SCALAR_LIMBS: usize = 3;
 
-use halo2ecc_s::{
-    circuit::{
-        base_chip::{BaseChip, BaseChipConfig},
-        range_chip::{RangeChip, RangeChipConfig},
-        select_chip::{SelectChip, SelectChipConfig},
-    },
-    context::{Context, GeneralScalarEccContext},
-};
+use halo2ecc_s::context::{Context, GeneralScalarEccContext};
 
 use num_bigint::BigUint;
 use std::ops::{AddAssign, Mul};
-use crate::utils::Limb;
-
-
-#[derive(Clone, Debug)]
-pub struct Bls381ChipConfig {
-    base_chip_config: BaseChipConfig,
-    range_chip_config: RangeChipConfig,
-    point_select_chip_config: SelectChipConfig,
-}
-
-pub struct Bls381PairChip<N: FieldExt> {
-    config: Bls381ChipConfig,
-    base_chip: BaseChip<Fr>,
-    pub range_chip: RangeChip<Fr>,
-    point_select_chip: SelectChip<Fr>,
-    _marker: PhantomData<N>,
-}
-
-impl<N: FieldExt> Chip<N> for Bls381PairChip<N> {
-    type Config = Bls381ChipConfig;
-    type Loaded = ();
+use crate::circuits::pairing::{
+    assign_fq_constant, assigned_cells_to_bn, enable_fq_permute, enable_g1affine_output,
+    enable_g1affine_permute, get_cell_of_ctx, get_g1_from_cells, G2SumChip, HashToCurveChip,
+    MsmChip, MultiPairChip, PairChip, PairingChipConfig, PairingCurve, SumChip,
+};
+use crate::utils::{bn_to_field, Limb};
+use crate::host::hash_to_curve::{
+    sswu_witness, H_EFF, MODULUS, ISO11_XDEN, ISO11_XNUM, ISO11_YDEN, ISO11_YNUM,
+    SSWU_A, SSWU_B, SSWU_C1, SSWU_C2, SSWU_Z,
+};
 
-    fn config(&self) -> &Self::Config {
-        &self.config
+pub use crate::circuits::pairing::{fr_to_bn, fr_to_bool};
+
+/// bls12_381 with G2 on the twist y^2 = x^3 + 4 * (u + 1)
+pub struct Bls381;
+
+impl PairingCurve for Bls381 {
+    type Fq = Bls381Fq;
+    type G1Affine = G1Affine;
+    type Context = GeneralScalarEccContext<G1Affine, Fr>;
+
+    const FQ_LIMBS: usize = 4;
+
+    const G1_B: u64 = 4;
+    const G2_B: [&'static [u8]; 2] = [b"4", b"4"];
+    // 1 / (u + 1)^((p - 1) / 3) and 1 / (u + 1)^((p - 1) / 2)
+    const PSI_X: [&'static [u8]; 2] = [
+        b"0",
+        b"1a0111ea397fe699ec02408663d4de85aa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaad",
+    ];
+    const PSI_Y: [&'static [u8]; 2] = [
+        b"135203e60180a68ee2e9c448d77a2cd91c3dedd930b1cf60ef396489f61eb45e304466cf3e67fa0af1ee7b04121bdea2",
+        b"6af0e0437ff400b6831e36d6bd17ffe48395dabc2d3435e77f76e17009241c5ee67992f72ec05f4c81084fbede3cc09",
+    ];
+    // x = -0xd201000000010000 is the BLS parameter
+    const PSI_EIGENVALUE: &'static [u8] = b"d201000000010000";
+    const PSI_EIGENVALUE_NEG: bool = true;
+
+    fn new_context(context: Rc<RefCell<Context<Fr>>>) -> Self::Context {
+        GeneralScalarEccContext::<G1Affine, Fr>::new(context)
     }
 
-    fn loaded(&self) -> &Self::Loaded {
-        &()
+    fn native_ctx(ctx: &Self::Context) -> &Rc<RefCell<Context<Fr>>> {
+        &ctx.native_ctx
     }
-}
 
-pub fn fr_to_bn(f: &Fr) -> BigUint {
-    let mut bytes: Vec<u8> = Vec::new();
-    f.write(&mut bytes).unwrap();
-    BigUint::from_bytes_le(&bytes[..])
+    /// The points of E with phi(P) = -[x^2] P are exactly the ones in G1,
+    /// where phi(x, y) = (beta * x, y) for a cube root of unity beta and x is the BLS parameter.
+    /// The identity is replaced by the generator before the check.
+    fn assert_g1_in_subgroup(
+        ctx: &mut Self::Context,
+        p: &AssignedPoint<G1Affine, Fr>,
+    ) {
+        let g_x = assign_fq_constant::<Bls381>(ctx, &BigUint::parse_bytes(b"17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb", 16).unwrap());
+        let g_y = assign_fq_constant::<Bls381>(ctx, &BigUint::parse_bytes(b"08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1", 16).unwrap());
+        let beta = assign_fq_constant::<Bls381>(ctx, &BigUint::parse_bytes(b"5f19672fdf76ce51ba69c6076a0f77eaddb3a93be6f89688de17d813620a00022e01fffffffefffe", 16).unwrap());
+        let x = ctx.base_integer_chip().bisec_int(&p.z, &g_x, &p.x);
+        let y = ctx.base_integer_chip().bisec_int(&p.z, &g_y, &p.y);
+        let z = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+        let q = AssignedPoint::new(x, y, AssignedCondition(z));
+
+        let x_square = ctx.scalar_integer_ctx.assign_int_constant(bn_to_field(&BigUint::parse_bytes(b"ac45a4010001a4020000000100000000", 16).unwrap()));
+        let ret = ctx.msm(&vec![q.clone()], &vec![x_square]);
+        let ret = ctx.ecc_reduce(&ret);
+        let zero = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+        ctx.native_ctx.borrow_mut().assert_equal(&ret.z.0, &zero);
+        let phi_x = ctx.base_integer_chip().int_mul(&beta, &q.x);
+        ctx.base_integer_chip().assert_int_equal(&phi_x, &ret.x);
+        let neg_y = ctx.base_integer_chip().int_neg(&ret.y);
+        ctx.base_integer_chip().assert_int_equal(&q.y, &neg_y);
+    }
 }
 
-pub fn fr_to_bool(f: &Fr) -> bool {
-    let mut bytes: Vec<u8> = Vec::new();
-    f.write(&mut bytes).unwrap();
-    return bytes[0] == 1u8;
-}
+pub type Bls381ChipConfig = PairingChipConfig;
+pub type Bls381PairChip<N> = PairChip<N, Bls381>;
+pub type Bls381SumChip<N> = SumChip<N, Bls381>;
+pub type Bls381MsmChip<N> = MsmChip<N, Bls381>;
+pub type Bls381MultiPairChip<N> = MultiPairChip<N, Bls381>;
+pub type Bls381G2SumChip<N> = G2SumChip<N, Bls381>;
+pub type Bls381HashToCurveChip<N> = HashToCurveChip<N, Bls381>;
 
-fn assigned_cells_to_bn381(
-    a: &Vec<Limb<Fr>>, //G1 (4 * 2 + 1)
+fn assigned_cells_to_scalar(
+    a: &Vec<Limb<Fr>>, //Scalar (3)
     start: usize,
 ) -> BigUint {
     let mut bn = BigUint::from(0 as u64);
-    for i in start..start + 4 {
+    for i in start..start + BLS381SCALAR_LIMBS {
         let shift = BigUint::from(2 as u32).pow(108 * (i - start) as u32);
         bn.add_assign(fr_to_bn(&a[i].value).mul(shift.clone()));
     }
     bn
 }
 
-fn get_g1_from_cells(
-    ctx: &mut GeneralScalarEccContext<G1Affine, Fr>,
-    a: &Vec<Limb<Fr>>, //G1 (4 * 2 + 1)
-) -> AssignedPoint<G1Affine, Fr> {
-    let x_bn = assigned_cells_to_bn381(a, 0);
-    let y_bn = assigned_cells_to_bn381(a, 4);
-    let is_identity = fr_to_bool(&a[8].value);
-    let x = ctx.base_integer_chip().assign_w(&x_bn);
-    let y = ctx.base_integer_chip().assign_w(&y_bn);
-    AssignedPoint::new(
-        x,
-        y,
-        AssignedCondition(ctx.native_ctx.borrow_mut().assign(if is_identity {
-            Fr::one()
-        } else {
-            Fr::zero()
-        })),
-    )
-}
-
-fn get_g2_from_cells(
-    ctx: &mut GeneralScalarEccContext<G1Affine, Fr>,
-    b: &Vec<Limb<Fr>>, //G2 (4 * 4 + 1)
-) -> AssignedG2Affine<G1Affine, Fr> {
-    let x1_bn = assigned_cells_to_bn381(b, 0);
-    let x2_bn = assigned_cells_to_bn381(b, 4);
-    let y1_bn = assigned_cells_to_bn381(b, 8);
-    let y2_bn = assigned_cells_to_bn381(b, 12);
-    let x1 = ctx.base_integer_chip().assign_w(&x1_bn);
-    let x2 = ctx.base_integer_chip().assign_w(&x2_bn);
-    let y1 = ctx.base_integer_chip().assign_w(&y1_bn);
-    let y2 = ctx.base_integer_chip().assign_w(&y2_bn);
-    let is_identity = fr_to_bool(&b[16].value);
-    AssignedG2Affine::new(
-        (x1, x2),
-        (y1, y2),
-        AssignedCondition(ctx.native_ctx.borrow_mut().assign(if is_identity {
-            Fr::one()
-        } else {
-            Fr::zero()
-        })),
-    )
-}
-
-fn get_cell_of_ctx(
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    cell: &ContextCell,
-) -> AssignedCell<Fr, Fr> {
-    cells[cell.region as usize][cell.col][cell.row]
-        .clone()
-        .unwrap()
-}
-
-fn enable_fq_permute(
+fn enable_scalar_permute(
     region: &mut Region<'_, Fr>,
     cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    fq: &AssignedFq<Bls381Fq, Fr>,
+    scalar: &AssignedFq<Bls381Fr, Fr>,
     input: &Vec<Limb<Fr>>,
 ) -> Result<(), Error> {
-    for i in 0..4 {
-        let limb = fq.limbs_le[i].cell;
+    for i in 0..BLS381SCALAR_LIMBS {
+        let limb = scalar.limbs_le[i].cell;
         let limb_assigned = get_cell_of_ctx(cells, &limb);
         region.constrain_equal(input[i].get_the_cell().cell(), limb_assigned.cell())?;
     }
     Ok(())
 }
 
-fn enable_g1affine_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    point: &AssignedPoint<G1Affine, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    let mut inputs = input.chunks(4);
-    enable_fq_permute(region, cells, &point.x, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.y, &inputs.next().unwrap().to_vec())?;
-    let z_limb0 = point.z.0.cell;
-    let z_limb0_assigned = get_cell_of_ctx(cells, &z_limb0);
-    region.constrain_equal(input[8].get_the_cell().cell(), z_limb0_assigned.cell())?;
-    Ok(())
-}
-
-fn enable_g2affine_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    point: &AssignedG2Affine<G1Affine, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    let mut inputs = input.chunks(4);
-    enable_fq_permute(region, cells, &point.x.0, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.x.1, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.y.0, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.y.1, &inputs.next().unwrap().to_vec())?;
-    let z_limb0 = point.z.0.cell;
-    let z_limb0_assigned = get_cell_of_ctx(cells, &z_limb0);
-    region.constrain_equal(input[16].get_the_cell().cell(), z_limb0_assigned.cell())?;
-    Ok(())
-}
-
-fn enable_fq12_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    fq12: &AssignedFq12<Bls381Fq, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    let mut inputs = input.chunks(4);
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .0 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .0 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .1 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .1 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .2 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .2 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .0 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .0 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .1 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .1 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .2 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .2 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    Ok(())
+/// evaluate the polynomial with constant coefficients (from the constant term) at x
+fn assign_horner(
+    ctx: &mut GeneralScalarEccContext<G1Affine, Fr>,
+    coeffs: &Vec<BigUint>,
+    x: &AssignedFq<Bls381Fq, Fr>,
+) -> AssignedFq<Bls381Fq, Fr> {
+    let mut acc = assign_fq_constant::<Bls381>(ctx, coeffs.last().unwrap());
+    for c in coeffs.iter().rev().skip(1) {
+        let c = assign_fq_constant::<Bls381>(ctx, c);
+        acc = ctx.base_integer_chip().int_mul(&acc, x);
+        acc = ctx.base_integer_chip().int_add(&acc, &c);
+    }
+    acc
 }
 
-impl Bls381PairChip<Fr> {
-    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
-        Self {
-            config: config.clone(),
-            point_select_chip: SelectChip::<Fr>::new(config.point_select_chip_config),
-            base_chip: BaseChip::new(config.base_chip_config),
-            range_chip: RangeChip::<Fr>::new(config.range_chip_config),
-            _marker: PhantomData,
+/// Constrain u to be less than the modulus so that its last bit is sgn0 of the canonical u:
+/// u + d = p - 1 limb by limb with carry bits (see crate::circuits::ecdsa),
+/// the limbs of u and d are range checked by assign_w so no equation wraps around.
+fn assert_fq_canonical(
+    ctx: &mut GeneralScalarEccContext<G1Affine, Fr>,
+    u: &AssignedFq<Bls381Fq, Fr>,
+    u_bn: &BigUint,
+) {
+    let max = &*MODULUS - 1u64;
+    // there is no d if u >= p, the equations then fail with d = 0
+    let d_bn = if *u_bn <= max { &max - u_bn } else { BigUint::from(0u64) };
+    let d = ctx.base_integer_chip().assign_w(&d_bn);
+    let mask = (BigUint::from(1u64) << 108) - 1u64;
+    let shift = bn_to_field::<Fr>(&(BigUint::from(1u64) << 108));
+    let zero = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+    let mut carry: Option<(AssignedCondition<Fr>, BigUint)> = None;
+    for i in 0..Bls381::FQ_LIMBS {
+        let limb = |v: &BigUint| (v >> (108 * i)) & &mask;
+        let mut sum_bn = limb(u_bn) + limb(&d_bn);
+        let mut elems = vec![(&u.limbs_le[i], Fr::one()), (&d.limbs_le[i], Fr::one())];
+        if let Some((c, c_bn)) = carry.as_ref() {
+            sum_bn += c_bn;
+            elems.push((&c.0, Fr::one()));
         }
-    }
-
-    pub fn configure(
-        cs: &mut ConstraintSystem<Fr>,
-    ) -> <Self as Chip<Fr>>::Config {
-        Bls381ChipConfig {
-            base_chip_config: BaseChip::configure(cs),
-            range_chip_config: RangeChip::<Fr>::configure(cs),
-            point_select_chip_config: SelectChip::configure(cs),
+        let next = if i + 1 < Bls381::FQ_LIMBS {
+            let c_bn = BigUint::from((sum_bn > mask) as u64);
+            let c = ctx.native_ctx.borrow_mut().assign_bit(bn_to_field(&c_bn));
+            Some((c, c_bn))
+        } else {
+            None
+        };
+        if let Some((c, _)) = next.as_ref() {
+            elems.push((&c.0, -shift));
         }
+        let m = ctx.native_ctx.borrow_mut().sum_with_constant(elems, Some(-bn_to_field::<Fr>(&limb(&max))));
+        ctx.native_ctx.borrow_mut().assert_equal(&m, &zero);
+        carry = next;
     }
+}
 
-    pub fn load_bls381_pair_circuit(
+/// The simplified SWU map of u onto E' followed by the 11-isogeny onto E,
+/// see crate::host::hash_to_curve for the witness w of the square root.
+fn assign_map_to_curve(
+    ctx: &mut GeneralScalarEccContext<G1Affine, Fr>,
+    u: &AssignedFq<Bls381Fq, Fr>,
+    u_bn: &BigUint,
+) -> AssignedPoint<G1Affine, Fr> {
+    let (gx1_square, w) = sswu_witness(u_bn);
+    let one = assign_fq_constant::<Bls381>(ctx, &BigUint::from(1u64));
+    let z = assign_fq_constant::<Bls381>(ctx, &SSWU_Z);
+    let a = assign_fq_constant::<Bls381>(ctx, &SSWU_A);
+    let b = assign_fq_constant::<Bls381>(ctx, &SSWU_B);
+    let c1 = assign_fq_constant::<Bls381>(ctx, &SSWU_C1);
+    let c2 = assign_fq_constant::<Bls381>(ctx, &SSWU_C2);
+
+    // x1 = C1 * (1 + 1 / d) or C2 if d = Z^2 * u^4 + Z * u^2 is zero
+    let u_square = ctx.base_integer_chip().int_mul(u, u);
+    let tv1 = ctx.base_integer_chip().int_mul(&z, &u_square);
+    let tv1_square = ctx.base_integer_chip().int_mul(&tv1, &tv1);
+    let d = ctx.base_integer_chip().int_add(&tv1_square, &tv1);
+    let d_is_zero = ctx.base_integer_chip().is_int_zero(&d);
+    let d = ctx.base_integer_chip().bisec_int(&d_is_zero, &one, &d);
+    let d_inv = ctx.base_integer_chip().int_unsafe_invert(&d);
+    let x1 = ctx.base_integer_chip().int_add(&one, &d_inv);
+    let x1 = ctx.base_integer_chip().int_mul(&c1, &x1);
+    let x1 = ctx.base_integer_chip().bisec_int(&d_is_zero, &c2, &x1);
+
+    // gx1 = x1^3 + A * x1 + B
+    let gx1 = ctx.base_integer_chip().int_mul(&x1, &x1);
+    let gx1 = ctx.base_integer_chip().int_add(&gx1, &a);
+    let gx1 = ctx.base_integer_chip().int_mul(&gx1, &x1);
+    let gx1 = ctx.base_integer_chip().int_add(&gx1, &b);
+
+    // w^2 is gx1 if gx1 is a square otherwise Z * gx1 which is then a square
+    let is_square = ctx.native_ctx.borrow_mut().assign_bit(if gx1_square { Fr::one() } else { Fr::zero() });
+    let w = ctx.base_integer_chip().assign_w(&w);
+    let w_square = ctx.base_integer_chip().int_mul(&w, &w);
+    let z_gx1 = ctx.base_integer_chip().int_mul(&z, &gx1);
+    let expected = ctx.base_integer_chip().bisec_int(&is_square, &gx1, &z_gx1);
+    ctx.base_integer_chip().assert_int_equal(&w_square, &expected);
+
+    // (x, y) is (x1, w) or (Z * u^2 * x1, Z * u^3 * w)
+    let x2 = ctx.base_integer_chip().int_mul(&tv1, &x1);
+    let tv1_u = ctx.base_integer_chip().int_mul(&tv1, u);
+    let y2 = ctx.base_integer_chip().int_mul(&tv1_u, &w);
+    let x = ctx.base_integer_chip().bisec_int(&is_square, &x1, &x2);
+    let y = ctx.base_integer_chip().bisec_int(&is_square, &w, &y2);
+
+    // sgn0(y) = sgn0(u), u is canonical (see assert_fq_canonical) and y is reduced by get_last_bit
+    let u_sign = AssignedCondition(ctx.base_integer_chip().get_last_bit(u));
+    let y_sign = AssignedCondition(ctx.base_integer_chip().get_last_bit(&y));
+    let flip = ctx.native_ctx.borrow_mut().xor(&u_sign, &y_sign);
+    let y_neg = ctx.base_integer_chip().int_neg(&y);
+    let y = ctx.base_integer_chip().bisec_int(&flip, &y_neg, &y);
+
+    // the 11-isogeny from E' to E
+    let x_num = assign_horner(ctx, &ISO11_XNUM, &x);
+    let x_den = assign_horner(ctx, &ISO11_XDEN, &x);
+    let y_num = assign_horner(ctx, &ISO11_YNUM, &x);
+    let y_den = assign_horner(ctx, &ISO11_YDEN, &x);
+    let x_den_inv = ctx.base_integer_chip().int_unsafe_invert(&x_den);
+    let x = ctx.base_integer_chip().int_mul(&x_num, &x_den_inv);
+    let y_den_inv = ctx.base_integer_chip().int_unsafe_invert(&y_den);
+    let y_ratio = ctx.base_integer_chip().int_mul(&y_num, &y_den_inv);
+    let y = ctx.base_integer_chip().int_mul(&y, &y_ratio);
+    let z = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+    AssignedPoint::new(x, y, AssignedCondition(z))
+}
+
+impl MsmChip<Fr, Bls381> {
+    /// Each call is (ls, msm) where ls is k pairs of G1 (4 * 2 + 1) and scalar (3)
+    /// and msm is the G1 (4 * 2 + 1) sum of the scalar multiplications.
+    pub fn load_bls381_msm_circuit(
         &self,
-        a: &Vec<Limb<Fr>>,  //G1 (4 * 2 + 1)
-        b: &Vec<Limb<Fr>>,  //G2 (4 * 4 + 1)
-        ab: &Vec<Limb<Fr>>, // Fq_12 (4 * 12)
+        calls: &Vec<(Vec<Limb<Fr>>, Vec<Limb<Fr>>)>,
         layouter: &mut impl Layouter<Fr>,
     ) -> Result<(), Error> {
         let contex = Rc::new(RefCell::new(Context::new()));
-        let mut ctx = GeneralScalarEccContext::<G1Affine, Fr>::new(contex);
-
-        let a_g1 = get_g1_from_cells(&mut ctx, a);
-        let b_g2 = get_g2_from_cells(&mut ctx, b);
-
-        let ab_fq12_raw = ctx.pairing(&[(&a_g1, &b_g2)]);
-        let ab_fq12 = ctx.fq12_reduce(&ab_fq12_raw);
-
-        let records = Arc::try_unwrap(Into::<Context<Fr>>::into(ctx).records)
-            .unwrap()
-            .into_inner()
-            .unwrap();
-
-        layouter.assign_region(
-            || "base",
-            |mut region| {
-                let timer = start_timer!(|| "assign");
-                let cells = records.assign_all(
-                    &mut region,
-                    &self.base_chip,
-                    &self.range_chip,
-                    &self.point_select_chip
-                )?;
-                enable_g1affine_permute(&mut region, &cells, &a_g1, a)?;
-                enable_g2affine_permute(&mut region, &cells, &b_g2, b)?;
-                enable_fq12_permute(&mut region, &cells, &ab_fq12, ab)?;
-                end_timer!(timer);
-                Ok(())
-            },
-        )?;
-        Ok(())
-    }
-}
-
-pub struct Bls381SumChip<N: FieldExt> {
-    config: Bls381ChipConfig,
-    base_chip: BaseChip<N>,
-    pub range_chip: RangeChip<N>,
-    point_select_chip: SelectChip<N>,
-    _marker: PhantomData<N>,
-}
-
-impl<N: FieldExt> Chip<N> for Bls381SumChip<N> {
-    type Config = Bls381ChipConfig;
-    type Loaded = ();
-
-    fn config(&self) -> &Self::Config {
-        &self.config
-    }
-
-    fn loaded(&self) -> &Self::Loaded {
-        &()
-    }
-}
-
-impl Bls381SumChip<Fr> {
-    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
-        Self {
-            config: config.clone(),
-            point_select_chip: SelectChip::<Fr>::new(config.point_select_chip_config),
-            base_chip: BaseChip::new(config.base_chip_config),
-            range_chip: RangeChip::<Fr>::new(config.range_chip_config),
-            _marker: PhantomData,
+        let mut ctx = Bls381::new_context(contex);
+
+        let mut assigned_calls = vec![];
+        for (ls, _) in calls.iter() {
+            let mut g1s = vec![];
+            let mut scalars = vec![];
+            for l in ls.chunks(Bls381::G1_LIMBS + BLS381SCALAR_LIMBS) {
+                g1s.push(get_g1_from_cells::<Bls381>(&mut ctx, &l[0..Bls381::G1_LIMBS].to_vec()));
+                let scalar_bn = assigned_cells_to_scalar(&l.to_vec(), Bls381::G1_LIMBS);
+                scalars.push(ctx.scalar_integer_ctx.assign_w(&scalar_bn));
+            }
+            let msm_ret = ctx.msm(&g1s, &scalars);
+            let msm_ret = ctx.ecc_reduce(&msm_ret);
+            enable_g1affine_output::<Bls381>(&mut ctx, &msm_ret);
+            assigned_calls.push((g1s, scalars, msm_ret));
         }
-    }
 
-    pub fn configure(
-        cs: &mut ConstraintSystem<Fr>,
-    ) -> <Self as Chip<Fr>>::Config {
-        Bls381ChipConfig {
-            base_chip_config: BaseChip::configure(cs),
-            range_chip_config: RangeChip::<Fr>::configure(cs),
-            point_select_chip_config: SelectChip::configure(cs),
-        }
+        self.assign_records(ctx, layouter, |region, cells| {
+            for ((g1s, scalars, msm_ret), (ls, msm)) in assigned_calls.iter().zip(calls.iter()) {
+                for (i, l) in ls.chunks(Bls381::G1_LIMBS + BLS381SCALAR_LIMBS).enumerate() {
+                    enable_g1affine_permute::<Bls381>(region, cells, &g1s[i], &l[0..Bls381::G1_LIMBS].to_vec())?;
+                    enable_scalar_permute(region, cells, &scalars[i], &l[Bls381::G1_LIMBS..].to_vec())?;
+                }
+                enable_g1affine_permute::<Bls381>(region, cells, msm_ret, msm)?;
+            }
+            Ok(())
+        })
     }
+}
 
-    pub fn load_bls381_sum_circuit(
+impl HashToCurveChip<Fr, Bls381> {
+    /// Each call is u0 (4), u1 (4) and the G1 (4 * 2 + 1) of
+    /// clear_cofactor(map_to_curve(u0) + map_to_curve(u1)) with u0, u1 < p.
+    /// Only the map to G1 is proven: u0, u1 = hash_to_field(msg, dst) (expand_message_xmd with
+    /// sha256 then a reduction mod p) is computed by the caller and trusted, see the calling convention
+    /// in crate::adaptor::bls381adaptor. Hashing to G2 is out of scope.
+    pub fn load_bls381_hash_to_curve_circuit(
         &self,
-        ls: &Vec<Limb<Fr>>,  // Vec<G1> (4 * 2 + 1) * k
-        sum: &Vec<Limb<Fr>>, // G1 (4 * 2 + 1)
+        calls: &Vec<Vec<Limb<Fr>>>,
         layouter: &mut impl Layouter<Fr>,
     ) -> Result<(), Error> {
         let contex = Rc::new(RefCell::new(Context::new()));
-        let mut ctx = GeneralScalarEccContext::<G1Affine, Fr>::new(contex);
-
-        let g1s: Vec<AssignedPoint<_, _>> = ls
-            .chunks(9)
-            .map(|l| get_g1_from_cells(&mut ctx, &l.to_vec()))
-            .collect();
+        let mut ctx = Bls381::new_context(contex);
+
+        let mut assigned_calls = vec![];
+        for call in calls.iter() {
+            let u0_bn = assigned_cells_to_bn::<Bls381>(call, 0);
+            let u1_bn = assigned_cells_to_bn::<Bls381>(call, Bls381::FQ_LIMBS);
+            let u0 = ctx.base_integer_chip().assign_w(&u0_bn);
+            let u1 = ctx.base_integer_chip().assign_w(&u1_bn);
+            assert_fq_canonical(&mut ctx, &u0, &u0_bn);
+            assert_fq_canonical(&mut ctx, &u1, &u1_bn);
+            let q0 = assign_map_to_curve(&mut ctx, &u0, &u0_bn);
+            let q1 = assign_map_to_curve(&mut ctx, &u1, &u1_bn);
+            let q0 = ctx.to_point_with_curvature(q0);
+            let q = ctx.ecc_add(&q0, &q1);
+            let h_eff = ctx.scalar_integer_ctx.assign_int_constant(Bls381Fr::from(H_EFF));
+            let ret = ctx.msm(&vec![q], &vec![h_eff]);
+            let ret = ctx.ecc_reduce(&ret);
+            enable_g1affine_output::<Bls381>(&mut ctx, &ret);
+            assigned_calls.push((u0, u1, ret));
+        }
 
-        let g0 = ctx.assign_identity();
-        let sum_ret = g1s.iter().fold(g0, |acc, x| {
-            let p = ctx.ecc_add(&acc, &x);
-            ctx.to_point_with_curvature(p)
-        });
-        let sum_ret = sum_ret.to_point();
-        let sum_ret = ctx.ecc_reduce(&sum_ret);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.z.0);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[0]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[1]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[2]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[3]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[0]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[1]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[2]);
-        ctx.native_ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[3]);
-        let records = Arc::try_unwrap(Into::<Context<Fr>>::into(ctx).records)
-            .unwrap()
-            .into_inner()
-            .unwrap();
-        layouter.assign_region(
-            || "base",
-            |mut region| {
-                let timer = start_timer!(|| "assign");
-                let cells = records.assign_all(
-                    &mut region,
-                    &self.base_chip,
-                    &self.range_chip,
-                    &self.point_select_chip
-                )?;
-                let ls = ls
-                    .chunks(9)
-                    .into_iter()
-                    .map(|x| x.to_vec())
-                    .collect::<Vec<_>>();
-                g1s.iter().enumerate().for_each(|(i, x)| {
-                    enable_g1affine_permute(&mut region, &cells, x, &ls[i]).unwrap()
-                });
-                enable_g1affine_permute(&mut region, &cells, &sum_ret, sum)?;
-                end_timer!(timer);
-                Ok(())
-            },
-        )?;
-        Ok(())
+        self.assign_records(ctx, layouter, |region, cells| {
+            for ((u0, u1, ret), call) in assigned_calls.iter().zip(calls.iter()) {
+                enable_fq_permute::<Bls381>(region, cells, u0, &call[0..Bls381::FQ_LIMBS].to_vec())?;
+                enable_fq_permute::<Bls381>(region, cells, u1, &call[Bls381::FQ_LIMBS..Bls381::FQ_LIMBS * 2].to_vec())?;
+                enable_g1affine_permute::<Bls381>(region, cells, ret, &call[Bls381::FQ_LIMBS * 2..].to_vec())?;
+            }
+            Ok(())
+        })
     }
 }
@@ -1,433 +1,134 @@
-use ark_std::{end_timer, start_timer};
 use halo2_proofs::pairing::bn256::Fr;
 use halo2_proofs::{
-    arithmetic::{BaseExt, FieldExt},
-    circuit::{AssignedCell, Chip, Layouter, Region},
+    circuit::Layouter,
     pairing::bn256::G1Affine,
-    plonk::{ConstraintSystem, Error},
+    plonk::Error,
 };
 use halo2ecc_s::circuit::base_chip::BaseChipOps;
-use halo2ecc_s::circuit::fq12::Fq12ChipOps;
 use std::cell::RefCell;
-use std::marker::PhantomData;
 use std::rc::Rc;
-use std::sync::Arc;
 
 use halo2_proofs::pairing::bn256::Fq as Bn256Fq;
-use halo2ecc_s::assign::{AssignedCondition, AssignedFq, Cell as ContextCell};
-use halo2ecc_s::circuit::ecc_chip::EccBaseIntegerChipWrapper;
-use halo2ecc_s::circuit::{ecc_chip::EccChipBaseOps, pairing_chip::PairingChipOps};
+use halo2ecc_s::circuit::ecc_chip::{EccChipBaseOps, EccChipScalarOps};
 
-use halo2ecc_s::assign::{AssignedFq12, AssignedG2Affine, AssignedPoint};
+use halo2ecc_s::context::{Context, IntegerContext, NativeScalarEccContext};
 
-use halo2ecc_s::{
-    circuit::{
-        base_chip::{BaseChip, BaseChipConfig},
-        range_chip::{RangeChip, RangeChipConfig},
-        select_chip::{SelectChip, SelectChipConfig},
-    },
-    context::{Context, IntegerContext, NativeScalarEccContext},
+use crate::circuits::pairing::{
+    enable_g1affine_output, enable_g1affine_permute, get_cell_of_ctx, get_g1_from_cells,
+    G2SumChip, MsmChip, MultiPairChip, PairChip, PairingChipConfig, PairingCurve, SumChip,
 };
-
-use num_bigint::BigUint;
-use std::ops::{AddAssign, Mul};
 use crate::utils::Limb;
 
-#[derive(Clone, Debug)]
-pub struct Bn256ChipConfig {
-    base_chip_config: BaseChipConfig,
-    range_chip_config: RangeChipConfig,
-    point_select_chip_config: SelectChipConfig,
-}
-
-pub struct Bn256PairChip<N: FieldExt> {
-    config: Bn256ChipConfig,
-    base_chip: BaseChip<N>,
-    pub range_chip: RangeChip<N>,
-    point_select_chip: SelectChip<N>,
-    _marker: PhantomData<N>,
-}
-
-impl<N: FieldExt> Chip<N> for Bn256PairChip<N> {
-    type Config = Bn256ChipConfig;
-    type Loaded = ();
-
-    fn config(&self) -> &Self::Config {
-        &self.config
-    }
-
-    fn loaded(&self) -> &Self::Loaded {
-        &()
+pub use crate::circuits::pairing::{fr_to_bn, fr_to_bool};
+
+/// bn254 with G2 on the twist y^2 = x^3 + 3 / (u + 9)
+pub struct Bn256;
+
+impl PairingCurve for Bn256 {
+    type Fq = Bn256Fq;
+    type G1Affine = G1Affine;
+    type Context = NativeScalarEccContext<G1Affine>;
+
+    const FQ_LIMBS: usize = 3;
+
+    const G1_B: u64 = 3;
+    const G2_B: [&'static [u8]; 2] = [
+        b"2b149d40ceb8aaae81be18991be06ac3b5b4c5e559dbefa33267e6dc24a138e5",
+        b"9713b03af0fed4cd2cafadeed8fdf4a74fa084e52d1852e4a2bd0685c315d2",
+    ];
+    // (u + 9)^((p - 1) / 3) and (u + 9)^((p - 1) / 2)
+    const PSI_X: [&'static [u8]; 2] = [
+        b"2fb347984f7911f74c0bec3cf559b143b78cc310c2c3330c99e39557176f553d",
+        b"16c9e55061ebae204ba4cc8bd75a079432ae2a1d0b7c9dce1665d51c640fcba2",
+    ];
+    const PSI_Y: [&'static [u8]; 2] = [
+        b"63cf305489af5dcdc5ec698b6e2f9b9dbaae0eda9c95998dc54014671a0135a",
+        b"7c03cbcac41049a0704b5a7ec796f2b21807dc98fa25bd282d37f632623b0e3",
+    ];
+    // 6 * x^2 where x is the BN parameter
+    const PSI_EIGENVALUE: &'static [u8] = b"6f4d8248eeb859fbf83e9682e87cfd46";
+    const PSI_EIGENVALUE_NEG: bool = false;
+
+    fn new_context(context: Rc<RefCell<Context<Fr>>>) -> Self::Context {
+        NativeScalarEccContext(IntegerContext::<Bn256Fq, Fr>::new(context))
     }
-}
-
-pub fn fr_to_bn(f: &Fr) -> BigUint {
-    let mut bytes: Vec<u8> = Vec::new();
-    f.write(&mut bytes).unwrap();
-    BigUint::from_bytes_le(&bytes[..])
-}
 
-pub fn fr_to_bool(f: &Fr) -> bool {
-    let mut bytes: Vec<u8> = Vec::new();
-    f.write(&mut bytes).unwrap();
-    return bytes[0] == 1u8;
-}
-
-fn assigned_cells_to_bn256(
-    a: &Vec<Limb<Fr>>, //G1 (3 * 2 + 1)
-    start: usize,
-) -> BigUint {
-    let mut bn = BigUint::from(0 as u64);
-    for i in start..start + 4 {
-        let shift = BigUint::from(2 as u32).pow(108 * (i - start) as u32);
-        bn.add_assign(fr_to_bn(&a[i].value).mul(shift.clone()));
+    fn native_ctx(ctx: &Self::Context) -> &Rc<RefCell<Context<Fr>>> {
+        &ctx.0.ctx
     }
-    bn
-}
-
-fn get_g1_from_cells(
-    ctx: &mut NativeScalarEccContext<G1Affine>,
-    a: &Vec<Limb<Fr>>, //G1 (3 * 2 + 1)
-) -> AssignedPoint<G1Affine, Fr> {
-    let x_bn = assigned_cells_to_bn256(a, 0);
-    let y_bn = assigned_cells_to_bn256(a, 3);
-    let is_identity = fr_to_bool(&a[6].value);
-    let x = ctx.base_integer_chip().assign_w(&x_bn);
-    let y = ctx.base_integer_chip().assign_w(&y_bn);
-    AssignedPoint::new(
-        x,
-        y,
-        AssignedCondition(ctx.0.ctx.borrow_mut().assign(if is_identity {
-            Fr::one()
-        } else {
-            Fr::zero()
-        })),
-    )
-}
-
-fn get_g2_from_cells(
-    ctx: &mut NativeScalarEccContext<G1Affine>,
-    b: &Vec<Limb<Fr>>, //G2 (3 * 4 + 1)
-) -> AssignedG2Affine<G1Affine, Fr> {
-    let x1_bn = assigned_cells_to_bn256(b, 0);
-    let x2_bn = assigned_cells_to_bn256(b, 3);
-    let y1_bn = assigned_cells_to_bn256(b, 6);
-    let y2_bn = assigned_cells_to_bn256(b, 9);
-    let x1 = ctx.base_integer_chip().assign_w(&x1_bn);
-    let x2 = ctx.base_integer_chip().assign_w(&x2_bn);
-    let y1 = ctx.base_integer_chip().assign_w(&y1_bn);
-    let y2 = ctx.base_integer_chip().assign_w(&y2_bn);
-    let is_identity = fr_to_bool(&b[12].value);
-    AssignedG2Affine::new(
-        (x1, x2),
-        (y1, y2),
-        AssignedCondition(ctx.0.ctx.borrow_mut().assign(if is_identity {
-            Fr::one()
-        } else {
-            Fr::zero()
-        })),
-    )
-}
-
-fn get_cell_of_ctx(
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    cell: &ContextCell,
-) -> AssignedCell<Fr, Fr> {
-    cells[cell.region as usize][cell.col][cell.row]
-        .clone()
-        .unwrap()
-}
-
-fn enable_fq_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    fq: &AssignedFq<Bn256Fq, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    for i in 0..3 {
-        let limb = fq.limbs_le[i].cell;
-        let limb_assigned = get_cell_of_ctx(cells, &limb);
-        region.constrain_equal(input[i].get_the_cell().cell(), limb_assigned.cell())?;
-    }
-    Ok(())
-}
-
-fn enable_g1affine_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    point: &AssignedPoint<G1Affine, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    let mut inputs = input.chunks(3);
-    enable_fq_permute(region, cells, &point.x, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.y, &inputs.next().unwrap().to_vec())?;
-    let z_limb0 = point.z.0.cell;
-    let z_limb0_assigned = get_cell_of_ctx(cells, &z_limb0);
-    region.constrain_equal(input[6].get_the_cell().cell(), z_limb0_assigned.cell())?;
-    Ok(())
-}
-
-fn enable_g2affine_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    point: &AssignedG2Affine<G1Affine, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    let mut inputs = input.chunks(3);
-    enable_fq_permute(region, cells, &point.x.0, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.x.1, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.y.0, &inputs.next().unwrap().to_vec())?;
-    enable_fq_permute(region, cells, &point.y.1, &inputs.next().unwrap().to_vec())?;
-    let z_limb0 = point.z.0.cell;
-    let z_limb0_assigned = get_cell_of_ctx(cells, &z_limb0);
-    region.constrain_equal(input[12].get_the_cell().cell(), z_limb0_assigned.cell())?;
-    Ok(())
-}
-
-fn enable_fq12_permute(
-    region: &mut Region<'_, Fr>,
-    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
-    fq12: &AssignedFq12<Bn256Fq, Fr>,
-    input: &Vec<Limb<Fr>>,
-) -> Result<(), Error> {
-    let mut inputs = input.chunks(3);
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .0 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .0 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .1 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .1 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .2 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.0 .2 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .0 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .0 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .1 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .1 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .2 .0,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    enable_fq_permute(
-        region,
-        cells,
-        &fq12.1 .2 .1,
-        &inputs.next().unwrap().to_vec(),
-    )?;
-    Ok(())
 }
 
-impl Bn256PairChip<Fr> {
-    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
-        Self {
-            config: config.clone(),
-            point_select_chip: SelectChip::<Fr>::new(config.point_select_chip_config),
-            base_chip: BaseChip::new(config.base_chip_config),
-            range_chip: RangeChip::<Fr>::new(config.range_chip_config),
-            _marker: PhantomData,
-        }
-    }
+pub type Bn256ChipConfig = PairingChipConfig;
+pub type Bn256PairChip<N> = PairChip<N, Bn256>;
+pub type Bn256SumChip<N> = SumChip<N, Bn256>;
+pub type Bn256MsmChip<N> = MsmChip<N, Bn256>;
+pub type Bn256MultiPairChip<N> = MultiPairChip<N, Bn256>;
+pub type Bn256G2SumChip<N> = G2SumChip<N, Bn256>;
 
-    pub fn configure(
-        cs: &mut ConstraintSystem<Fr>,
-    ) -> <Self as Chip<Fr>>::Config {
-        Bn256ChipConfig {
-            base_chip_config: BaseChip::configure(cs),
-            range_chip_config: RangeChip::<Fr>::configure(cs),
-            point_select_chip_config: SelectChip::configure(cs),
-        }
-    }
-
-    pub fn load_bn256_pair_circuit(
+impl MsmChip<Fr, Bn256> {
+    /// Each call is (ls, msm) where ls is k pairs of G1 (3 * 2 + 1) and scalar (1)
+    /// and msm is the G1 (3 * 2 + 1) sum of the scalar multiplications.
+    /// The scalar field of bn256 is native so the scalars are single cells.
+    pub fn load_bn256_msm_circuit(
         &self,
-        a: &Vec<Limb<Fr>>,  //G1 (3 * 2 + 1)
-        b: &Vec<Limb<Fr>>,  //G2 (3 * 4 + 1)
-        ab: &Vec<Limb<Fr>>, // Fq_12 (3 * 12)
+        calls: &Vec<(Vec<Limb<Fr>>, Vec<Limb<Fr>>)>,
         layouter: &mut impl Layouter<Fr>,
     ) -> Result<(), Error> {
         let context = Rc::new(RefCell::new(Context::new()));
-        let ctx = IntegerContext::<Bn256Fq, Fr>::new(context);
-        let mut ctx = NativeScalarEccContext(ctx);
-
-        let a_g1 = get_g1_from_cells(&mut ctx, a);
-        let b_g2 = get_g2_from_cells(&mut ctx, b);
-
-        let ab_fq12_raw = ctx.pairing(&[(&a_g1, &b_g2)]);
-        let ab_fq12 = ctx.fq12_reduce(&ab_fq12_raw);
-
-        let records = Arc::try_unwrap(Into::<Context<Fr>>::into(ctx).records)
-            .unwrap()
-            .into_inner()
-            .unwrap();
-
-        layouter.assign_region(
-            || "base",
-            |mut region| {
-                let timer = start_timer!(|| "assign");
-                let cells = records.assign_all(
-                    &mut region,
-                    &self.base_chip,
-                    &self.range_chip,
-                    &self.point_select_chip
-                )?;
-                enable_g1affine_permute(&mut region, &cells, &a_g1, a)?;
-                enable_g2affine_permute(&mut region, &cells, &b_g2, b)?;
-                enable_fq12_permute(&mut region, &cells, &ab_fq12, ab)?;
-                end_timer!(timer);
-                Ok(())
-            },
-        )?;
-        Ok(())
-    }
-}
-
-pub struct Bn256SumChip<N: FieldExt> {
-    config: Bn256ChipConfig,
-    base_chip: BaseChip<N>,
-    pub range_chip: RangeChip<N>,
-    point_select_chip: SelectChip<N>,
-    _marker: PhantomData<N>,
-}
-
-impl<N: FieldExt> Chip<N> for Bn256SumChip<N> {
-    type Config = Bn256ChipConfig;
-    type Loaded = ();
-
-    fn config(&self) -> &Self::Config {
-        &self.config
-    }
+        let mut ctx = Bn256::new_context(context);
+
+        let mut assigned_calls = vec![];
+        for (ls, _) in calls.iter() {
+            let mut g1s = vec![];
+            let mut scalars = vec![];
+            for l in ls.chunks(Bn256::G1_LIMBS + 1) {
+                g1s.push(get_g1_from_cells::<Bn256>(&mut ctx, &l[0..Bn256::G1_LIMBS].to_vec()));
+                let scalar = ctx.0.ctx.borrow_mut().assign(l[Bn256::G1_LIMBS].value);
+                ctx.0.ctx.borrow_mut().enable_permute(&scalar);
+                scalars.push(scalar);
+            }
+            let msm_ret = ctx.msm(&g1s, &scalars);
+            let msm_ret = ctx.ecc_reduce(&msm_ret);
+            enable_g1affine_output::<Bn256>(&mut ctx, &msm_ret);
+            assigned_calls.push((g1s, scalars, msm_ret));
+        }
 
-    fn loaded(&self) -> &Self::Loaded {
-        &()
+        self.assign_records(ctx, layouter, |region, cells| {
+            for ((g1s, scalars, msm_ret), (ls, msm)) in assigned_calls.iter().zip(calls.iter()) {
+                for (i, l) in ls.chunks(Bn256::G1_LIMBS + 1).enumerate() {
+                    enable_g1affine_permute::<Bn256>(region, cells, &g1s[i], &l[0..Bn256::G1_LIMBS].to_vec())?;
+                    let scalar = get_cell_of_ctx(cells, &scalars[i].cell);
+                    region.constrain_equal(l[Bn256::G1_LIMBS].get_the_cell().cell(), scalar.cell())?;
+                }
+                enable_g1affine_permute::<Bn256>(region, cells, msm_ret, msm)?;
+            }
+            Ok(())
+        })
     }
 }
 
-impl Bn256SumChip<Fr> {
-    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
-        Self {
-            config: config.clone(),
-            point_select_chip: SelectChip::<Fr>::new(config.point_select_chip_config),
-            base_chip: BaseChip::new(config.base_chip_config),
-            range_chip: RangeChip::<Fr>::new(config.range_chip_config),
-            _marker: PhantomData,
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use num_bigint::BigUint;
+    use crate::circuits::pairing::assigned_cells_to_bn;
+    use crate::utils::{bn_to_field, Limb};
+    use super::Bn256;
 
-    pub fn configure(
-        cs: &mut ConstraintSystem<Fr>,
-    ) -> <Self as Chip<Fr>>::Config {
-        Bn256ChipConfig {
-            base_chip_config: BaseChip::configure(cs),
-            range_chip_config: RangeChip::<Fr>::configure(cs),
-            point_select_chip_config: SelectChip::configure(cs),
-        }
+    fn fq_to_limbs(v: &BigUint) -> Vec<Limb<Fr>> {
+        let mask = (BigUint::from(1u64) << 108) - 1u64;
+        (0..3).map(|i| Limb::new(None, bn_to_field(&((v >> (108 * i)) & &mask)))).collect()
     }
 
-    pub fn load_bn256_sum_circuit(
-        &self,
-        ls: &Vec<Limb<Fr>>,  // Vec<G1> (3 * 2 + 1) * k
-        sum: &Vec<Limb<Fr>>, // G1 (3 * 2 + 1)
-        layouter: &mut impl Layouter<Fr>,
-    ) -> Result<(), Error> {
-        let context = Rc::new(RefCell::new(Context::new()));
-        let ctx = IntegerContext::<Bn256Fq, Fr>::new(context);
-        let mut ctx = NativeScalarEccContext(ctx);
-
-        let g1s: Vec<AssignedPoint<_, _>> = ls
-            .chunks(7)
-            .map(|l| get_g1_from_cells(&mut ctx, &l.to_vec()))
-            .collect();
-
-        let g0 = ctx.assign_identity();
-        let sum_ret = g1s.iter().fold(g0, |acc, x| {
-            let p = ctx.ecc_add(&acc, &x);
-            ctx.to_point_with_curvature(p)
-        });
-        let sum_ret = sum_ret.to_point();
-        let sum_ret = ctx.ecc_reduce(&sum_ret);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.z.0);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[0]);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[1]);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.x.limbs_le[2]);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[0]);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[1]);
-        ctx.0.ctx.borrow_mut().enable_permute(&sum_ret.y.limbs_le[2]);
-        let records = Arc::try_unwrap(Into::<Context<Fr>>::into(ctx).records)
-            .unwrap()
-            .into_inner()
-            .unwrap();
-        layouter.assign_region(
-            || "base",
-            |mut region| {
-                let timer = start_timer!(|| "assign");
-                let cells = records.assign_all(
-                    &mut region,
-                    &self.base_chip,
-                    &self.range_chip,
-                    &self.point_select_chip
-                )?;
-                let ls = ls
-                    .chunks(7)
-                    .into_iter()
-                    .map(|x| x.to_vec())
-                    .collect::<Vec<_>>();
-                g1s.iter().enumerate().for_each(|(i, x)| {
-                    enable_g1affine_permute(&mut region, &cells, x, &ls[i]).unwrap()
-                });
-                enable_g1affine_permute(&mut region, &cells, &sum_ret, sum)?;
-                end_timer!(timer);
-                Ok(())
-            },
-        )?;
-        Ok(())
+    #[test]
+    fn test_bn256_fq_from_cells() {
+        // x is read back from its three limbs without the first limb of y
+        let x = BigUint::parse_bytes(b"30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd46", 16).unwrap();
+        let y = BigUint::parse_bytes(b"2ef3f9b423a2c8c74e9803958f6c320e854a1c1c06cd5cc8fd221dc052d76df7", 16).unwrap();
+        let mut cells = fq_to_limbs(&x);
+        cells.append(&mut fq_to_limbs(&y));
+        cells.push(Limb::new(None, Fr::zero()));
+        assert_eq!(assigned_cells_to_bn::<Bn256>(&cells, 0), x);
+        assert_eq!(assigned_cells_to_bn::<Bn256>(&cells, 3), y);
     }
 }
@@ -0,0 +1,468 @@
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::{
+    arithmetic::{BaseExt, FieldExt},
+    circuit::{AssignedCell, Chip, Layouter, Region},
+    pairing::secp256k1::{Fp as Secp256k1Fp, Fq as Secp256k1Fq, Secp256k1Affine},
+    plonk::{ConstraintSystem, Error},
+};
+use halo2ecc_s::circuit::base_chip::BaseChipOps;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use halo2ecc_s::assign::{AssignedCondition, AssignedFq, AssignedPoint, Cell as ContextCell};
+use halo2ecc_s::circuit::ecc_chip::{EccBaseIntegerChipWrapper, EccChipBaseOps, EccChipScalarOps};
+use halo2ecc_s::circuit::integer_chip::IntegerChipOps;
+
+use halo2ecc_s::{
+    circuit::{
+        base_chip::{BaseChip, BaseChipConfig},
+        range_chip::{RangeChip, RangeChipConfig},
+        select_chip::{SelectChip, SelectChipConfig},
+    },
+    context::{Context, GeneralScalarEccContext},
+};
+
+use num_bigint::BigUint;
+use std::ops::{AddAssign, Mul};
+use crate::host::ecdsa::{scalar_inv, Point, BASE, MODULUS, ORDER};
+use crate::utils::{bn_to_field, field_to_bn, Limb};
+
+/// limbs of 108 bits of a field element or a scalar of secp256k1
+pub const SECP256K1_LIMBS: usize = 3;
+
+#[derive(Clone, Debug)]
+pub struct EcdsaChipConfig {
+    base_chip_config: BaseChipConfig,
+    range_chip_config: RangeChipConfig,
+    point_select_chip_config: SelectChipConfig,
+}
+
+/// ECDSA verification over secp256k1 (see crate::host::ecdsa).
+pub struct EcdsaChip<N: FieldExt> {
+    config: EcdsaChipConfig,
+    base_chip: BaseChip<N>,
+    pub range_chip: RangeChip<N>,
+    point_select_chip: SelectChip<N>,
+    _marker: PhantomData<N>,
+}
+
+impl<N: FieldExt> Chip<N> for EcdsaChip<N> {
+    type Config = EcdsaChipConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+fn assigned_cells_to_bn(
+    a: &Vec<Limb<Fr>>,
+    start: usize,
+) -> BigUint {
+    let mut bn = BigUint::from(0 as u64);
+    for i in start..start + SECP256K1_LIMBS {
+        let shift = BigUint::from(2 as u32).pow(108 * (i - start) as u32);
+        bn.add_assign(field_to_bn(&a[i].value).mul(shift.clone()));
+    }
+    bn
+}
+
+fn get_cell_of_ctx(
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    cell: &ContextCell,
+) -> AssignedCell<Fr, Fr> {
+    cells[cell.region as usize][cell.col][cell.row]
+        .clone()
+        .unwrap()
+}
+
+fn enable_fq_permute<W: BaseExt>(
+    region: &mut Region<'_, Fr>,
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    fq: &AssignedFq<W, Fr>,
+    input: &[Limb<Fr>],
+) -> Result<(), Error> {
+    for i in 0..SECP256K1_LIMBS {
+        let limb = fq.limbs_le[i].cell;
+        let limb_assigned = get_cell_of_ctx(cells, &limb);
+        region.constrain_equal(input[i].get_the_cell().cell(), limb_assigned.cell())?;
+    }
+    Ok(())
+}
+
+/// Constrain a + d = max limb by limb with carry bits,
+/// the limbs of a and d are range checked by assign_w so no equation wraps around.
+fn assert_int_sum<W: BaseExt>(
+    ctx: &mut GeneralScalarEccContext<Secp256k1Affine, Fr>,
+    a: &AssignedFq<W, Fr>,
+    a_bn: &BigUint,
+    d: &AssignedFq<W, Fr>,
+    d_bn: &BigUint,
+    max: &BigUint,
+) {
+    let mask = (BigUint::from(1u64) << 108) - 1u64;
+    let shift = bn_to_field::<Fr>(&(BigUint::from(1u64) << 108));
+    let zero = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+    let mut carry: Option<(AssignedCondition<Fr>, BigUint)> = None;
+    for i in 0..SECP256K1_LIMBS {
+        let limb = |v: &BigUint| (v >> (108 * i)) & &mask;
+        let mut sum_bn = limb(a_bn) + limb(d_bn);
+        let mut elems = vec![(&a.limbs_le[i], Fr::one()), (&d.limbs_le[i], Fr::one())];
+        if let Some((c, c_bn)) = carry.as_ref() {
+            sum_bn += c_bn;
+            elems.push((&c.0, Fr::one()));
+        }
+        let next = if i + 1 < SECP256K1_LIMBS {
+            let c_bn = BigUint::from((sum_bn > mask) as u64);
+            let c = ctx.native_ctx.borrow_mut().assign_bit(bn_to_field(&c_bn));
+            Some((c, c_bn))
+        } else {
+            None
+        };
+        if let Some((c, _)) = next.as_ref() {
+            elems.push((&c.0, -shift));
+        }
+        let m = ctx.native_ctx.borrow_mut().sum_with_constant(elems, Some(-bn_to_field::<Fr>(&limb(max))));
+        ctx.native_ctx.borrow_mut().assert_equal(&m, &zero);
+        carry = next;
+    }
+}
+
+/// the witness of max - a, there is none if a > max and the equations then fail with 0
+fn complement(a_bn: &BigUint, max: &BigUint) -> BigUint {
+    if a_bn <= max { max - a_bn } else { BigUint::from(0u64) }
+}
+
+/// Constrain the scalar a to satisfy 0 < a < N: a + d = N - 1 and a' + d = N - 2,
+/// so a = a' + 1 with a', d >= 0.
+fn assert_scalar_nonzero_canonical(
+    ctx: &mut GeneralScalarEccContext<Secp256k1Affine, Fr>,
+    a: &AssignedFq<Secp256k1Fq, Fr>,
+    a_bn: &BigUint,
+) {
+    let max = &*ORDER - 1u64;
+    let d_bn = complement(a_bn, &max);
+    let d = ctx.scalar_integer_ctx.assign_w(&d_bn);
+    assert_int_sum(ctx, a, a_bn, &d, &d_bn, &max);
+    let a_prev_bn = if *a_bn > BigUint::from(0u64) { a_bn - 1u64 } else { BigUint::from(0u64) };
+    let a_prev = ctx.scalar_integer_ctx.assign_w(&a_prev_bn);
+    assert_int_sum(ctx, &a_prev, &a_prev_bn, &d, &d_bn, &(&max - 1u64));
+}
+
+/// Constrain the field element a to be less than the modulus: a + d = p - 1.
+fn assert_base_canonical(
+    ctx: &mut GeneralScalarEccContext<Secp256k1Affine, Fr>,
+    a: &AssignedFq<Secp256k1Fp, Fr>,
+    a_bn: &BigUint,
+) {
+    let max = &*MODULUS - 1u64;
+    let d_bn = complement(a_bn, &max);
+    let d = ctx.base_integer_chip().assign_w(&d_bn);
+    assert_int_sum(ctx, a, a_bn, &d, &d_bn, &max);
+}
+
+impl EcdsaChip<Fr> {
+    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
+        Self {
+            config: config.clone(),
+            point_select_chip: SelectChip::<Fr>::new(config.point_select_chip_config),
+            base_chip: BaseChip::new(config.base_chip_config),
+            range_chip: RangeChip::<Fr>::new(config.range_chip_config),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        cs: &mut ConstraintSystem<Fr>,
+    ) -> <Self as Chip<Fr>>::Config {
+        EcdsaChipConfig {
+            base_chip_config: BaseChip::configure(cs),
+            range_chip_config: RangeChip::<Fr>::configure(cs),
+            point_select_chip_config: SelectChip::configure(cs),
+        }
+    }
+
+    /// Each signature is vk (3 * 2), r (3), s (3) and the message hash z (3).
+    /// The circuit checks 0 < r, s < N and R = (z / s) * G + (r / s) * vk is not the identity
+    /// with R.x == r, so the signatures with N <= R.x (about 2^-128 of them) are not accepted.
+    pub fn load_ecdsa_circuit(
+        &self,
+        calls: &Vec<Vec<Limb<Fr>>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.load_signatures(calls, false, layouter)
+    }
+
+    /// Each call is a signature as in load_ecdsa_circuit followed by the recovery id v (1),
+    /// the circuit checks the signature with R.y = v mod 2 as well, which makes vk unique.
+    pub fn load_ecrecover_circuit(
+        &self,
+        calls: &Vec<Vec<Limb<Fr>>>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        self.load_signatures(calls, true, layouter)
+    }
+
+    fn load_signatures(
+        &self,
+        calls: &Vec<Vec<Limb<Fr>>>,
+        recover: bool,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let contex = Rc::new(RefCell::new(Context::new()));
+        let mut ctx = GeneralScalarEccContext::<Secp256k1Affine, Fr>::new(contex);
+
+        let mut assigned_calls = vec![];
+        for call in calls.iter() {
+            // vk is on the curve y^2 = x^3 + 7
+            let vk_x_bn = assigned_cells_to_bn(call, 0);
+            let vk_y_bn = assigned_cells_to_bn(call, 3);
+            let vk_x = ctx.base_integer_chip().assign_w(&vk_x_bn);
+            let vk_y = ctx.base_integer_chip().assign_w(&vk_y_bn);
+            let y_square = ctx.base_integer_chip().int_mul(&vk_y, &vk_y);
+            let x_square = ctx.base_integer_chip().int_mul(&vk_x, &vk_x);
+            let x_cube = ctx.base_integer_chip().int_mul(&x_square, &vk_x);
+            let seven = ctx.base_integer_chip().assign_int_constant(Secp256k1Fp::from(7u64));
+            let rhs = ctx.base_integer_chip().int_add(&x_cube, &seven);
+            ctx.base_integer_chip().assert_int_equal(&y_square, &rhs);
+            let vk_z = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+            let vk = AssignedPoint::new(vk_x, vk_y, AssignedCondition(vk_z));
+
+            let g_x = ctx.base_integer_chip().assign_int_constant(bn_to_field(&BASE.x));
+            let g_y = ctx.base_integer_chip().assign_int_constant(bn_to_field(&BASE.y));
+            let g_z = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+            let g = AssignedPoint::new(g_x, g_y, AssignedCondition(g_z));
+
+            let r_bn = assigned_cells_to_bn(call, 6);
+            let s_bn = assigned_cells_to_bn(call, 9);
+            let z_bn = assigned_cells_to_bn(call, 12);
+            let r = ctx.scalar_integer_ctx.assign_w(&r_bn);
+            let s = ctx.scalar_integer_ctx.assign_w(&s_bn);
+            let z = ctx.scalar_integer_ctx.assign_w(&z_bn);
+            assert_scalar_nonzero_canonical(&mut ctx, &r, &r_bn);
+            assert_scalar_nonzero_canonical(&mut ctx, &s, &s_bn);
+            // s * s_inv = 1 with a witness s_inv so that a zero s fails the constraints
+            // instead of the witness generation
+            let s_inv_bn = scalar_inv(&(&s_bn % &*ORDER));
+            let s_inv = ctx.scalar_integer_ctx.assign_w(&s_inv_bn);
+            let one = ctx.scalar_integer_ctx.assign_int_constant(Secp256k1Fq::one());
+            let s_s_inv = ctx.scalar_integer_ctx.int_mul(&s, &s_inv);
+            ctx.scalar_integer_ctx.assert_int_equal(&s_s_inv, &one);
+            let u1 = ctx.scalar_integer_ctx.int_mul(&z, &s_inv);
+            let u2 = ctx.scalar_integer_ctx.int_mul(&r, &s_inv);
+
+            let p = ctx.msm(&vec![g, vk.clone()], &vec![u1, u2]);
+            let p = ctx.ecc_reduce(&p);
+            let zero = ctx.native_ctx.borrow_mut().assign_constant(Fr::zero());
+            ctx.native_ctx.borrow_mut().assert_equal(&p.z.0, &zero);
+            let r_base = ctx.base_integer_chip().assign_w(&r_bn);
+            ctx.base_integer_chip().assert_int_equal(&p.x, &r_base);
+
+            // the parity of the canonical R.y is the recovery id
+            let v = if recover {
+                let u1_bn = (&z_bn * &s_inv_bn) % &*ORDER;
+                let u2_bn = (&r_bn * &s_inv_bn) % &*ORDER;
+                let vk_bn = Point { x: vk_x_bn, y: vk_y_bn, infinity: false };
+                let p_bn = BASE.mul_scalar(&u1_bn).add(&vk_bn.mul_scalar(&u2_bn));
+                let y = ctx.base_integer_chip().assign_w(&p_bn.y);
+                ctx.base_integer_chip().assert_int_equal(&p.y, &y);
+                assert_base_canonical(&mut ctx, &y, &p_bn.y);
+                let y_sign = ctx.base_integer_chip().get_last_bit(&y);
+                let v = ctx.native_ctx.borrow_mut().assign_bit(call[15].value);
+                ctx.native_ctx.borrow_mut().assert_equal(&y_sign, &v.0);
+                Some(v)
+            } else {
+                None
+            };
+
+            assigned_calls.push((vk, r, r_base, s, z, v));
+        }
+
+        let records = Arc::try_unwrap(Into::<Context<Fr>>::into(ctx).records)
+            .unwrap()
+            .into_inner()
+            .unwrap();
+        layouter.assign_region(
+            || "base",
+            |mut region| {
+                let timer = start_timer!(|| "assign");
+                let cells = records.assign_all(
+                    &mut region,
+                    &self.base_chip,
+                    &self.range_chip,
+                    &self.point_select_chip
+                )?;
+                for ((vk, r, r_base, s, z, v), call) in assigned_calls.iter().zip(calls.iter()) {
+                    enable_fq_permute(&mut region, &cells, &vk.x, &call[0..3])?;
+                    enable_fq_permute(&mut region, &cells, &vk.y, &call[3..6])?;
+                    enable_fq_permute(&mut region, &cells, r, &call[6..9])?;
+                    enable_fq_permute(&mut region, &cells, r_base, &call[6..9])?;
+                    enable_fq_permute(&mut region, &cells, s, &call[9..12])?;
+                    enable_fq_permute(&mut region, &cells, z, &call[12..15])?;
+                    if let Some(v) = v {
+                        let v_assigned = get_cell_of_ctx(&cells, &v.0.cell);
+                        region.constrain_equal(call[15].get_the_cell().cell(), v_assigned.cell())?;
+                    }
+                }
+                end_timer!(timer);
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error};
+    use num_bigint::BigUint;
+
+    use super::{EcdsaChip, EcdsaChipConfig, SECP256K1_LIMBS};
+    use crate::host::ecdsa::{public_key, recover, scalar_inv, sign, verify, Point, Signature, BASE, MODULUS, ORDER};
+    use crate::utils::{bn_to_field, Limb};
+    use crate::value_for_assign;
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        // vk.x, vk.y, r, s, z
+        values: Vec<BigUint>,
+        // the recovery id of ecrecover
+        v: Option<u64>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        ecdsa: EcdsaChipConfig,
+        limb: Column<Advice>,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let limb = meta.advice_column();
+            meta.enable_equality(limb);
+            TestConfig {
+                ecdsa: EcdsaChip::<Fr>::configure(meta),
+                limb,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = EcdsaChip::<Fr>::construct(config.ecdsa.clone());
+            chip.range_chip.init_table(&mut layouter)?;
+            let mask = (BigUint::from(1u64) << 108) - 1u64;
+            let limbs = self.values.iter().flat_map(|v| {
+                (0..SECP256K1_LIMBS).map(|i| bn_to_field::<Fr>(&((v >> (108 * i)) & &mask))).collect::<Vec<_>>()
+            }).chain(self.v.iter().map(|v| Fr::from(*v))).collect::<Vec<_>>();
+            let call = layouter.assign_region(
+                || "ecdsa call",
+                |mut region| {
+                    let mut call = vec![];
+                    for (i, v) in limbs.iter().enumerate() {
+                        let cell = region.assign_advice(|| "call", config.limb, i, || value_for_assign!(*v))?;
+                        call.push(Limb::new(Some(cell), *v));
+                    }
+                    Ok(call)
+                }
+            )?;
+            if self.v.is_some() {
+                chip.load_ecrecover_circuit(&vec![call], &mut layouter)?;
+            } else {
+                chip.load_ecdsa_circuit(&vec![call], &mut layouter)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn test_circuit(vk: &Point, sig: &Signature, z: &BigUint) -> TestCircuit {
+        TestCircuit {
+            values: vec![vk.x.clone(), vk.y.clone(), sig.r.clone(), sig.s.clone(), z.clone()],
+            v: None,
+        }
+    }
+
+    fn signed() -> (Point, BigUint, Signature, u64) {
+        let sk = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef", 16).unwrap();
+        let vk = public_key(&sk);
+        let z = BigUint::parse_bytes(b"4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", 16).unwrap();
+        let k = BigUint::parse_bytes(b"fedcba0987654321fedcba0987654321", 16).unwrap();
+        let sig = sign(&sk, &z, &k);
+        let v = ((BASE.mul_scalar(&k).y & BigUint::from(1u64)) == BigUint::from(1u64)) as u64;
+        (vk, z, sig, v)
+    }
+
+    #[test]
+    fn test_ecdsa_verify_circuit() {
+        let (vk, z, sig, _) = signed();
+        assert!(verify(&vk, &z, &sig));
+        let prover = MockProver::run(22, &test_circuit(&vk, &sig, &z), vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_ecdsa_verify_circuit_zero_s() {
+        let (vk, z, sig, _) = signed();
+        let sig = Signature { r: sig.r, s: BigUint::from(0u64) };
+        assert!(!verify(&vk, &z, &sig));
+        let prover = MockProver::run(22, &test_circuit(&vk, &sig, &z), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_ecrecover_circuit() {
+        let (vk, z, sig, v) = signed();
+        assert_eq!(recover(&z, &sig, v), Some(vk.clone()));
+        let circuit = TestCircuit { v: Some(v), ..test_circuit(&vk, &sig, &z) };
+        let prover = MockProver::run(22, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_ecrecover_circuit_wrong_recovery_id() {
+        let (vk, z, sig, v) = signed();
+        let circuit = TestCircuit { v: Some(1 - v), ..test_circuit(&vk, &sig, &z) };
+        let prover = MockProver::run(22, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_ecdsa_verify_circuit_r_out_of_range() {
+        // R.x = N + 2 so (2, s) is a valid signature of z for vk = (s * R - z * G) / 2,
+        // (N + 2, s) satisfies R.x == r as well and is only rejected by r < N
+        let x = &*ORDER + 2u64;
+        let y = (&x * &x * &x + 7u64).modpow(&((&*MODULUS + 1u64) >> 2), &MODULUS);
+        let p = Point { x: x.clone(), y, infinity: false };
+        assert!(p.is_on_curve());
+        let z = BigUint::parse_bytes(b"4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", 16).unwrap();
+        let s = BigUint::from(0x1234567u64);
+        let vk = p.mul_scalar(&s)
+            .add(&BASE.mul_scalar(&(&*ORDER - &z % &*ORDER)))
+            .mul_scalar(&scalar_inv(&BigUint::from(2u64)));
+        assert!(verify(&vk, &z, &Signature { r: BigUint::from(2u64), s: s.clone() }));
+
+        let sig = Signature { r: x, s };
+        assert!(!verify(&vk, &z, &sig));
+        let prover = MockProver::run(22, &test_circuit(&vk, &sig, &z), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
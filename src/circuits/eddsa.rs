@@ -0,0 +1,227 @@
+use crate::utils::{bn_to_field, field_to_bn};
+use crate::host::eddsa::BASE;
+use crate::host::jubjub::SUBGROUP_ORDER;
+use crate::circuits::babyjub::{
+    AltJubChip,
+    FixedBaseTable,
+    Point,
+};
+use crate::circuits::poseidon::PoseidonChip;
+
+use crate::circuits::{
+    CommonGateConfig,
+    Limb,
+};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region},
+    plonk::{
+        ConstraintSystem, Error
+    },
+};
+
+/// EdDSA verification (see crate::host::eddsa) on the gates of AltJubChip and PoseidonChip.
+pub struct EddsaChip<F:FieldExt> {
+    pub config: CommonGateConfig,
+    jubjub: AltJubChip<F>,
+    poseidon: PoseidonChip<F>,
+    base_table: FixedBaseTable<F>,
+}
+
+impl<F: FieldExt> Chip<F> for EddsaChip<F> {
+    type Config = CommonGateConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: FieldExt> EddsaChip<F> {
+    pub fn new(config: CommonGateConfig) -> Self {
+        let base = (
+            bn_to_field(&field_to_bn(&BASE.x)),
+            bn_to_field(&field_to_bn(&BASE.y)),
+        );
+        EddsaChip {
+            jubjub: AltJubChip::new(config.clone()),
+            poseidon: PoseidonChip::construct(config.clone()),
+            base_table: AltJubChip::<F>::fixed_base_table(&base),
+            config,
+        }
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<F>) -> CommonGateConfig {
+        CommonGateConfig::configure(cs, &())
+    }
+
+    /// the curve chip, e.g. to decompress the keys before assign_verify
+    pub fn jubjub(&self) -> &AltJubChip<F> {
+        &self.jubjub
+    }
+
+    pub fn initialize(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        // the poseidon state starts at the beginning of the region
+        self.poseidon.initialize(config, region, offset)?;
+        self.jubjub.initialize(config, region, offset)
+    }
+
+    /// constrain s < L and s * G = R + c * vk where c = H(R.x, R.y, vk.x, vk.y, msg)
+    pub fn assign_verify(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        vk: &Point<F>,
+        r: &Point<F>,
+        s: &Limb<F>,
+        msg: &Limb<F>,
+    ) -> Result<(), Error> {
+        self.jubjub.assert_on_curve(region, offset, vk)?;
+        self.jubjub.assert_on_curve(region, offset, r)?;
+        let c = self.poseidon.assign_hash(
+            region,
+            offset,
+            &[r.x.clone(), r.y.clone(), vk.x.clone(), vk.y.clone(), msg.clone()],
+        )?;
+        let lhs = self.jubjub.mul_scalar_fixed_base(region, offset, s, &self.base_table, &SUBGROUP_ORDER)?;
+        let cvk = self.jubjub.mul_scalar(region, offset, &c, vk)?;
+        let rhs = self.jubjub.add(region, offset, r, &cvk)?;
+
+        region.constrain_equal(
+            lhs.x.cell.as_ref().unwrap().cell(),
+            rhs.x.cell.as_ref().unwrap().cell()
+        )?;
+        region.constrain_equal(
+            lhs.y.cell.as_ref().unwrap().cell(),
+            rhs.y.cell.as_ref().unwrap().cell()
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+    use num_bigint::BigUint;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{
+            Circuit, ConstraintSystem, Error,
+        },
+    };
+
+    use super::EddsaChip;
+    use crate::circuits::babyjub::Point;
+    use crate::circuits::CommonGateConfig;
+    use crate::host::eddsa;
+    use crate::host::jubjub::{self, SUBGROUP_ORDER};
+    use crate::utils::{bn_to_field, field_to_bn};
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        vk: (Fr, Fr),
+        r: (Fr, Fr),
+        s: Fr,
+        msg: Fr,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = CommonGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            EddsaChip::<Fr>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut chip = EddsaChip::<Fr>::new(config.clone());
+            layouter.assign_region(
+                || "eddsa verify",
+                |mut region| {
+                    let mut offset = 0;
+                    chip.initialize(&config, &mut region, &mut offset)?;
+                    let vk = Point {
+                        x: config.assign_constant(&mut region, &mut (), &mut offset, &self.vk.0)?,
+                        y: config.assign_constant(&mut region, &mut (), &mut offset, &self.vk.1)?,
+                    };
+                    let r = Point {
+                        x: config.assign_constant(&mut region, &mut (), &mut offset, &self.r.0)?,
+                        y: config.assign_constant(&mut region, &mut (), &mut offset, &self.r.1)?,
+                    };
+                    let s = config.assign_constant(&mut region, &mut (), &mut offset, &self.s)?;
+                    let msg = config.assign_constant(&mut region, &mut (), &mut offset, &self.msg)?;
+                    chip.assign_verify(&mut region, &mut offset, &vk, &r, &s, &msg)?;
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    fn signed_circuit() -> TestCircuit {
+        let sk = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef", 16).unwrap();
+        let vk = eddsa::public_key(&sk);
+        let msg = Fr::from(0x1234u64);
+        let sig = eddsa::sign(&sk, &msg);
+        TestCircuit {
+            vk: (vk.x, vk.y),
+            r: (sig.r.x, sig.r.y),
+            s: bn_to_field(&sig.s),
+            msg,
+        }
+    }
+
+    #[test]
+    fn test_eddsa_verify_circuit() {
+        let test_circuit = signed_circuit();
+        let prover = MockProver::run(15, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_eddsa_verify_circuit_wrong_message() {
+        let test_circuit = TestCircuit { msg: Fr::from(0x1235u64), ..signed_circuit() };
+        let prover = MockProver::run(15, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_eddsa_verify_circuit_tampered_r() {
+        let test_circuit = signed_circuit();
+        // R + G is still on the curve
+        let r = jubjub::Point { x: test_circuit.r.0, y: test_circuit.r.1 }.add(&eddsa::BASE);
+        let test_circuit = TestCircuit { r: (r.x, r.y), ..test_circuit };
+        let prover = MockProver::run(15, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_eddsa_verify_circuit_s_not_reduced() {
+        let test_circuit = signed_circuit();
+        // (s + L) * G = s * G thus only the range of s rejects it
+        let s = field_to_bn(&test_circuit.s) + &*SUBGROUP_ORDER;
+        let test_circuit = TestCircuit { s: bn_to_field(&s), ..test_circuit };
+        let prover = MockProver::run(15, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -68,6 +68,19 @@ impl HostOpConfig {
         indicator: Fr,
         enable: bool,
     ) -> Result<Limb<Fr>, Error> {
+        let (merged, _) = self.assign_merged_operands_with_opcode(region, offset, values, indicator, enable)?;
+        Ok(merged)
+    }
+
+    /// Same as assign_merged_operands but also returns the opcode limb of the first line
+    pub fn assign_merged_operands_with_opcode(
+        &self,
+        region: &mut Region<Fr>,
+        offset: &mut usize,
+        values: Vec<&((Fr, Fr), Fr)>,
+        indicator: Fr,
+        enable: bool,
+    ) -> Result<(Limb<Fr>, Limb<Fr>), Error> {
         let mut rev = values.clone();
         let len = values.len();
         rev.reverse();
@@ -79,9 +92,13 @@ impl HostOpConfig {
         };
         merged_ops.reverse();
         let mut ret = None;
+        let mut first_opcode = None;
         for (i, (((operand, opcode), index), merged_op)) in values.into_iter().zip(merged_ops).enumerate() {
             self.assign_cell(region, *offset, &HostOpConfig::filtered_operand(), *operand)?;
-            self.assign_cell(region, *offset, &HostOpConfig::filtered_opcode(), *opcode)?;
+            let opcode = self.assign_cell(region, *offset, &HostOpConfig::filtered_opcode(), *opcode)?;
+            if i == 0 {
+                first_opcode = Some(opcode);
+            }
             self.assign_cell(region, *offset, &HostOpConfig::filtered_index(), *index)?;
             self.assign_cell(region, *offset, &HostOpConfig::enable(), Fr::from(enable as u64))?;
             self.assign_cell(region, *offset, &HostOpConfig::sel(), Fr::one())?;
@@ -96,7 +113,7 @@ impl HostOpConfig {
             }
             *offset += 1;
         }
-        Ok(ret.unwrap())
+        Ok((ret.unwrap(), first_opcode.unwrap()))
     }
 
     pub fn assign_one_line(
@@ -110,15 +127,31 @@ impl HostOpConfig {
         ind: Fr,
         enable: bool,
     ) -> Result<Limb<Fr>, Error> {
+        let (r, _) = self.assign_one_line_with_opcode(region, offset, operand, opcode, index, merge, ind, enable)?;
+        Ok(r)
+    }
+
+    /// Same as assign_one_line but also returns the opcode limb
+    pub fn assign_one_line_with_opcode(
+        &self,
+        region: &mut Region<Fr>,
+        offset: &mut usize,
+        operand: Fr,
+        opcode: Fr,
+        index: Fr,
+        merge: Fr,
+        ind: Fr,
+        enable: bool,
+    ) -> Result<(Limb<Fr>, Limb<Fr>), Error> {
         let r = self.assign_cell(region, *offset, &HostOpConfig::filtered_operand(), operand)?;
-        self.assign_cell(region, *offset, &HostOpConfig::filtered_opcode(), opcode)?;
+        let opcode = self.assign_cell(region, *offset, &HostOpConfig::filtered_opcode(), opcode)?;
         self.assign_cell(region, *offset, &HostOpConfig::filtered_index(), index)?;
         self.assign_cell(region, *offset, &HostOpConfig::indicator(), ind)?;
         self.assign_cell(region, *offset, &HostOpConfig::merged_op(), merge)?;
         self.assign_cell(region, *offset, &HostOpConfig::enable(), Fr::from(enable as u64))?;
         self.assign_cell(region, *offset, &HostOpConfig::sel(), Fr::one())?;
         *offset +=1;
-        Ok(r)
+        Ok((r, opcode))
     }
 }
 
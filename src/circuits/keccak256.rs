@@ -0,0 +1,468 @@
+use crate::host::keccak256::{
+    LANES,
+    RATE_LANES,
+    DIGEST_LANES,
+    ROUND_CONSTANTS,
+    ROTATIONS,
+    pi_index,
+};
+use halo2_proofs::arithmetic::FieldExt;
+
+use crate::circuits::{
+    CommonGateConfig,
+    Limb,
+};
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::Region,
+    plonk::{
+        ConstraintSystem,
+        Error
+    },
+};
+
+const LANE_BITS: usize = 64;
+
+/* The state of keccak-f[1600] is kept as bits, the bit z of lane (x, y)
+ * is at (x + 5 * y) * 64 + z. Every bit operation takes one line of the common gate:
+ * xor: a + b - 2ab - d = 0
+ * andn: c - bc - d = 0 (d = !b & c)
+ * not: a + d - 1 = 0
+ */
+pub struct KeccakState<F: FieldExt> {
+    state: Vec<Limb<F>>,
+    default: Vec<Limb<F>>,
+}
+
+pub struct KeccakChip<F:FieldExt> {
+    pub config: CommonGateConfig,
+    keccak_state: KeccakState<F>,
+    round: u64,
+    _marker: PhantomData<F>
+}
+
+impl<F: FieldExt> KeccakChip<F> {
+    pub fn construct(config: CommonGateConfig) -> Self {
+        let state = vec![Limb::new(None, F::zero()); LANES * LANE_BITS];
+        let state = KeccakState {
+            default: state.clone(),
+            state,
+        };
+
+        KeccakChip {
+            round: 0,
+            config,
+            keccak_state: state,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn initialize(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        self.keccak_state.initialize(config, region, offset)
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<F>) -> CommonGateConfig {
+        CommonGateConfig::configure(cs, &())
+    }
+
+    /// Absorb one padded block of RATE_LANES u64 lanes and check that the first
+    /// DIGEST_LANES lanes of the permuted state equal to the result.
+    pub fn assign_permute(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        values: &[Limb<F>; RATE_LANES],
+        reset: &Limb<F>,
+        result: &[Limb<F>; DIGEST_LANES],
+    ) -> Result<(), Error> {
+        // reset * reset - reset = 0
+        self.config.assign_line(region, &mut (), offset,
+            [Some(reset.clone()), Some(reset.clone()), Some(reset.clone()), None, None, None],
+            [Some(-F::one()), None, None, None, None, None, None, Some(F::one()), None],
+            0,
+        )?;
+        let mut new_state = vec![];
+        for (value, default) in self.keccak_state.state.iter().zip(self.keccak_state.default.iter()) {
+            new_state.push(self.config.select(region, &mut (), offset, reset, value, default, self.round)?);
+        }
+        self.keccak_state.state = new_state;
+
+        for (i, value) in values.iter().enumerate() {
+            let bits = self.keccak_state.decompose_lane(&self.config, region, offset, value)?;
+            for (z, bit) in bits.iter().enumerate() {
+                let pos = i * LANE_BITS + z;
+                self.keccak_state.state[pos] = KeccakState::xor(
+                    &self.config, region, offset, &self.keccak_state.state[pos], bit
+                )?;
+            }
+        }
+
+        self.keccak_state.permute(&self.config, region, offset)?;
+
+        for (i, r) in result.iter().enumerate() {
+            let lane = self.keccak_state.compose_lane(&self.config, region, offset, i)?;
+            assert!(lane.value == r.value);
+            region.constrain_equal(
+                r.cell.as_ref().unwrap().cell(),
+                lane.cell.as_ref().unwrap().cell()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> KeccakState<F> {
+    pub fn initialize(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        *offset = 0;
+        let zero = config.assign_constant(region, &mut (), offset, &F::zero())?;
+        self.default = vec![zero; LANES * LANE_BITS];
+        self.state = self.default.clone();
+        Ok(())
+    }
+
+    fn xor(
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        a: &Limb<F>,
+        b: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        let d = a.value + b.value - F::from(2u64) * a.value * b.value;
+        let l = config.assign_line(region, &mut (), offset,
+            [Some(a.clone()), None, None, Some(b.clone()), Some(Limb::new(None, d)), None],
+            [Some(F::one()), None, None, Some(F::one()), Some(-F::one()), None, Some(-F::from(2u64)), None, None],
+            0,
+        )?;
+        Ok(l[2].clone())
+    }
+
+    /// !b & c
+    fn andn(
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        b: &Limb<F>,
+        c: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        let d = c.value - b.value * c.value;
+        let l = config.assign_line(region, &mut (), offset,
+            [Some(c.clone()), Some(b.clone()), Some(c.clone()), None, Some(Limb::new(None, d)), None],
+            [Some(F::one()), None, None, None, Some(-F::one()), None, None, Some(-F::one()), None],
+            0,
+        )?;
+        Ok(l[3].clone())
+    }
+
+    fn not(
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        a: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        let d = F::one() - a.value;
+        let l = config.assign_line(region, &mut (), offset,
+            [Some(a.clone()), None, None, None, Some(Limb::new(None, d)), None],
+            [Some(F::one()), None, None, None, Some(F::one()), None, None, None, Some(-F::one())],
+            0,
+        )?;
+        Ok(l[1].clone())
+    }
+
+    /// decompose a u64 lane into 64 bits in little endian
+    fn decompose_lane(
+        &self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        lane: &Limb<F>,
+    ) -> Result<Vec<Limb<F>>, Error> {
+        let mut bits = vec![];
+        config.decompose_limb(region, &mut (), offset, lane, &mut bits, LANE_BITS)?;
+        bits.reverse();
+        Ok(bits)
+    }
+
+    fn compose_lane(
+        &self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        lane: usize,
+    ) -> Result<Limb<F>, Error> {
+        let bits = self.state[lane * LANE_BITS..(lane + 1) * LANE_BITS]
+            .iter()
+            .enumerate()
+            .map(|(z, bit)| (bit, F::from_u128(1u128 << z)))
+            .collect::<Vec<_>>();
+        config.sum_with_constant(region, &mut (), offset, bits, None)
+    }
+
+    fn theta(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        let mut c = vec![];
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                let mut acc = self.state[x * LANE_BITS + z].clone();
+                for y in 1..5 {
+                    acc = Self::xor(config, region, offset, &acc, &self.state[(x + 5 * y) * LANE_BITS + z])?;
+                }
+                c.push(acc);
+            }
+        }
+        let mut d = vec![];
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                let left = &c[((x + 4) % 5) * LANE_BITS + z];
+                // rotate left by 1
+                let right = &c[((x + 1) % 5) * LANE_BITS + (z + LANE_BITS - 1) % LANE_BITS];
+                d.push(Self::xor(config, region, offset, left, right)?);
+            }
+        }
+        for lane in 0..LANES {
+            for z in 0..LANE_BITS {
+                let pos = lane * LANE_BITS + z;
+                self.state[pos] = Self::xor(config, region, offset, &self.state[pos], &d[(lane % 5) * LANE_BITS + z])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// rho and pi only move bits around thus do not need any line
+    fn rho_pi(&mut self) {
+        let mut b = self.state.clone();
+        for x in 0..5 {
+            for y in 0..5 {
+                let rotation = ROTATIONS[x][y] as usize;
+                for z in 0..LANE_BITS {
+                    b[pi_index(x, y) * LANE_BITS + z] =
+                        self.state[(x + 5 * y) * LANE_BITS + (z + LANE_BITS - rotation) % LANE_BITS].clone();
+                }
+            }
+        }
+        self.state = b;
+    }
+
+    fn chi(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        let b = self.state.clone();
+        for y in 0..5 {
+            for x in 0..5 {
+                for z in 0..LANE_BITS {
+                    let t = Self::andn(
+                        config, region, offset,
+                        &b[((x + 1) % 5 + 5 * y) * LANE_BITS + z],
+                        &b[((x + 2) % 5 + 5 * y) * LANE_BITS + z],
+                    )?;
+                    let pos = (x + 5 * y) * LANE_BITS + z;
+                    self.state[pos] = Self::xor(config, region, offset, &b[pos], &t)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn iota(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        rc: u64,
+    ) -> Result<(), Error> {
+        for z in 0..LANE_BITS {
+            if (rc >> z) & 1 == 1 {
+                self.state[z] = Self::not(config, region, offset, &self.state[z])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn permute(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        for rc in ROUND_CONSTANTS.iter() {
+            self.theta(config, region, offset)?;
+            self.rho_pi();
+            self.chi(config, region, offset)?;
+            self.iota(config, region, offset, *rc)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+    use crate::value_for_assign;
+    use crate::circuits::CommonGateConfig;
+    use crate::host::keccak256::{
+        LANES,
+        RATE_LANES,
+        DIGEST_LANES,
+        pad,
+        absorb,
+    };
+
+    use halo2_proofs::{
+        circuit::{Chip, Layouter, Region, SimpleFloorPlanner},
+        plonk::{
+            Advice, Circuit, Column, ConstraintSystem, Error
+        },
+    };
+
+    use super::{
+        KeccakChip,
+        Limb,
+    };
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChipConfig {
+        limb: Column<Advice>
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChip {
+        config: HelperChipConfig
+    }
+
+    impl Chip<Fr> for HelperChip {
+        type Config = HelperChipConfig;
+        type Loaded = ();
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+
+        fn loaded(&self) -> &Self::Loaded {
+            &()
+        }
+    }
+
+    impl HelperChip {
+        fn new(config: HelperChipConfig) -> Self {
+            HelperChip{
+                config,
+            }
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> HelperChipConfig {
+            let limb= cs.advice_column();
+            cs.enable_equality(limb);
+            HelperChipConfig {
+                limb,
+            }
+        }
+
+        fn assign_values(
+            &self,
+            region: &mut Region<Fr>,
+            offset: &mut usize,
+            values: &Vec<u64>,
+        ) -> Result<Vec<Limb<Fr>>, Error> {
+            let mut r = vec![];
+            for v in values.iter() {
+                let c = region.assign_advice(
+                    || format!("assign input"),
+                    self.config.limb,
+                    *offset,
+                    || value_for_assign!(Fr::from(*v))
+                )?;
+                *offset += 1;
+                r.push(Limb::new(Some(c), Fr::from(*v)));
+            }
+            Ok(r)
+        }
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        inputs: Vec<u64>,
+        result: Vec<u64>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        keccakconfig: CommonGateConfig,
+        helperconfig: HelperChipConfig,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Self::Config {
+               keccakconfig: KeccakChip::<Fr>::configure(meta),
+               helperconfig: HelperChip::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut keccakchip = KeccakChip::<Fr>::construct(config.clone().keccakconfig);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            layouter.assign_region(
+                || "assign keccak test",
+                |mut region| {
+                    let mut offset = 0;
+                    let result = helperchip.assign_values(&mut region, &mut offset, &self.result)?;
+                    let inputs = helperchip.assign_values(&mut region, &mut offset, &self.inputs)?;
+                    let reset = helperchip.assign_values(&mut region, &mut offset, &vec![1])?;
+                    offset = 0;
+                    keccakchip.initialize(&config.keccakconfig, &mut region, &mut offset)?;
+                    keccakchip.assign_permute(
+                        &mut region,
+                        &mut offset,
+                        &inputs.try_into().unwrap(),
+                        &reset[0],
+                        &result.try_into().unwrap(),
+                    )?;
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_keccak_circuit_00() {
+        let block = pad(b"abc")[0];
+        let mut state = [0u64; LANES];
+        let result = absorb(&mut state, &block);
+        assert_eq!(block.len(), RATE_LANES);
+        assert_eq!(result.len(), DIGEST_LANES);
+        let test_circuit = TestCircuit {inputs: block.to_vec(), result: result.to_vec()};
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
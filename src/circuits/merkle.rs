@@ -21,7 +21,12 @@ use halo2_proofs::circuit::{Chip, Region};
 
 use crate::host::merkle::{MerkleTree, MerkleProof};
 use crate::host::kvpair::MongoMerkle;
+use crate::host::kvpair::MAX_LEAF_WORDS;
+use crate::host::poseidon::RATE;
 use crate::circuits::Limb;
+use crate::circuits::poseidon::PoseidonChip;
+use crate::circuits::CommonGateConfig;
+use crate::utils::field_to_u64;
 
 
 /* Given a merkel tree eg1 with height=3:
@@ -30,6 +35,11 @@ use crate::circuits::Limb;
  * 3 4 5 6
  * 7 8 9 10 11 12 13 14
  * A proof of 7 = {source: 7.hash, root: 0.hash, assist: [8.hash,4.hash,2.hash], index: 7}
+ *
+ * The source of a proof is the hash of the leaf data. A value of k words is
+ * absorbed as 2k limbs of 128 bits by the poseidon sponge (see MerkleRecord::set),
+ * which are exactly the value limbs returned by the merkle adaptor.
+ * assign_leaf computes that hash in circuit.
  */
 
 customized_circuits!(MerkleConfig, 2, 7, 1, 2,
@@ -59,6 +69,7 @@ customized_circuits!(MerkleConfig, 2, 7, 1, 2,
 
 pub struct MerkleChip<F:FieldExt> {
     config: MerkleConfig,
+    poseidon: PoseidonChip<F>,
     _marker: PhantomData<F>
 }
 
@@ -77,13 +88,24 @@ impl<F: FieldExt> Chip<F> for MerkleChip<F> {
 }
 
 impl<F: FieldExt> MerkleChip<F> {
-    pub fn new(config: MerkleConfig) -> Self {
+    pub fn new(config: MerkleConfig, poseidon_config: CommonGateConfig) -> Self {
         MerkleChip {
             config,
+            poseidon: PoseidonChip::construct(poseidon_config),
             _marker: PhantomData,
         }
     }
 
+    /// Initialize the poseidon chip that hashes the leaves
+    pub fn initialize(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        let config = self.poseidon.config.clone();
+        self.poseidon.initialize(&config, region, offset)
+    }
+
     pub fn proof_height() -> usize {
         MongoMerkle::height()
     }
@@ -148,15 +170,17 @@ impl<F: FieldExt> MerkleChip<F> {
     }
 
 
+    /// Returns the source of the proof
     fn assign_proof<const D: usize, M: MerkleTree<F, D>>(
         &self,
         region: &mut Region<F>,
         offset: &mut usize,
         _merkle: &M,
         proof: &MerkleProof<F, D>,
-    ) -> Result<(), Error> {
+    ) -> Result<Limb<F>, Error> {
         let mut index_offset = proof.index - (1u32 << D) - 1;
         let mut carry = proof.source;
+        let mut source = None;
         self.config.enable_selector(region, *offset, &MerkleConfig::is_proof_start())?;
         for i in 0..D {
             let depth = D-i-1;
@@ -168,7 +192,10 @@ impl<F: FieldExt> MerkleChip<F> {
             self.config.assign_cell(region, *offset+i, &MerkleConfig::pos(), F::from(pos as u64))?;
             self.config.assign_cell(region, *offset+i, &MerkleConfig::k(), F::from(k as u64))?;
             self.config.assign_cell(region, *offset+i, &MerkleConfig::odd(), F::from(odd as u64))?;
-            self.config.assign_cell(region, *offset+i, &MerkleConfig::carry(), carry)?;
+            let limb = self.config.assign_cell(region, *offset+i, &MerkleConfig::carry(), carry)?;
+            if i == 0 {
+                source = Some(limb);
+            }
             self.config.assign_cell(region, *offset+i, &MerkleConfig::index(), F::from(index as u64))?;
             self.config.assign_cell(region, *offset+i, &MerkleConfig::left(), *left)?;
             self.config.assign_cell(region, *offset+i, &MerkleConfig::right(), *right)?;
@@ -177,19 +204,21 @@ impl<F: FieldExt> MerkleChip<F> {
             carry = M::hash(left, right);
         }
         *offset += D;
-        Ok(())
+        Ok(source.unwrap())
     }
 
+    /// Returns the source of the proof, which is the hash returned by assign_leaf
     pub fn assign_get<const D: usize, M: MerkleTree<F, D>>(
         &self,
         region: &mut Region<F>,
         offset: &mut usize,
         merkle: &M,
         proof: &MerkleProof<F, D>,
-    ) -> Result<(), Error> {
+    ) -> Result<Limb<F>, Error> {
         self.assign_proof(region, offset, merkle, proof)
     }
 
+    /// Returns the source of the proof of the new leaf, which is the hash returned by assign_leaf
     pub fn assign_set<const D: usize, M: MerkleTree<F, D>>(
         &self,
         region: &mut Region<F>,
@@ -197,8 +226,194 @@ impl<F: FieldExt> MerkleChip<F> {
         merkle: &M,
         proof_get: &MerkleProof<F, D>,
         proof_set: &MerkleProof<F, D>,
-    ) -> Result<(), Error> {
+    ) -> Result<Limb<F>, Error> {
         self.assign_proof(region, offset, merkle, proof_get)?;
         self.assign_proof(region, offset, merkle, proof_set)
     }
+
+    /// Returns the hash of the leaf value, which is the source of its proofs.
+    /// The value is given as the two 128 bits limbs of MAX_LEAF_WORDS words and
+    /// words, the number of used words, is constrained to be between 1 and MAX_LEAF_WORDS.
+    /// Only the limbs of the used words are hashed, the others must be zero.
+    /// The hash is laid out by the poseidon chip at offset.
+    pub fn assign_leaf(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        value: &[Limb<F>],
+        words: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        assert!(value.len() == 2 * MAX_LEAF_WORDS);
+        let n = field_to_u64(&words.value) as usize;
+        let mut used: Vec<Limb<F>> = vec![];
+        for (i, limbs) in value.chunks_exact(2).enumerate() {
+            let flag = if i < n { F::one() } else { F::zero() };
+            let flag = self.poseidon.assign_flag(region, offset, flag, used.last())?;
+            // limb * (1 - flag) = 0
+            for limb in limbs.iter() {
+                self.poseidon.config.assign_line(region, &mut (), offset,
+                    [Some(limb.clone()), None, None, Some(flag.clone()), None, None],
+                    [Some(F::one()), None, None, None, None, None, Some(-F::one()), None, None],
+                    0,
+                )?;
+            }
+            used.push(flag);
+        }
+        // the first word is always used
+        self.poseidon.config.assign_line(region, &mut (), offset,
+            [Some(used[0].clone()), None, None, None, None, None],
+            [Some(F::one()), None, None, None, None, None, None, None, Some(-F::one())],
+            0,
+        )?;
+        // words = number of used words
+        let count = self.poseidon.config.sum_with_constant(
+            region,
+            &mut (),
+            offset,
+            used.iter().map(|x| (x, F::one())).collect(),
+            None,
+        )?;
+        region.constrain_equal(
+            count.cell.as_ref().unwrap().cell(),
+            words.cell.as_ref().unwrap().cell()
+        )?;
+
+        let mut inputs = value.to_vec();
+        while (inputs.len() + 1) % RATE != 0 {
+            inputs.push(Limb::new(None, F::zero()));
+        }
+        let len = self.poseidon.config.sum_with_constant(
+            region,
+            &mut (),
+            offset,
+            vec![(&count, F::from(2u64))],
+            None,
+        )?;
+        self.poseidon.assign_variable_hash(region, offset, &inputs, &len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+    use crate::value_for_assign;
+    use crate::circuits::CommonGateConfig;
+    use crate::circuits::poseidon::PoseidonChip;
+    use crate::host::kvpair::MAX_LEAF_WORDS;
+    use crate::host::poseidon::gen_hasher;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{
+            Advice, Circuit, Column, ConstraintSystem, Error
+        },
+    };
+
+    use super::{
+        MerkleChip,
+        MerkleConfig,
+        Limb,
+    };
+
+    #[derive(Clone, Debug, Default)]
+    struct LeafCircuit {
+        value: Vec<Fr>,
+        words: u64,
+        result: Fr,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        merkleconfig: MerkleConfig,
+        poseidonconfig: CommonGateConfig,
+        limb: Column<Advice>,
+    }
+
+    impl Circuit<Fr> for LeafCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let limb = meta.advice_column();
+            meta.enable_equality(limb);
+            Self::Config {
+               merkleconfig: MerkleChip::<Fr>::configure(meta),
+               poseidonconfig: PoseidonChip::<Fr>::configure(meta),
+               limb,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut merklechip = MerkleChip::<Fr>::new(config.merkleconfig.clone(), config.poseidonconfig.clone());
+            layouter.assign_region(
+                || "assign merkle leaf test",
+                |mut region| {
+                    let mut limbs = vec![];
+                    let inputs = std::iter::once(Fr::from(self.words))
+                        .chain(std::iter::once(self.result))
+                        .chain(self.value.iter().cloned());
+                    for (i, v) in inputs.enumerate() {
+                        let c = region.assign_advice(
+                            || format!("assign input"),
+                            config.limb,
+                            i,
+                            || value_for_assign!(v)
+                        )?;
+                        limbs.push(Limb::new(Some(c), v));
+                    }
+                    let mut offset = 0;
+                    merklechip.initialize(&mut region, &mut offset)?;
+                    let hash = merklechip.assign_leaf(&mut region, &mut offset, &limbs[2..], &limbs[0])?;
+                    region.constrain_equal(
+                        hash.cell.as_ref().unwrap().cell(),
+                        limbs[1].cell.as_ref().unwrap().cell()
+                    )?;
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    fn leaf_circuit(value: &[Fr], words: u64, hashed: usize) -> LeafCircuit {
+        let mut hasher = gen_hasher();
+        hasher.update(&value[0..hashed]);
+        LeafCircuit { value: value.to_vec(), words, result: hasher.squeeze() }
+    }
+
+    #[test]
+    fn test_merkle_leaf_circuit() {
+        let value = (1..=2 * MAX_LEAF_WORDS).map(|x| Fr::from(x as u64)).collect::<Vec<_>>();
+        for words in 1..=MAX_LEAF_WORDS {
+            let mut value = value.clone();
+            value[2 * words..].iter_mut().for_each(|x| *x = Fr::zero());
+            let test_circuit = leaf_circuit(&value, words as u64, 2 * words);
+            let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // the hash of two words is not the one of three words
+        let mut value = value.clone();
+        value[6..].iter_mut().for_each(|x| *x = Fr::zero());
+        let test_circuit = leaf_circuit(&value, 3, 4);
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_merkle_leaf_circuit_too_many_words() {
+        let value = (1..=2 * MAX_LEAF_WORDS).map(|x| Fr::from(x as u64)).collect::<Vec<_>>();
+        let test_circuit = leaf_circuit(&value, MAX_LEAF_WORDS as u64 + 1, 2 * MAX_LEAF_WORDS);
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
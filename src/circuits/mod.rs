@@ -1,11 +1,18 @@
 pub mod bls;
 pub mod bn256;
+pub mod pairing;
 pub mod merkle;
 pub mod rmd160;
 pub mod modexp;
 pub mod poseidon;
 pub mod range;
+pub mod bitwise;
 pub mod babyjub;
+pub mod eddsa;
+pub mod ecdsa;
+pub mod keccak256;
+pub mod blake2;
+pub mod sha256;
 pub mod host;
 
 
@@ -13,7 +20,9 @@ use crate::utils::{
     Limb,
     GateCell,
     field_to_bn,
+    bn_to_field,
 };
+use num_bigint::BigUint;
 
 
 use crate::{
@@ -39,19 +48,23 @@ use halo2_proofs::{
  * Customized gates for some of the common host circuits.
  * lookup_hint: lookup information that is usually combined with l0
  * lookup_ind: whether perform lookup at this line
+ * lookup_shift: the shift of the last limb of the lookup (one if the range is whole limbs)
  */
-customized_circuits!(CommonGateConfig, 2, 5, 12, 0,
-   | l0  | l1   | l2  | l3  | d   |  c0  | c1  | c2  | c3  | cd  | cdn | c   | c03  | c12  | lookup_hint | lookup_ind  | sel
-   | nil | nil  | nil | nil | d_n |  nil | nil | nil | nil | nil | nil | nil | nil  | nil  | nil         | nil         | nil
+customized_circuits!(CommonGateConfig, 2, 5, 13, 0,
+   | l0  | l1   | l2  | l3  | d   |  c0  | c1  | c2  | c3  | cd  | cdn | c   | c03  | c12  | lookup_hint | lookup_ind  | lookup_shift | sel
+   | nil | nil  | nil | nil | d_n |  nil | nil | nil | nil | nil | nil | nil | nil  | nil  | nil         | nil         | nil          | nil
 );
 
 pub trait LookupAssistConfig {
-    /// register a column (col) to be range checked by limb size (sz)
+    /// register a column (col) to be range checked by limb size (sz),
+    /// the last limb times shift also needs to fit in a limb so that the range can be
+    /// a number of bits which is not a multiple of the limb bits
     fn register<F: FieldExt> (
         &self,
         cs: &mut ConstraintSystem<F>,
         col: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
         sz: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
+        shift: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
     );
 }
 
@@ -61,7 +74,13 @@ pub trait LookupAssistChip<F:FieldExt> {
         region: &mut Region<F>,
         value: F,
         sz: u64,
+        shift: F,
     ) -> Result<(), Error>;
+
+    /// the number of bits covered by one unit of the limb size (sz)
+    fn limb_bits(&self) -> usize {
+        12
+    }
 }
 
 impl CommonGateConfig {
@@ -69,7 +88,7 @@ impl CommonGateConfig {
         let witness= [0; 5]
                 .map(|_|cs.advice_column());
         witness.map(|x| cs.enable_equality(x));
-        let fixed = [0; 12].map(|_| cs.fixed_column());
+        let fixed = [0; 13].map(|_| cs.fixed_column());
         let selector =[];
 
         let config = CommonGateConfig { fixed, selector, witness };
@@ -78,6 +97,7 @@ impl CommonGateConfig {
             cs,
             |c| config.get_expr(c, CommonGateConfig::l0()) * config.get_expr(c, CommonGateConfig::lookup_ind()),
             |c| config.get_expr(c, CommonGateConfig::lookup_hint()),
+            |c| config.get_expr(c, CommonGateConfig::lookup_shift()),
         );
 
         cs.create_gate("one line constraint", |meta| {
@@ -166,6 +186,7 @@ impl CommonGateConfig {
         bool_limbs.truncate(limbsize);
         bool_limbs.resize_with(limbsize, | | 0);
         bool_limbs.reverse();
+        let start = limbs.len();
         let mut v = F::zero();
         for i in 0..(limbsize/4) {
             let l0 = F::from_u128(bool_limbs[4*i] as u128);
@@ -228,13 +249,9 @@ impl CommonGateConfig {
                 ],
                 0,
             )?;
-        /* todo
-         * constraint all the limbs to be either 1 or 0
-         */
-
         // apply eqn: (val * val) - val = 0,
         // by: (ws[1] * ws[2] * cs[7]) + (ws[0] * cs[0]) = 0,
-        for i in 0..(limbs.len()) {
+        for i in start..(limbs.len()) {
             let lm = limbs[i].clone();
             let _l = self.assign_line(
                 region,
@@ -261,6 +278,206 @@ impl CommonGateConfig {
         Ok(())
     }
 
+    ///
+    /// decompose a limb into windows of window_bits bits, in big endian
+    /// limbsize needs to be a multiple of window_bits and each window is looked up
+    /// as a range of window_bits bits by the lookup assist chip (one line per window).
+    /// The `()` chip does not check the windows, window_indicators then bounds them.
+    pub fn decompose_windows<F:FieldExt, LC:LookupAssistChip<F>>(
+        &self,
+        region: &mut Region<F>,
+        lookup_assist_chip: &mut LC,
+        offset: &mut usize,
+        limb: &Limb<F>,
+        windows: &mut Vec<Limb<F>>,
+        limbsize: usize,
+        window_bits: usize,
+    ) -> Result <(), Error> {
+        assert!(limbsize % window_bits == 0);
+        assert!(window_bits <= lookup_assist_chip.limb_bits());
+        let bn = field_to_bn(&limb.value);
+        let mut v = F::zero();
+        let mut new_windows = vec![];
+        for i in (0..(limbsize/window_bits)).rev() {
+            let w = (bn.clone() >> (i * window_bits)) % (BigUint::from(1u128) << window_bits);
+            let w = bn_to_field::<F>(&w);
+            let v_next = v * F::from_u128(1u128 << window_bits) + w;
+            let l = self.assign_line_with_bits(
+                region,
+                lookup_assist_chip,
+                offset,
+                [
+                    Some(Limb::new(None, w)),
+                    None,
+                    None,
+                    None,
+                    Some(Limb::new(None, v)),
+                    Some(Limb::new(None, v_next)),
+                ],
+                [
+                    Some(F::one()),
+                    None,
+                    None,
+                    None,
+                    if new_windows.is_empty() {None} else {Some(F::from_u128(1u128 << window_bits))},
+                    Some(-F::one()),
+                    None, None, None
+                ],
+                window_bits,
+            )?;
+            new_windows.push(l[0].clone());
+            v = v_next;
+        }
+        // constraint that limb.value is equal v_next so that the above windows is
+        // a real decompose of the limb.value
+        self.assign_line(
+                region,
+                lookup_assist_chip,
+                offset,
+                [
+                    Some(limb.clone()),
+                    None,
+                    None,
+                    None,
+                    Some(Limb::new(None, v)),
+                    None,
+                ],
+                [
+                    Some(F::one()),
+                    None,
+                    None,
+                    None,
+                    Some(-F::one()),
+                    None,
+                    None, None, None
+                ],
+                0,
+            )?;
+        windows.append(&mut new_windows);
+        Ok(())
+    }
+
+    /// The indicators e_j (j < size) of a window: e_j = 1 if window = j else 0.
+    /// Each indicator takes one line (w - j) * e_j = 0 and the indicators sum to one,
+    /// thus e_window = 1 and a window out of [0, size) can not be satisfied.
+    pub fn window_indicators<F:FieldExt, LC:LookupAssistChip<F>>(
+        &self,
+        region: &mut Region<F>,
+        lookup_assist_chip: &mut LC,
+        offset: &mut usize,
+        window: &Limb<F>,
+        size: usize,
+    ) -> Result<Vec<Limb<F>>, Error> {
+        let mut indicators = vec![];
+        for j in 0..size {
+            let e = if window.value == F::from(j as u64) { F::one() } else { F::zero() };
+            // (w - j) * e = 0
+            let l = self.assign_line(
+                region,
+                lookup_assist_chip,
+                offset,
+                [
+                    Some(window.clone()),
+                    None,
+                    None,
+                    Some(Limb::new(None, e)),
+                    None,
+                    None,
+                ],
+                [None, None, None, Some(-F::from(j as u64)), None, None, Some(F::one()), None, None],
+                0,
+            )?;
+            indicators.push(l[1].clone());
+        }
+        // sum of e_j - 1 = 0, four indicators per line
+        let mut acc = -F::one();
+        let chunks = indicators.chunks(4).collect::<Vec<_>>();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let last = i == chunks.len() - 1;
+            let acc_next = chunk.iter().fold(acc, |acc, e| acc + e.value);
+            let mut limbs = chunk.iter().map(|e| Some(e.clone())).collect::<Vec<_>>();
+            let mut coeffs = chunk.iter().map(|_| Some(F::one())).collect::<Vec<_>>();
+            limbs.resize_with(4, || None);
+            coeffs.resize_with(4, || None);
+            limbs.append(&mut vec![
+                if i == 0 {None} else {Some(Limb::new(None, acc))},
+                if last {None} else {Some(Limb::new(None, acc_next))},
+            ]);
+            coeffs.append(&mut vec![
+                if i == 0 {None} else {Some(F::one())},
+                if last {None} else {Some(-F::one())},
+                None,
+                None,
+                if i == 0 {Some(-F::one())} else {None},
+            ]);
+            self.assign_line(
+                region,
+                lookup_assist_chip,
+                offset,
+                limbs.try_into().unwrap(),
+                coeffs.try_into().unwrap(),
+                0,
+            )?;
+            acc = acc_next;
+        }
+        Ok(indicators)
+    }
+
+    /// sum of e_j * candidates_j, selects the candidate of the window when e are the window indicators
+    pub fn select_by_indicators<F:FieldExt, LC:LookupAssistChip<F>>(
+        &self,
+        region: &mut Region<F>,
+        lookup_assist_chip: &mut LC,
+        offset: &mut usize,
+        indicators: &Vec<Limb<F>>,
+        candidates: &Vec<Limb<F>>,
+    ) -> Result<Limb<F>, Error> {
+        assert!(indicators.len() == candidates.len());
+        let mut acc = F::zero();
+        let mut firstline = true;
+        let pairs = indicators.iter().zip(candidates.iter()).collect::<Vec<_>>();
+        for chunk in pairs.chunks(2) {
+            let (e0, c0) = chunk[0];
+            let (e1, c1) = chunk.get(1).map_or((None, None), |(e, c)| (Some((*e).clone()), Some((*c).clone())));
+            let acc_next = acc + e0.value * c0.value
+                + e1.as_ref().map_or(F::zero(), |e| e.value) * c1.as_ref().map_or(F::zero(), |c| c.value);
+            self.assign_line(
+                region,
+                lookup_assist_chip,
+                offset,
+                [
+                    Some(e0.clone()),
+                    e1.clone(),
+                    c1.clone(),
+                    Some(c0.clone()),
+                    Some(Limb::new(None, acc)),
+                    Some(Limb::new(None, acc_next)),
+                ],
+                [
+                    None, None, None, None,
+                    if firstline {None} else {Some(F::one())},
+                    Some(-F::one()),
+                    Some(F::one()),
+                    e1.as_ref().map(|_| F::one()),
+                    None
+                ],
+                0,
+            )?;
+            acc = acc_next;
+            firstline = false;
+        }
+        // collect the last acc as result
+        let l = self.assign_line(
+            region,
+            lookup_assist_chip,
+            offset,
+            [Some(Limb::new(None, acc)), None, None, None, Some(Limb::new(None, acc)), None],
+            [Some(-F::one()), None, None, None, Some(F::one()), None, None, None, None],
+            0,
+        )?;
+        Ok(l[0].clone())
+    }
+
     /// put pure witness advices with no constraints.
     fn assign_witness<F:FieldExt, LC:LookupAssistChip<F>> (
        &self,
@@ -292,6 +509,9 @@ impl CommonGateConfig {
         self.assign_cell(region, *offset, &CommonGateConfig::lookup_ind(), F::from(
             if hint == 0 {0u64} else {1u64}
         ))?;
+        self.assign_cell(region, *offset, &CommonGateConfig::lookup_shift(), F::from(
+            if hint == 0 {0u64} else {1u64}
+        ))?;
 
         *offset = *offset+1;
         Ok(limbs)
@@ -307,6 +527,35 @@ impl CommonGateConfig {
        value:  [Option<Limb<F>>; 6],
        coeffs: [Option<F>; 9],
        hint: u64, // the boundary limit of the first cell
+    ) -> Result<Vec<Limb<F>>, Error> {
+        self.assign_line_with_shift(region, lookup_assist_chip, offset, value, coeffs, hint, F::one())
+    }
+
+    /// assign a line whose first cell is less than 2^bits (no lookup if bits is zero)
+    fn assign_line_with_bits<F:FieldExt, LC:LookupAssistChip<F>> (
+       &self,
+       region: &mut Region<F>,
+       lookup_assist_chip: &mut LC,
+       offset: &mut usize,
+       value:  [Option<Limb<F>>; 6],
+       coeffs: [Option<F>; 9],
+       bits: usize,
+    ) -> Result<Vec<Limb<F>>, Error> {
+        let limb_bits = lookup_assist_chip.limb_bits();
+        let sz = (bits + limb_bits - 1) / limb_bits;
+        let shift = F::from_u128(1u128 << (sz * limb_bits - bits));
+        self.assign_line_with_shift(region, lookup_assist_chip, offset, value, coeffs, sz as u64, shift)
+    }
+
+    fn assign_line_with_shift<F:FieldExt, LC:LookupAssistChip<F>> (
+       &self,
+       region: &mut Region<F>,
+       lookup_assist_chip: &mut LC,
+       offset: &mut usize,
+       value:  [Option<Limb<F>>; 6],
+       coeffs: [Option<F>; 9],
+       hint: u64, // the boundary limit of the first cell
+       shift: F, // the shift of the last limb of the boundary
     ) -> Result<Vec<Limb<F>>, Error> {
         let ws = value.clone().to_vec().iter()
             .map(|x|x.clone().map_or(F::zero(), |x| x.value))
@@ -365,9 +614,12 @@ impl CommonGateConfig {
         self.assign_cell(region, *offset, &CommonGateConfig::lookup_ind(), F::from(
             if hint == 0 {0u64} else {1u64}
         ))?;
+        self.assign_cell(region, *offset, &CommonGateConfig::lookup_shift(),
+            if hint == 0 {F::zero()} else {shift}
+        )?;
 
         if hint != 0 {
-            lookup_assist_chip.provide_lookup_evidence(region, value[0].as_ref().unwrap().value, hint)?;
+            lookup_assist_chip.provide_lookup_evidence(region, value[0].as_ref().unwrap().value, hint, shift)?;
         };
 
         *offset = *offset+1;
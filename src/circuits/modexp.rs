@@ -1,8 +1,8 @@
 use crate::utils::{bn_to_field, field_to_bn, Limb};
 
-use crate::circuits::CommonGateConfig;
+use crate::circuits::{CommonGateConfig, LookupAssistChip};
 
-use crate::circuits::range::{RangeCheckChip, RangeCheckConfig};
+use crate::circuits::range::RangeCheckConfig;
 
 use std::ops::{Div, Mul};
 
@@ -14,6 +14,10 @@ use halo2_proofs::{
 use num_bigint::BigUint;
 use std::marker::PhantomData;
 
+/// The number of exponent bits consumed by one multiplication in mod_exp
+/// (256 squarings and 64 + 14 multiplications instead of 2 * 256 bit by bit, see test_modexp_rows)
+pub const EXP_WINDOW_BITS: usize = 4;
+
 pub struct ModExpChip<F: FieldExt> {
     config: CommonGateConfig,
     _marker: PhantomData<F>,
@@ -89,10 +93,10 @@ impl<F: FieldExt> ModExpChip<F> {
         CommonGateConfig::configure(cs, range_check_config)
     }
 
-    pub fn assign_constant_number(
+    pub fn assign_constant_number<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         number: Number<F>,
     ) -> Result<Number<F>, Error> {
@@ -111,20 +115,20 @@ impl<F: FieldExt> ModExpChip<F> {
         })
     }
 
-    pub fn assign_number(
+    pub fn assign_number<LC: LookupAssistChip<F>>(
         &self,
         _region: &mut Region<F>,
-        _range_check_chip: &mut RangeCheckChip<F>,
+        _range_check_chip: &mut LC,
         _offset: &mut usize,
         number: Number<F>,
     ) -> Result<Number<F>, Error> {
         Ok(number)
     }
 
-    pub fn mod_add(
+    pub fn mod_add<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         lhs: &Number<F>,
         rhs: &Number<F>,
@@ -172,10 +176,10 @@ impl<F: FieldExt> ModExpChip<F> {
         })
     }
 
-    pub fn mod_native_mul(
+    pub fn mod_native_mul<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         rem: &Number<F>,
         lhs: &Number<F>,
@@ -209,10 +213,10 @@ impl<F: FieldExt> ModExpChip<F> {
         Ok(l[2].clone())
     }
 
-    pub fn mod_power108m1(
+    pub fn mod_power108m1<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         number: &Number<F>,
     ) -> Result<[Limb<F>; 4], Error> {
@@ -245,10 +249,10 @@ impl<F: FieldExt> ModExpChip<F> {
         Ok(l.try_into().unwrap())
     }
 
-    pub fn mod_power216(
+    pub fn mod_power216<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         number: &Number<F>,
     ) -> Result<Limb<F>, Error> {
@@ -281,10 +285,10 @@ impl<F: FieldExt> ModExpChip<F> {
         Ok(l[2].clone())
     }
 
-    pub fn mod_power108m1_mul(
+    pub fn mod_power108m1_mul<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         lhs: &Number<F>,
         rhs: &Number<F>,
@@ -297,7 +301,7 @@ impl<F: FieldExt> ModExpChip<F> {
         let bn_r = field_to_bn(&v) - bn_q.clone() * bn_modulus; // at most 108 bits
         let q = Limb::new(None, bn_to_field(&bn_q));
         let r = Limb::new(None, bn_to_field(&bn_r));
-        let l = self.config.assign_line(
+        let l = self.config.assign_line_with_bits(
             region,
             range_check_chip,
             offset,
@@ -313,7 +317,7 @@ impl<F: FieldExt> ModExpChip<F> {
                 Some(-F::one()),
                 None,
             ],
-            10,
+            120, // q has at most 112 bits when the limbs have 108 bits
         )?;
         Ok(l[3].clone())
     }
@@ -323,10 +327,10 @@ impl<F: FieldExt> ModExpChip<F> {
     /// (r     * 1    ) + (x0    * y0    * 1    ) + (v     * 1    ) = 0 \
     /// (ws[0] * cs[0]) + (ws[1] * ws[2] * cs[7]) + (ws[4] * cs[4]) = 0
     ///
-    pub fn mod_power216_mul(
+    pub fn mod_power216_mul<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         lhs: &Number<F>,
         rhs: &Number<F>,
@@ -369,7 +373,7 @@ impl<F: FieldExt> ModExpChip<F> {
         let q = Limb::new(None, bn_to_field(&bn_q));
         let r = Limb::new(None, bn_to_field(&bn_r));
 
-        let l = self.config.assign_line(
+        let l = self.config.assign_line_with_bits(
             region,
             range_check_chip,
             offset,
@@ -385,12 +389,12 @@ impl<F: FieldExt> ModExpChip<F> {
                 None,
                 None,
             ],
-            10,
+            109, // v has at most 217 bits
         )?;
         let rcell = l[2].clone();
         v = rcell.value * F::from_u128(1u128 << 108) + x0 * y0;
 
-        let l = self.config.assign_line(
+        let l = self.config.assign_line_with_bits(
             region,
             range_check_chip,
             offset,
@@ -413,15 +417,15 @@ impl<F: FieldExt> ModExpChip<F> {
                 Some(F::one()),
                 None,
             ],
-            0, // TODO: need to check rcell range
+            108,
         )?;
         Ok(l[3].clone())
     }
 
-    pub fn mod_power108m1_zero(
+    pub fn mod_power108m1_zero<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         limbs: Vec<Limb<F>>,
         signs: Vec<F>,
@@ -432,7 +436,7 @@ impl<F: FieldExt> ModExpChip<F> {
             + limbs[1].value * signs[1]
             + limbs[2].value * signs[2];
         let q = field_to_bn(&v).div(c);
-        self.config.assign_line(
+        self.config.assign_line_with_bits(
             region,
             range_check_chip,
             offset,
@@ -455,7 +459,7 @@ impl<F: FieldExt> ModExpChip<F> {
                 None,
                 Some(F::from_u128(c * 16u128)),
             ],
-            1, // check rcell range
+            12, // check rcell range
         )?;
         Ok(())
     }
@@ -464,10 +468,10 @@ impl<F: FieldExt> ModExpChip<F> {
     /// # Apply constraint:
     /// (q * -c) + (sum(limb_i * sign_i)) + (c * BUFMULT)  = 0
     ///
-    pub fn mod_power216_zero(
+    pub fn mod_power216_zero<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         limbs: Vec<Limb<F>>,
         signs: Vec<F>,
@@ -480,7 +484,7 @@ impl<F: FieldExt> ModExpChip<F> {
             + limbs[1].value * signs[1]
             + limbs[2].value * signs[2];
         let q = field_to_bn(&v).div(field_to_bn(&f_c));
-        self.config.assign_line(
+        self.config.assign_line_with_bits(
             region,
             range_check_chip,
             offset,
@@ -503,7 +507,7 @@ impl<F: FieldExt> ModExpChip<F> {
                 None,
                 Some(f_cm),
             ],
-            1, // check rcell range
+            12, // check rcell range
         )?;
         Ok(())
     }
@@ -516,10 +520,10 @@ impl<F: FieldExt> ModExpChip<F> {
     ///    (x1*y0+x0*y1)*2^216+x0*y0 = (q0*m1+q1*m0)*2^{216}+q0*m0+r1+r0
     /// native:
     ///   x*y = q*m + r
-    pub fn mod_mult(
+    pub fn mod_mult<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         lhs: &Number<F>,
         rhs: &Number<F>,
@@ -585,10 +589,10 @@ impl<F: FieldExt> ModExpChip<F> {
     /// * `cond` - the exp_bit as a Limb in F, is only 0x1 or 0x0
     /// * `base` - the value of the base as a Number<F>
     /// * `one`  - the value of 1 as a Number<F>
-    pub fn select(
+    pub fn select<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         cond: &Limb<F>,
         base: &Number<F>,
@@ -612,51 +616,95 @@ impl<F: FieldExt> ModExpChip<F> {
         })
     }
 
-    /// base^exp mod modulus
-    pub fn mod_exp(
+    /// Selects powers[window] where indicators are the indicators of the window
+    pub fn select_window<LC: LookupAssistChip<F>>(
+        &self,
+        region: &mut Region<F>,
+        range_check_chip: &mut LC,
+        offset: &mut usize,
+        indicators: &Vec<Limb<F>>,
+        powers: &Vec<Number<F>>,
+    ) -> Result<Number<F>, Error> {
+        let mut limbs = vec![];
+        for i in 0..4 {
+            let candidates = powers.iter().map(|p| p.limbs[i].clone()).collect::<Vec<_>>();
+            let l = self.config.select_by_indicators(
+                region,
+                range_check_chip,
+                offset,
+                indicators,
+                &candidates,
+            )?;
+            limbs.push(l);
+        }
+        Ok(Number {
+            limbs: limbs.try_into().unwrap(),
+        })
+    }
+
+    /// base^exp mod modulus with windows of EXP_WINDOW_BITS bits of the exponent
+    pub fn mod_exp<LC: LookupAssistChip<F>>(
         &self,
         region: &mut Region<F>,
-        range_check_chip: &mut RangeCheckChip<F>,
+        range_check_chip: &mut LC,
         offset: &mut usize,
         base: &Number<F>,
         exp: &Number<F>,
         modulus: &Number<F>,
     ) -> Result<Number<F>, Error> {
-        let mut limbs = vec![];
-        self.config.decompose_limb(
+        let mut windows = vec![];
+        self.config.decompose_windows(
             region,
             range_check_chip,
             offset,
             &exp.limbs[2],
-            &mut limbs,
+            &mut windows,
             40,
+            EXP_WINDOW_BITS,
         )?; //256 - 216 = 40
-        self.config.decompose_limb(
+        self.config.decompose_windows(
             region,
             range_check_chip,
             offset,
             &exp.limbs[1],
-            &mut limbs,
+            &mut windows,
             108,
+            EXP_WINDOW_BITS,
         )?;
-        self.config.decompose_limb(
+        self.config.decompose_windows(
             region,
             range_check_chip,
             offset,
             &exp.limbs[0],
-            &mut limbs,
+            &mut windows,
             108,
+            EXP_WINDOW_BITS,
         )?;
-        let mut acc = self.assign_constant_number(
+        let one = self.assign_constant_number(
             region,
             range_check_chip,
             offset,
             Number::from_bn(&BigUint::from(1 as u128)),
         )?;
-        let one = acc.clone();
-        for limb in limbs.iter() {
-            acc = self.mod_mult(region, range_check_chip, offset, &acc, &acc, modulus)?;
-            let sval = self.select(region, range_check_chip, offset, &limb, &base, &one)?;
+        // powers[j] = base^j
+        let mut powers = vec![one.clone(), base.clone()];
+        for _ in 2..(1 << EXP_WINDOW_BITS) {
+            let p = self.mod_mult(region, range_check_chip, offset, powers.last().unwrap(), base, modulus)?;
+            powers.push(p);
+        }
+        let mut acc = one;
+        for window in windows.iter() {
+            for _ in 0..EXP_WINDOW_BITS {
+                acc = self.mod_mult(region, range_check_chip, offset, &acc, &acc, modulus)?;
+            }
+            let indicators = self.config.window_indicators(
+                region,
+                range_check_chip,
+                offset,
+                window,
+                1 << EXP_WINDOW_BITS,
+            )?;
+            let sval = self.select_window(region, range_check_chip, offset, &indicators, &powers)?;
             acc = self.mod_mult(region, range_check_chip, offset, &acc, &sval, modulus)?;
         }
         Ok(acc)
@@ -1145,15 +1193,16 @@ mod tests {
         }
     }
 
+    /// mod_exp with a range check of RANGE_BITS bits per limb
     #[derive(Clone, Debug, Default)]
-    struct TestModExpCircuit {
+    struct TestModExpCircuit<const RANGE_BITS: usize> {
         base: BigUint,
         exp: BigUint,
         modulus: BigUint,
         bn_test_res: BigUint,
     }
 
-    impl Circuit<Fr> for TestModExpCircuit {
+    impl<const RANGE_BITS: usize> Circuit<Fr> for TestModExpCircuit<RANGE_BITS> {
         type Config = TestConfig;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1162,7 +1211,7 @@ mod tests {
         }
 
         fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
-            let rangecheckconfig = RangeCheckChip::<Fr>::configure(meta);
+            let rangecheckconfig = RangeCheckChip::<Fr, RANGE_BITS>::configure(meta);
             Self::Config {
                 modexpconfig: ModExpChip::<Fr>::configure(meta, &rangecheckconfig),
                 helperconfig: HelperChip::configure(meta),
@@ -1177,7 +1226,7 @@ mod tests {
         ) -> Result<(), Error> {
             let modexpchip = ModExpChip::<Fr>::new(config.clone().modexpconfig);
             let helperchip = HelperChip::new(config.clone().helperconfig);
-            let mut range_chip = RangeCheckChip::<Fr>::new(config.clone().rangecheckconfig);
+            let mut range_chip = RangeCheckChip::<Fr, RANGE_BITS>::new(config.clone().rangecheckconfig);
             layouter.assign_region(
                 || "assign mod exp",
                 |mut region| {
@@ -1213,6 +1262,102 @@ mod tests {
         }
     }
 
+    /// mod_exp next to the bit by bit square and multiply it replaces,
+    /// the rows taken by each of them are compared in synthesize
+    #[derive(Clone, Debug, Default)]
+    struct TestModExpRowsCircuit {
+        base: BigUint,
+        exp: BigUint,
+        modulus: BigUint,
+    }
+
+    impl Circuit<Fr> for TestModExpRowsCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let rangecheckconfig = RangeCheckChip::<Fr>::configure(meta);
+            Self::Config {
+                modexpconfig: ModExpChip::<Fr>::configure(meta, &rangecheckconfig),
+                helperconfig: HelperChip::configure(meta),
+                rangecheckconfig,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let modexpchip = ModExpChip::<Fr>::new(config.clone().modexpconfig);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            let mut range_chip = RangeCheckChip::<Fr>::new(config.clone().rangecheckconfig);
+            layouter.assign_region(
+                || "assign mod exp rows",
+                |mut region| {
+                    range_chip.initialize(&mut region)?;
+                    let mut offset = 0;
+                    let base = helperchip.assign_base(&mut region, &mut offset, &self.base)?;
+                    let exp = helperchip.assign_exp(&mut region, &mut offset, &self.exp)?;
+                    let modulus =
+                        helperchip.assign_modulus(&mut region, &mut offset, &self.modulus)?;
+
+                    let start = offset;
+                    let windowed = modexpchip.mod_exp(
+                        &mut region,
+                        &mut range_chip,
+                        &mut offset,
+                        &base,
+                        &exp,
+                        &modulus,
+                    )?;
+                    let window_rows = offset - start;
+
+                    let start = offset;
+                    let mut bits = vec![];
+                    for (limb, size) in [(2, 40), (1, 108), (0, 108)] {
+                        modexpchip.config.decompose_limb(
+                            &mut region,
+                            &mut range_chip,
+                            &mut offset,
+                            &exp.limbs[limb],
+                            &mut bits,
+                            size,
+                        )?;
+                    }
+                    let one = modexpchip.assign_constant_number(
+                        &mut region,
+                        &mut range_chip,
+                        &mut offset,
+                        Number::from_bn(&BigUint::from(1u128)),
+                    )?;
+                    let mut acc = one.clone();
+                    for bit in bits.iter() {
+                        acc = modexpchip.mod_mult(&mut region, &mut range_chip, &mut offset, &acc, &acc, &modulus)?;
+                        let sval = modexpchip.select(&mut region, &mut range_chip, &mut offset, bit, &base, &one)?;
+                        acc = modexpchip.mod_mult(&mut region, &mut range_chip, &mut offset, &acc, &sval, &modulus)?;
+                    }
+                    let bit_rows = offset - start;
+
+                    println!("mod_exp rows: {} with windows, {} bit by bit", window_rows, bit_rows);
+                    assert!(window_rows < bit_rows);
+                    for i in 0..4 {
+                        region.constrain_equal(
+                            windowed.limbs[i].clone().cell.unwrap().cell(),
+                            acc.limbs[i].clone().cell.unwrap().cell(),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
     fn run_mod_power108m1_circuit() -> Result<(), CircuitError> {
         // Create an a set of test vectors varying in bit lengths.
         // Test will pass if:
@@ -1394,7 +1539,7 @@ mod tests {
         Ok(())
     }
 
-    fn run_modexp_circuit() -> Result<(), CircuitError> {
+    fn run_modexp_circuit<const RANGE_BITS: usize>() -> Result<(), CircuitError> {
         // Create an a set of test vectors varying in bit lengths for base, exp & modulus.
         // Test will pass if:
         //  (1) result returned from circuit constrain_equal() to the
@@ -1437,7 +1582,7 @@ mod tests {
             let base = base_testcase.clone();
             let exp = exp_testcase.clone();
             let modulus = modulus_testcase.clone();
-            let test_circuit = TestModExpCircuit {
+            let test_circuit = TestModExpCircuit::<RANGE_BITS> {
                 base,
                 exp,
                 modulus,
@@ -1491,10 +1636,27 @@ mod tests {
 
     #[test]
     fn test_modexp() {
-        let output = run_modexp_circuit().expect("\nmodexp_circuit failed prover verify");
+        let output = run_modexp_circuit::<12>().expect("\nmodexp_circuit failed prover verify");
+        println!("\nproof generation successful!\nresult: {:#?}", output);
+    }
+
+    #[test]
+    fn test_modexp_with_8bit_range() {
+        // the exponent windows and the exact bit ranges also work with smaller limbs
+        let output = run_modexp_circuit::<8>().expect("\nmodexp_circuit failed prover verify");
         println!("\nproof generation successful!\nresult: {:#?}", output);
     }
 
+    #[test]
+    fn test_modexp_rows() {
+        let base = get_random_x_bit_bn(16);
+        let exp = get_random_x_bit_bn(LIMB_WIDTH + LIMB_WIDTH + 40);
+        let modulus = get_random_x_bit_bn(20);
+        let test_circuit = TestModExpRowsCircuit { base, exp, modulus };
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
     // test helpers:
     use halo2_proofs::{
         dev::VerifyFailure,
@@ -0,0 +1,647 @@
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::{
+    arithmetic::{BaseExt, CurveAffine, FieldExt},
+    circuit::{AssignedCell, Chip, Layouter, Region},
+    plonk::{ConstraintSystem, Error},
+};
+use halo2ecc_s::circuit::base_chip::BaseChipOps;
+use halo2ecc_s::circuit::fq12::{Fq12ChipOps, Fq2ChipOps};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use halo2ecc_s::assign::{AssignedCondition, AssignedFq, Cell as ContextCell};
+use halo2ecc_s::assign::{AssignedFq12, AssignedFq2, AssignedG2Affine, AssignedPoint};
+use halo2ecc_s::circuit::ecc_chip::EccBaseIntegerChipWrapper;
+use halo2ecc_s::circuit::{ecc_chip::EccChipBaseOps, pairing_chip::PairingChipOps};
+
+use halo2ecc_s::{
+    circuit::{
+        base_chip::{BaseChip, BaseChipConfig},
+        range_chip::{RangeChip, RangeChipConfig},
+        select_chip::{SelectChip, SelectChipConfig},
+    },
+    context::Context,
+};
+
+use num_bigint::BigUint;
+use std::ops::{AddAssign, Mul};
+use crate::utils::{bn_to_field, Limb};
+
+/// A pairing friendly curve whose G2 lies on a twist over Fq2.
+/// The limbs of the host call table are 108 bits so an Fq takes FQ_LIMBS limbs,
+/// a G1 is (x, y, z) and a G2 is ((x0, x1), (y0, y1), z) where z is the identity flag.
+pub trait PairingCurve {
+    type Fq: BaseExt;
+    type G1Affine: CurveAffine<Base = Self::Fq>;
+    type Context: EccChipBaseOps<Self::G1Affine, Fr>
+        + EccBaseIntegerChipWrapper<Self::Fq, Fr>
+        + Fq2ChipOps<Self::Fq, Fr>
+        + Fq12ChipOps<Self::Fq, Fr>
+        + PairingChipOps<Self::G1Affine, Fr>
+        + Into<Context<Fr>>;
+
+    /// limbs of 108 bits of an Fq
+    const FQ_LIMBS: usize;
+    const G1_LIMBS: usize = Self::FQ_LIMBS * 2 + 1;
+    const G2_LIMBS: usize = Self::FQ_LIMBS * 4 + 1;
+    const GT_LIMBS: usize = Self::FQ_LIMBS * 12;
+
+    /// b of the curve y^2 = x^3 + b
+    const G1_B: u64;
+    /// b of the twist y^2 = x^3 + b in hex of (c0, c1)
+    const G2_B: [&'static [u8]; 2];
+    /// psi(x, y) = (conj(x) * PSI_X, conj(y) * PSI_Y) in hex of (c0, c1)
+    const PSI_X: [&'static [u8]; 2];
+    const PSI_Y: [&'static [u8]; 2];
+    /// psi acts as the multiplication by p (mod r) on G2, the hex of |p (mod r)| and its sign
+    const PSI_EIGENVALUE: &'static [u8];
+    const PSI_EIGENVALUE_NEG: bool;
+
+    fn new_context(context: Rc<RefCell<Context<Fr>>>) -> Self::Context;
+
+    fn native_ctx(ctx: &Self::Context) -> &Rc<RefCell<Context<Fr>>>;
+
+    /// Constrain a G1 point on the curve to be in the subgroup, nothing to do for a cofactor of one.
+    fn assert_g1_in_subgroup(
+        _ctx: &mut Self::Context,
+        _p: &AssignedPoint<Self::G1Affine, Fr>,
+    ) {
+    }
+}
+
+pub fn fr_to_bn(f: &Fr) -> BigUint {
+    let mut bytes: Vec<u8> = Vec::new();
+    f.write(&mut bytes).unwrap();
+    BigUint::from_bytes_le(&bytes[..])
+}
+
+pub fn fr_to_bool(f: &Fr) -> bool {
+    let mut bytes: Vec<u8> = Vec::new();
+    f.write(&mut bytes).unwrap();
+    return bytes[0] == 1u8;
+}
+
+pub fn assigned_cells_to_bn<C: PairingCurve>(
+    a: &Vec<Limb<Fr>>,
+    start: usize,
+) -> BigUint {
+    let mut bn = BigUint::from(0 as u64);
+    for i in start..start + C::FQ_LIMBS {
+        let shift = BigUint::from(2 as u32).pow(108 * (i - start) as u32);
+        bn.add_assign(fr_to_bn(&a[i].value).mul(shift.clone()));
+    }
+    bn
+}
+
+pub fn assign_fq_constant<C: PairingCurve>(
+    ctx: &mut C::Context,
+    v: &BigUint,
+) -> AssignedFq<C::Fq, Fr> {
+    ctx.base_integer_chip().assign_int_constant(bn_to_field(v))
+}
+
+fn assign_fq2_constant<C: PairingCurve>(
+    ctx: &mut C::Context,
+    c: [&'static [u8]; 2],
+) -> AssignedFq2<C::Fq, Fr> {
+    let c0 = assign_fq_constant::<C>(ctx, &BigUint::parse_bytes(c[0], 16).unwrap());
+    let c1 = assign_fq_constant::<C>(ctx, &BigUint::parse_bytes(c[1], 16).unwrap());
+    (c0, c1)
+}
+
+pub fn get_g1_from_cells<C: PairingCurve>(
+    ctx: &mut C::Context,
+    a: &Vec<Limb<Fr>>, //G1 (FQ_LIMBS * 2 + 1)
+) -> AssignedPoint<C::G1Affine, Fr> {
+    let x_bn = assigned_cells_to_bn::<C>(a, 0);
+    let y_bn = assigned_cells_to_bn::<C>(a, C::FQ_LIMBS);
+    let is_identity = fr_to_bool(&a[C::FQ_LIMBS * 2].value);
+    let x = ctx.base_integer_chip().assign_w(&x_bn);
+    let y = ctx.base_integer_chip().assign_w(&y_bn);
+    let z = C::native_ctx(ctx).borrow_mut().assign_bit(if is_identity {
+        Fr::one()
+    } else {
+        Fr::zero()
+    });
+    let p = AssignedPoint::new(x, y, z);
+    assert_g1_on_curve::<C>(ctx, &p);
+    C::assert_g1_in_subgroup(ctx, &p);
+    p
+}
+
+pub fn get_g2_from_cells<C: PairingCurve>(
+    ctx: &mut C::Context,
+    b: &Vec<Limb<Fr>>, //G2 (FQ_LIMBS * 4 + 1)
+) -> AssignedG2Affine<C::G1Affine, Fr> {
+    let x1_bn = assigned_cells_to_bn::<C>(b, 0);
+    let x2_bn = assigned_cells_to_bn::<C>(b, C::FQ_LIMBS);
+    let y1_bn = assigned_cells_to_bn::<C>(b, C::FQ_LIMBS * 2);
+    let y2_bn = assigned_cells_to_bn::<C>(b, C::FQ_LIMBS * 3);
+    let x1 = ctx.base_integer_chip().assign_w(&x1_bn);
+    let x2 = ctx.base_integer_chip().assign_w(&x2_bn);
+    let y1 = ctx.base_integer_chip().assign_w(&y1_bn);
+    let y2 = ctx.base_integer_chip().assign_w(&y2_bn);
+    let is_identity = fr_to_bool(&b[C::FQ_LIMBS * 4].value);
+    let z = C::native_ctx(ctx).borrow_mut().assign_bit(if is_identity {
+        Fr::one()
+    } else {
+        Fr::zero()
+    });
+    let p = AssignedG2Affine::new((x1, x2), (y1, y2), z);
+    assert_g2_on_curve::<C>(ctx, &p);
+    assert_g2_in_subgroup::<C>(ctx, &p);
+    p
+}
+
+fn fq2_assert_equal<C: PairingCurve>(
+    ctx: &mut C::Context,
+    a: &AssignedFq2<C::Fq, Fr>,
+    b: &AssignedFq2<C::Fq, Fr>,
+) {
+    ctx.base_integer_chip().assert_int_equal(&a.0, &b.0);
+    ctx.base_integer_chip().assert_int_equal(&a.1, &b.1);
+}
+
+/// y^2 = x^3 + b if z is zero, otherwise x and y are both zero.
+fn assert_g1_on_curve<C: PairingCurve>(
+    ctx: &mut C::Context,
+    p: &AssignedPoint<C::G1Affine, Fr>,
+) {
+    let zero = assign_fq_constant::<C>(ctx, &BigUint::from(0u64));
+    let b = assign_fq_constant::<C>(ctx, &BigUint::from(C::G1_B));
+    let y_square = ctx.base_integer_chip().int_mul(&p.y, &p.y);
+    let x_square = ctx.base_integer_chip().int_mul(&p.x, &p.x);
+    let x_cube = ctx.base_integer_chip().int_mul(&x_square, &p.x);
+    let rhs = ctx.base_integer_chip().int_add(&x_cube, &b);
+    let rhs = ctx.base_integer_chip().bisec_int(&p.z, &zero, &rhs);
+    ctx.base_integer_chip().assert_int_equal(&y_square, &rhs);
+    let x = ctx.base_integer_chip().bisec_int(&p.z, &p.x, &zero);
+    ctx.base_integer_chip().assert_int_equal(&x, &zero);
+}
+
+/// y^2 = x^3 + b on the twist if z is zero, otherwise x and y are both zero.
+fn assert_g2_on_curve<C: PairingCurve>(
+    ctx: &mut C::Context,
+    p: &AssignedG2Affine<C::G1Affine, Fr>,
+) {
+    let zero = ctx.fq2_assign_zero();
+    let b = assign_fq2_constant::<C>(ctx, C::G2_B);
+    let y_square = ctx.fq2_square(&p.y);
+    let x_square = ctx.fq2_square(&p.x);
+    let x_cube = ctx.fq2_mul(&x_square, &p.x);
+    let rhs = ctx.fq2_add(&x_cube, &b);
+    let rhs = fq2_bisec::<C>(ctx, &p.z, &zero, &rhs);
+    fq2_assert_equal::<C>(ctx, &y_square, &rhs);
+    let x = fq2_bisec::<C>(ctx, &p.z, &p.x, &zero);
+    fq2_assert_equal::<C>(ctx, &x, &zero);
+}
+
+/// the untwist-Frobenius-twist endomorphism
+fn g2_psi<C: PairingCurve>(
+    ctx: &mut C::Context,
+    p: &AssignedG2Affine<C::G1Affine, Fr>,
+) -> AssignedG2Affine<C::G1Affine, Fr> {
+    let cx = assign_fq2_constant::<C>(ctx, C::PSI_X);
+    let cy = assign_fq2_constant::<C>(ctx, C::PSI_Y);
+    let x = (p.x.0.clone(), ctx.base_integer_chip().int_neg(&p.x.1));
+    let y = (p.y.0.clone(), ctx.base_integer_chip().int_neg(&p.y.1));
+    let x = ctx.fq2_mul(&x, &cx);
+    let y = ctx.fq2_mul(&y, &cy);
+    AssignedG2Affine::new(x, y, p.z.clone())
+}
+
+/// multiply by a constant scalar with the complete addition g2_add
+fn g2_mul_constant<C: PairingCurve>(
+    ctx: &mut C::Context,
+    p: &AssignedG2Affine<C::G1Affine, Fr>,
+    k: &BigUint,
+) -> AssignedG2Affine<C::G1Affine, Fr> {
+    let mut acc = p.clone();
+    for i in (0..k.bits() - 1).rev() {
+        acc = g2_add::<C>(ctx, &acc, &acc);
+        if (k >> i) & BigUint::from(1u64) == BigUint::from(1u64) {
+            acc = g2_add::<C>(ctx, &acc, p);
+        }
+    }
+    acc
+}
+
+/// The points of the twist with psi(P) = [p (mod r)] P are exactly the ones in G2
+/// for the BN and BLS12 curves.
+fn assert_g2_in_subgroup<C: PairingCurve>(
+    ctx: &mut C::Context,
+    p: &AssignedG2Affine<C::G1Affine, Fr>,
+) {
+    let lhs = g2_psi::<C>(ctx, p);
+    let rhs = g2_mul_constant::<C>(ctx, p, &BigUint::parse_bytes(C::PSI_EIGENVALUE, 16).unwrap());
+    let rhs_y = if C::PSI_EIGENVALUE_NEG {
+        (
+            ctx.base_integer_chip().int_neg(&rhs.y.0),
+            ctx.base_integer_chip().int_neg(&rhs.y.1),
+        )
+    } else {
+        rhs.y.clone()
+    };
+    fq2_assert_equal::<C>(ctx, &lhs.x, &rhs.x);
+    fq2_assert_equal::<C>(ctx, &lhs.y, &rhs_y);
+    C::native_ctx(ctx).borrow_mut().assert_equal(&lhs.z.0, &rhs.z.0);
+}
+
+fn fq2_bisec<C: PairingCurve>(
+    ctx: &mut C::Context,
+    cond: &AssignedCondition<Fr>,
+    a: &AssignedFq2<C::Fq, Fr>,
+    b: &AssignedFq2<C::Fq, Fr>,
+) -> AssignedFq2<C::Fq, Fr> {
+    let c0 = ctx.base_integer_chip().bisec_int(cond, &a.0, &b.0);
+    let c1 = ctx.base_integer_chip().bisec_int(cond, &a.1, &b.1);
+    (c0, c1)
+}
+
+fn fq2_is_zero<C: PairingCurve>(
+    ctx: &mut C::Context,
+    a: &AssignedFq2<C::Fq, Fr>,
+) -> AssignedCondition<Fr> {
+    let c0 = ctx.base_integer_chip().is_int_zero(&a.0);
+    let c1 = ctx.base_integer_chip().is_int_zero(&a.1);
+    C::native_ctx(ctx).borrow_mut().and(&c0, &c1)
+}
+
+/// Complete addition of G2 points in affine coordinates.
+/// The identity is represented by the z flag with zero coordinates.
+pub fn g2_add<C: PairingCurve>(
+    ctx: &mut C::Context,
+    a: &AssignedG2Affine<C::G1Affine, Fr>,
+    b: &AssignedG2Affine<C::G1Affine, Fr>,
+) -> AssignedG2Affine<C::G1Affine, Fr> {
+    let zero = ctx.fq2_assign_zero();
+    let one = ctx.fq2_assign_one();
+
+    // lambda = (b.y - a.y) / (b.x - a.x), the denominator is replaced by one if a.x == b.x
+    let diff_x = ctx.fq2_sub(&b.x, &a.x);
+    let diff_x_is_zero = fq2_is_zero::<C>(ctx, &diff_x);
+    let diff_y = ctx.fq2_sub(&b.y, &a.y);
+    let denom = fq2_bisec::<C>(ctx, &diff_x_is_zero, &one, &diff_x);
+    let denom_inv = ctx.fq2_unsafe_invert(&denom);
+    let lambda_add = ctx.fq2_mul(&diff_y, &denom_inv);
+
+    // lambda = 3 * a.x^2 / (2 * a.y), the denominator is replaced by one if a.y == 0
+    let a_y_is_zero = fq2_is_zero::<C>(ctx, &a.y);
+    let double_y = ctx.fq2_double(&a.y);
+    let denom = fq2_bisec::<C>(ctx, &a_y_is_zero, &one, &double_y);
+    let denom_inv = ctx.fq2_unsafe_invert(&denom);
+    let x_square = ctx.fq2_square(&a.x);
+    let double_x_square = ctx.fq2_double(&x_square);
+    let numer = ctx.fq2_add(&x_square, &double_x_square);
+    let lambda_double = ctx.fq2_mul(&numer, &denom_inv);
+
+    let lambda = fq2_bisec::<C>(ctx, &diff_x_is_zero, &lambda_double, &lambda_add);
+
+    // x3 = lambda^2 - a.x - b.x, y3 = lambda * (a.x - x3) - a.y
+    let lambda_square = ctx.fq2_square(&lambda);
+    let x3 = ctx.fq2_sub(&lambda_square, &a.x);
+    let x3 = ctx.fq2_sub(&x3, &b.x);
+    let x_diff = ctx.fq2_sub(&a.x, &x3);
+    let y3 = ctx.fq2_mul(&lambda, &x_diff);
+    let y3 = ctx.fq2_sub(&y3, &a.y);
+
+    // a + b is the identity if a == -b
+    let sum_y = ctx.fq2_add(&a.y, &b.y);
+    let sum_y_is_zero = fq2_is_zero::<C>(ctx, &sum_y);
+    let is_neg = C::native_ctx(ctx).borrow_mut().and(&diff_x_is_zero, &sum_y_is_zero);
+    let x3 = fq2_bisec::<C>(ctx, &is_neg, &zero, &x3);
+    let y3 = fq2_bisec::<C>(ctx, &is_neg, &zero, &y3);
+
+    let x = fq2_bisec::<C>(ctx, &b.z, &a.x, &x3);
+    let x = fq2_bisec::<C>(ctx, &a.z, &b.x, &x);
+    let y = fq2_bisec::<C>(ctx, &b.z, &a.y, &y3);
+    let y = fq2_bisec::<C>(ctx, &a.z, &b.y, &y);
+    let z = C::native_ctx(ctx).borrow_mut().bisec_cond(&b.z, &a.z, &is_neg);
+    let z = C::native_ctx(ctx).borrow_mut().bisec_cond(&a.z, &b.z, &z);
+    AssignedG2Affine::new(x, y, z)
+}
+
+pub fn get_cell_of_ctx(
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    cell: &ContextCell,
+) -> AssignedCell<Fr, Fr> {
+    cells[cell.region as usize][cell.col][cell.row]
+        .clone()
+        .unwrap()
+}
+
+pub fn enable_fq_permute<C: PairingCurve>(
+    region: &mut Region<'_, Fr>,
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    fq: &AssignedFq<C::Fq, Fr>,
+    input: &Vec<Limb<Fr>>,
+) -> Result<(), Error> {
+    for i in 0..C::FQ_LIMBS {
+        let limb = fq.limbs_le[i].cell;
+        let limb_assigned = get_cell_of_ctx(cells, &limb);
+        region.constrain_equal(input[i].get_the_cell().cell(), limb_assigned.cell())?;
+    }
+    Ok(())
+}
+
+pub fn enable_g1affine_permute<C: PairingCurve>(
+    region: &mut Region<'_, Fr>,
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    point: &AssignedPoint<C::G1Affine, Fr>,
+    input: &Vec<Limb<Fr>>,
+) -> Result<(), Error> {
+    let mut inputs = input.chunks(C::FQ_LIMBS);
+    enable_fq_permute::<C>(region, cells, &point.x, &inputs.next().unwrap().to_vec())?;
+    enable_fq_permute::<C>(region, cells, &point.y, &inputs.next().unwrap().to_vec())?;
+    let z_limb0 = point.z.0.cell;
+    let z_limb0_assigned = get_cell_of_ctx(cells, &z_limb0);
+    region.constrain_equal(input[C::FQ_LIMBS * 2].get_the_cell().cell(), z_limb0_assigned.cell())?;
+    Ok(())
+}
+
+pub fn enable_g2affine_permute<C: PairingCurve>(
+    region: &mut Region<'_, Fr>,
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    point: &AssignedG2Affine<C::G1Affine, Fr>,
+    input: &Vec<Limb<Fr>>,
+) -> Result<(), Error> {
+    let mut inputs = input.chunks(C::FQ_LIMBS);
+    for fq in [&point.x.0, &point.x.1, &point.y.0, &point.y.1] {
+        enable_fq_permute::<C>(region, cells, fq, &inputs.next().unwrap().to_vec())?;
+    }
+    let z_limb0 = point.z.0.cell;
+    let z_limb0_assigned = get_cell_of_ctx(cells, &z_limb0);
+    region.constrain_equal(input[C::FQ_LIMBS * 4].get_the_cell().cell(), z_limb0_assigned.cell())?;
+    Ok(())
+}
+
+pub fn enable_fq12_permute<C: PairingCurve>(
+    region: &mut Region<'_, Fr>,
+    cells: &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>,
+    fq12: &AssignedFq12<C::Fq, Fr>,
+    input: &Vec<Limb<Fr>>,
+) -> Result<(), Error> {
+    let mut inputs = input.chunks(C::FQ_LIMBS);
+    for fq6 in [&fq12.0, &fq12.1] {
+        for fq2 in [&fq6.0, &fq6.1, &fq6.2] {
+            enable_fq_permute::<C>(region, cells, &fq2.0, &inputs.next().unwrap().to_vec())?;
+            enable_fq_permute::<C>(region, cells, &fq2.1, &inputs.next().unwrap().to_vec())?;
+        }
+    }
+    Ok(())
+}
+
+/// enable the permutation of an output G1 so that its cells can be constrained against the host call table
+pub fn enable_g1affine_output<C: PairingCurve>(
+    ctx: &mut C::Context,
+    point: &AssignedPoint<C::G1Affine, Fr>,
+) {
+    C::native_ctx(ctx).borrow_mut().enable_permute(&point.z.0);
+    for i in 0..C::FQ_LIMBS {
+        C::native_ctx(ctx).borrow_mut().enable_permute(&point.x.limbs_le[i]);
+        C::native_ctx(ctx).borrow_mut().enable_permute(&point.y.limbs_le[i]);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PairingChipConfig {
+    base_chip_config: BaseChipConfig,
+    range_chip_config: RangeChipConfig,
+    point_select_chip_config: SelectChipConfig,
+}
+
+impl PairingChipConfig {
+    pub fn configure(cs: &mut ConstraintSystem<Fr>) -> Self {
+        PairingChipConfig {
+            base_chip_config: BaseChip::configure(cs),
+            range_chip_config: RangeChip::<Fr>::configure(cs),
+            point_select_chip_config: SelectChip::configure(cs),
+        }
+    }
+}
+
+macro_rules! pairing_chip {
+    ($name:ident, $doc:literal) => {
+        #[doc = $doc]
+        pub struct $name<N: FieldExt, C: PairingCurve> {
+            config: PairingChipConfig,
+            base_chip: BaseChip<N>,
+            pub range_chip: RangeChip<N>,
+            point_select_chip: SelectChip<N>,
+            _marker: PhantomData<(N, C)>,
+        }
+
+        impl<N: FieldExt, C: PairingCurve> Chip<N> for $name<N, C> {
+            type Config = PairingChipConfig;
+            type Loaded = ();
+
+            fn config(&self) -> &Self::Config {
+                &self.config
+            }
+
+            fn loaded(&self) -> &Self::Loaded {
+                &()
+            }
+        }
+
+        impl<C: PairingCurve> $name<Fr, C> {
+            pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
+                Self {
+                    config: config.clone(),
+                    point_select_chip: SelectChip::<Fr>::new(config.point_select_chip_config),
+                    base_chip: BaseChip::new(config.base_chip_config),
+                    range_chip: RangeChip::<Fr>::new(config.range_chip_config),
+                    _marker: PhantomData,
+                }
+            }
+
+            pub fn configure(
+                cs: &mut ConstraintSystem<Fr>,
+            ) -> <Self as Chip<Fr>>::Config {
+                PairingChipConfig::configure(cs)
+            }
+
+            /// assign the records of ctx into a region and constrain its cells against the input cells
+            pub(crate) fn assign_records(
+                &self,
+                ctx: C::Context,
+                layouter: &mut impl Layouter<Fr>,
+                permute: impl Fn(&mut Region<'_, Fr>, &Vec<Vec<Vec<Option<AssignedCell<Fr, Fr>>>>>) -> Result<(), Error>,
+            ) -> Result<(), Error> {
+                let records = Arc::try_unwrap(Into::<Context<Fr>>::into(ctx).records)
+                    .unwrap()
+                    .into_inner()
+                    .unwrap();
+                layouter.assign_region(
+                    || "base",
+                    |mut region| {
+                        let timer = start_timer!(|| "assign");
+                        let cells = records.assign_all(
+                            &mut region,
+                            &self.base_chip,
+                            &self.range_chip,
+                            &self.point_select_chip
+                        )?;
+                        permute(&mut region, &cells)?;
+                        end_timer!(timer);
+                        Ok(())
+                    },
+                )?;
+                Ok(())
+            }
+        }
+    };
+}
+
+pairing_chip!(PairChip, "The pairing of a G1 and a G2.");
+pairing_chip!(MsmChip, "The multi scalar multiplication of a list of (G1, scalar), see the curves for the scalar limbs.");
+pairing_chip!(SumChip, "The sum of a list of G1.");
+pairing_chip!(MultiPairChip, "The product of the pairings of a list of (G1, G2).");
+pairing_chip!(G2SumChip, "The sum of a list of G2.");
+pairing_chip!(HashToCurveChip, "The hash of a pair of field elements onto G1.");
+
+impl<C: PairingCurve> PairChip<Fr, C> {
+    pub fn load_pair_circuit(
+        &self,
+        a: &Vec<Limb<Fr>>,  // G1 (FQ_LIMBS * 2 + 1)
+        b: &Vec<Limb<Fr>>,  // G2 (FQ_LIMBS * 4 + 1)
+        ab: &Vec<Limb<Fr>>, // Fq_12 (FQ_LIMBS * 12)
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let context = Rc::new(RefCell::new(Context::new()));
+        let mut ctx = C::new_context(context);
+
+        let a_g1 = get_g1_from_cells::<C>(&mut ctx, a);
+        let b_g2 = get_g2_from_cells::<C>(&mut ctx, b);
+
+        let ab_fq12_raw = ctx.pairing(&[(&a_g1, &b_g2)]);
+        let ab_fq12 = ctx.fq12_reduce(&ab_fq12_raw);
+
+        self.assign_records(ctx, layouter, |region, cells| {
+            enable_g1affine_permute::<C>(region, cells, &a_g1, a)?;
+            enable_g2affine_permute::<C>(region, cells, &b_g2, b)?;
+            enable_fq12_permute::<C>(region, cells, &ab_fq12, ab)?;
+            Ok(())
+        })
+    }
+}
+
+impl<C: PairingCurve> SumChip<Fr, C> {
+    pub fn load_sum_circuit(
+        &self,
+        ls: &Vec<Limb<Fr>>,  // Vec<G1> (FQ_LIMBS * 2 + 1) * k
+        sum: &Vec<Limb<Fr>>, // G1 (FQ_LIMBS * 2 + 1)
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let context = Rc::new(RefCell::new(Context::new()));
+        let mut ctx = C::new_context(context);
+
+        let g1s: Vec<AssignedPoint<_, _>> = ls
+            .chunks(C::G1_LIMBS)
+            .map(|l| get_g1_from_cells::<C>(&mut ctx, &l.to_vec()))
+            .collect();
+
+        let g0 = ctx.assign_identity();
+        let sum_ret = g1s.iter().fold(g0, |acc, x| {
+            let p = ctx.ecc_add(&acc, &x);
+            ctx.to_point_with_curvature(p)
+        });
+        let sum_ret = sum_ret.to_point();
+        let sum_ret = ctx.ecc_reduce(&sum_ret);
+        enable_g1affine_output::<C>(&mut ctx, &sum_ret);
+
+        self.assign_records(ctx, layouter, |region, cells| {
+            for (x, l) in g1s.iter().zip(ls.chunks(C::G1_LIMBS)) {
+                enable_g1affine_permute::<C>(region, cells, x, &l.to_vec())?;
+            }
+            enable_g1affine_permute::<C>(region, cells, &sum_ret, sum)?;
+            Ok(())
+        })
+    }
+}
+
+impl<C: PairingCurve> MultiPairChip<Fr, C> {
+    /// Each call is (ls, gt) where ls is k pairs of G1 (FQ_LIMBS * 2 + 1) and G2 (FQ_LIMBS * 4 + 1)
+    /// and gt is the Fq_12 (FQ_LIMBS * 12) product of the k pairings.
+    /// The miller loops of a call share a single final exponentiation.
+    pub fn load_multi_pair_circuit(
+        &self,
+        calls: &Vec<(Vec<Limb<Fr>>, Vec<Limb<Fr>>)>,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let context = Rc::new(RefCell::new(Context::new()));
+        let mut ctx = C::new_context(context);
+
+        let mut assigned_calls = vec![];
+        for (ls, _) in calls.iter() {
+            let mut pairs = vec![];
+            for l in ls.chunks(C::G1_LIMBS + C::G2_LIMBS) {
+                let a_g1 = get_g1_from_cells::<C>(&mut ctx, &l[0..C::G1_LIMBS].to_vec());
+                let b_g2 = get_g2_from_cells::<C>(&mut ctx, &l[C::G1_LIMBS..].to_vec());
+                pairs.push((a_g1, b_g2));
+            }
+            let terms = pairs.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+            let gt_fq12_raw = ctx.pairing(&terms);
+            let gt_fq12 = ctx.fq12_reduce(&gt_fq12_raw);
+            assigned_calls.push((pairs, gt_fq12));
+        }
+
+        self.assign_records(ctx, layouter, |region, cells| {
+            for ((pairs, gt_fq12), (ls, gt)) in assigned_calls.iter().zip(calls.iter()) {
+                for ((a_g1, b_g2), l) in pairs.iter().zip(ls.chunks(C::G1_LIMBS + C::G2_LIMBS)) {
+                    enable_g1affine_permute::<C>(region, cells, a_g1, &l[0..C::G1_LIMBS].to_vec())?;
+                    enable_g2affine_permute::<C>(region, cells, b_g2, &l[C::G1_LIMBS..].to_vec())?;
+                }
+                enable_fq12_permute::<C>(region, cells, gt_fq12, gt)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<C: PairingCurve> G2SumChip<Fr, C> {
+    pub fn load_g2_sum_circuit(
+        &self,
+        ls: &Vec<Limb<Fr>>,  // Vec<G2> (FQ_LIMBS * 4 + 1) * k
+        sum: &Vec<Limb<Fr>>, // G2 (FQ_LIMBS * 4 + 1)
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let context = Rc::new(RefCell::new(Context::new()));
+        let mut ctx = C::new_context(context);
+
+        let g2s: Vec<AssignedG2Affine<_, _>> = ls
+            .chunks(C::G2_LIMBS)
+            .map(|l| get_g2_from_cells::<C>(&mut ctx, &l.to_vec()))
+            .collect();
+
+        let zero = ctx.fq2_assign_zero();
+        let g0 = AssignedG2Affine::new(
+            zero.clone(),
+            zero,
+            AssignedCondition(C::native_ctx(&ctx).borrow_mut().assign_constant(Fr::one())),
+        );
+        let sum_ret = g2s.iter().fold(g0, |acc, x| g2_add::<C>(&mut ctx, &acc, x));
+        let sum_ret = AssignedG2Affine::new(
+            ctx.fq2_reduce(&sum_ret.x),
+            ctx.fq2_reduce(&sum_ret.y),
+            sum_ret.z,
+        );
+        C::native_ctx(&ctx).borrow_mut().enable_permute(&sum_ret.z.0);
+        for fq in [&sum_ret.x.0, &sum_ret.x.1, &sum_ret.y.0, &sum_ret.y.1].iter() {
+            for i in 0..C::FQ_LIMBS {
+                C::native_ctx(&ctx).borrow_mut().enable_permute(&fq.limbs_le[i]);
+            }
+        }
+
+        self.assign_records(ctx, layouter, |region, cells| {
+            for (x, l) in g2s.iter().zip(ls.chunks(C::G2_LIMBS)) {
+                enable_g2affine_permute::<C>(region, cells, x, &l.to_vec())?;
+            }
+            enable_g2affine_permute::<C>(region, cells, &sum_ret, sum)?;
+            Ok(())
+        })
+    }
+}
@@ -1,10 +1,5 @@
-use crate::host::poseidon::PREFIX_CHALLENGE;
-use crate::host::poseidon::PREFIX_POINT;
-use crate::host::poseidon::PREFIX_SCALAR;
-use crate::host::poseidon::RATE;
-use crate::host::poseidon::R_F;
-use crate::host::poseidon::R_P;
-use crate::host::poseidon::T;
+use crate::host::poseidon::preset_rounds;
+use crate::utils::field_to_u64;
 use halo2_proofs::arithmetic::FieldExt;
 use poseidon::SparseMDSMatrix;
 use poseidon::Spec;
@@ -24,33 +19,42 @@ use halo2_proofs::{
     },
 };
 
-pub struct PoseidonState<F: FieldExt> {
+pub struct PoseidonState<F: FieldExt, const T: usize, const RATE: usize> {
     state: [Limb<F>; T],
     default: [Limb<F>; T],
-    prefix: Vec<Limb<F>>,
 }
 
-pub struct PoseidonChip<F:FieldExt> {
+/// Poseidon chip of width T and rate RATE, the default is the T=9 instance
+/// used by the host (see crate::host::poseidon for the presets).
+pub struct PoseidonChip<F:FieldExt, const T: usize = 9, const RATE: usize = 8> {
     pub config: CommonGateConfig,
     pub spec: Spec<F, T, RATE>,
-    poseidon_state: PoseidonState<F>,
+    poseidon_state: PoseidonState<F, T, RATE>,
     round: u64,
+    r_f: usize,
     _marker: PhantomData<F>
 }
 
-impl<F: FieldExt> PoseidonChip<F> {
+impl<F: FieldExt, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE> {
+    /// Construct the chip with the rounds of the preset of width T
     pub fn construct(config: CommonGateConfig) -> Self {
+        let (r_f, r_p) = preset_rounds(T);
+        Self::construct_with_rounds(config, r_f, r_p)
+    }
+
+    pub fn construct_with_rounds(config: CommonGateConfig, r_f: usize, r_p: usize) -> Self {
+        assert!(RATE + 1 == T);
         let state = [0u32;T].map(|_| Limb::new(None, F::zero()));
         let state = PoseidonState {
             default: state.clone(),
             state,
-            prefix: vec![],
         };
 
         PoseidonChip {
             round: 0,
             config,
-            spec: Spec::new(R_F, R_P),
+            spec: Spec::new(r_f, r_p),
+            r_f,
             poseidon_state: state,
             _marker: PhantomData,
         }
@@ -77,48 +81,383 @@ impl<F: FieldExt> PoseidonChip<F> {
         reset: &Limb<F>,
         result: &Limb<F>,
     ) -> Result<(), Error> {
-        let mut new_state = vec![];
-        for (value, default) in self.poseidon_state.state.iter().zip(self.poseidon_state.default.iter()) {
-            new_state.push(self.config.select(region, &mut (), offset, &reset, value, default, self.round)?);
+        // limbs without cell (continued blocks and padding) are fixed constants
+        let reset = self.assign_fixed(region, offset, reset)?;
+        let mut fixed_values = vec![];
+        for value in values.iter() {
+            fixed_values.push(self.assign_fixed(region, offset, value)?);
         }
-        self.poseidon_state.state = new_state.try_into().unwrap();
-        let parts = values.clone().map(|x| {Some(x)});
-        let parts = parts.chunks(4).collect::<Vec<_>>();
-        let mut part0 = parts[0].to_vec();
-        let mut part1 = parts[1].to_vec();
-        part0.push(None);
-        part1.push(None);
-        let mut inputs = self.config.assign_witness(
-            region,
-            &mut (),
-            offset,
-            part0.try_into().unwrap(),
+        let values: [Limb<F>; RATE] = fixed_values.try_into().unwrap();
+        self.assign_block(region, offset, &values, Some(&reset))?;
+        // the intermediate blocks of a multi-block absorption have no result
+        self.constrain_result(region, result)
+    }
+
+    /// Absorb one block of a call, a call of n pushes spans n / RATE + 1 blocks chained
+    /// through the sponge state. Each block is made of PoseidonNew, RATE push slots and PoseidonFinalize,
+    /// every entry is (data, opcode) where the opcode is zero for an entry that is not part of the call.
+    /// start is one if the block starts a call: its PoseidonNew is used, otherwise it is zero.
+    /// The used slots form a prefix, a block whose slots are all used is followed by the next
+    /// block of the call, otherwise it ends the call: the padding of Poseidon::squeeze follows
+    /// the used slots and PoseidonFinalize is used. Returns whether the block ends the call,
+    /// which is whether the next block starts a call.
+    pub fn assign_call_block(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        start: &Limb<F>,
+        new: &(Limb<F>, Limb<F>),
+        pushes: &[(Limb<F>, Limb<F>)],
+        result: &(Limb<F>, Limb<F>),
+        opcodes: [F; 3],
+    ) -> Result<Limb<F>, Error> {
+        assert!(pushes.len() == RATE);
+        let [new_opcode, push_opcode, result_opcode] = opcodes;
+        let start = self.assign_fixed(region, offset, start)?;
+        let (new, opcode) = new;
+        // opcode = new_opcode * start, new * (1 - start) = 0
+        self.config.assign_line(region, &mut (), offset,
+            [Some(start.clone()), Some(opcode.clone()), None, None, None, None],
+            [Some(new_opcode), Some(-F::one()), None, None, None, None, None, None, None],
+            0,
+        )?;
+        let new = self.config.assign_line(region, &mut (), offset,
+            [Some(new.clone()), None, None, Some(start.clone()), None, None],
+            [Some(F::one()), None, None, None, None, None, Some(-F::one()), None, None],
+            0,
+        )?[0].clone();
+
+        let mut flags: Vec<Limb<F>> = vec![];
+        let mut data = vec![];
+        for (value, opcode) in pushes.iter() {
+            let flag = if opcode.value == push_opcode { F::one() } else { F::zero() };
+            let flag = self.assign_flag(region, offset, flag, flags.last())?;
+            // opcode = push_opcode * flag
+            self.config.assign_line(region, &mut (), offset,
+                [Some(flag.clone()), Some(opcode.clone()), None, None, None, None],
+                [Some(push_opcode), Some(-F::one()), None, None, None, None, None, None, None],
+                0,
+            )?;
+            data.push(value.clone());
+            flags.push(flag);
+        }
+
+        // the block ends the call unless all its slots are used
+        let last = flags.last().unwrap();
+        let end = self.config.assign_line(region, &mut (), offset,
+            [Some(Limb::new(None, F::one() - last.value)), Some(last.clone()), None, None, None, None],
+            [Some(F::one()), Some(F::one()), None, None, None, None, None, None, Some(-F::one())],
+            0,
+        )?[0].clone();
+
+        let one = self.config.assign_constant(region, &mut (), offset, &F::one())?;
+        let lanes = self.assign_lanes(region, offset, &one, &data, &flags)?;
+        self.assign_block(region, offset, &lanes.try_into().unwrap(), Some(&new))?;
+
+        // opcode = result_opcode * end, end * result = end * state[1]
+        let (result, opcode) = result;
+        self.config.assign_line(region, &mut (), offset,
+            [Some(end.clone()), Some(opcode.clone()), None, None, None, None],
+            [Some(result_opcode), Some(-F::one()), None, None, None, None, None, None, None],
             0,
         )?;
-        inputs.append(&mut self.config.assign_witness(
+        let mut gated = vec![];
+        for limb in [result.clone(), self.poseidon_state.state[1].clone()] {
+            let value = end.value * limb.value;
+            gated.push(self.config.assign_line(region, &mut (), offset,
+                [Some(end.clone()), None, None, Some(limb), Some(Limb::new(None, value)), None],
+                [None, None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+                0,
+            )?[2].clone());
+        }
+        region.constrain_equal(
+            gated[0].cell.as_ref().unwrap().cell(),
+            gated[1].cell.as_ref().unwrap().cell()
+        )?;
+        Ok(end)
+    }
+
+    /// Hash the first len inputs with a restarted sponge and return the result,
+    /// the layout only depends on the number of inputs which must be RATE * k - 1.
+    pub fn assign_variable_hash(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        inputs: &[Limb<F>],
+        len: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        let n = field_to_u64(&len.value) as usize;
+        assert!(n <= inputs.len());
+        let len = self.assign_fixed(region, offset, len)?;
+        let mut flags: Vec<Limb<F>> = vec![];
+        let mut data = vec![];
+        for (i, value) in inputs.iter().enumerate() {
+            data.push(self.assign_fixed(region, offset, value)?);
+            let flag = if i < n { F::one() } else { F::zero() };
+            let flag = self.assign_flag(region, offset, flag, flags.last())?;
+            flags.push(flag);
+        }
+        // the number of used inputs is len
+        let sum = self.config.sum_with_constant(
             region,
             &mut (),
             offset,
-            part1.try_into().unwrap(),
+            flags.iter().map(|x| (x, F::one())).collect(),
+            None,
+        )?;
+        region.constrain_equal(
+            sum.cell.as_ref().unwrap().cell(),
+            len.cell.as_ref().unwrap().cell()
+        )?;
+        let new = self.config.assign_constant(region, &mut (), offset, &F::one())?;
+        self.assign_flagged_blocks(region, offset, &new, &data, &flags)?;
+        Ok(self.poseidon_state.state[1].clone())
+    }
+
+    /// Assign a boolean flag, a flag can only be set if the last one is set
+    pub fn assign_flag(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        flag: F,
+        last: Option<&Limb<F>>,
+    ) -> Result<Limb<F>, Error> {
+        // flag * flag - flag = 0
+        let flag = self.config.assign_line(region, &mut (), offset,
+            [Some(Limb::new(None, flag)), None, None, Some(Limb::new(None, flag)), None, None],
+            [Some(-F::one()), None, None, None, None, None, Some(F::one()), None, None],
             0,
-        )?);
+        )?;
+        region.constrain_equal(
+            flag[0].cell.as_ref().unwrap().cell(),
+            flag[1].cell.as_ref().unwrap().cell()
+        )?;
+        let flag = flag[0].clone();
+        // flag * (1 - last) = 0
+        if let Some(last) = last {
+            self.config.assign_line(region, &mut (), offset,
+                [Some(flag.clone()), None, None, Some(last.clone()), None, None],
+                [Some(F::one()), None, None, None, None, None, Some(-F::one()), None, None],
+                0,
+            )?;
+        }
+        Ok(flag)
+    }
+
+    /// Absorb the data whose used entries are given by the flags,
+    /// the data is followed by the padding one and zeros up to data.len() + 1 lanes.
+    /// A block after the first one is absorbed only if the entry before it is used,
+    /// thus the layout does not depend on the number of used entries.
+    fn assign_flagged_blocks(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        new: &Limb<F>,
+        data: &[Limb<F>],
+        flags: &[Limb<F>],
+    ) -> Result<(), Error> {
+        assert!((data.len() + 1) % RATE == 0);
+        let one = self.config.assign_constant(region, &mut (), offset, &F::one())?;
+        let mut lanes = self.assign_lanes(region, offset, &one, data, flags)?;
+        // the last lane is the padding one when all the data is used
+        lanes.push(flags.last().map_or(one, |x| x.clone()));
+
+        for (i, block) in lanes.chunks_exact(RATE).enumerate() {
+            let block: [Limb<F>; RATE] = block.to_vec().try_into().unwrap();
+            if i == 0 {
+                self.assign_block(region, offset, &block, Some(new))?;
+            } else {
+                let absorb = flags[i * RATE - 1].clone();
+                let before = self.poseidon_state.state.clone();
+                self.assign_block(region, offset, &block, None)?;
+                let mut new_state = vec![];
+                for (f, t) in before.iter().zip(self.poseidon_state.state.iter()) {
+                    new_state.push(self.config.select(region, &mut (), offset, &absorb, f, t, self.round)?);
+                }
+                self.poseidon_state.state = new_state.try_into().unwrap();
+            }
+        }
+        Ok(())
+    }
+
+    /// Assign the lanes of the data whose used entries are given by the flags,
+    /// lane = flag * data + last - flag: the used data, the padding one right after it, then zeros.
+    fn assign_lanes(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        one: &Limb<F>,
+        data: &[Limb<F>],
+        flags: &[Limb<F>],
+    ) -> Result<Vec<Limb<F>>, Error> {
+        let mut lanes = vec![];
+        for (i, (flag, value)) in flags.iter().zip(data.iter()).enumerate() {
+            let last = if i == 0 { one.clone() } else { flags[i - 1].clone() };
+            let lane = flag.value * value.value + last.value - flag.value;
+            lanes.push(self.config.assign_line(region, &mut (), offset,
+                [Some(flag.clone()), Some(last), None, Some(value.clone()), Some(Limb::new(None, lane)), None],
+                [Some(-F::one()), Some(F::one()), None, None, Some(-F::one()), None, Some(F::one()), None, None],
+                0,
+            )?[3].clone());
+        }
+        Ok(lanes)
+    }
+
+    /// Permute the state with a block of lanes, the first block of a call carries
+    /// the operand of PoseidonNew which restarts the sponge in the domain of its tag.
+    fn assign_block(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        values: &[Limb<F>; RATE],
+        new: Option<&Limb<F>>,
+    ) -> Result<(), Error> {
+        if let Some(new) = new {
+            let (reset, tag) = self.assign_domain(region, offset, new)?;
+            // the capacity of a restarted sponge is 2^64 + tag
+            let mut default = self.poseidon_state.default.clone();
+            default[0] = self.config.sum_with_constant(
+                region,
+                &mut (),
+                offset,
+                vec![(&default[0], F::one()), (&tag, F::one())],
+                None,
+            )?;
+            let mut new_state = vec![];
+            for (value, default) in self.poseidon_state.state.iter().zip(default.iter()) {
+                new_state.push(self.config.select(region, &mut (), offset, &reset, value, default, self.round)?);
+            }
+            self.poseidon_state.state = new_state.try_into().unwrap();
+        }
+        let parts = values.clone().map(|x| {Some(x)});
+        let mut inputs = vec![];
+        for part in parts.chunks(4) {
+            let mut part = part.to_vec();
+            part.resize(5, None);
+            inputs.append(&mut self.config.assign_witness(
+                region,
+                &mut (),
+                offset,
+                part.try_into().unwrap(),
+                0,
+            )?);
+        }
         self.poseidon_state.permute(
             &self.config,
             &self.spec,
+            self.r_f,
             region,
             offset,
             &inputs.try_into().unwrap(),
+        )
+    }
+
+    /// Constrain the squeezed element to the result limb if it has a cell
+    fn constrain_result(
+        &self,
+        region: &mut Region<F>,
+        result: &Limb<F>,
+    ) -> Result<(), Error> {
+        result.cell.as_ref().map_or(Ok(()), |cell| {
+            assert!(self.poseidon_state.state[1].value == result.value);
+            region.constrain_equal(
+                cell.cell(),
+                self.poseidon_state.state[1].cell.as_ref().unwrap().cell()
+            )
+        })
+    }
+
+    /// Hash the inputs with a restarted sponge and return the result,
+    /// this is Poseidon::update followed by Poseidon::squeeze on the host.
+    pub fn assign_hash(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        inputs: &[Limb<F>],
+    ) -> Result<Limb<F>, Error> {
+        let mut lanes = inputs.to_vec();
+        // padding rule of Poseidon::squeeze
+        lanes.push(Limb::new(None, F::one()));
+        while lanes.len() % RATE != 0 {
+            lanes.push(Limb::new(None, F::zero()));
+        }
+        for (i, block) in lanes.chunks_exact(RATE).enumerate() {
+            let reset = Limb::new(None, if i == 0 { F::one() } else { F::zero() });
+            self.assign_permute(
+                region,
+                offset,
+                &block.to_vec().try_into().unwrap(),
+                &reset,
+                &Limb::new(None, F::zero()),
+            )?;
+        }
+        Ok(self.poseidon_state.state[1].clone())
+    }
+
+    /// Split the operand of PoseidonNew into restart + 2 * tag and return (restart, tag),
+    /// the tag is constrained to be at most MAX_DOMAIN_TAG.
+    fn assign_domain(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        new: &Limb<F>,
+    ) -> Result<(Limb<F>, Limb<F>), Error> {
+        let restart = field_to_u64(&new.value) & 1;
+        let tag = field_to_u64(&new.value) >> 1;
+        let restart = Limb::new(None, F::from(restart));
+        let tag = Limb::new(None, F::from(tag));
+
+        // new = restart + 2 * tag
+        let l = self.config.assign_line(region, &mut (), offset,
+            [Some(restart), Some(tag), None, None, Some(new.clone()), None],
+            [Some(F::one()), Some(F::from(2u64)), None, None, Some(-F::one()), None, None, None, None],
+            0,
         )?;
-        assert!(self.poseidon_state.state[1].value == result.value);
-        region.constrain_equal(
-            result.cell.as_ref().unwrap().cell(),
-            self.poseidon_state.state[1].cell.as_ref().unwrap().cell()
+        let restart = l[0].clone();
+        let tag = l[1].clone();
+
+        // restart * restart - restart = 0
+        self.config.assign_line(region, &mut (), offset,
+            [Some(restart.clone()), Some(restart.clone()), Some(restart.clone()), None, None, None],
+            [Some(-F::one()), None, None, None, None, None, None, Some(F::one()), None],
+            0,
         )?;
-        Ok(())
+
+        // tag * (tag - 1) * (tag - 2) * (tag - 3) = 0
+        let a = tag.value * tag.value - tag.value;
+        let a = self.config.assign_line(region, &mut (), offset,
+            [Some(tag.clone()), None, None, Some(tag.clone()), Some(Limb::new(None, a)), None],
+            [Some(-F::one()), None, None, None, Some(-F::one()), None, Some(F::one()), None, None],
+            0,
+        )?[2].clone();
+        let b = tag.value * tag.value - F::from(5u64) * tag.value + F::from(6u64);
+        let b = self.config.assign_line(region, &mut (), offset,
+            [Some(tag.clone()), None, None, Some(tag.clone()), Some(Limb::new(None, b)), None],
+            [Some(-F::from(5u64)), None, None, None, Some(-F::one()), None, Some(F::one()), None, Some(F::from(6u64))],
+            0,
+        )?[2].clone();
+        self.config.assign_line(region, &mut (), offset,
+            [Some(a), None, None, Some(b), None, None],
+            [None, None, None, None, None, None, Some(F::one()), None, None],
+            0,
+        )?;
+        Ok((restart, tag))
+    }
+
+    fn assign_fixed(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        limb: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        match limb.cell {
+            Some(_) => Ok(limb.clone()),
+            None => self.config.assign_constant(region, &mut (), offset, &limb.value),
+        }
     }
 }
 
-impl<F: FieldExt> PoseidonState<F> {
+impl<F: FieldExt, const T: usize, const RATE: usize> PoseidonState<F, T, RATE> {
     pub fn initialize(
         &mut self,
         config: &CommonGateConfig,
@@ -131,11 +470,6 @@ impl<F: FieldExt> PoseidonState<F> {
         state[0] = config.assign_constant(region, &mut (), offset, &F::from_u128(1u128<<64))?;
         self.default = state.clone();
         self.state = state;
-        self.prefix = vec![
-                config.assign_constant(region, &mut (), offset, &F::from(PREFIX_CHALLENGE))?,
-                config.assign_constant(region, &mut (), offset, &F::from(PREFIX_POINT))?,
-                config.assign_constant(region, &mut (), offset, &F::from(PREFIX_SCALAR))?,
-            ];
         Ok(())
     }
 
@@ -219,11 +553,12 @@ impl<F: FieldExt> PoseidonState<F> {
         &mut self,
         config: &CommonGateConfig,
         spec: &Spec<F, T, RATE>,
+        r_f: usize,
         region: &mut Region<F>,
         offset: &mut usize,
         inputs: &[Limb<F>; RATE],
     ) -> Result<(), Error> {
-        let r_f = R_F / 2;
+        let r_f = r_f / 2;
         let mds = &spec.mds_matrices().mds().rows();
 
         let constants = &spec.constants().start();
@@ -369,7 +704,6 @@ mod tests {
     use halo2_proofs::dev::MockProver;
     use crate::value_for_assign;
     use crate::circuits::CommonGateConfig;
-    use crate::host::poseidon::RATE;
 
     use halo2_proofs::{
         circuit::{Chip, Layouter, Region, SimpleFloorPlanner},
@@ -425,9 +759,9 @@ mod tests {
             &self,
             region: &mut Region<Fr>,
             offset: &mut usize,
-            reset: bool,
+            new: u64,
         ) -> Result<Limb<Fr>, Error> {
-            let v = if reset {Fr::one()} else {Fr::zero()};
+            let v = Fr::from(new);
             let c = region.assign_advice(
                 || format!("assign input"),
                 self.config.limb,
@@ -439,7 +773,7 @@ mod tests {
         }
 
 
-        fn assign_inputs(
+        fn assign_inputs<const RATE: usize>(
             &self,
             region: &mut Region<Fr>,
             offset: &mut usize,
@@ -478,7 +812,8 @@ mod tests {
     }
 
     #[derive(Clone, Debug, Default)]
-    struct TestCircuit {
+    struct TestCircuit<const T: usize, const RATE: usize> {
+        new: u64,
         inputs: Vec<Fr>,
         result: Fr,
     }
@@ -489,7 +824,7 @@ mod tests {
         helperconfig: HelperChipConfig,
     }
 
-    impl Circuit<Fr> for TestCircuit {
+    impl<const T: usize, const RATE: usize> Circuit<Fr> for TestCircuit<T, RATE> {
         type Config = TestConfig;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -499,7 +834,7 @@ mod tests {
 
         fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
             Self::Config {
-               poseidonconfig: PoseidonChip::<Fr>::configure(meta),
+               poseidonconfig: PoseidonChip::<Fr, T, RATE>::configure(meta),
                helperconfig: HelperChip::configure(meta),
             }
         }
@@ -509,7 +844,7 @@ mod tests {
             config: Self::Config,
             mut layouter: impl Layouter<Fr>,
         ) -> Result<(), Error> {
-            let mut poseidonchip = PoseidonChip::<Fr>::construct(config.clone().poseidonconfig);
+            let mut poseidonchip = PoseidonChip::<Fr, T, RATE>::construct(config.clone().poseidonconfig);
             let helperchip = HelperChip::new(config.clone().helperconfig);
             layouter.assign_region(
                 || "assign poseidon test",
@@ -517,7 +852,7 @@ mod tests {
                     let mut offset = 0;
                     let result = helperchip.assign_result(&mut region, &mut offset, &self.result)?;
                     let inputs = helperchip.assign_inputs(&mut region, &mut offset, &self.inputs.clone().try_into().unwrap())?;
-                    let reset = helperchip.assign_reset(&mut region, &mut offset, true)?;
+                    let reset = helperchip.assign_reset(&mut region, &mut offset, self.new)?;
                     offset = 0;
                     poseidonchip.poseidon_state.initialize(&config.poseidonconfig, &mut region, &mut offset)?;
                     poseidonchip.assign_permute(
@@ -535,14 +870,233 @@ mod tests {
     }
 
 
+    #[derive(Clone, Debug, Default)]
+    struct VariableHashCircuit {
+        inputs: Vec<Fr>,
+        len: u64,
+        result: Fr,
+    }
+
+    impl Circuit<Fr> for VariableHashCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Self::Config {
+               poseidonconfig: PoseidonChip::<Fr>::configure(meta),
+               helperconfig: HelperChip::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut poseidonchip = PoseidonChip::<Fr>::construct(config.clone().poseidonconfig);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            layouter.assign_region(
+                || "assign poseidon variable hash test",
+                |mut region| {
+                    let mut offset = 0;
+                    let result = helperchip.assign_result(&mut region, &mut offset, &self.result)?;
+                    offset = 0;
+                    poseidonchip.poseidon_state.initialize(&config.poseidonconfig, &mut region, &mut offset)?;
+                    let inputs = self.inputs.iter().map(|x| Limb::new(None, *x)).collect::<Vec<_>>();
+                    let len = Limb::new(None, Fr::from(self.len));
+                    let hash = poseidonchip.assign_variable_hash(&mut region, &mut offset, &inputs, &len)?;
+                    region.constrain_equal(
+                        hash.cell.as_ref().unwrap().cell(),
+                        result.cell.as_ref().unwrap().cell()
+                    )?;
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    /// Calls of (restart, pushes, result) laid out in blocks as the poseidon adaptor does
+    #[derive(Clone, Debug, Default)]
+    struct CallCircuit {
+        calls: Vec<(bool, Vec<Fr>, Fr)>,
+    }
+
+    const NEW: u64 = 1;
+    const PUSH: u64 = 2;
+    const FINALIZE: u64 = 3;
+
+    impl CallCircuit {
+        /// The (data, opcode) entries of the blocks of all calls
+        fn blocks(&self) -> Vec<Vec<(Fr, Fr)>> {
+            let unused = (Fr::zero(), Fr::zero());
+            let mut r = vec![];
+            for (restart, pushes, result) in self.calls.iter() {
+                let blocks = pushes.len() / 8 + 1;
+                for b in 0..blocks {
+                    let mut block = vec![];
+                    block.push(if b == 0 { (Fr::from(*restart as u64), Fr::from(NEW)) } else { unused });
+                    for i in b * 8..(b + 1) * 8 {
+                        block.push(pushes.get(i).map_or(unused, |x| (*x, Fr::from(PUSH))));
+                    }
+                    block.push(if b == blocks - 1 { (*result, Fr::from(FINALIZE)) } else { unused });
+                    r.push(block);
+                }
+            }
+            r
+        }
+    }
+
+    impl Circuit<Fr> for CallCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Self::Config {
+               poseidonconfig: PoseidonChip::<Fr>::configure(meta),
+               helperconfig: HelperChip::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut poseidonchip = PoseidonChip::<Fr>::construct(config.clone().poseidonconfig);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            layouter.assign_region(
+                || "assign poseidon call test",
+                |mut region| {
+                    let mut offset = 0;
+                    let mut blocks = vec![];
+                    for block in self.blocks().iter() {
+                        let mut entries = vec![];
+                        for (data, opcode) in block.iter() {
+                            let data = helperchip.assign_result(&mut region, &mut offset, data)?;
+                            let opcode = helperchip.assign_result(&mut region, &mut offset, opcode)?;
+                            entries.push((data, opcode));
+                        }
+                        blocks.push(entries);
+                    }
+                    offset = 0;
+                    poseidonchip.poseidon_state.initialize(&config.poseidonconfig, &mut region, &mut offset)?;
+                    let mut start = Limb::new(None, Fr::one());
+                    for entries in blocks.iter() {
+                        start = poseidonchip.assign_call_block(
+                            &mut region,
+                            &mut offset,
+                            &start,
+                            &entries[0],
+                            &entries[1..9],
+                            &entries[9],
+                            [Fr::from(NEW), Fr::from(PUSH), Fr::from(FINALIZE)],
+                        )?;
+                    }
+                    assert!(start.value == Fr::one());
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    fn call_circuit(calls: Vec<(bool, Vec<Fr>)>) -> CallCircuit {
+        let mut hasher = crate::host::poseidon::gen_hasher();
+        let calls = calls.into_iter().map(|(restart, pushes)| {
+            if restart {
+                hasher = crate::host::poseidon::gen_hasher();
+            }
+            hasher.update(&pushes);
+            (restart, pushes, hasher.squeeze())
+        }).collect();
+        CallCircuit { calls }
+    }
+
     #[test]
     fn test_poseidon_circuit_00() {
         let mut hasher = crate::host::poseidon::gen_hasher();
         let result = hasher.squeeze();
         let inputs = vec![Fr::one(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()];
-        let test_circuit = TestCircuit {inputs, result};
+        let test_circuit = TestCircuit::<9, 8> {new: 1, inputs, result};
         println!("result is {:?}", result);
         let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_poseidon_circuit_t3() {
+        let mut hasher = crate::host::poseidon::gen_hasher_t3();
+        hasher.update(&[Fr::one()]);
+        let result = hasher.squeeze();
+        let inputs = vec![Fr::one(), Fr::one()];
+        let test_circuit = TestCircuit::<3, 2> {new: 1, inputs, result};
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_poseidon_circuit_domain() {
+        let prefix = crate::host::poseidon::PREFIX_POINT;
+        let mut hasher = crate::host::poseidon::gen_hasher_with_prefix(prefix).unwrap();
+        hasher.update(&[Fr::one(); 7]);
+        let result = hasher.squeeze();
+        // seven pushed ones followed by the padding one
+        let inputs = vec![Fr::one(); 8];
+        let new = crate::host::poseidon::encode_new(true, Some(prefix)).unwrap();
+        let test_circuit = TestCircuit::<9, 8> {new, inputs, result};
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_poseidon_circuit_variable_hash() {
+        let inputs = (1..16).map(|x| Fr::from(x as u64)).collect::<Vec<_>>();
+        for len in [0, 3, 7, 8, 15] {
+            let mut hasher = crate::host::poseidon::gen_hasher();
+            hasher.update(&inputs[0..len]);
+            let result = hasher.squeeze();
+            let test_circuit = VariableHashCircuit {inputs: inputs.clone(), len: len as u64, result};
+            let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // the hash of the first three inputs is not the one of the first four
+        let mut hasher = crate::host::poseidon::gen_hasher();
+        hasher.update(&inputs[0..3]);
+        let test_circuit = VariableHashCircuit {inputs, len: 4, result: hasher.squeeze()};
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_poseidon_circuit_call_blocks() {
+        let inputs = (1..=20).map(|x| Fr::from(x as u64)).collect::<Vec<_>>();
+        // calls of 20, 8 (a whole block followed by the padding block), 0 and 3 pushes,
+        // the last one continues the sponge of the call before it
+        let test_circuit = call_circuit(vec![
+            (true, inputs.clone()),
+            (true, inputs[0..8].to_vec()),
+            (true, vec![]),
+            (false, inputs[0..3].to_vec()),
+        ]);
+        assert_eq!(test_circuit.blocks().len(), 3 + 2 + 1 + 1);
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the result of a call of 20 pushes is not the hash of its first 19 pushes
+        let mut hasher = crate::host::poseidon::gen_hasher();
+        hasher.update(&inputs[0..19]);
+        let test_circuit = CallCircuit { calls: vec![(true, inputs, hasher.squeeze())] };
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
@@ -30,50 +30,70 @@ use std::marker::PhantomData;
 use num_bigint::BigUint;
 
 /*
- * Customized gates range_check(target) with each limb less than 2^12
+ * Customized gates range_check(target) with each limb less than 2^LIMB_BITS
  * acc will be the sum of the target limbs and rem is the remaining limbs
  * of the target value.
+ * shift is the same on all the limbs of a range and short = limb * shift is also
+ * looked up in the table on the last limb, so that the last limb of a range of bits
+ * that is not a multiple of LIMB_BITS is less than 2^(LIMB_BITS - log(shift)).
+ * A range of whole limbs has shift = 1 and the line after a range is all zero.
  */
-customized_circuits!(RangeCheckConfig, 2, 3, 2, 0,
-   | limb   |  acc   | rem   | table | sel
-   | nil    |  acc_n | rem_n | nil   | sel_n
+customized_circuits!(RangeCheckConfig, 2, 5, 2, 0,
+   | limb   |  acc   | rem   | short | shift   | table | sel
+   | nil    |  acc_n | rem_n | nil   | shift_n | nil   | sel_n
 );
 
 impl LookupAssistConfig for RangeCheckConfig {
-    /// register a column (col) to be range checked by limb size (sz)
+    /// register a column (col) to be range checked by limb size (sz) and the shift of the last limb
     fn register<F: FieldExt> (
         &self,
         cs: &mut ConstraintSystem<F>,
         col: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
         sz: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
+        shift: impl FnOnce(&mut VirtualCells<F>) -> Expression<F>,
     ) {
         cs.lookup_any("check ranges", |meta| {
             let acc = self.get_expr(meta, RangeCheckConfig::acc());
             let rem = self.get_expr(meta, RangeCheckConfig::rem());
-            vec![(col(meta), acc), (sz(meta), rem)]
+            let last_shift = self.get_expr(meta, RangeCheckConfig::shift());
+            vec![(col(meta), acc), (sz(meta), rem), (shift(meta), last_shift)]
         });
     }
 }
 
-pub struct RangeCheckChip<F:FieldExt> {
+impl RangeCheckConfig {
+    /// the table column which can be shared with other range check configs of the same limb size
+    pub fn table_column(&self) -> Column<Fixed> {
+        self.get_fixed_column(RangeCheckConfig::table())
+    }
+}
+
+/// Range check chip with limbs of LIMB_BITS bits (the table has 2^LIMB_BITS lines),
+/// a range given by a limb count (sz) covers sz * LIMB_BITS bits.
+pub struct RangeCheckChip<F:FieldExt, const LIMB_BITS: usize = 12> {
     config: RangeCheckConfig,
     offset: usize,
     _marker: PhantomData<F>
 }
 
-impl<F:FieldExt> LookupAssistChip<F> for RangeCheckChip<F> {
+impl<F:FieldExt, const LIMB_BITS: usize> LookupAssistChip<F> for RangeCheckChip<F, LIMB_BITS> {
     fn provide_lookup_evidence (
         &mut self,
         region: &mut Region<F>,
         value: F,
         sz: u64,
+        shift: F,
     ) -> Result<(), Error> {
-        self.assign_value_with_range(region, value, sz)
+        self.assign_limbs(region, value, sz, shift)
+    }
+
+    fn limb_bits(&self) -> usize {
+        LIMB_BITS
     }
 }
 
 
-impl<F: FieldExt> RangeCheckChip<F> {
+impl<F: FieldExt, const LIMB_BITS: usize> RangeCheckChip<F, LIMB_BITS> {
     pub fn new(config: RangeCheckConfig) -> Self {
         RangeCheckChip {
             config,
@@ -83,10 +103,17 @@ impl<F: FieldExt> RangeCheckChip<F> {
     }
 
     pub fn configure(cs: &mut ConstraintSystem<F>) -> RangeCheckConfig {
-        let witness= [0; 3]
+        let table = cs.fixed_column();
+        Self::configure_with_table(cs, table)
+    }
+
+    /// configure the chip with a table column shared with another range check chip
+    /// of the same LIMB_BITS, the table is filled by the chip which owns it (see initialize)
+    pub fn configure_with_table(cs: &mut ConstraintSystem<F>, table: Column<Fixed>) -> RangeCheckConfig {
+        let witness= [0; 5]
                 .map(|_|cs.advice_column());
         witness.map(|x| cs.enable_equality(x));
-        let fixed = [0; 2].map(|_| cs.fixed_column());
+        let fixed = [table, cs.fixed_column()];
         let selector =[];
 
         let config = RangeCheckConfig { fixed, selector, witness };
@@ -99,6 +126,13 @@ impl<F: FieldExt> RangeCheckChip<F> {
             vec![(limb, table)]
         });
 
+        // Range Check of the shifted last limb
+        cs.lookup_any("short limb within ranges", |meta| {
+            let short = config.get_expr(meta, RangeCheckConfig::short());
+            let table = config.get_expr(meta, RangeCheckConfig::table());
+            vec![(short, table)]
+        });
+
         // First we require the rem is continus if it is not zero
         cs.create_gate("range check constraint", |meta| {
             let rem = config.get_expr(meta, RangeCheckConfig::rem());
@@ -112,7 +146,7 @@ impl<F: FieldExt> RangeCheckChip<F> {
         });
 
         // Second we make sure if the rem is not zero then
-        // carry = carry_n * 2^12 + limb
+        // carry = carry_n * 2^LIMB_BITS + limb
         cs.create_gate("limb acc constraint", |meta| {
             let limb = config.get_expr(meta, RangeCheckConfig::limb());
             let acc = config.get_expr(meta, RangeCheckConfig::acc());
@@ -121,7 +155,7 @@ impl<F: FieldExt> RangeCheckChip<F> {
             let sel_n = config.get_expr(meta, RangeCheckConfig::sel_n());
 
             vec![
-                sel.clone() * (acc.clone() - limb - acc_n * constant_from!(1u64<<12) * sel_n),
+                sel.clone() * (acc.clone() - limb - acc_n * constant_from!(1u64<<LIMB_BITS) * sel_n),
                 sel.clone() * (constant_from!(1) - sel.clone()),
                 //(constant_from!(1) - sel) * acc, // if sel is 0 then acc must equal to 0
             ]
@@ -136,23 +170,41 @@ impl<F: FieldExt> RangeCheckChip<F> {
             ]
         });
 
+        cs.create_gate("short limb", |meta| {
+            let limb = config.get_expr(meta, RangeCheckConfig::limb());
+            let short = config.get_expr(meta, RangeCheckConfig::short());
+            let shift = config.get_expr(meta, RangeCheckConfig::shift());
+            let shift_n = config.get_expr(meta, RangeCheckConfig::shift_n());
+            let sel = config.get_expr(meta, RangeCheckConfig::sel());
+            let sel_n = config.get_expr(meta, RangeCheckConfig::sel_n());
+            vec![
+                sel.clone() * sel_n.clone() * (shift.clone() - shift_n), // same shift within a range
+                sel * (constant_from!(1) - sel_n) * (short - limb * shift), // checked on the last limb
+            ]
+        });
+
         config
     }
 
-    /// Make sure the (value, sz) pair is lookupable in the range_chip
-    pub fn assign_value_with_range (
+    /// The number of limbs of the zero evidence, enough for ranges of 300 bits
+    fn max_limbs() -> u64 {
+        ((300 + LIMB_BITS - 1) / LIMB_BITS) as u64
+    }
+
+    fn assign_limbs(
         &mut self,
         region: &mut Region<F>,
         value: F,
         sz: u64,
+        shift: F,
     ) -> Result<(), Error> {
         let mut limbs = vec![];
         let mut bn = field_to_bn(&value);
         let mut cs = vec![];
         for _ in 0..sz {
             cs.push(bn_to_field(&bn));
-            let limb = bn.modpow(&BigUint::from(1u128), &BigUint::from(1u128<<12));
-            bn = (bn - limb.clone()).div(BigUint::from(1u128<<12));
+            let limb = bn.modpow(&BigUint::from(1u128), &BigUint::from(1u128<<LIMB_BITS));
+            bn = (bn - limb.clone()).div(BigUint::from(1u128<<LIMB_BITS));
             limbs.push(bn_to_field(&limb));
         }
         cs.reverse();
@@ -164,27 +216,67 @@ impl<F: FieldExt> RangeCheckChip<F> {
             self.config.assign_cell(region, self.offset, &RangeCheckConfig::acc(), acc)?;
             self.config.assign_cell(region, self.offset, &RangeCheckConfig::rem(), F::from_u128((sz-i) as u128))?;
             self.config.assign_cell(region, self.offset, &RangeCheckConfig::sel(), F::one())?;
+            self.config.assign_cell(region, self.offset, &RangeCheckConfig::shift(), shift)?;
+            let short = if i == sz - 1 { limb * shift } else { F::zero() };
+            self.config.assign_cell(region, self.offset, &RangeCheckConfig::short(), short)?;
             self.offset += 1;
         }
         self.config.assign_cell(region, self.offset, &RangeCheckConfig::limb(), F::zero())?;
         self.config.assign_cell(region, self.offset, &RangeCheckConfig::acc(), F::zero())?;
         self.config.assign_cell(region, self.offset, &RangeCheckConfig::rem(), F::zero())?;
+        self.config.assign_cell(region, self.offset, &RangeCheckConfig::shift(), F::zero())?;
         self.config.assign_cell(region, self.offset, &RangeCheckConfig::sel(), F::zero())?;
         self.offset += 1;
         Ok(())
     }
 
-    /// initialize the table column from 1 to 2^12
+    /// The limb size (sz) and the shift of the last limb of a range of bits
+    pub fn bits_range(bits: usize) -> (u64, F) {
+        let sz = (bits + LIMB_BITS - 1) / LIMB_BITS;
+        (sz as u64, F::from_u128(1u128 << (sz * LIMB_BITS - bits)))
+    }
+
+    /// Make sure the (value, sz, 1) triple is lookupable in the range_chip
+    pub fn assign_value_with_range (
+        &mut self,
+        region: &mut Region<F>,
+        value: F,
+        sz: u64,
+    ) -> Result<(), Error> {
+        self.assign_limbs(region, value, sz, F::one())
+    }
+
+    /// Make sure the value is less than 2^bits, the (value, sz, shift) triple of bits_range
+    /// is then lookupable in the range_chip
+    pub fn assign_value_with_bits (
+        &mut self,
+        region: &mut Region<F>,
+        value: F,
+        bits: usize,
+    ) -> Result<(), Error> {
+        let (sz, shift) = Self::bits_range(bits);
+        self.assign_limbs(region, value, sz, shift)
+    }
+
+    /// initialize the table column from 0 to 2^LIMB_BITS
     /// initialize needs to be called before using the range_chip
     pub fn initialize(
         &mut self,
         region: &mut Region<F>,
     ) -> Result<(), Error> {
-        for i in 0..4096 {
+        for i in 0..(1usize << LIMB_BITS) {
             self.config.assign_cell(region, i, &RangeCheckConfig::table(), F::from_u128(i as u128))?;
         }
+        self.initialize_shared(region)
+    }
+
+    /// initialize the chip whose table column is shared with (and filled by) another range_chip
+    pub fn initialize_shared(
+        &mut self,
+        region: &mut Region<F>,
+    ) -> Result<(), Error> {
         self.offset = 0;
-        self.assign_value_with_range(region, F::zero(), 25)?;
+        self.assign_value_with_range(region, F::zero(), Self::max_limbs())?;
         Ok(())
     }
 }
@@ -199,7 +291,7 @@ mod tests {
         circuit::{Chip, Layouter, Region, SimpleFloorPlanner, AssignedCell},
         plonk::{
             Advice, Circuit, Column, ConstraintSystem, Error, VirtualCells,
-            Expression
+            Expression, Fixed
         },
         poly::Rotation,
     };
@@ -299,7 +391,8 @@ mod tests {
             rangecheckconfig.register(
                 meta,
                 |c| helperconfig.range_check_column(c),
-                |_| Expression::Constant(Fr::from(4 as u64))
+                |_| Expression::Constant(Fr::from(4 as u64)),
+                |_| Expression::Constant(Fr::one()),
             );
 
             Self::Config {
@@ -338,4 +431,94 @@ mod tests {
         let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[derive(Clone, Debug, Default)]
+    struct TestBitsCircuit {
+        value: u64,
+        bits: usize,
+        /// provide the evidence as whole limbs instead of bits
+        loose: bool,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestBitsConfig {
+        rangecheckconfig: RangeCheckConfig,
+        sharedconfig: RangeCheckConfig,
+        limb: Column<Advice>,
+        sel: Column<Fixed>,
+    }
+
+    const TEST_BITS: usize = 20;
+
+    impl Circuit<Fr> for TestBitsCircuit {
+        type Config = TestBitsConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let rangecheckconfig = RangeCheckChip::<Fr, 8>::configure(meta);
+            let sharedconfig = RangeCheckChip::<Fr, 8>::configure_with_table(meta, rangecheckconfig.table_column());
+            let limb = meta.advice_column();
+            let sel = meta.fixed_column();
+            let (sz, shift) = RangeCheckChip::<Fr, 8>::bits_range(TEST_BITS);
+            sharedconfig.register(
+                meta,
+                |c| c.query_advice(limb, Rotation::cur()) * c.query_fixed(sel, Rotation::cur()),
+                |c| c.query_fixed(sel, Rotation::cur()) * Fr::from(sz),
+                |c| c.query_fixed(sel, Rotation::cur()) * shift,
+            );
+            Self::Config {
+               rangecheckconfig,
+               sharedconfig,
+               limb,
+               sel,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut range_chip = RangeCheckChip::<Fr, 8>::new(config.clone().rangecheckconfig);
+            let mut shared_chip = RangeCheckChip::<Fr, 8>::new(config.clone().sharedconfig);
+            layouter.assign_region(
+                || "range check bits test",
+                |mut region| {
+                    let value = Fr::from(self.value);
+                    range_chip.initialize(&mut region)?;
+                    shared_chip.initialize_shared(&mut region)?;
+                    range_chip.assign_value_with_bits(&mut region, value, self.bits)?;
+                    if self.loose {
+                        shared_chip.assign_value_with_range(&mut region, value, 3)?;
+                    } else {
+                        shared_chip.assign_value_with_bits(&mut region, value, TEST_BITS)?;
+                    }
+                    region.assign_advice(|| "value", config.limb, 0, || value_for_assign!(value))?;
+                    region.assign_fixed(|| "sel", config.sel, 0, || value_for_assign!(Fr::one()))?;
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_range_circuit_bits() {
+        let test_circuit = TestBitsCircuit { value: (1u64 << 20) - 1, bits: 20, loose: false };
+        let prover = MockProver::run(10, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let test_circuit = TestBitsCircuit { value: 1u64 << 20, bits: 20, loose: false };
+        let prover = MockProver::run(10, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // the registered lookup requires 20 bits and a 24 bits evidence is not enough
+        let test_circuit = TestBitsCircuit { value: 1u64 << 20, bits: 24, loose: true };
+        let prover = MockProver::run(10, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
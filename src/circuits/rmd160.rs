@@ -12,12 +12,19 @@ use halo2_proofs::{
 
 use std::marker::PhantomData;
 use crate::host::rmd160::{
+    H0,
     ROUNDS_OFFSET,
     PROUNDS_OFFSET,
     R, O, PR, PO,
     RMD160Atomic,
 };
 use crate::constant;
+use crate::constant_from;
+use crate::circuits::bitwise::{
+    BitwiseOp,
+    BitwiseTableChip,
+    BitwiseTableConfig,
+};
 use crate::{
     customized_circuits,
     table_item,
@@ -33,17 +40,26 @@ use crate::utils::{
 };
 use crate::utils::GateCell;
 
-pub fn u32_to_limbs<F: FieldExt>(v: u32) -> [F; 4] {
+/// A word is decomposed into LIMBS bytes, the operands of the bitwise table
+pub const LIMB_BITS: usize = 8;
+pub const LIMBS: usize = 32 / LIMB_BITS;
+/// The rows of a round: the words followed by one row per byte,
+/// the same five rows as the round gate without lookups
+const ROUND_ROWS: usize = 1 + LIMBS;
+/// The rows of a padded word: the word and its bytes followed by the scale
+const PADDING_ROWS: usize = 2;
+
+pub fn u32_to_limbs<F: FieldExt>(v: u32) -> [F; LIMBS] {
     let mut rem = v;
     let mut r = vec![];
-    for _ in 0..4 {
-        r.append(&mut vec![F::from((rem % 256) as u64)]);
-        rem = rem/256;
+    for _ in 0..LIMBS {
+        r.append(&mut vec![F::from((rem % (1 << LIMB_BITS)) as u64)]);
+        rem = rem >> LIMB_BITS;
     }
     r.try_into().unwrap()
 }
 
-pub fn limb_to_u32<F: FieldExt>(limb: &Limb<F>) -> [F; 4] {
+pub fn limb_to_u32<F: FieldExt>(limb: &Limb<F>) -> [F; LIMBS] {
     let a = field_to_u32(&limb.value);
     u32_to_limbs(a)
 }
@@ -54,12 +70,16 @@ pub struct RMD160Chip<F: FieldExt> {
 }
 
 
-customized_circuits!(RoundGateConfig, 5, 7, 3, 0,
-    | a   | b     | c    |  d   | x    | e     | c_next |  offset  | h_sel | r_sel
-    | w0  | b0    | c0   |  d0  | r0   | w1_h  | w4_h   |  w1_r    | nil   | nil
-    | wb  | b1    | c1   |  d1  | r1   | w1_l  | w4_l   |  w1_rr   | nil   | nil
-    | wc  | b2    | c2   |  d2  | r2   | a_next| w2b    |  nil     | nil   | nil
-    | w1  | b3    | c3   |  d3  | r3   | nil   | w2c    |  nil     | nil   | nil
+/* The byte rows (b_i, c_i, d_i, r_i) also hold the intermediate bytes t1, t2 of
+ * the boolean function of the round which is selected by f0..f4, see
+ * boolean_function_lookup. The cells t1, t2, f0..f4 are assigned on the four byte rows.
+ */
+customized_circuits!(RoundGateConfig, 5, 9, 10, 0,
+    | a   | b     | c    |  d   | x    | e     | c_next | nil | nil |  offset  | h_sel | r_sel | nil | nil | nil | nil | nil | nil | nil
+    | w0  | b0    | c0   |  d0  | r0   | w1_h  | w4_h   | t1  | t2  |  w1_r    | nil   | nil   | nil | nil | f0  | f1  | f2  | f3  | f4
+    | wb  | b1    | c1   |  d1  | r1   | w1_l  | w4_l   | nil | nil |  w1_rr   | nil   | nil   | nil | nil | nil | nil | nil | nil | nil
+    | wc  | b2    | c2   |  d2  | r2   | a_next| w2b    | nil | nil |  nil     | nil   | nil   | nil | nil | nil | nil | nil | nil | nil
+    | w1  | b3    | c3   |  d3  | r3   | nil   | w2c    | nil | nil |  nil     | nil   | nil   | nil | nil | nil | nil | nil | nil | nil
 );
 
 /* All witness we need to fill the gate */
@@ -105,20 +125,125 @@ fn get_witnesses<F: FieldExt>(round: usize, rol: &[u32; 5], x: u32, shift: u32,
 }
 
 
-customized_circuits!(CompressSumConfig, 5, 7, 3, 0,
-| a   | b1    | c2   | sum0 | ca0  | bnew  | col6 | col7 | h_sel| r_sel
-| b   | c1    | d2   | sum1 | ca1  | cnew  | nil  | nil  | nil  | nil
-| c   | d1    | e2   | sum2 | ca2  | dnew  | nil  | nil  | nil  | nil
-| d   | e1    | a2   | sum3 | ca3  | enew  | nil  | nil  | nil  | nil
-| e   | a1    | b2   | sum4 | ca4  | anew  | nil  | nil  | nil  | nil
+customized_circuits!(CompressSumConfig, 5, 9, 10, 0,
+| a   | b1    | c2   | sum0 | ca0  | bnew  | col6 | nil | nil | col7 | h_sel| r_sel | nil | nil | nil | nil | nil | nil | nil
+| b   | c1    | d2   | sum1 | ca1  | cnew  | nil  | nil | nil | nil  | nil  | nil   | nil | nil | nil | nil | nil | nil | nil
+| c   | d1    | e2   | sum2 | ca2  | dnew  | nil  | nil | nil | nil  | nil  | nil   | nil | nil | nil | nil | nil | nil | nil
+| d   | e1    | a2   | sum3 | ca3  | enew  | nil  | nil | nil | nil  | nil  | nil   | nil | nil | nil | nil | nil | nil | nil
+| e   | a1    | b2   | sum4 | ca4  | anew  | nil  | nil | nil | nil  | nil  | nil   | nil | nil | nil | nil | nil | nil | nil
 );
 
+/* The padded words of a message, two lines per word:
+ * word = m_sel * msg + pad
+ * the message words are put in msg while the padding words and the initial buffer are
+ * constants (m_sel = 0). The last (partial) word of the message also has the padding byte in pad.
+ * When m_sel is set, msg * scale = sum(n_i * 256^i) with bytes n_i looked up in the bitwise
+ * table, where scale = 2^(8 * (4 - k)) for a partial word of k bytes and 1 otherwise, thus
+ * msg has at most 32 bits and the unused bytes of the partial word are zero.
+ */
+customized_circuits!(PaddingConfig, 2, 9, 10, 0,
+| msg | word  | n0   | n1   | n2   | n3    | nil  | nil | nil | pad   | nil  | nil   | p_sel | m_sel | nil | nil | nil | nil | nil
+| nil | nil   | nil  | nil  | nil  | nil   | nil  | nil | nil | scale | nil  | nil   | nil   | nil   | nil | nil | nil | nil | nil
+);
 
+const PADDING_BYTES: [RoundCell; LIMBS] = [
+    PaddingConfig::n0, PaddingConfig::n1, PaddingConfig::n2, PaddingConfig::n3,
+];
+
+
+
+type RoundCell = fn() -> GateCell;
+const ROUND_B_LIMBS: [RoundCell; LIMBS] = [
+    RoundGateConfig::b0, RoundGateConfig::b1, RoundGateConfig::b2, RoundGateConfig::b3,
+];
+const ROUND_C_LIMBS: [RoundCell; LIMBS] = [
+    RoundGateConfig::c0, RoundGateConfig::c1, RoundGateConfig::c2, RoundGateConfig::c3,
+];
+const ROUND_D_LIMBS: [RoundCell; LIMBS] = [
+    RoundGateConfig::d0, RoundGateConfig::d1, RoundGateConfig::d2, RoundGateConfig::d3,
+];
+const ROUND_R_LIMBS: [RoundCell; LIMBS] = [
+    RoundGateConfig::r0, RoundGateConfig::r1, RoundGateConfig::r2, RoundGateConfig::r3,
+];
+
+/// The word whose little endian limbs are the given cells
+fn limbs_sum<F: FieldExt>(
+    config: &RoundGateConfig,
+    meta: &mut VirtualCells<F>,
+    limbs: [RoundCell; LIMBS],
+) -> Expression<F> {
+    limbs.iter().rev().fold(constant_from!(0), |acc, limb| {
+        acc * F::from(1u64 << LIMB_BITS) + config.get_expr(meta, limb())
+    })
+}
+
+/// The lookup [op, x, y, z] of the boolean function f of a round at the given slot,
+/// unused slots of a function are left empty (all zero).
+fn boolean_function_lookup<F: FieldExt>(
+    config: &RoundGateConfig,
+    meta: &mut VirtualCells<F>,
+    slot: usize,
+) -> [Expression<F>; 4] {
+    let b = config.get_expr(meta, RoundGateConfig::b0());
+    let c = config.get_expr(meta, RoundGateConfig::c0());
+    let d = config.get_expr(meta, RoundGateConfig::d0());
+    let r = config.get_expr(meta, RoundGateConfig::r0());
+    let t1 = config.get_expr(meta, RoundGateConfig::t1());
+    let t2 = config.get_expr(meta, RoundGateConfig::t2());
+    let mask = BitwiseTableChip::<F, LIMB_BITS>::mask();
+    let nb = constant_from!(mask) - b.clone();
+    let nc = constant_from!(mask) - c.clone();
+    let nd = constant_from!(mask) - d.clone();
+    let (xor, and, or) = (BitwiseOp::Xor.expr(), BitwiseOp::And.expr(), BitwiseOp::Or.expr());
+    let functions = [
+        // x ^ y ^ z
+        [Some([xor.clone(), b.clone(), c.clone(), t1.clone()]), Some([xor.clone(), t1.clone(), d.clone(), r.clone()]), None],
+        // (x & y) | (!x & z)
+        [Some([and.clone(), b.clone(), c.clone(), t1.clone()]), Some([and.clone(), nb, d.clone(), t2.clone()]), Some([or.clone(), t1.clone(), t2.clone(), r.clone()])],
+        // (x | !y) ^ z
+        [Some([or.clone(), b.clone(), nc, t1.clone()]), Some([xor.clone(), t1.clone(), d.clone(), r.clone()]), None],
+        // (x & z) | (y & !z)
+        [Some([and.clone(), b.clone(), d, t1.clone()]), Some([and, c.clone(), nd.clone(), t2.clone()]), Some([or.clone(), t1.clone(), t2, r.clone()])],
+        // x ^ (y | !z)
+        [Some([or, c, nd, t1.clone()]), Some([xor, b, t1, r]), None],
+    ];
+    let fsel = [
+        RoundGateConfig::f0(),
+        RoundGateConfig::f1(),
+        RoundGateConfig::f2(),
+        RoundGateConfig::f3(),
+        RoundGateConfig::f4(),
+    ].map(|f| config.get_expr(meta, f));
+    let mut lookup = [0; 4].map(|_| constant_from!(0));
+    for (f, sel) in functions.into_iter().zip(fsel.into_iter()) {
+        if let Some(exprs) = f[slot].clone() {
+            for (l, e) in lookup.iter_mut().zip(exprs.into_iter()) {
+                *l = l.clone() + e * sel.clone();
+            }
+        }
+    }
+    lookup
+}
+
+/// The intermediate limbs (t1, t2) of the boolean function f, see boolean_function_lookup
+fn boolean_function_witness(f: usize, b: u32, c: u32, d: u32) -> (u32, u32) {
+    let mask = (1u32 << LIMB_BITS) - 1;
+    match f {
+        0 => (b ^ c, 0),
+        1 => (b & c, (mask ^ b) & d),
+        2 => (b | (mask ^ c), 0),
+        3 => (b & d, c & (mask ^ d)),
+        4 => (c | (mask ^ d), 0),
+        _ => unreachable!(),
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct RMD160Config {
     compress_sum_config: CompressSumConfig,
     round_config: RoundGateConfig,
+    padding_config: PaddingConfig,
+    bitwise_config: BitwiseTableConfig,
 }
 
 impl<F: FieldExt> Chip<F> for RMD160Chip<F> {
@@ -143,24 +268,34 @@ impl<F: FieldExt> RMD160Chip<F> {
     }
 
     pub fn configure(cs: &mut ConstraintSystem<F>) -> RMD160Config {
-        let witness= [0; 7]
+        let bitwise_config = BitwiseTableChip::<F, LIMB_BITS>::configure(cs);
+        Self::configure_with_bitwise(cs, &bitwise_config)
+    }
+
+    /// configure the chip against a (possibly shared) bitwise table of LIMB_BITS bits
+    pub fn configure_with_bitwise(cs: &mut ConstraintSystem<F>, bitwise_config: &BitwiseTableConfig) -> RMD160Config {
+        let witness= [0; 9]
                 .map(|_|cs.advice_column());
-        let fixed= [0; 3]
+        let fixed= [0; 10]
                 .map(|_|cs.fixed_column());
         let selector= [];
         witness.map(|x| cs.enable_equality(x));
 
         let config = RMD160Config {
             compress_sum_config: CompressSumConfig::new(witness, fixed, selector),
-            round_config: RoundGateConfig::new(witness, fixed, selector)
+            round_config: RoundGateConfig::new(witness, fixed, selector),
+            padding_config: PaddingConfig::new(witness, fixed, selector),
+            bitwise_config: bitwise_config.clone(),
         };
 
+        for slot in 0..3 {
+            config.bitwise_config.register(cs, "rmd160 boolean function", |meta| {
+                boolean_function_lookup(&config.round_config, meta, slot)
+            });
+        }
+
         cs.create_gate("sum with bound", |meta| {
-            let r0 = config.round_config.get_expr(meta, RoundGateConfig::r0());
-            let r1 = config.round_config.get_expr(meta, RoundGateConfig::r0());
-            let r2 = config.round_config.get_expr(meta, RoundGateConfig::r0());
-            let r3 = config.round_config.get_expr(meta, RoundGateConfig::r0());
-            let sum_r = r0 + (r1 + (r2 + r3 * F::from(1u64 << 8)) * F::from(1u64 << 8)) * F::from(1u64 << 8);
+            let sum_r = limbs_sum(&config.round_config, meta, ROUND_R_LIMBS);
             let w0 = config.round_config.get_expr(meta, RoundGateConfig::w0());
             let wb = config.round_config.get_expr(meta, RoundGateConfig::wb());
             let wc = config.round_config.get_expr(meta, RoundGateConfig::wc());
@@ -194,25 +329,11 @@ impl<F: FieldExt> RMD160Chip<F> {
             let hsel = config.round_config.get_expr(meta, RoundGateConfig::h_sel());
 
             let b = config.round_config.get_expr(meta, RoundGateConfig::b());
-            let b0 = config.round_config.get_expr(meta, RoundGateConfig::b0());
-            let b1 = config.round_config.get_expr(meta, RoundGateConfig::b1());
-            let b2 = config.round_config.get_expr(meta, RoundGateConfig::b2());
-            let b3 = config.round_config.get_expr(meta, RoundGateConfig::b3());
-            let sum_b = b0 + (b1 + (b2 + b3 * F::from(1u64 << 8)) * F::from(1u64 << 8)) * F::from(1u64 << 8);
-
+            let sum_b = limbs_sum(&config.round_config, meta, ROUND_B_LIMBS);
             let c = config.round_config.get_expr(meta, RoundGateConfig::c());
-            let c0 = config.round_config.get_expr(meta, RoundGateConfig::c0());
-            let c1 = config.round_config.get_expr(meta, RoundGateConfig::c1());
-            let c2 = config.round_config.get_expr(meta, RoundGateConfig::c2());
-            let c3 = config.round_config.get_expr(meta, RoundGateConfig::c3());
-            let sum_c = c0 + (c1 + (c2 + c3 * F::from(1u64 << 8)) * F::from(1u64 << 8)) * F::from(1u64 << 8);
-
-            let d = config.round_config.get_expr(meta, RoundGateConfig::c());
-            let d0 = config.round_config.get_expr(meta, RoundGateConfig::d0());
-            let d1 = config.round_config.get_expr(meta, RoundGateConfig::d1());
-            let d2 = config.round_config.get_expr(meta, RoundGateConfig::d2());
-            let d3 = config.round_config.get_expr(meta, RoundGateConfig::d3());
-            let sum_d = d0 + (d1 + (d2 + d3 * F::from(1u64 << 8)) * F::from(1u64 << 8)) * F::from(1u64 << 8);
+            let sum_c = limbs_sum(&config.round_config, meta, ROUND_C_LIMBS);
+            let d = config.round_config.get_expr(meta, RoundGateConfig::d());
+            let sum_d = limbs_sum(&config.round_config, meta, ROUND_D_LIMBS);
 
             vec![
                 (sum_b - b) * hsel.clone(),
@@ -250,6 +371,62 @@ impl<F: FieldExt> RMD160Chip<F> {
             ]
         });
 
+        cs.create_gate("compress sum", |meta| {
+            let sel = config.compress_sum_config.get_expr(meta, CompressSumConfig::r_sel());
+            let sums = [
+                (CompressSumConfig::a(), CompressSumConfig::b1(), CompressSumConfig::c2(), CompressSumConfig::sum0(), CompressSumConfig::ca0(), CompressSumConfig::anew()),
+                (CompressSumConfig::b(), CompressSumConfig::c1(), CompressSumConfig::d2(), CompressSumConfig::sum1(), CompressSumConfig::ca1(), CompressSumConfig::bnew()),
+                (CompressSumConfig::c(), CompressSumConfig::d1(), CompressSumConfig::e2(), CompressSumConfig::sum2(), CompressSumConfig::ca2(), CompressSumConfig::cnew()),
+                (CompressSumConfig::d(), CompressSumConfig::e1(), CompressSumConfig::a2(), CompressSumConfig::sum3(), CompressSumConfig::ca3(), CompressSumConfig::dnew()),
+                (CompressSumConfig::e(), CompressSumConfig::a1(), CompressSumConfig::b2(), CompressSumConfig::sum4(), CompressSumConfig::ca4(), CompressSumConfig::enew()),
+            ];
+            sums.into_iter().map(|(x, y, z, sum, ca, new)| {
+                let x = config.compress_sum_config.get_expr(meta, x);
+                let y = config.compress_sum_config.get_expr(meta, y);
+                let z = config.compress_sum_config.get_expr(meta, z);
+                let sum = config.compress_sum_config.get_expr(meta, sum);
+                let ca = config.compress_sum_config.get_expr(meta, ca);
+                let new = config.compress_sum_config.get_expr(meta, new);
+                vec![
+                    (x + y + z - sum.clone()) * sel.clone(),
+                    (new + ca.clone() * F::from(1u64 << 32) - sum) * sel.clone(),
+                    ca.clone() * (ca.clone() - constant!(F::one())) * (ca - constant!(F::from(2u64))) * sel.clone(),
+                ]
+            }).flatten().collect::<Vec<_>>()
+        });
+
+        cs.create_gate("padding word", |meta| {
+            let sel = config.padding_config.get_expr(meta, PaddingConfig::p_sel());
+            let msel = config.padding_config.get_expr(meta, PaddingConfig::m_sel());
+            let msg = config.padding_config.get_expr(meta, PaddingConfig::msg());
+            let word = config.padding_config.get_expr(meta, PaddingConfig::word());
+            let pad = config.padding_config.get_expr(meta, PaddingConfig::pad());
+            vec![
+                (word - msel * msg - pad) * sel,
+            ]
+        });
+
+        cs.create_gate("padding message bytes", |meta| {
+            let msel = config.padding_config.get_expr(meta, PaddingConfig::m_sel());
+            let msg = config.padding_config.get_expr(meta, PaddingConfig::msg());
+            let scale = config.padding_config.get_expr(meta, PaddingConfig::scale());
+            let sum = PADDING_BYTES.iter().rev().fold(constant_from!(0), |acc, byte| {
+                acc * F::from(1u64 << LIMB_BITS) + config.padding_config.get_expr(meta, byte())
+            });
+            vec![
+                (msg * scale - sum) * msel,
+            ]
+        });
+
+        for byte in PADDING_BYTES {
+            config.bitwise_config.register_range(cs, "rmd160 padding byte", |meta| {
+                (
+                    config.padding_config.get_expr(meta, PaddingConfig::m_sel()),
+                    config.padding_config.get_expr(meta, byte()),
+                )
+            });
+        }
+
         config
     }
 
@@ -279,22 +456,13 @@ impl<F: FieldExt> RMD160Chip<F> {
         self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::w1_rr(), F::from(1u64 << (32 - shift[round][index])))?;
 
         let blimbs = limb_to_u32(&previous[1]);
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::b0(), blimbs[0])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::b1(), blimbs[1])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::b2(), blimbs[2])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::b3(), blimbs[3])?;
-
         let climbs = limb_to_u32(&previous[2]);
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::c0(), climbs[0])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::c1(), climbs[1])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::c2(), climbs[2])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::c3(), climbs[3])?;
-
         let dlimbs = limb_to_u32(&previous[3]);
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::d0(), dlimbs[0])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::d1(), dlimbs[1])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::d2(), dlimbs[2])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::d3(), dlimbs[3])?;
+        for i in 0..LIMBS {
+            self.config.round_config.assign_cell(region, start_offset, &ROUND_B_LIMBS[i](), blimbs[i])?;
+            self.config.round_config.assign_cell(region, start_offset, &ROUND_C_LIMBS[i](), climbs[i])?;
+            self.config.round_config.assign_cell(region, start_offset, &ROUND_D_LIMBS[i](), dlimbs[i])?;
+        }
 
         let rol = previous.into_iter()
             .map(|c| {
@@ -311,8 +479,8 @@ impl<F: FieldExt> RMD160Chip<F> {
         let rlimbs = u32_to_limbs(witness.r);
 
         let mut sum_r = rlimbs[0];
-        for i in 1..4 {
-            sum_r = sum_r + rlimbs[i] * F::from(1u64 << (8*i));
+        for i in 1..LIMBS {
+            sum_r = sum_r + rlimbs[i] * F::from(1u64 << (LIMB_BITS*i));
         }
 
         assert!(sum_r == F::from(witness.r as u64));
@@ -321,10 +489,29 @@ impl<F: FieldExt> RMD160Chip<F> {
         assert!(witness.wb == F::from(witness.r as u64) + F::from(field_to_u32(&previous[0].value) as u64)
                 + F::from(field_to_u32(&input.value) as u64) + F::from(offset[round] as u64));
 
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::r0(), rlimbs[0])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::r1(), rlimbs[1])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::r2(), rlimbs[2])?;
-        self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::r3(), rlimbs[3])?;
+        let f = if pround {5 - round - 1} else { round };
+        let fsel = match f {
+            0 => RoundGateConfig::f0(),
+            1 => RoundGateConfig::f1(),
+            2 => RoundGateConfig::f2(),
+            3 => RoundGateConfig::f3(),
+            _ => RoundGateConfig::f4(),
+        };
+        for i in 0..LIMBS {
+            let (t1, t2) = boolean_function_witness(
+                f,
+                field_to_u32(&blimbs[i]),
+                field_to_u32(&climbs[i]),
+                field_to_u32(&dlimbs[i]),
+            );
+            self.config.round_config.assign_cell(region, start_offset + i, &RoundGateConfig::t1(), F::from(t1 as u64))?;
+            self.config.round_config.assign_cell(region, start_offset + i, &RoundGateConfig::t2(), F::from(t2 as u64))?;
+            self.config.round_config.assign_cell(region, start_offset + i, &fsel, F::one())?;
+        }
+
+        for i in 0..LIMBS {
+            self.config.round_config.assign_cell(region, start_offset, &ROUND_R_LIMBS[i](), rlimbs[i])?;
+        }
 
         self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::w0(), F::from(witness.w0 as u64))?;
         self.config.round_config.assign_cell(region, start_offset, &RoundGateConfig::wb(), witness.wb)?;
@@ -358,7 +545,7 @@ impl<F: FieldExt> RMD160Chip<F> {
         r1: &[Limb<F>; 5],
         r2: &[Limb<F>; 5],
     ) -> Result<[Limb<F>; 5], Error> {
-        self.config.round_config.bind_cell(region, start_offset, &CompressSumConfig::a(), &r0[0])?;
+        self.config.compress_sum_config.bind_cell(region, start_offset, &CompressSumConfig::a(), &r0[0])?;
         self.config.compress_sum_config.bind_cell(region, start_offset, &CompressSumConfig::b(), &r0[1])?;
         self.config.compress_sum_config.bind_cell(region, start_offset, &CompressSumConfig::c(), &r0[2])?;
         self.config.compress_sum_config.bind_cell(region, start_offset, &CompressSumConfig::d(), &r0[3])?;
@@ -409,7 +596,7 @@ impl<F: FieldExt> RMD160Chip<F> {
                 + r2[4].value;
             let ca2 = (field_to_u64(&sum2) - cnew as u64) >> 32;
             self.config.compress_sum_config.assign_cell(region, start_offset, &CompressSumConfig::sum2(), sum2)?;
-            self.config.compress_sum_config.assign_cell(region, start_offset, &CompressSumConfig::ca0(), F::from(ca2))?;
+            self.config.compress_sum_config.assign_cell(region, start_offset, &CompressSumConfig::ca2(), F::from(ca2))?;
             self.config.compress_sum_config.assign_cell(region, start_offset, &CompressSumConfig::cnew(), F::from(cnew as u64))?
         };
 
@@ -439,7 +626,10 @@ impl<F: FieldExt> RMD160Chip<F> {
             self.config.compress_sum_config.assign_cell(region, start_offset, &CompressSumConfig::enew(), F::from(enew as u64))?
         };
 
-        Ok([anew, bnew, cnew, dnew, enew])
+        self.config.compress_sum_config.assign_cell(region, start_offset, &CompressSumConfig::r_sel(), F::one())?;
+
+        // the new buffer starts from the sum of the second words (see host::rmd160::compress)
+        Ok([bnew, cnew, dnew, enew, anew])
     }
 
 
@@ -467,7 +657,7 @@ impl<F: FieldExt> RMD160Chip<F> {
                             &ROUNDS_OFFSET,
                             false,
                         )?;
-                        start_offset += 5;
+                        start_offset += ROUND_ROWS;
                     }
                 }
                 /*
@@ -494,7 +684,7 @@ impl<F: FieldExt> RMD160Chip<F> {
                             &PROUNDS_OFFSET,
                             true
                         )?;
-                        start_offset += 5;
+                        start_offset += ROUND_ROWS;
                     }
                 }
                 self.assign_compress(&mut region, start_offset, start_buf, &r1, &r2)
@@ -502,6 +692,101 @@ impl<F: FieldExt> RMD160Chip<F> {
         )?;
         Ok(r)
     }
+
+    /// fill the bitwise table, needs to be called once before assigning any content
+    pub fn initialize(
+        &self,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rmd160 bitwise table",
+            |mut region| {
+                BitwiseTableChip::<F, LIMB_BITS>::new(self.config.bitwise_config.clone()).initialize(&mut region)
+            }
+        )
+    }
+
+    /// Assign a padded word, the message word msg (if any) must fit in bytes bytes
+    fn assign_padding_word(
+        &self,
+        region: &mut Region<F>,
+        start_offset: usize,
+        msg: Option<(&Limb<F>, usize)>,
+        pad: u32,
+    ) -> Result<Limb<F>, Error> {
+        let pad = F::from(pad as u64);
+        let word = match msg {
+            Some((msg, bytes)) => {
+                let scale = F::from(1u64 << (8 * (4 - bytes)));
+                self.config.padding_config.bind_cell(region, start_offset, &PaddingConfig::msg(), msg)?;
+                self.config.padding_config.assign_cell(region, start_offset, &PaddingConfig::m_sel(), F::one())?;
+                self.config.padding_config.assign_cell(region, start_offset, &PaddingConfig::scale(), scale)?;
+                let bytes = u32_to_limbs::<F>(field_to_u32(&(msg.value * scale)));
+                for (byte, value) in PADDING_BYTES.iter().zip(bytes.into_iter()) {
+                    self.config.padding_config.assign_cell(region, start_offset, &byte(), value)?;
+                }
+                msg.value + pad
+            },
+            None => pad
+        };
+        self.config.padding_config.assign_cell(region, start_offset, &PaddingConfig::pad(), pad)?;
+        self.config.padding_config.assign_cell(region, start_offset, &PaddingConfig::p_sel(), F::one())?;
+        self.config.padding_config.assign_cell(region, start_offset, &PaddingConfig::word(), word)
+    }
+
+    /// Hash a message of len bytes given as little endian u32 words (the last word may be partial
+    /// with its unused high bytes set to zero). The message words are range checked and padded
+    /// in circuit and the compressions are chained from H0, the result is the rmd160 digest in words.
+    pub fn assign_message(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &Vec<Limb<F>>,
+        len: usize,
+    ) -> Result<[Limb<F>; 5], Error> {
+        assert!(message.len() == (len + 3) / 4);
+        let full_words = len / 4;
+        let nb_words = ((len + 8) / 64 + 1) * 16;
+        let bit_len = (len as u64) * 8;
+        let (start_buf, words) = layouter.assign_region(
+            || "rmd160 padding",
+            |mut region| {
+                let mut start_offset = 0;
+                let mut start_buf = vec![];
+                for h in H0.iter() {
+                    start_buf.push(self.assign_padding_word(&mut region, start_offset, None, *h)?);
+                    start_offset += PADDING_ROWS;
+                }
+                let mut words = vec![];
+                for w in message[0..full_words].iter() {
+                    words.push(self.assign_padding_word(&mut region, start_offset, Some((w, 4)), 0)?);
+                    start_offset += PADDING_ROWS;
+                }
+                if len % 4 != 0 {
+                    let pad = 0x80u32 << (8 * (len % 4));
+                    words.push(self.assign_padding_word(&mut region, start_offset, Some((&message[full_words], len % 4)), pad)?);
+                    start_offset += PADDING_ROWS;
+                }
+                let mut pad = if len % 4 == 0 { 0x80u32 } else { 0 };
+                while words.len() < nb_words - 2 {
+                    words.push(self.assign_padding_word(&mut region, start_offset, None, pad)?);
+                    start_offset += PADDING_ROWS;
+                    pad = 0;
+                }
+                for w in [bit_len as u32, (bit_len >> 32) as u32] {
+                    words.push(self.assign_padding_word(&mut region, start_offset, None, w)?);
+                    start_offset += PADDING_ROWS;
+                }
+                let start_buf: [Limb<F>; 5] = start_buf.try_into().unwrap();
+                Ok((start_buf, words))
+            }
+        )?;
+
+        let mut buf = start_buf;
+        for block in words.chunks_exact(16) {
+            buf = self.assign_content(layouter, &buf, &block.to_vec().try_into().unwrap())?;
+        }
+        Ok(buf)
+    }
 }
 
 
@@ -520,7 +805,7 @@ mod tests {
 
     use super::RMD160Chip;
     use super::RMD160Config;
-    use crate::host::rmd160::H0;
+    use crate::host::rmd160::{H0, rmd160};
     use crate::value_for_assign;
     use crate::utils::{
         field_to_u32,
@@ -614,6 +899,36 @@ mod tests {
                 }
             )
         }
+
+        fn assign_message(
+            &self,
+            layouter: &mut impl Layouter<Fr>,
+            message: &Vec<u8>,
+            junk: u64,
+        ) -> Result<Vec<Limb<Fr>>, Error> {
+            layouter.assign_region(
+                || "message",
+                |mut region| {
+                    let mut r = vec![];
+                    for (i, w) in message.chunks(4).enumerate() {
+                        let mut bytes = [0u8; 4];
+                        bytes[..w.len()].copy_from_slice(w);
+                        let mut v = Fr::from(u32::from_le_bytes(bytes) as u64);
+                        if (i + 1) * 4 >= message.len() {
+                            v += Fr::from(junk);
+                        }
+                        let cell = region.assign_advice(
+                            || format!("assign message"),
+                            self.config.limb,
+                            i,
+                            || value_for_assign!(v)
+                        )?;
+                        r.push(Limb::new(Some(cell), v));
+                    }
+                    Ok(r)
+                }
+            )
+        }
     }
 
     #[derive(Clone, Debug, Default)]
@@ -649,6 +964,7 @@ mod tests {
         ) -> Result<(), Error> {
             let rmd160chip = RMD160Chip::<Fr>::new(config.clone().rmd160config);
             let helperchip = HelperChip::new(config.clone().helperconfig);
+            rmd160chip.initialize(&mut layouter)?;
             let w= helperchip.assign_w(&mut layouter, &H0, 0)?;
             let input = helperchip.assign_inputs(&mut layouter, &self.inputs, 0)?;
             let r = rmd160chip.assign_content(&mut layouter, &w, &input)?;
@@ -666,9 +982,69 @@ mod tests {
     #[test]
     fn test_rmd160_circuit() {
         let test_circuit = RMD160Circuit {inputs: [1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16].map(|x| Fr::from(x as u64))} ;
-        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
-}
 
+    #[derive(Clone, Debug, Default)]
+    struct RMD160MessageCircuit {
+        message: Vec<u8>,
+        /// added to the last message word to fake its unused bytes
+        junk: u64,
+    }
+
+    impl Circuit<Fr> for RMD160MessageCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
 
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Self::Config {
+               rmd160config: RMD160Chip::<Fr>::configure(meta),
+               helperconfig: HelperChip::configure(meta)
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let rmd160chip = RMD160Chip::<Fr>::new(config.clone().rmd160config);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            rmd160chip.initialize(&mut layouter)?;
+            let message = helperchip.assign_message(&mut layouter, &self.message, self.junk)?;
+            let r = rmd160chip.assign_message(&mut layouter, &message, self.message.len())?;
+            if self.junk == 0 {
+                let expected = rmd160(&self.message);
+                for i in 0..5 {
+                    assert_eq!(field_to_u32(&r[i].value).to_le_bytes(), expected[4*i..4*i+4]);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rmd160_message_circuit() {
+        for len in [3, 56, 100] {
+            let test_circuit = RMD160MessageCircuit {message: (0..len).map(|x| x as u8).collect(), junk: 0};
+            let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_rmd160_message_circuit_unused_bytes() {
+        // a non zero unused byte in the partial word, then a word over 32 bits
+        for (len, junk) in [(3, 1u64 << 24), (56, 1u64 << 32)] {
+            let message = (0..len).map(|x| x as u8).collect();
+            let test_circuit = RMD160MessageCircuit {message, junk};
+            let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
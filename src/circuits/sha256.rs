@@ -0,0 +1,353 @@
+use crate::host::sha256::{
+    BIG_SIGMA,
+    BLOCK_WORDS,
+    H0,
+    K,
+    ROUNDS,
+    SMALL_SIGMA,
+    STATE_WORDS,
+};
+use halo2_proofs::arithmetic::FieldExt;
+
+use crate::circuits::Limb;
+use crate::circuits::bitwise::{
+    BitwiseWordChip,
+    BitwiseWordConfig,
+    Word,
+};
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::Region,
+    plonk::{
+        ConstraintSystem,
+        Error
+    },
+};
+
+const WORD_BYTES: usize = 4;
+
+/* Words are kept as little endian bytes of their u32 value (see BitwiseWordChip),
+ * the boolean functions are looked up byte by byte:
+ * Ch(e, f, g) = g ^ (e & (f ^ g))
+ * Maj(a, b, c) = (a & b) | (c & (a | b))
+ * and the sums of a round are computed at once:
+ * e' = d + h + Σ1(e) + Ch(e, f, g) + K[t] + W[t]
+ * a' = h + Σ1(e) + Ch(e, f, g) + K[t] + W[t] + Σ0(a) + Maj(a, b, c)
+ */
+pub struct Sha256State<F: FieldExt> {
+    h: Vec<Word<F>>,
+}
+
+pub struct Sha256Chip<F:FieldExt> {
+    pub config: BitwiseWordConfig,
+    word_chip: BitwiseWordChip<F>,
+    sha256_state: Sha256State<F>,
+    _marker: PhantomData<F>
+}
+
+impl<F: FieldExt> Sha256Chip<F> {
+    pub fn construct(config: BitwiseWordConfig) -> Self {
+        let state = Sha256State {
+            h: vec![],
+        };
+
+        Sha256Chip {
+            word_chip: BitwiseWordChip::new(config.clone()),
+            config,
+            sha256_state: state,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn initialize(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        *offset = 0;
+        self.word_chip.initialize(region, offset)?;
+        self.sha256_state.h = vec![self.constant_word(0); STATE_WORDS];
+        Ok(())
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<F>) -> BitwiseWordConfig {
+        BitwiseWordChip::configure(cs)
+    }
+
+    fn constant_word(&self, v: u32) -> Word<F> {
+        self.word_chip.constant_word(v as u64, WORD_BYTES)
+    }
+
+    /// x >>> r0 ^ x >>> r1 ^ x >>> r2 (or x >> r2 if shift)
+    fn sigma(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        x: &Word<F>,
+        [r0, r1, r2]: [u32; 3],
+        shift: bool,
+    ) -> Result<Word<F>, Error> {
+        let x0 = self.word_chip.rotate_right(region, offset, x, r0 as usize)?;
+        let x1 = self.word_chip.rotate_right(region, offset, x, r1 as usize)?;
+        let x2 = if shift {
+            self.word_chip.shift_right(region, offset, x, r2 as usize)?
+        } else {
+            self.word_chip.rotate_right(region, offset, x, r2 as usize)?
+        };
+        let x = self.word_chip.xor(region, &x0, &x1)?;
+        self.word_chip.xor(region, &x, &x2)
+    }
+
+    /// the message schedule of a block
+    fn schedule(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        m: Vec<Word<F>>,
+    ) -> Result<Vec<Word<F>>, Error> {
+        let mut w = m;
+        for t in BLOCK_WORDS..ROUNDS {
+            let s1 = self.sigma(region, offset, &w[t - 2], SMALL_SIGMA[1], true)?;
+            let s0 = self.sigma(region, offset, &w[t - 15], SMALL_SIGMA[0], true)?;
+            let word = self.word_chip.add(region, offset, vec![&s1, &w[t - 7], &s0, &w[t - 16]])?;
+            w.push(word);
+        }
+        Ok(w)
+    }
+
+    fn round(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        v: &Vec<Word<F>>,
+        k: &Word<F>,
+        w: &Word<F>,
+    ) -> Result<Vec<Word<F>>, Error> {
+        let (a, b, c, d, e, f, g, h) = (&v[0], &v[1], &v[2], &v[3], &v[4], &v[5], &v[6], &v[7]);
+        let s1 = self.sigma(region, offset, e, BIG_SIGMA[1], false)?;
+        let fg = self.word_chip.xor(region, f, g)?;
+        let efg = self.word_chip.and(region, e, &fg)?;
+        let ch = self.word_chip.xor(region, g, &efg)?;
+        let s0 = self.sigma(region, offset, a, BIG_SIGMA[0], false)?;
+        let ab = self.word_chip.and(region, a, b)?;
+        let a_or_b = self.word_chip.or(region, a, b)?;
+        let cab = self.word_chip.and(region, c, &a_or_b)?;
+        let maj = self.word_chip.or(region, &ab, &cab)?;
+        let new_e = self.word_chip.add(region, offset, vec![d, h, &s1, &ch, k, w])?;
+        let new_a = self.word_chip.add(region, offset, vec![h, &s1, &ch, k, w, &s0, &maj])?;
+        Ok(vec![new_a, a.clone(), b.clone(), c.clone(), new_e, e.clone(), f.clone(), g.clone()])
+    }
+
+    /// Compress one block of BLOCK_WORDS words.
+    /// new is 1 when a new hash starts from H0 or 0 to continue with the current state.
+    pub fn assign_compress(
+        &mut self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        new: &Limb<F>,
+        values: &[Limb<F>; BLOCK_WORDS],
+        result: &[Limb<F>; STATE_WORDS],
+    ) -> Result<(), Error> {
+        // new * new - new = 0
+        self.config.common.assign_line(region, &mut (), offset,
+            [Some(new.clone()), Some(new.clone()), Some(new.clone()), None, None, None],
+            [Some(-F::one()), None, None, None, None, None, None, Some(F::one()), None],
+            0,
+        )?;
+        let init_h = H0.iter().map(|v| self.constant_word(*v)).collect::<Vec<_>>();
+        let mut h = vec![];
+        for (word, init_word) in self.sha256_state.h.iter().zip(init_h.iter()) {
+            let mut w = vec![];
+            for (byte, init_byte) in word.iter().zip(init_word.iter()) {
+                w.push(self.config.common.select(region, &mut (), offset, new, byte, init_byte, 0)?);
+            }
+            h.push(w);
+        }
+
+        let mut m = vec![];
+        for value in values.iter() {
+            m.push(self.word_chip.decompose(region, offset, value, WORD_BYTES)?);
+        }
+        let w = self.schedule(region, offset, m)?;
+
+        let mut v = h.clone();
+        for t in 0..ROUNDS {
+            let k = self.constant_word(K[t]);
+            v = self.round(region, offset, &v, &k, &w[t])?;
+        }
+
+        for i in 0..STATE_WORDS {
+            h[i] = self.word_chip.add(region, offset, vec![&h[i], &v[i]])?;
+            let word = self.word_chip.compose(region, offset, &h[i])?;
+            assert!(word.value == result[i].value);
+            region.constrain_equal(
+                result[i].cell.as_ref().unwrap().cell(),
+                word.cell.as_ref().unwrap().cell()
+            )?;
+        }
+        self.sha256_state.h = h;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::dev::MockProver;
+    use crate::value_for_assign;
+    use crate::circuits::bitwise::BitwiseWordConfig;
+    use crate::host::sha256::{blocks, compress, H0};
+
+    use halo2_proofs::{
+        circuit::{Chip, Layouter, Region, SimpleFloorPlanner},
+        plonk::{
+            Advice, Circuit, Column, ConstraintSystem, Error
+        },
+    };
+
+    use super::{
+        Sha256Chip,
+        Limb,
+    };
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChipConfig {
+        limb: Column<Advice>
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct HelperChip {
+        config: HelperChipConfig
+    }
+
+    impl Chip<Fr> for HelperChip {
+        type Config = HelperChipConfig;
+        type Loaded = ();
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+
+        fn loaded(&self) -> &Self::Loaded {
+            &()
+        }
+    }
+
+    impl HelperChip {
+        fn new(config: HelperChipConfig) -> Self {
+            HelperChip{
+                config,
+            }
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> HelperChipConfig {
+            let limb= cs.advice_column();
+            cs.enable_equality(limb);
+            HelperChipConfig {
+                limb,
+            }
+        }
+
+        fn assign_values(
+            &self,
+            region: &mut Region<Fr>,
+            offset: &mut usize,
+            values: &Vec<u64>,
+        ) -> Result<Vec<Limb<Fr>>, Error> {
+            let mut r = vec![];
+            for v in values.iter() {
+                let c = region.assign_advice(
+                    || format!("assign input"),
+                    self.config.limb,
+                    *offset,
+                    || value_for_assign!(Fr::from(*v))
+                )?;
+                *offset += 1;
+                r.push(Limb::new(Some(c), Fr::from(*v)));
+            }
+            Ok(r)
+        }
+    }
+
+    /// blocks of (new, message words, result words)
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        blocks: Vec<(Vec<u64>, Vec<u64>, Vec<u64>)>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        sha256config: BitwiseWordConfig,
+        helperconfig: HelperChipConfig,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Self::Config {
+               sha256config: Sha256Chip::<Fr>::configure(meta),
+               helperconfig: HelperChip::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut sha256chip = Sha256Chip::<Fr>::construct(config.clone().sha256config);
+            let helperchip = HelperChip::new(config.clone().helperconfig);
+            layouter.assign_region(
+                || "assign sha256 test",
+                |mut region| {
+                    let mut offset = 0;
+                    let mut blocks = vec![];
+                    for (new, inputs, result) in self.blocks.iter() {
+                        let new = helperchip.assign_values(&mut region, &mut offset, new)?;
+                        let inputs = helperchip.assign_values(&mut region, &mut offset, inputs)?;
+                        let result = helperchip.assign_values(&mut region, &mut offset, result)?;
+                        blocks.push((new, inputs, result));
+                    }
+                    offset = 0;
+                    sha256chip.initialize(&mut region, &mut offset)?;
+                    for (new, inputs, result) in blocks.into_iter() {
+                        sha256chip.assign_compress(
+                            &mut region,
+                            &mut offset,
+                            &new[0],
+                            &inputs.try_into().unwrap(),
+                            &result.try_into().unwrap(),
+                        )?;
+                    }
+                    Ok(())
+                }
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sha256_circuit() {
+        let message = (0..100).map(|x| x as u8).collect::<Vec<_>>();
+        let mut h = H0;
+        let blocks = blocks(&message).into_iter().enumerate().map(|(i, m)| {
+            compress(&mut h, &m);
+            (
+                vec![(i == 0) as u64],
+                m.iter().map(|x| *x as u64).collect(),
+                h.iter().map(|x| *x as u64).collect(),
+            )
+        }).collect();
+        let test_circuit = TestCircuit { blocks };
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
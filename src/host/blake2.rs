@@ -0,0 +1,210 @@
+/* BLAKE2b and BLAKE2s (RFC 7693) without key.
+ * Both variants share the same compression function over words of
+ * WORD_BITS bits, the words of BLAKE2s are kept in u64 with the high bits cleared.
+ */
+pub const STATE_WORDS: usize = 8;
+pub const BLOCK_WORDS: usize = 16;
+
+pub const SIGMA: [[usize; BLOCK_WORDS]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+pub struct Blake2Variant {
+    pub word_bits: usize,
+    pub rounds: usize,
+    pub rotations: [u32; 4],
+    pub iv: [u64; STATE_WORDS],
+}
+
+pub const BLAKE2B: Blake2Variant = Blake2Variant {
+    word_bits: 64,
+    rounds: 12,
+    rotations: [32, 24, 16, 63],
+    iv: [
+        0x6a09_e667_f3bc_c908,
+        0xbb67_ae85_84ca_a73b,
+        0x3c6e_f372_fe94_f82b,
+        0xa54f_f53a_5f1d_36f1,
+        0x510e_527f_ade6_82d1,
+        0x9b05_688c_2b3e_6c1f,
+        0x1f83_d9ab_fb41_bd6b,
+        0x5be0_cd19_137e_2179,
+    ],
+};
+
+pub const BLAKE2S: Blake2Variant = Blake2Variant {
+    word_bits: 32,
+    rounds: 10,
+    rotations: [16, 12, 8, 7],
+    iv: [
+        0x6a09_e667,
+        0xbb67_ae85,
+        0x3c6e_f372,
+        0xa54f_f53a,
+        0x510e_527f,
+        0x9b05_688c,
+        0x1f83_d9ab,
+        0x5be0_cd19,
+    ],
+};
+
+impl Blake2Variant {
+    pub fn from_word_bits(word_bits: usize) -> &'static Blake2Variant {
+        match word_bits {
+            64 => &BLAKE2B,
+            32 => &BLAKE2S,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn word_bytes(&self) -> usize {
+        self.word_bits / 8
+    }
+
+    pub fn block_bytes(&self) -> usize {
+        BLOCK_WORDS * self.word_bytes()
+    }
+
+    pub fn max_digest_len(&self) -> usize {
+        STATE_WORDS * self.word_bytes()
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.word_bits)
+    }
+
+    fn add(&self, a: u64, b: u64) -> u64 {
+        a.wrapping_add(b) & self.mask()
+    }
+
+    fn rotate_right(&self, a: u64, r: u32) -> u64 {
+        ((a >> r) | (a << (self.word_bits as u32 - r))) & self.mask()
+    }
+
+    /// The initial state of an unkeyed hash with the given digest length in bytes
+    pub fn init(&self, digest_len: usize) -> [u64; STATE_WORDS] {
+        assert!(digest_len > 0 && digest_len <= self.max_digest_len());
+        let mut h = self.iv;
+        h[0] ^= 0x0101_0000 ^ digest_len as u64;
+        h
+    }
+
+    fn g(&self, v: &mut [u64; BLOCK_WORDS], [a, b, c, d]: [usize; 4], x: u64, y: u64) {
+        v[a] = self.add(self.add(v[a], v[b]), x);
+        v[d] = self.rotate_right(v[d] ^ v[a], self.rotations[0]);
+        v[c] = self.add(v[c], v[d]);
+        v[b] = self.rotate_right(v[b] ^ v[c], self.rotations[1]);
+        v[a] = self.add(self.add(v[a], v[b]), y);
+        v[d] = self.rotate_right(v[d] ^ v[a], self.rotations[2]);
+        v[c] = self.add(v[c], v[d]);
+        v[b] = self.rotate_right(v[b] ^ v[c], self.rotations[3]);
+    }
+
+    /// The compression function F, t is the number of bytes hashed so far (including this block)
+    pub fn compress(&self, h: &mut [u64; STATE_WORDS], m: &[u64; BLOCK_WORDS], t: u64, last: bool) {
+        let mut v = [0u64; BLOCK_WORDS];
+        v[..STATE_WORDS].copy_from_slice(h);
+        v[STATE_WORDS..].copy_from_slice(&self.iv);
+        v[12] ^= t & self.mask();
+        v[13] ^= if self.word_bits == 64 { 0 } else { t >> self.word_bits };
+        if last {
+            v[14] ^= self.mask();
+        }
+        for round in 0..self.rounds {
+            let s = &SIGMA[round % 10];
+            self.g(&mut v, [0, 4, 8, 12], m[s[0]], m[s[1]]);
+            self.g(&mut v, [1, 5, 9, 13], m[s[2]], m[s[3]]);
+            self.g(&mut v, [2, 6, 10, 14], m[s[4]], m[s[5]]);
+            self.g(&mut v, [3, 7, 11, 15], m[s[6]], m[s[7]]);
+            self.g(&mut v, [0, 5, 10, 15], m[s[8]], m[s[9]]);
+            self.g(&mut v, [1, 6, 11, 12], m[s[10]], m[s[11]]);
+            self.g(&mut v, [2, 7, 8, 13], m[s[12]], m[s[13]]);
+            self.g(&mut v, [3, 4, 9, 14], m[s[14]], m[s[15]]);
+        }
+        for i in 0..STATE_WORDS {
+            h[i] ^= v[i] ^ v[i + STATE_WORDS];
+        }
+    }
+
+    /// Split the message into zero padded blocks of words,
+    /// each block comes with its byte counter and whether it is the last block.
+    pub fn blocks(&self, message: &[u8]) -> Vec<([u64; BLOCK_WORDS], u64, bool)> {
+        let block_bytes = self.block_bytes();
+        let nb_blocks = std::cmp::max(1, (message.len() + block_bytes - 1) / block_bytes);
+        (0..nb_blocks).map(|i| {
+            let start = i * block_bytes;
+            let end = std::cmp::min(message.len(), start + block_bytes);
+            let mut bytes = message[start..end].to_vec();
+            bytes.resize(block_bytes, 0);
+            let words = bytes.chunks_exact(self.word_bytes()).map(|w| {
+                let mut word = [0u8; 8];
+                word[..w.len()].copy_from_slice(w);
+                u64::from_le_bytes(word)
+            }).collect::<Vec<_>>();
+            (words.try_into().unwrap(), end as u64, i == nb_blocks - 1)
+        }).collect()
+    }
+
+    pub fn hash(&self, message: &[u8], digest_len: usize) -> Vec<u8> {
+        let mut h = self.init(digest_len);
+        for (m, t, last) in self.blocks(message).iter() {
+            self.compress(&mut h, m, *t, *last);
+        }
+        let mut bytes = h.iter()
+            .map(|w| w.to_le_bytes()[..self.word_bytes()].to_vec())
+            .collect::<Vec<_>>()
+            .concat();
+        bytes.truncate(digest_len);
+        bytes
+    }
+}
+
+pub fn blake2b(message: &[u8], digest_len: usize) -> Vec<u8> {
+    BLAKE2B.hash(message, digest_len)
+}
+
+pub fn blake2s(message: &[u8], digest_len: usize) -> Vec<u8> {
+    BLAKE2S.hash(message, digest_len)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_blake2() {
+        let long = (0..512).map(|x| x as u8).collect::<Vec<_>>();
+        let cases: [(&[u8], &str, &str, &str); 3] = [
+            (
+                b"",
+                "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce",
+                "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8",
+                "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9",
+            ),
+            (
+                b"abc",
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+                "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319",
+                "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+            ),
+            (
+                &long,
+                "c59ab1095ca4579525338b6b74689ff234bc3fe9765fe26dfb04ddceaee0ab84dfd8967594cb261fcd88687f4454d80f718116c1b3c32f9f7e169357468cbe67",
+                "540b20132d8aeae54057cb69c24f95d26a1c472cc700dd450defe9bb796d4f14",
+                "aeb5499d81f14cb10c2539411cbe3e71167293458543bfa4ca1f9584625fd4c6",
+            ),
+        ];
+        for (message, b512, b256, s256) in cases.iter() {
+            assert_eq!(hex::encode(super::blake2b(message, 64)), *b512);
+            assert_eq!(hex::encode(super::blake2b(message, 32)), *b256);
+            assert_eq!(hex::encode(super::blake2s(message, 32)), *s256);
+        }
+    }
+}
@@ -1,94 +1,23 @@
 #[cfg(test)]
 mod tests {
     use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
-    use crate::utils::field_to_bn;
+    use crate::utils::bn_to_field;
+    use crate::host::hash_to_curve::{hash_to_field, map_to_g1};
     use halo2_proofs::pairing::bls12_381::pairing;
-    use halo2_proofs::pairing::bls12_381::{Fq as Bls381Fq, G1Affine, G2Affine, G1, G2, Gt as Bls381Gt};
+    use halo2_proofs::pairing::bls12_381::{Fq as Bls381Fq, Fr as Bls381Fr, G1Affine, G2Affine, G1, G2, Gt as Bls381Gt};
     use halo2_proofs::pairing::group::Group;
-    use num_bigint::BigUint;
+    use ff::Field;
     use rand::rngs::OsRng;
     use std::fs::File;
     use std::ops::Add;
 
-    fn bls381_fr_to_args(f: Bls381Fq, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
-        let mut bn = field_to_bn(&f);
-        let mut ret = vec![];
-        for _ in 0..8 {
-            let d: BigUint = BigUint::from(1u64 << 54);
-            let r = bn.clone() % d.clone();
-            let value = if r == BigUint::from(0 as u32) {
-                0 as u64
-            } else {
-                r.to_u64_digits()[0]
-            };
-            println!("d is {:?}, remainder is {:?}", d, r);
-            bn = bn / d;
-            let entry = ExternalHostCallEntry {
-                op: op as usize,
-                value,
-                is_ret: false,
-            };
-            ret.append(&mut vec![entry]);
-        }
-        ret
-    }
-
-    fn bls381_gt_to_pair_args(f: Bls381Gt) -> Vec<ExternalHostCallEntry> {
-        let c000 = bls381_fr_to_args(f.0.c0.c0.c0, ForeignInst::BlspairG3);
-        let c001 = bls381_fr_to_args(f.0.c0.c0.c1, ForeignInst::BlspairG3);
-        let c010 = bls381_fr_to_args(f.0.c0.c1.c0, ForeignInst::BlspairG3);
-        let c011 = bls381_fr_to_args(f.0.c0.c1.c1, ForeignInst::BlspairG3);
-        let c020 = bls381_fr_to_args(f.0.c0.c2.c0, ForeignInst::BlspairG3);
-        let c021 = bls381_fr_to_args(f.0.c0.c2.c1, ForeignInst::BlspairG3);
-        let c100 = bls381_fr_to_args(f.0.c1.c0.c0, ForeignInst::BlspairG3);
-        let c101 = bls381_fr_to_args(f.0.c1.c0.c1, ForeignInst::BlspairG3);
-        let c110 = bls381_fr_to_args(f.0.c1.c1.c0, ForeignInst::BlspairG3);
-        let c111 = bls381_fr_to_args(f.0.c1.c1.c1, ForeignInst::BlspairG3);
-        let c120 = bls381_fr_to_args(f.0.c1.c2.c0, ForeignInst::BlspairG3);
-        let c121 = bls381_fr_to_args(f.0.c1.c2.c1, ForeignInst::BlspairG3);
-        vec![
-            c000, c001, c010, c011, c020, c021, c100, c101, c110, c111, c120, c121,
-        ]
-        .into_iter()
-        .flatten()
-        .collect()
-    }
-
-    fn bls381_g1_to_args(g: G1Affine, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
-        let mut a = bls381_fr_to_args(g.x, op);
-        let mut b = bls381_fr_to_args(g.y, op);
-        let z: u64 = g.is_identity().unwrap_u8() as u64;
-        a.append(&mut b);
-        a.append(&mut vec![ExternalHostCallEntry {
-            op: op as usize,
-            value: z,
-            is_ret: false,
-        }]);
-        a
-    }
-
-    fn bls381_g2_to_pair_args(g: G2Affine) -> Vec<ExternalHostCallEntry> {
-        let x0 = bls381_fr_to_args(g.x.c0, ForeignInst::BlspairG2);
-        let x1 = bls381_fr_to_args(g.x.c1, ForeignInst::BlspairG2);
-        let y0 = bls381_fr_to_args(g.y.c0, ForeignInst::BlspairG2);
-        let y1 = bls381_fr_to_args(g.y.c1, ForeignInst::BlspairG2);
-        let z: u64 = g.is_identity().unwrap_u8() as u64;
-        let zentry = ExternalHostCallEntry {
-            op: ForeignInst::BlspairG2 as usize,
-            value: z,
-            is_ret: false,
-        };
-        vec![x0, x1, y0, y1, vec![zentry]]
-            .into_iter()
-            .flatten()
-            .collect()
-    }
+    crate::pairing_host_args!(Bls381Fq, G1Affine, G2Affine, Bls381Gt, 8);
 
     pub fn create_bls_pair_shared_table(a: G1Affine, b: G2Affine) -> ExternalHostCallEntryTable {
         let ab: Bls381Gt = pairing(&a, &b);
-        let g1_args = bls381_g1_to_args(a, ForeignInst::BlspairG1);
-        let g2_args = bls381_g2_to_pair_args(b);
-        let ab_args = bls381_gt_to_pair_args(ab);
+        let g1_args = g1_to_args(a, ForeignInst::BlspairG1);
+        let g2_args = g2_to_args(b, ForeignInst::BlspairG2);
+        let ab_args = gt_to_args(ab, ForeignInst::BlspairG3);
         let table = ExternalHostCallEntryTable(
             vec![g1_args, g2_args, ab_args]
                 .into_iter()
@@ -104,12 +33,82 @@ mod tests {
     ) -> ExternalHostCallEntryTable {
         let mut r = ls
             .iter()
-            .map(|x| bls381_g1_to_args(x.clone(), ForeignInst::BlsSumG1))
+            .map(|x| g1_to_args(x.clone(), ForeignInst::BlsSumG1))
+            .flatten()
+            .collect::<Vec<ExternalHostCallEntry>>();
+        r.append(&mut g1_to_args(sum.clone(), ForeignInst::BlsSumResult));
+        ExternalHostCallEntryTable(r)
+    }
+
+    pub fn create_bls_msm_shared_table(
+        ls: Vec<(G1Affine, Bls381Fr)>,
+        msm: G1Affine,
+    ) -> ExternalHostCallEntryTable {
+        let mut r = vec![ExternalHostCallEntry {
+            op: ForeignInst::BlsMsmNew as usize,
+            value: ls.len() as u64,
+            is_ret: false,
+        }];
+        for (g, s) in ls.iter() {
+            r.append(&mut g1_to_args(g.clone(), ForeignInst::BlsMsmG1));
+            r.append(&mut crate::adaptor::fr_to_args(*s, 5, 54, ForeignInst::BlsMsmScalar));
+        }
+        r.append(&mut g1_to_args(msm, ForeignInst::BlsMsmResult));
+        ExternalHostCallEntryTable(r)
+    }
+
+    pub fn create_bls_g2_sum_shared_table(
+        ls: Vec<G2Affine>,
+        sum: G2Affine,
+    ) -> ExternalHostCallEntryTable {
+        let mut r = ls
+            .iter()
+            .map(|x| g2_to_args(x.clone(), ForeignInst::BlsSumG2))
             .flatten()
             .collect::<Vec<ExternalHostCallEntry>>();
-        r.append(&mut bls381_g1_to_args(sum.clone(), ForeignInst::BlsSumResult));
+        r.append(&mut g2_to_args(sum, ForeignInst::BlsSumG2Result));
         ExternalHostCallEntryTable(r)
     }
+
+    pub fn create_bls_hash_to_curve_shared_table(
+        msgs: Vec<&[u8]>,
+        dst: &[u8],
+    ) -> ExternalHostCallEntryTable {
+        let mut r = vec![];
+        for msg in msgs.iter() {
+            let [u0, u1] = hash_to_field(msg, dst);
+            let p = map_to_g1(&u0, &u1);
+            r.append(&mut fq_to_args(bn_to_field(&u0), ForeignInst::BlsHashToCurveU));
+            r.append(&mut fq_to_args(bn_to_field(&u1), ForeignInst::BlsHashToCurveU));
+            r.append(&mut fq_to_args(bn_to_field(&p.x), ForeignInst::BlsHashToCurveResult));
+            r.append(&mut fq_to_args(bn_to_field(&p.y), ForeignInst::BlsHashToCurveResult));
+            r.push(ExternalHostCallEntry {
+                op: ForeignInst::BlsHashToCurveResult as usize,
+                value: p.infinity as u64,
+                is_ret: false,
+            });
+        }
+        ExternalHostCallEntryTable(r)
+    }
+
+    pub fn create_bls_multi_pair_shared_table(
+        ls: Vec<(G1Affine, G2Affine)>,
+    ) -> ExternalHostCallEntryTable {
+        let mut r = vec![ExternalHostCallEntry {
+            op: ForeignInst::BlsMultiPairNew as usize,
+            value: ls.len() as u64,
+            is_ret: false,
+        }];
+        let mut gt = Bls381Gt::identity();
+        for (a, b) in ls.iter() {
+            gt = gt + pairing(a, b);
+            r.append(&mut g1_to_args(a.clone(), ForeignInst::BlsMultiPairG1));
+            r.append(&mut g2_to_args(b.clone(), ForeignInst::BlsMultiPairG2));
+        }
+        r.append(&mut gt_to_args(gt, ForeignInst::BlsMultiPairGt));
+        ExternalHostCallEntryTable(r)
+    }
+
     #[test]
     fn generate_bls_pair_input() {
         let a: G1Affine = G1::random(&mut OsRng).into();
@@ -133,4 +132,47 @@ mod tests {
         let file = File::create("blssumtest.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &table).expect("can not write to file");
     }
+
+    #[test]
+    fn generate_bls_msm_input() {
+        let mut inputs = vec![];
+        for _ in 0..2 {
+            inputs.push((G1::random(&mut OsRng).into(), Bls381Fr::random(&mut OsRng)));
+        }
+        let ret: G1Affine = inputs
+            .iter()
+            .fold(G1::identity(), |acc, (g, s): &(G1Affine, Bls381Fr)| acc.add(*g * *s))
+            .into();
+
+        let table = create_bls_msm_shared_table(inputs, ret);
+        let file = File::create("blsmsmtest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+
+    #[test]
+    fn generate_bls_multi_pair_input() {
+        // e(a, b) * e(-a, b) is the identity of Gt
+        let a: G1Affine = G1::random(&mut OsRng).into();
+        let b: G2Affine = G2Affine::from(G2::random(&mut OsRng));
+        let table = create_bls_multi_pair_shared_table(vec![(a, b), (-a, b)]);
+        let file = File::create("blsmultipairtest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+
+    #[test]
+    fn generate_bls_g2_sum_input() {
+        let inputs: Vec<G2Affine> = vec![G2::random(&mut OsRng).into(), G2::random(&mut OsRng).into()];
+        let ret: G2Affine = (G2::from(inputs[0]) + inputs[1]).into();
+        let table = create_bls_g2_sum_shared_table(inputs, ret);
+        let file = File::create("blsg2sumtest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
+
+    #[test]
+    fn generate_bls_hash_to_curve_input() {
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let table = create_bls_hash_to_curve_shared_table(vec![&b""[..], &b"abc"[..]], dst);
+        let file = File::create("blshashtocurvetest.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
 }
@@ -0,0 +1,219 @@
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+
+/*
+ * ECDSA over secp256k1: y^2 = x^3 + 7
+ *   vk = sk * G
+ *   R = k * G, r = R.x mod N
+ *   s = (z + r * sk) / k mod N
+ * where z is the message hash and k the nonce.
+ * A signature (r, s) of z is valid if 0 < r, s < N and
+ * R = (z / s) * G + (r / s) * vk is not the identity with R.x mod N == r.
+ * ecrecover takes the parity v of R.y as well, R is then (r, y) and vk = (s * R - z * G) / r,
+ * the ethereum address is the last 20 bytes of keccak256(vk.x || vk.y) in big endian.
+ */
+lazy_static! {
+    pub static ref MODULUS: BigUint = BigUint::parse_bytes(b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f", 16).unwrap();
+    /// order of the group of secp256k1
+    pub static ref ORDER: BigUint = BigUint::parse_bytes(b"fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141", 16).unwrap();
+    pub static ref BASE: Point = Point {
+        x: BigUint::parse_bytes(b"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798", 16).unwrap(),
+        y: BigUint::parse_bytes(b"483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8", 16).unwrap(),
+        infinity: false,
+    };
+}
+
+fn add(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + b) % &*MODULUS
+}
+
+fn sub(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + &*MODULUS - b) % &*MODULUS
+}
+
+fn mul(a: &BigUint, b: &BigUint) -> BigUint {
+    (a * b) % &*MODULUS
+}
+
+fn inv(a: &BigUint) -> BigUint {
+    a.modpow(&(&*MODULUS - 2u64), &MODULUS)
+}
+
+/// inverse modulo the group order
+pub fn scalar_inv(a: &BigUint) -> BigUint {
+    a.modpow(&(&*ORDER - 2u64), &ORDER)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub x: BigUint,
+    pub y: BigUint,
+    pub infinity: bool,
+}
+
+impl Point {
+    pub fn identity() -> Self {
+        Point {
+            x: BigUint::from(0u64),
+            y: BigUint::from(0u64),
+            infinity: true,
+        }
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        self.infinity
+            || (self.x < *MODULUS
+                && self.y < *MODULUS
+                && mul(&self.y, &self.y) == add(&mul(&mul(&self.x, &self.x), &self.x), &BigUint::from(7u64)))
+    }
+
+    pub fn add(&self, q: &Point) -> Point {
+        if self.infinity {
+            return q.clone();
+        }
+        if q.infinity {
+            return self.clone();
+        }
+        let lambda = if self.x == q.x {
+            if add(&self.y, &q.y) == BigUint::from(0u64) {
+                return Point::identity();
+            }
+            let x_square = mul(&self.x, &self.x);
+            mul(&mul(&x_square, &BigUint::from(3u64)), &inv(&add(&self.y, &self.y)))
+        } else {
+            mul(&sub(&q.y, &self.y), &inv(&sub(&q.x, &self.x)))
+        };
+        let x = sub(&sub(&mul(&lambda, &lambda), &self.x), &q.x);
+        let y = sub(&mul(&lambda, &sub(&self.x, &x)), &self.y);
+        Point { x, y, infinity: false }
+    }
+
+    pub fn mul_scalar(&self, scalar: &BigUint) -> Point {
+        let mut r = Point::identity();
+        for i in (0..scalar.bits()).rev() {
+            r = r.add(&r);
+            if (scalar >> i) & BigUint::from(1u64) == BigUint::from(1u64) {
+                r = r.add(self);
+            }
+        }
+        r
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+pub fn public_key(sk: &BigUint) -> Point {
+    BASE.mul_scalar(sk)
+}
+
+/// Sign the message hash z with the nonce k, the nonce must be secret and never reused
+pub fn sign(sk: &BigUint, z: &BigUint, k: &BigUint) -> Signature {
+    let r = &BASE.mul_scalar(k).x % &*ORDER;
+    let s = ((z + &r * sk) * scalar_inv(k)) % &*ORDER;
+    assert!(r != BigUint::from(0u64) && s != BigUint::from(0u64));
+    Signature { r, s }
+}
+
+pub fn verify(vk: &Point, z: &BigUint, sig: &Signature) -> bool {
+    let zero = BigUint::from(0u64);
+    if sig.r == zero || sig.s == zero || sig.r >= *ORDER || sig.s >= *ORDER {
+        return false;
+    }
+    if vk.infinity || !vk.is_on_curve() {
+        return false;
+    }
+    let s_inv = scalar_inv(&sig.s);
+    let u1 = (z * &s_inv) % &*ORDER;
+    let u2 = (&sig.r * &s_inv) % &*ORDER;
+    let p = BASE.mul_scalar(&u1).add(&vk.mul_scalar(&u2));
+    !p.infinity && &p.x % &*ORDER == sig.r
+}
+
+/// Recover the public key of the signature (r, s) of z where v is the parity of R.y,
+/// the signatures with N <= R.x are not supported
+pub fn recover(z: &BigUint, sig: &Signature, v: u64) -> Option<Point> {
+    let zero = BigUint::from(0u64);
+    if sig.r == zero || sig.s == zero || sig.r >= *ORDER || sig.s >= *ORDER || v > 1 {
+        return None;
+    }
+    let x = sig.r.clone();
+    let rhs = add(&mul(&mul(&x, &x), &x), &BigUint::from(7u64));
+    // MODULUS = 3 mod 4 so the square root is rhs^((MODULUS + 1) / 4)
+    let y = rhs.modpow(&((&*MODULUS + 1u64) >> 2), &MODULUS);
+    if mul(&y, &y) != rhs {
+        return None;
+    }
+    let y = if (&y & BigUint::from(1u64)) == BigUint::from(v) { y } else { sub(&zero, &y) };
+    let p = Point { x, y, infinity: false };
+    let z_neg = (&*ORDER - z % &*ORDER) % &*ORDER;
+    let vk = p.mul_scalar(&sig.s)
+        .add(&BASE.mul_scalar(&z_neg))
+        .mul_scalar(&scalar_inv(&sig.r));
+    if vk.infinity {
+        None
+    } else {
+        Some(vk)
+    }
+}
+
+fn to_bytes_be32(a: &BigUint) -> Vec<u8> {
+    let bytes = a.to_bytes_be();
+    let mut r = vec![0u8; 32 - bytes.len()];
+    r.extend_from_slice(&bytes);
+    r
+}
+
+/// The ethereum address of the public key
+pub fn address(vk: &Point) -> [u8; 20] {
+    let mut message = to_bytes_be32(&vk.x);
+    message.extend_from_slice(&to_bytes_be32(&vk.y));
+    let hash = crate::host::keccak256::keccak256(&message);
+    let mut r = [0u8; 20];
+    r.copy_from_slice(&hash[12..]);
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{address, public_key, recover, sign, verify, BASE, ORDER};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_ecdsa_sign_verify() {
+        assert!(BASE.is_on_curve());
+        assert!(BASE.mul_scalar(&ORDER).infinity);
+        assert_eq!(
+            public_key(&BigUint::from(2u64)).x,
+            BigUint::parse_bytes(b"c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5", 16).unwrap()
+        );
+        let sk = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef", 16).unwrap();
+        let vk = public_key(&sk);
+        let z = BigUint::parse_bytes(b"4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", 16).unwrap();
+        let k = BigUint::parse_bytes(b"fedcba0987654321fedcba0987654321", 16).unwrap();
+        let sig = sign(&sk, &z, &k);
+        assert!(verify(&vk, &z, &sig));
+        assert!(!verify(&vk, &(&z + 1u64), &sig));
+        assert!(!verify(&BASE, &z, &sig));
+    }
+
+    #[test]
+    fn test_ecdsa_recover() {
+        let sk = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef", 16).unwrap();
+        let vk = public_key(&sk);
+        let z = BigUint::parse_bytes(b"4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", 16).unwrap();
+        let k = BigUint::parse_bytes(b"fedcba0987654321fedcba0987654321", 16).unwrap();
+        let sig = sign(&sk, &z, &k);
+        let v = (&BASE.mul_scalar(&k).y & BigUint::from(1u64)) == BigUint::from(1u64);
+        assert_eq!(recover(&z, &sig, v as u64), Some(vk.clone()));
+        assert_ne!(recover(&z, &sig, !v as u64), Some(vk));
+        // the address of sk = 1 is the address of the generator
+        assert_eq!(
+            address(&public_key(&BigUint::from(1u64))).to_vec(),
+            hex::decode("7e5f4552091a69125d5dfcb7b8c2659029395bdf").unwrap()
+        );
+    }
+}
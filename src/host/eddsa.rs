@@ -0,0 +1,79 @@
+use lazy_static::lazy_static;
+use crate::utils::{bn_to_field, field_to_bn};
+use crate::host::jubjub::{Point, SUBGROUP_ORDER};
+use crate::host::poseidon::gen_hasher;
+use halo2_proofs::pairing::bn256::Fr;
+use num_bigint::BigUint;
+
+/*
+ * EdDSA over the (alt) BabyJubjub curve of crate::host::jubjub:
+ *   vk = sk * G
+ *   r = H(sk, msg) mod L, R = r * G
+ *   c = H(R.x, R.y, vk.x, vk.y, msg)
+ *   s = r + c * sk mod L
+ * where H is the poseidon sponge (update then squeeze) and L is the order of G.
+ * A signature (R, s) of msg is valid if s < L and s * G = R + c * vk.
+ */
+lazy_static! {
+    pub static ref BASE: Point = Point {
+        x: bn_to_field(&(BigUint::parse_bytes(b"2ef3f9b423a2c8c74e9803958f6c320e854a1c1c06cd5cc8fd221dc052d76df7", 16).unwrap())),
+        y: bn_to_field(&(BigUint::parse_bytes(b"05a01167ea785d3f784224644a68e4067532c815f5f6d57d984b5c0e9c6c94b7", 16).unwrap())),
+    };
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub r: Point,
+    pub s: BigUint,
+}
+
+pub fn public_key(sk: &BigUint) -> Point {
+    BASE.mul_scalar(sk)
+}
+
+/// The challenge c = H(R.x, R.y, vk.x, vk.y, msg)
+pub fn challenge(r: &Point, vk: &Point, msg: &Fr) -> Fr {
+    let mut hasher = gen_hasher();
+    hasher.update(&[r.x, r.y, vk.x, vk.y, *msg]);
+    hasher.squeeze()
+}
+
+/// Sign msg with a deterministic nonce derived from the secret key
+pub fn sign(sk: &BigUint, msg: &Fr) -> Signature {
+    assert!(*sk < *SUBGROUP_ORDER);
+    let mut hasher = gen_hasher();
+    hasher.update(&[bn_to_field(sk), *msg]);
+    let nonce = field_to_bn(&hasher.squeeze()) % &*SUBGROUP_ORDER;
+    let r = BASE.mul_scalar(&nonce);
+    let c = field_to_bn(&challenge(&r, &public_key(sk), msg));
+    let s = (nonce + c * sk) % &*SUBGROUP_ORDER;
+    Signature { r, s }
+}
+
+pub fn verify(vk: &Point, msg: &Fr, sig: &Signature) -> bool {
+    if sig.s >= *SUBGROUP_ORDER || !vk.is_on_curve() || !sig.r.is_on_curve() {
+        return false;
+    }
+    let c = field_to_bn(&challenge(&sig.r, vk, msg));
+    BASE.mul_scalar(&sig.s) == sig.r.add(&vk.mul_scalar(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{public_key, sign, verify, BASE};
+    use crate::host::jubjub::{Point, SUBGROUP_ORDER};
+    use halo2_proofs::pairing::bn256::Fr;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_eddsa_sign_verify() {
+        assert_eq!(BASE.mul_scalar(&SUBGROUP_ORDER), Point::identity());
+        let sk = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef", 16).unwrap();
+        let vk = public_key(&sk);
+        let msg = Fr::from(0x1234u64);
+        let sig = sign(&sk, &msg);
+        assert!(verify(&vk, &msg, &sig));
+        assert!(!verify(&vk, &Fr::from(0x1235u64), &sig));
+        assert!(!verify(&BASE, &msg, &sig));
+    }
+}
@@ -0,0 +1,326 @@
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/*
+ * Hash to G1 of bls12_381 with the suite BLS12381G1_XMD:SHA-256_SSWU_RO_ of RFC 9380:
+ *   (u0, u1) = hash_to_field(msg, dst)
+ *   P = clear_cofactor(iso_map(sswu(u0)) + iso_map(sswu(u1)))
+ * where sswu maps onto the curve E' which is 11-isogenous to E and
+ * iso_map is the isogeny from E' to E.
+ * The host op only proves the map from (u0, u1) to P, hash_to_field is sha256
+ * based and is computed by the caller. There is no hash to G2.
+ */
+
+fn fq(hex: &[u8]) -> BigUint {
+    BigUint::parse_bytes(hex, 16).unwrap()
+}
+
+lazy_static! {
+    pub static ref MODULUS: BigUint = fq(b"1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab");
+    /// coefficients of E': y^2 = x^3 + A' * x + B'
+    pub static ref SSWU_A: BigUint = fq(b"00144698a3b8e9433d693a02c96d4982b0ea985383ee66a8d8e8981aefd881ac98936f8da0e0f97f5cf428082d584c1d");
+    pub static ref SSWU_B: BigUint = fq(b"12e2908d11688030018b12e8753eee3b2016c1f0f24f4070a0b9c14fcef35ef55a23215a316ceaa5d1cc48e98e172be0");
+    /// the non square Z of the sswu map
+    pub static ref SSWU_Z: BigUint = BigUint::from(11u64);
+    /// -B' / A'
+    pub static ref SSWU_C1: BigUint = fq(b"0793154fd85631d966ef2470460c78f6a928ad9f5bdbfac21df39753aa278ba751bdfcf95a84188e29d670675e4c9c7c");
+    /// B' / (Z * A')
+    pub static ref SSWU_C2: BigUint = fq(b"123939a31626a32de772bc7a591ea140683bca0c62efb105310d5ce1d27d1aadf79a5d5cbe8e2c4ff7d4816af76d2814");
+    /// coefficients (from the constant term) of the 11-isogeny map
+    pub static ref ISO11_XNUM: Vec<BigUint> = vec![
+        fq(b"11a05f2b1e833340b809101dd99815856b303e88a2d7005ff2627b56cdb4e2c85610c2d5f2e62d6eaeac1662734649b7"),
+        fq(b"17294ed3e943ab2f0588bab22147a81c7c17e75b2f6a8417f565e33c70d1e86b4838f2a6f318c356e834eef1b3cb83bb"),
+        fq(b"0d54005db97678ec1d1048c5d10a9a1bce032473295983e56878e501ec68e25c958c3e3d2a09729fe0179f9dac9edcb0"),
+        fq(b"1778e7166fcc6db74e0609d307e55412d7f5e4656a8dbf25f1b33289f1b330835336e25ce3107193c5b388641d9b6861"),
+        fq(b"0e99726a3199f4436642b4b3e4118e5499db995a1257fb3f086eeb65982fac18985a286f301e77c451154ce9ac8895d9"),
+        fq(b"1630c3250d7313ff01d1201bf7a74ab5db3cb17dd952799b9ed3ab9097e68f90a0870d2dcae73d19cd13c1c66f652983"),
+        fq(b"0d6ed6553fe44d296a3726c38ae652bfb11586264f0f8ce19008e218f9c86b2a8da25128c1052ecaddd7f225a139ed84"),
+        fq(b"17b81e7701abdbe2e8743884d1117e53356de5ab275b4db1a682c62ef0f2753339b7c8f8c8f475af9ccb5618e3f0c88e"),
+        fq(b"080d3cf1f9a78fc47b90b33563be990dc43b756ce79f5574a2c596c928c5d1de4fa295f296b74e956d71986a8497e317"),
+        fq(b"169b1f8e1bcfa7c42e0c37515d138f22dd2ecb803a0c5c99676314baf4bb1b7fa3190b2edc0327797f241067be390c9e"),
+        fq(b"10321da079ce07e272d8ec09d2565b0dfa7dccdde6787f96d50af36003b14866f69b771f8c285decca67df3f1605fb7b"),
+        fq(b"06e08c248e260e70bd1e962381edee3d31d79d7e22c837bc23c0bf1bc24c6b68c24b1b80b64d391fa9c8ba2e8ba2d229"),
+    ];
+    pub static ref ISO11_XDEN: Vec<BigUint> = vec![
+        fq(b"08ca8d548cff19ae18b2e62f4bd3fa6f01d5ef4ba35b48ba9c9588617fc8ac62b558d681be343df8993cf9fa40d21b1c"),
+        fq(b"12561a5deb559c4348b4711298e536367041e8ca0cf0800c0126c2588c48bf5713daa8846cb026e9e5c8276ec82b3bff"),
+        fq(b"0b2962fe57a3225e8137e629bff2991f6f89416f5a718cd1fca64e00b11aceacd6a3d0967c94fedcfcc239ba5cb83e19"),
+        fq(b"03425581a58ae2fec83aafef7c40eb545b08243f16b1655154cca8abc28d6fd04976d5243eecf5c4130de8938dc62cd8"),
+        fq(b"13a8e162022914a80a6f1d5f43e7a07dffdfc759a12062bb8d6b44e833b306da9bd29ba81f35781d539d395b3532a21e"),
+        fq(b"0e7355f8e4e667b955390f7f0506c6e9395735e9ce9cad4d0a43bcef24b8982f7400d24bc4228f11c02df9a29f6304a5"),
+        fq(b"0772caacf16936190f3e0c63e0596721570f5799af53a1894e2e073062aede9cea73b3538f0de06cec2574496ee84a3a"),
+        fq(b"14a7ac2a9d64a8b230b3f5b074cf01996e7f63c21bca68a81996e1cdf9822c580fa5b9489d11e2d311f7d99bbdcc5a5e"),
+        fq(b"0a10ecf6ada54f825e920b3dafc7a3cce07f8d1d7161366b74100da67f39883503826692abba43704776ec3a79a1d641"),
+        fq(b"095fc13ab9e92ad4476d6e3eb3a56680f682b4ee96f7d03776df533978f31c1593174e4b4b7865002d6384d168ecdd0a"),
+        fq(b"000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001"),
+    ];
+    pub static ref ISO11_YNUM: Vec<BigUint> = vec![
+        fq(b"090d97c81ba24ee0259d1f094980dcfa11ad138e48a869522b52af6c956543d3cd0c7aee9b3ba3c2be9845719707bb33"),
+        fq(b"134996a104ee5811d51036d776fb46831223e96c254f383d0f906343eb67ad34d6c56711962fa8bfe097e75a2e41c696"),
+        fq(b"00cc786baa966e66f4a384c86a3b49942552e2d658a31ce2c344be4b91400da7d26d521628b00523b8dfe240c72de1f6"),
+        fq(b"01f86376e8981c217898751ad8746757d42aa7b90eeb791c09e4a3ec03251cf9de405aba9ec61deca6355c77b0e5f4cb"),
+        fq(b"08cc03fdefe0ff135caf4fe2a21529c4195536fbe3ce50b879833fd221351adc2ee7f8dc099040a841b6daecf2e8fedb"),
+        fq(b"16603fca40634b6a2211e11db8f0a6a074a7d0d4afadb7bd76505c3d3ad5544e203f6326c95a807299b23ab13633a5f0"),
+        fq(b"04ab0b9bcfac1bbcb2c977d027796b3ce75bb8ca2be184cb5231413c4d634f3747a87ac2460f415ec961f8855fe9d6f2"),
+        fq(b"0987c8d5333ab86fde9926bd2ca6c674170a05bfe3bdd81ffd038da6c26c842642f64550fedfe935a15e4ca31870fb29"),
+        fq(b"09fc4018bd96684be88c9e221e4da1bb8f3abd16679dc26c1e8b6e6a1f20cabe69d65201c78607a360370e577bdba587"),
+        fq(b"0e1bba7a1186bdb5223abde7ada14a23c42a0ca7915af6fe06985e7ed1e4d43b9b3f7055dd4eba6f2bafaaebca731c30"),
+        fq(b"19713e47937cd1be0dfd0b8f1d43fb93cd2fcbcb6caf493fd1183e416389e61031bf3a5cce3fbafce813711ad011c132"),
+        fq(b"18b46a908f36f6deb918c143fed2edcc523559b8aaf0c2462e6bfe7f911f643249d9cdf41b44d606ce07c8a4d0074d8e"),
+        fq(b"0b182cac101b9399d155096004f53f447aa7b12a3426b08ec02710e807b4633f06c851c1919211f20d4c04f00b971ef8"),
+        fq(b"0245a394ad1eca9b72fc00ae7be315dc757b3b080d4c158013e6632d3c40659cc6cf90ad1c232a6442d9d3f5db980133"),
+        fq(b"05c129645e44cf1102a159f748c4a3fc5e673d81d7e86568d9ab0f5d396a7ce46ba1049b6579afb7866b1e715475224b"),
+        fq(b"15e6be4e990f03ce4ea50b3b42df2eb5cb181d8f84965a3957add4fa95af01b2b665027efec01c7704b456be69c8b604"),
+    ];
+    pub static ref ISO11_YDEN: Vec<BigUint> = vec![
+        fq(b"16112c4c3a9c98b252181140fad0eae9601a6de578980be6eec3232b5be72e7a07f3688ef60c206d01479253b03663c1"),
+        fq(b"1962d75c2381201e1a0cbd6c43c348b885c84ff731c4d59ca4a10356f453e01f78a4260763529e3532f6102c2e49a03d"),
+        fq(b"058df3306640da276faaae7d6e8eb15778c4855551ae7f310c35a5dd279cd2eca6757cd636f96f891e2538b53dbf67f2"),
+        fq(b"16b7d288798e5395f20d23bf89edb4d1d115c5dbddbcd30e123da489e726af41727364f2c28297ada8d26d98445f5416"),
+        fq(b"0be0e079545f43e4b00cc912f8228ddcc6d19c9f0f69bbb0542eda0fc9dec916a20b15dc0fd2ededda39142311a5001d"),
+        fq(b"08d9e5297186db2d9fb266eaac783182b70152c65550d881c5ecd87b6f0f5a6449f38db9dfa9cce202c6477faaf9b7ac"),
+        fq(b"166007c08a99db2fc3ba8734ace9824b5eecfdfa8d0cf8ef5dd365bc400a0051d5fa9c01a58b1fb93d1a1399126a775c"),
+        fq(b"16a3ef08be3ea7ea03bcddfabba6ff6ee5a4375efa1f4fd7feb34fd206357132b920f5b00801dee460ee415a15812ed9"),
+        fq(b"1866c8ed336c61231a1be54fd1d74cc4f9fb0ce4c6af5920abc5750c4bf39b4852cfe2f7bb9248836b233d9d55535d4a"),
+        fq(b"167a55cda70a6e1cea820597d94a84903216f763e13d87bb5308592e7ea7d4fbc7385ea3d529b35e346ef48bb8913f55"),
+        fq(b"04d2f259eea405bd48f010a01ad2911d9c6dd039bb61a6290e591b36e636a5c871a5c29f4f83060400f8b49cba8f6aa8"),
+        fq(b"0accbb67481d033ff5852c1e48c50c477f94ff8aefce42d28c0f9a88cea7913516f968986f7ebbea9684b529e2561092"),
+        fq(b"0ad6b9514c767fe3c3613144b45f1496543346d98adf02267d5ceef9a00d9b8693000763e3b90ac11e99b138573345cc"),
+        fq(b"02660400eb2e4f3b628bdd0d53cd76f2bf565b94e72927c1cb748df27942480e420517bd8714cc80d1fadc1326ed06f7"),
+        fq(b"0e0fa1d816ddc03e6b24255e0d7819c171c40f65e273b853324efcd6356caa205ca2f570f13497804415473a1d634b8f"),
+        fq(b"000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001"),
+    ];
+}
+
+/// the effective cofactor h_eff which clears the cofactor of G1
+pub const H_EFF: u64 = 0xd201000000010001;
+
+fn add(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + b) % &*MODULUS
+}
+
+fn sub(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + &*MODULUS - b) % &*MODULUS
+}
+
+fn mul(a: &BigUint, b: &BigUint) -> BigUint {
+    (a * b) % &*MODULUS
+}
+
+fn neg(a: &BigUint) -> BigUint {
+    sub(&BigUint::from(0u64), a)
+}
+
+fn inv(a: &BigUint) -> BigUint {
+    a.modpow(&(&*MODULUS - 2u64), &MODULUS)
+}
+
+fn is_square(a: &BigUint) -> bool {
+    a.modpow(&((&*MODULUS - 1u64) >> 1), &MODULUS) != &*MODULUS - 1u64
+}
+
+/// the modulus is 3 mod 4 so the square root is a^((p + 1) / 4)
+fn sqrt(a: &BigUint) -> BigUint {
+    a.modpow(&((&*MODULUS + 1u64) >> 2), &MODULUS)
+}
+
+pub fn sgn0(a: &BigUint) -> bool {
+    a.to_bytes_le()[0] & 1 == 1
+}
+
+/// evaluate the polynomial with coefficients from the constant term at x
+fn horner(coeffs: &Vec<BigUint>, x: &BigUint) -> BigUint {
+    coeffs.iter().rev().fold(BigUint::from(0u64), |acc, c| add(&mul(&acc, x), c))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub x: BigUint,
+    pub y: BigUint,
+    pub infinity: bool,
+}
+
+impl Point {
+    pub fn identity() -> Self {
+        Point {
+            x: BigUint::from(0u64),
+            y: BigUint::from(0u64),
+            infinity: true,
+        }
+    }
+
+    pub fn add(&self, q: &Point) -> Point {
+        if self.infinity {
+            return q.clone();
+        }
+        if q.infinity {
+            return self.clone();
+        }
+        let lambda = if self.x == q.x {
+            if add(&self.y, &q.y) == BigUint::from(0u64) {
+                return Point::identity();
+            }
+            let x_square = mul(&self.x, &self.x);
+            mul(&mul(&x_square, &BigUint::from(3u64)), &inv(&add(&self.y, &self.y)))
+        } else {
+            mul(&sub(&q.y, &self.y), &inv(&sub(&q.x, &self.x)))
+        };
+        let x = sub(&sub(&mul(&lambda, &lambda), &self.x), &q.x);
+        let y = sub(&mul(&lambda, &sub(&self.x, &x)), &self.y);
+        Point { x, y, infinity: false }
+    }
+
+    pub fn mul_scalar(&self, scalar: u64) -> Point {
+        let mut r = Point::identity();
+        for i in (0..64).rev() {
+            r = r.add(&r);
+            if (scalar >> i) & 1 == 1 {
+                r = r.add(self);
+            }
+        }
+        r
+    }
+}
+
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let ell = (len + 31) / 32;
+    assert!(ell <= 255 && dst.len() <= 255);
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b0 = Sha256::new()
+        .chain_update([0u8; 64])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut bi = Sha256::new()
+        .chain_update(b0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = bi.to_vec();
+    for i in 2..=ell {
+        let xor = b0.iter().zip(bi.iter()).map(|(a, b)| a ^ b).collect::<Vec<u8>>();
+        bi = Sha256::new()
+            .chain_update(xor)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&bi);
+    }
+    uniform_bytes.truncate(len);
+    uniform_bytes
+}
+
+/// hash msg into two field elements with 64 bytes each
+pub fn hash_to_field(msg: &[u8], dst: &[u8]) -> [BigUint; 2] {
+    let uniform_bytes = expand_message_xmd(msg, dst, 128);
+    [
+        BigUint::from_bytes_be(&uniform_bytes[0..64]) % &*MODULUS,
+        BigUint::from_bytes_be(&uniform_bytes[64..128]) % &*MODULUS,
+    ]
+}
+
+/// x1 = -B / A * (1 + 1 / (Z^2 * u^4 + Z * u^2)) or B / (Z * A) if the denominator is zero
+pub fn sswu_x1(u: &BigUint) -> BigUint {
+    let tv1 = mul(&SSWU_Z, &mul(u, u));
+    let d = add(&mul(&tv1, &tv1), &tv1);
+    if d == BigUint::from(0u64) {
+        SSWU_C2.clone()
+    } else {
+        mul(&SSWU_C1, &add(&BigUint::from(1u64), &inv(&d)))
+    }
+}
+
+fn sswu_g(x: &BigUint) -> BigUint {
+    add(&mul(&add(&mul(x, x), &SSWU_A), x), &SSWU_B)
+}
+
+/// Returns whether g(x1) is a square and the square root w of g(x1) or Z * g(x1).
+/// Since Z is not a square exactly one of them is a square, and as
+/// g(Z * u^2 * x1) = Z^3 * u^6 * g(x1), the y of x2 = Z * u^2 * x1 is Z * u^3 * w.
+pub fn sswu_witness(u: &BigUint) -> (bool, BigUint) {
+    let gx1 = sswu_g(&sswu_x1(u));
+    if is_square(&gx1) {
+        (true, sqrt(&gx1))
+    } else {
+        (false, sqrt(&mul(&SSWU_Z, &gx1)))
+    }
+}
+
+/// the simplified SWU map onto E'
+pub fn sswu(u: &BigUint) -> (BigUint, BigUint) {
+    let x1 = sswu_x1(u);
+    let (gx1_square, w) = sswu_witness(u);
+    let (x, y) = if gx1_square {
+        (x1, w)
+    } else {
+        let tv1 = mul(&SSWU_Z, &mul(u, u));
+        (mul(&tv1, &x1), mul(&mul(&SSWU_Z, &mul(u, &mul(u, u))), &w))
+    };
+    if sgn0(u) != sgn0(&y) {
+        (x, neg(&y))
+    } else {
+        (x, y)
+    }
+}
+
+/// the 11-isogeny from E' to E
+pub fn iso_map(x: &BigUint, y: &BigUint) -> Point {
+    let x_num = horner(&ISO11_XNUM, x);
+    let x_den = horner(&ISO11_XDEN, x);
+    let y_num = horner(&ISO11_YNUM, x);
+    let y_den = horner(&ISO11_YDEN, x);
+    Point {
+        x: mul(&x_num, &inv(&x_den)),
+        y: mul(y, &mul(&y_num, &inv(&y_den))),
+        infinity: false,
+    }
+}
+
+pub fn map_to_curve(u: &BigUint) -> Point {
+    let (x, y) = sswu(u);
+    iso_map(&x, &y)
+}
+
+/// the map from the two field elements of hash_to_field onto G1
+pub fn map_to_g1(u0: &BigUint, u1: &BigUint) -> Point {
+    map_to_curve(u0).add(&map_to_curve(u1)).mul_scalar(H_EFF)
+}
+
+pub fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Point {
+    let [u0, u1] = hash_to_field(msg, dst);
+    map_to_g1(&u0, &u1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_to_curve, inv, mul, neg, SSWU_A, SSWU_B, SSWU_C1, SSWU_C2, SSWU_Z};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_hash_to_curve_vectors() {
+        // test vectors of RFC 9380 appendix J.9.1
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let cases: [(&[u8], &[u8], &[u8]); 2] = [
+            (
+                b"",
+                b"052926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a1",
+                b"08ba738453bfed09cb546dbb0783dbb3a5f1f566ed67bb6be0e8c67e2e81a4cc68ee29813bb7994998f3eae0c9c6a265",
+            ),
+            (
+                b"abc",
+                b"03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903",
+                b"0b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d",
+            ),
+        ];
+        assert_eq!(*SSWU_C1, mul(&neg(&SSWU_B), &inv(&SSWU_A)));
+        assert_eq!(*SSWU_C2, mul(&SSWU_B, &inv(&mul(&SSWU_Z, &SSWU_A))));
+        for (msg, x, y) in cases.iter() {
+            let p = hash_to_curve(msg, dst);
+            assert_eq!(p.x, BigUint::parse_bytes(x, 16).unwrap());
+            assert_eq!(p.y, BigUint::parse_bytes(y, 16).unwrap());
+        }
+    }
+}
@@ -1,19 +1,27 @@
 use lazy_static::lazy_static;
-use crate::utils::bn_to_field;
+use crate::utils::{bn_to_field, field_to_bn};
 use halo2_proofs::pairing::bn256::Fr;
 use std::ops::{SubAssign, MulAssign, AddAssign};
 use ff::Field;
 use num_bigint::BigUint;
+use serde::{
+    de::{Error, Unexpected},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 
 lazy_static! {
-    static ref D_BIG: BigUint = BigUint::parse_bytes(b"12181644023421730124874158521699555681764249180949974110617291017600649128846", 10).unwrap();
+    pub static ref D_BIG: BigUint = BigUint::parse_bytes(b"12181644023421730124874158521699555681764249180949974110617291017600649128846", 10).unwrap();
     static ref D: Fr = bn_to_field(&(D_BIG));
-    static ref A_BIG: BigUint = BigUint::parse_bytes(b"21888242871839275222246405745257275088548364400416034343698204186575808495616", 10).unwrap();
+    pub static ref A_BIG: BigUint = BigUint::parse_bytes(b"21888242871839275222246405745257275088548364400416034343698204186575808495616", 10).unwrap();
     static ref A: Fr = bn_to_field(&(A_BIG));
     pub static ref Q: BigUint = BigUint::parse_bytes(
         b"21888242871839275222246405745257275088548364400416034343698204186575808495617",10
     ).unwrap();
+    /// order of the prime order subgroup, the curve has cofactor 8
+    pub static ref SUBGROUP_ORDER: BigUint = BigUint::parse_bytes(
+        b"2736030358979909402780800718157159386076813972158567259200215660948447373041", 10
+    ).unwrap();
 }
 
 #[derive(Clone, Debug)]
@@ -94,6 +102,48 @@ pub struct Point {
     pub y: Fr,
 }
 
+impl Serialize for Point {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&hex::encode(self.compress()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Point {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let bytes: [u8; 32] = hex::decode(&s)
+            .map_err(|_| Error::invalid_value(Unexpected::Str(&s), &"hex"))?
+            .try_into()
+            .map_err(|_| Error::invalid_length(s.len() / 2, &"32 bytes"))?;
+        Point::decompress(&bytes)
+            .ok_or_else(|| Error::invalid_value(Unexpected::Str(&s), &"compressed point"))
+    }
+}
+
+impl From<&Point> for [u8; 32] {
+    fn from(p: &Point) -> Self {
+        p.compress()
+    }
+}
+
+impl TryFrom<&[u8; 32]> for Point {
+    type Error = ();
+    fn try_from(bytes: &[u8; 32]) -> Result<Self, Self::Error> {
+        Point::decompress(bytes).ok_or(())
+    }
+}
+
+/// A coordinate is negative if it is greater than (q - 1) / 2
+pub fn is_negative(f: &Fr) -> bool {
+    field_to_bn(f) > (&*Q - 1u64) / 2u64
+}
+
 impl Point {
     pub fn identity() -> Self {
         Point {
@@ -115,6 +165,63 @@ impl Point {
         }
     }
 
+    /// a * x^2 + y^2 = 1 + d * x^2 * y^2
+    pub fn is_on_curve(&self) -> bool {
+        let xx = self.x.square();
+        let yy = self.y.square();
+        let mut lhs = *A;
+        lhs.mul_assign(&xx);
+        lhs.add_assign(&yy);
+        let mut rhs = *D;
+        rhs.mul_assign(&xx);
+        rhs.mul_assign(&yy);
+        rhs.add_assign(&Fr::one());
+        lhs == rhs
+    }
+
+    /// the point is on the curve and in the prime order subgroup
+    pub fn is_in_subgroup(&self) -> bool {
+        self.is_on_curve() && self.mul_scalar(&SUBGROUP_ORDER) == Point::identity()
+    }
+
+    /// The 32 bytes little endian encoding of y with the sign of x (see is_negative) in the highest bit
+    pub fn compress(&self) -> [u8; 32] {
+        let mut bytes = field_to_bn(&self.y).to_bytes_le();
+        bytes.resize(32, 0);
+        if is_negative(&self.x) {
+            bytes[31] |= 0x80;
+        }
+        bytes.try_into().unwrap()
+    }
+
+    /// Recover x from x^2 = (1 - y^2) / (a - d * y^2), returns None if the
+    /// encoding is not canonical or there is no point with the given y and sign.
+    pub fn decompress(bytes: &[u8; 32]) -> Option<Point> {
+        let sign = bytes[31] & 0x80 != 0;
+        let mut y = bytes.clone();
+        y[31] &= 0x7f;
+        let y = BigUint::from_bytes_le(&y);
+        if y >= *Q {
+            return None;
+        }
+        let y: Fr = bn_to_field(&y);
+        let yy = y.square();
+        let mut num = Fr::one();
+        num.sub_assign(&yy);
+        let mut dyy = *D;
+        dyy.mul_assign(&yy);
+        let mut den = *A;
+        den.sub_assign(&dyy);
+        num.mul_assign(&den.invert().unwrap());
+        let x: Fr = Option::from(num.sqrt())?;
+        let x = if is_negative(&x) == sign { x } else { -x };
+        if is_negative(&x) != sign {
+            // x is zero and the sign is set
+            return None;
+        }
+        Some(Point { x, y })
+    }
+
     pub fn add(&self, other: &Point) -> Point{
         self.projective().add(&other.projective()).affine()
     }
@@ -144,9 +251,12 @@ pub fn test_bit(b: &[u8], i: usize) -> bool {
 
 mod tests {
     use super::Point;
+    use halo2_proofs::pairing::bn256::Fr;
+    use ff::Field;
     use num_bigint::BigUint;
     use crate::utils::bn_to_field;
     use std::str::FromStr;
+    use crate::host::eddsa::BASE;
     #[test]
     pub fn verify_alt_jubjub_signature() {
         let msg = b"Foo bar";
@@ -183,4 +293,34 @@ mod tests {
         assert_eq!(lhs,rhs)
     }
 
+    #[test]
+    pub fn check_point_validity() {
+        let p_g = BASE.clone();
+        assert!(p_g.is_in_subgroup());
+        assert!(Point::identity().is_in_subgroup());
+        // the point of order 2
+        let p_2 = Point { x: Fr::zero(), y: -Fr::one() };
+        assert!(p_2.is_on_curve());
+        assert!(!p_2.is_in_subgroup());
+        let p_off = Point { x: Fr::one(), y: Fr::one() };
+        assert!(!p_off.is_on_curve());
+    }
+
+    #[test]
+    pub fn compress_point() {
+        let p_g = BASE.clone();
+        let p_neg = Point { x: -p_g.x, y: p_g.y };
+        for p in [p_g.clone(), p_neg, Point::identity()] {
+            assert_eq!(Point::decompress(&p.compress()), Some(p.clone()));
+            let json = serde_json::to_string(&p).unwrap();
+            assert_eq!(serde_json::from_str::<Point>(&json).unwrap(), p);
+        }
+        assert_ne!(p_g.compress()[31] & 0x80, (Point { x: -p_g.x, y: p_g.y }).compress()[31] & 0x80);
+
+        // the identity with the sign of x set
+        let mut bytes = Point::identity().compress();
+        bytes[31] |= 0x80;
+        assert_eq!(Point::decompress(&bytes), None);
+    }
+
 }
@@ -0,0 +1,126 @@
+/* Keccak-256 as used by ethereum (the original keccak padding 0x01 .. 0x80)
+ * The state is 25 lanes of u64 where lane (x, y) is at x + 5 * y.
+ */
+pub const LANES: usize = 25;
+pub const RATE_LANES: usize = 17;
+pub const RATE_BYTES: usize = RATE_LANES * 8;
+pub const DIGEST_LANES: usize = 4;
+pub const ROUNDS: usize = 24;
+
+pub const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808a,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808b,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008a,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000a,
+    0x0000_0000_8000_808b,
+    0x8000_0000_0000_008b,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800a,
+    0x8000_0000_8000_000a,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+/// Rotation offsets of rho indexed by [x][y]
+pub const ROTATIONS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The destination lane of lane (x, y) in pi
+pub fn pi_index(x: usize, y: usize) -> usize {
+    y + 5 * ((2 * x + 3 * y) % 5)
+}
+
+pub fn keccak_f(state: &mut [u64; LANES]) {
+    for rc in ROUND_CONSTANTS.iter() {
+        // theta
+        let c = [0, 1, 2, 3, 4].map(|x| {
+            state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20]
+        });
+        for i in 0..LANES {
+            let x = i % 5;
+            state[i] ^= c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+        }
+        // rho and pi
+        let mut b = [0u64; LANES];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[pi_index(x, y)] = state[x + 5 * y].rotate_left(ROTATIONS[x][y]);
+            }
+        }
+        // chi
+        for y in 0..5 {
+            for x in 0..5 {
+                state[x + 5 * y] = b[x + 5 * y] ^ ((!b[(x + 1) % 5 + 5 * y]) & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+        // iota
+        state[0] ^= rc;
+    }
+}
+
+/// Pad the message and split it into blocks of RATE_LANES little endian lanes
+pub fn pad(message: &[u8]) -> Vec<[u64; RATE_LANES]> {
+    let mut bytes = message.to_vec();
+    bytes.push(0x01);
+    bytes.resize((bytes.len() + RATE_BYTES - 1) / RATE_BYTES * RATE_BYTES, 0);
+    *bytes.last_mut().unwrap() |= 0x80;
+    bytes.chunks_exact(RATE_BYTES).map(|block| {
+        block.chunks_exact(8)
+            .map(|lane| u64::from_le_bytes(lane.try_into().unwrap()))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }).collect()
+}
+
+/// Absorb one block and return the first DIGEST_LANES lanes of the new state
+pub fn absorb(state: &mut [u64; LANES], block: &[u64; RATE_LANES]) -> [u64; DIGEST_LANES] {
+    for (lane, v) in state.iter_mut().zip(block.iter()) {
+        *lane ^= v;
+    }
+    keccak_f(state);
+    state[0..DIGEST_LANES].try_into().unwrap()
+}
+
+pub fn keccak256(message: &[u8]) -> [u8; 32] {
+    let mut state = [0u64; LANES];
+    let mut digest = [0u64; DIGEST_LANES];
+    for block in pad(message).iter() {
+        digest = absorb(&mut state, block);
+    }
+    digest.map(|x| x.to_le_bytes()).concat().try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_keccak256() {
+        let cases: [(&[u8], &str); 3] = [
+            (b"", "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            (b"abc", "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+            (&[b'a'; 200], "96ea54061def936c4be90b518992fdc6f12f535068a256229aca54267b4d084d"),
+        ];
+        for (message, expected) in cases.iter() {
+            assert_eq!(hex::encode(super::keccak256(message)), *expected);
+        }
+        assert_eq!(super::pad(&[0u8; 136]).len(), 2);
+    }
+}
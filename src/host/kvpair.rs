@@ -1,5 +1,5 @@
 use super::MONGODB_URI;
-use crate::host::merkle::{MerkleError, MerkleErrorCode, MerkleNode, MerkleTree};
+use crate::host::merkle::{MerkleError, MerkleNode, MerkleTree};
 use crate::host::poseidon::gen_hasher;
 use ff::PrimeField;
 use futures::executor;
@@ -12,13 +12,35 @@ use serde::{
     de::{Error, Unexpected},
     Deserialize, Deserializer, Serialize, Serializer,
 };
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Mutex;
 
 fn deserialize_u256_as_binary<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
 where
     D: Deserializer<'de>,
 {
     match Bson::deserialize(deserializer) {
-        Ok(Bson::Binary(bytes)) => Ok(bytes.bytes.try_into().unwrap()),
+        Ok(Bson::Binary(bytes)) => {
+            let len = bytes.bytes.len();
+            bytes
+                .bytes
+                .try_into()
+                .map_err(|_| Error::invalid_length(len, &"32 bytes"))
+        }
+        Ok(..) => Err(Error::invalid_value(Unexpected::Enum, &"Bson::Binary")),
+        Err(e) => Err(e),
+    }
+}
+
+fn deserialize_bytes_from_binary<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Bson::deserialize(deserializer) {
+        Ok(Bson::Binary(bytes)) => Ok(bytes.bytes),
         Ok(..) => Err(Error::invalid_value(Unexpected::Enum, &"Bson::Binary")),
         Err(e) => Err(e),
     }
@@ -35,6 +57,32 @@ where
     binary.serialize(serializer)
 }
 
+fn deserialize_hex_as_u256<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let bytes = hex::decode(&s).map_err(|_| Error::invalid_value(Unexpected::Str(&s), &"hex"))?;
+    bytes
+        .try_into()
+        .map_err(|_| Error::invalid_length(s.len() / 2, &"32 bytes"))
+}
+
+fn deserialize_hex_as_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    hex::decode(&s).map_err(|_| Error::invalid_value(Unexpected::Str(&s), &"hex"))
+}
+
+fn serialize_bytes_as_hex<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
 fn bytes_to_bson(x: &[u8; 32]) -> Bson {
     Bson::Binary(mongodb::bson::Binary {
         subtype: BinarySubtype::Generic,
@@ -48,6 +96,10 @@ pub struct MongoMerkle {
     contract_address: [u8; 32],
     root_hash: [u8; 32],
     default_hash: Vec<[u8; 32]>,
+    /// Nodes (index, hash) already checked when read, records are never
+    /// overwritten in the store thus they do not need to be hashed again.
+    /// It is emptied once it holds MAX_CHECKED_NODES nodes.
+    checked: Mutex<HashSet<(u32, [u8; 32])>>,
 }
 
 pub async fn get_collection<T>(
@@ -127,10 +179,17 @@ pub struct MerkleRecord {
     #[serde(deserialize_with = "self::deserialize_u256_as_binary")]
     right: [u8; 32],
     #[serde(serialize_with = "self::serialize_bytes_as_binary")]
-    #[serde(deserialize_with = "self::deserialize_u256_as_binary")]
-    data: [u8; 32],
+    #[serde(deserialize_with = "self::deserialize_bytes_from_binary")]
+    data: Vec<u8>,
 }
 
+/// Leaf data is a sequence of words of LEAF_WORD_SIZE bytes.
+pub const LEAF_WORD_SIZE: usize = 32;
+/// The largest number of words of a leaf, all of them fit in two blocks of the sponge.
+pub const MAX_LEAF_WORDS: usize = 4;
+/// The largest number of nodes remembered as checked by a MongoMerkle.
+const MAX_CHECKED_NODES: usize = 1 << 16;
+
 impl MerkleNode<[u8; 32]> for MerkleRecord {
     fn index(&self) -> u32 {
         self.index
@@ -138,9 +197,21 @@ impl MerkleNode<[u8; 32]> for MerkleRecord {
     fn hash(&self) -> [u8; 32] {
         self.hash
     }
-    fn set(&mut self, data: &Vec<u8>) {
+    /// Each word of the data is split into two 16 bytes field elements
+    /// and all of them are absorbed by the poseidon sponge.
+    /// The data must be a non-empty sequence of at most MAX_LEAF_WORDS words.
+    fn set(&mut self, data: &Vec<u8>) -> Result<(), MerkleError> {
+        if data.is_empty()
+            || data.len() % LEAF_WORD_SIZE != 0
+            || data.len() > MAX_LEAF_WORDS * LEAF_WORD_SIZE
+        {
+            return Err(MerkleError::InvalidLeafData {
+                index: self.index,
+                len: data.len(),
+            });
+        }
         let mut hasher = gen_hasher();
-        self.data = data.clone().try_into().unwrap();
+        self.data = data.clone();
         let batchdata = data
             .chunks(16)
             .into_iter()
@@ -151,9 +222,9 @@ impl MerkleNode<[u8; 32]> for MerkleRecord {
                 Fr::from_repr(f).unwrap()
             })
             .collect::<Vec<Fr>>();
-        let values: [Fr; 2] = batchdata.try_into().unwrap();
-        hasher.update(&values);
+        hasher.update(&batchdata);
         self.hash = hasher.squeeze().to_repr();
+        Ok(())
     }
     fn right(&self) -> Option<[u8; 32]> {
         Some(self.right)
@@ -168,19 +239,17 @@ impl MerkleRecord {
         MerkleRecord {
             index,
             hash: [0; 32],
-            data: [0; 32],
+            data: [0; LEAF_WORD_SIZE].to_vec(),
             left: [0; 32],
             right: [0; 32],
         }
     }
 
-    pub fn data_as_u64(&self) -> [u64; 4] {
-        [
-            u64::from_le_bytes(self.data[0..8].try_into().unwrap()),
-            u64::from_le_bytes(self.data[8..16].try_into().unwrap()),
-            u64::from_le_bytes(self.data[16..24].try_into().unwrap()),
-            u64::from_le_bytes(self.data[24..32].try_into().unwrap()),
-        ]
+    pub fn data_as_u64(&self) -> Vec<u64> {
+        self.data
+            .chunks(8)
+            .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
+            .collect()
     }
 }
 
@@ -190,19 +259,38 @@ impl MongoMerkle {
     }
     fn empty_leaf(index: u32) -> MerkleRecord {
         let mut leaf = MerkleRecord::new(index);
-        leaf.set(&[0; 32].to_vec());
+        leaf.set(&[0; LEAF_WORD_SIZE].to_vec()).unwrap();
         leaf
     }
+    /// Check that a record read from the store is consistent with its hash:
+    /// leaves hash their data and internal nodes hash their children.
+    /// A leaf whose data can not be held by a leaf is reported as InvalidLeafData.
+    fn check_record(&self, record: &MerkleRecord) -> Result<(), MerkleError> {
+        let depth = (record.index + 1).ilog2() as usize;
+        let expected = if depth == Self::height() {
+            let mut leaf = MerkleRecord::new(record.index);
+            leaf.set(&record.data)?;
+            leaf.hash
+        } else {
+            Self::hash(&record.left, &record.right)
+        };
+        if expected != record.hash {
+            Err(MerkleError::HashMismatch {
+                index: record.index,
+                expected,
+                found: record.hash,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
     /// depth start from 0 up to Self::height(). Example 20 height MongoMerkle, root depth=0, leaf depth=20
     fn get_default_hash(&self, depth: usize) -> Result<[u8; 32], MerkleError> {
         if depth <= Self::height() {
             Ok(self.default_hash[Self::height() - depth])
         } else {
-            Err(MerkleError::new(
-                [0; 32],
-                depth as u32,
-                MerkleErrorCode::InvalidDepth,
-            ))
+            Err(MerkleError::InvalidDepth(depth as u32))
         }
     }
 }
@@ -236,6 +324,7 @@ impl MerkleTree<[u8; 32], 20> for MongoMerkle {
             contract_address: addr,
             root_hash: root,
             default_hash: (*DEFAULT_HASH_VEC).clone(),
+            checked: Mutex::new(HashSet::new()),
         }
     }
 
@@ -265,18 +354,20 @@ impl MerkleTree<[u8; 32], 20> for MongoMerkle {
         self.boundary_check(index)?;
         let record = MerkleRecord {
             index,
-            data: [0; 32],
+            data: [0; LEAF_WORD_SIZE].to_vec(),
             left: *left,
             right: *right,
             hash: *hash,
         };
         //println!("set_node_with_hash {} {:?}", index, hash);
-        executor::block_on(self.update_record(record)).expect("Unexpected DB Error");
+        executor::block_on(self.update_record(record))
+            .map_err(|e| MerkleError::storage(index, e))?;
         Ok(())
     }
 
     fn get_node_with_hash(&self, index: u32, hash: &[u8; 32]) -> Result<Self::Node, MerkleError> {
-        let v = executor::block_on(self.get_record(index, hash)).expect("Unexpected DB Error");
+        let v = executor::block_on(self.get_record(index, hash))
+            .map_err(|e| MerkleError::storage(index, e))?;
         //println!("get_node_with_hash {} {:?} {:?}", index, hash, v);
         let height = (index + 1).ilog2();
         v.map_or(
@@ -291,16 +382,24 @@ impl MerkleTree<[u8; 32], 20> for MongoMerkle {
                     Ok(MerkleRecord {
                         index,
                         hash: self.get_default_hash(height as usize)?,
-                        data: [0; 32],
+                        data: [0; LEAF_WORD_SIZE].to_vec(),
                         left: child_hash,
                         right: child_hash,
                     })
                 } else {
-                    Err(MerkleError::new(*hash, index, MerkleErrorCode::InvalidHash))
+                    Err(MerkleError::MissingNode { index, hash: *hash })
                 }
             },
             |x| {
-                assert!(x.index == index);
+                // a node is only hashed again the first time it is read
+                if !self.checked.lock().unwrap().contains(&(index, *hash)) {
+                    self.check_record(&x)?;
+                    let mut checked = self.checked.lock().unwrap();
+                    if checked.len() >= MAX_CHECKED_NODES {
+                        checked.clear();
+                    }
+                    checked.insert((index, *hash));
+                }
                 Ok(x)
             },
         )
@@ -308,19 +407,167 @@ impl MerkleTree<[u8; 32], 20> for MongoMerkle {
 
     fn set_leaf(&mut self, leaf: &MerkleRecord) -> Result<(), MerkleError> {
         self.boundary_check(leaf.index())?; //should be leaf check?
-        executor::block_on(self.update_record(leaf.clone())).expect("Unexpected DB Error");
+        executor::block_on(self.update_record(leaf.clone()))
+            .map_err(|e| MerkleError::storage(leaf.index(), e))?;
         Ok(())
     }
 }
 
+/// A leaf that is not in its default (empty) state.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MerkleLeafSnapshot {
+    pub index: u32,
+    #[serde(serialize_with = "self::serialize_bytes_as_hex")]
+    #[serde(deserialize_with = "self::deserialize_hex_as_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// Portable dump of the merkle tree of one contract under a given root.
+/// Only non-empty leaves are recorded, ordered by their index,
+/// so that the tree can be rebuilt in any store from the leaves alone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MerkleSnapshot {
+    #[serde(serialize_with = "self::serialize_bytes_as_hex")]
+    #[serde(deserialize_with = "self::deserialize_hex_as_u256")]
+    pub contract_address: [u8; 32],
+    #[serde(serialize_with = "self::serialize_bytes_as_hex")]
+    #[serde(deserialize_with = "self::deserialize_hex_as_u256")]
+    pub root: [u8; 32],
+    pub leaves: Vec<MerkleLeafSnapshot>,
+}
+
+#[derive(Debug, Default)]
+pub struct MerkleFsckReport {
+    /// Number of non-default nodes that have been looked up
+    pub checked: usize,
+    pub errors: Vec<MerkleError>,
+}
+
+impl MerkleFsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl MerkleSnapshot {
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let snapshot = serde_json::from_reader(BufReader::new(file))?;
+        Ok(snapshot)
+    }
+}
+
+impl MongoMerkle {
+    /// Collect the non-empty leaves below the node (index, hash).
+    /// Subtrees whose hash equals the default hash of their depth are empty and skipped.
+    fn collect_leaves(
+        &self,
+        index: u32,
+        hash: &[u8; 32],
+        leaves: &mut Vec<MerkleLeafSnapshot>,
+    ) -> Result<(), MerkleError> {
+        let depth = (index + 1).ilog2() as usize;
+        if *hash == self.get_default_hash(depth)? {
+            return Ok(());
+        }
+        let node = self.get_node_with_hash(index, hash)?;
+        if depth == Self::height() {
+            leaves.push(MerkleLeafSnapshot {
+                index,
+                data: node.data,
+            });
+        } else {
+            self.collect_leaves(index * 2 + 1, &node.left, leaves)?;
+            self.collect_leaves(index * 2 + 2, &node.right, leaves)?;
+        }
+        Ok(())
+    }
+
+    /// Check the nodes below (index, hash) and record the ones that are missing or inconsistent.
+    /// The subtree of a node that can not be read is not visited.
+    fn fsck_node(&self, index: u32, hash: &[u8; 32], report: &mut MerkleFsckReport) {
+        let depth = (index + 1).ilog2() as usize;
+        match self.get_default_hash(depth) {
+            Ok(default) if default == *hash => return,
+            Ok(_) => (),
+            Err(e) => {
+                report.errors.push(e);
+                return;
+            }
+        }
+        report.checked += 1;
+        match self.get_node_with_hash(index, hash) {
+            Ok(node) => {
+                if depth < Self::height() {
+                    self.fsck_node(index * 2 + 1, &node.left, report);
+                    self.fsck_node(index * 2 + 2, &node.right, report);
+                }
+            }
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    /// Walk all non-default nodes under the current root and check that
+    /// each of them exists and hashes to the value referenced by its parent.
+    /// Nodes that have been checked before are checked again.
+    pub fn fsck(&self) -> MerkleFsckReport {
+        self.checked.lock().unwrap().clear();
+        let mut report = MerkleFsckReport::default();
+        self.fsck_node(0, &self.root_hash, &mut report);
+        report
+    }
+
+    /// Dump all live leaves under the current root.
+    pub fn export_snapshot(&self) -> Result<MerkleSnapshot, MerkleError> {
+        let mut leaves = vec![];
+        self.collect_leaves(0, &self.root_hash, &mut leaves)?;
+        Ok(MerkleSnapshot {
+            contract_address: self.contract_address,
+            root: self.root_hash,
+            leaves,
+        })
+    }
+
+    /// Rebuild the tree of snapshot.contract_address from an empty tree by replaying
+    /// all leaves of the snapshot. Fails if the rebuilt root differs from snapshot.root.
+    pub fn import_snapshot(snapshot: &MerkleSnapshot) -> Result<Self, MerkleError> {
+        let mut mt = MongoMerkle::construct(
+            snapshot.contract_address,
+            DEFAULT_HASH_VEC[MongoMerkle::height()],
+        );
+        for leaf in snapshot.leaves.iter() {
+            mt.update_leaf_data_with_proof(leaf.index, &leaf.data)?;
+        }
+        if mt.root_hash != snapshot.root {
+            return Err(MerkleError::HashMismatch {
+                index: 0,
+                expected: snapshot.root,
+                found: mt.root_hash,
+            });
+        }
+        Ok(mt)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{MerkleRecord, MongoMerkle, DEFAULT_HASH_VEC};
+    use super::{
+        MerkleRecord, MerkleSnapshot, MongoMerkle, DEFAULT_HASH_VEC, LEAF_WORD_SIZE, MAX_LEAF_WORDS,
+    };
     use crate::host::{
         kvpair::drop_collection,
-        merkle::{MerkleNode, MerkleTree},
+        merkle::{MerkleError, MerkleNode, MerkleTree},
+        poseidon::gen_hasher,
     };
+    use ff::PrimeField;
     use futures::executor;
+    use halo2_proofs::pairing::bn256::Fr;
 
     #[test]
     /* Test for check parent node
@@ -401,7 +648,7 @@ mod tests {
 
         // 2
         let (mut leaf1, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
-        leaf1.set(&LEAF1_DATA.to_vec());
+        leaf1.set(&LEAF1_DATA.to_vec()).unwrap();
         mt.set_leaf_with_proof(&leaf1).unwrap();
 
         let root = mt.get_root_hash();
@@ -415,7 +662,7 @@ mod tests {
 
         // 3
         let (mut leaf2, _) = mt.get_leaf_with_proof(INDEX2).unwrap();
-        leaf2.set(&LEAF2_DATA.to_vec());
+        leaf2.set(&LEAF2_DATA.to_vec()).unwrap();
         mt.set_leaf_with_proof(&leaf2).unwrap();
 
         let root = mt.get_root_hash();
@@ -508,7 +755,7 @@ mod tests {
 
         // 2
         let (mut leaf, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
-        leaf.set(&LEAF1_DATA.to_vec());
+        leaf.set(&LEAF1_DATA.to_vec()).unwrap();
         mt.set_leaf_with_proof(&leaf).unwrap();
 
         let root = mt.get_root_hash();
@@ -625,7 +872,7 @@ mod tests {
 
         // 2
         let (mut leaf, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
-        leaf.set(&LEAF1_DATA.to_vec());
+        leaf.set(&LEAF1_DATA.to_vec()).unwrap();
         mt.set_leaf_with_proof(&leaf).unwrap();
 
         let root = mt.get_root_hash();
@@ -645,7 +892,7 @@ mod tests {
 
         // 3
         let (mut leaf, _) = mt.get_leaf_with_proof(INDEX2).unwrap();
-        leaf.set(&LEAF2_DATA.to_vec());
+        leaf.set(&LEAF2_DATA.to_vec()).unwrap();
         mt.set_leaf_with_proof(&leaf).unwrap();
 
         let root = mt.get_root_hash();
@@ -664,7 +911,7 @@ mod tests {
 
         // 4
         let (mut leaf, _) = mt.get_leaf_with_proof(INDEX3).unwrap();
-        leaf.set(&LEAF3_DATA.to_vec());
+        leaf.set(&LEAF3_DATA.to_vec()).unwrap();
         mt.set_leaf_with_proof(&leaf).unwrap();
 
         let root = mt.get_root_hash();
@@ -694,4 +941,265 @@ mod tests {
         assert_eq!(leaf.index, INDEX3);
         assert_eq!(leaf.data, LEAF3_DATA);
     }
+
+    #[test]
+    /* Tests for snapshot export and import
+     * 1. Clear m tree collections. Update two leaves of the source tree.
+     * 2. Export the snapshot and check that it survives a round trip through a file.
+     * 3. Import the snapshot under another address and check root and leaves' values.
+     * 4. Import a snapshot with a tampered leaf and check that it is rejected.
+     */
+    fn test_mongo_merkle_snapshot() {
+        const SRC_ADDR: [u8; 32] = [4; 32];
+        const DST_ADDR: [u8; 32] = [5; 32];
+        const INDEX1: u32 = 2_u32.pow(20) - 1;
+        const LEAF1_DATA: [u8; 32] = [
+            0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+        const INDEX2: u32 = 2_u32.pow(21) - 2;
+        const LEAF2_DATA: [u8; 32] = [
+            18, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+
+        // 1
+        let mut mt = MongoMerkle::construct(SRC_ADDR, DEFAULT_HASH_VEC[MongoMerkle::height()]);
+        for addr in [SRC_ADDR, DST_ADDR] {
+            let m = MongoMerkle::construct(addr, DEFAULT_HASH_VEC[MongoMerkle::height()]);
+            executor::block_on(drop_collection::<MerkleRecord>(
+                &m.client,
+                MongoMerkle::get_db_name(),
+                m.get_collection_name(),
+            ))
+            .expect("Unexpected DB Error");
+        }
+        mt.update_leaf_data_with_proof(INDEX2, &LEAF2_DATA.to_vec())
+            .unwrap();
+        mt.update_leaf_data_with_proof(INDEX1, &LEAF1_DATA.to_vec())
+            .unwrap();
+
+        // 2
+        let snapshot = mt.export_snapshot().unwrap();
+        assert_eq!(snapshot.root, mt.get_root_hash());
+        assert_eq!(snapshot.leaves.len(), 2);
+        assert_eq!(snapshot.leaves[0].index, INDEX1);
+        assert_eq!(snapshot.leaves[1].index, INDEX2);
+        let path = std::env::temp_dir().join("merkle_snapshot_test.json");
+        snapshot.save(&path).unwrap();
+        let mut loaded = MerkleSnapshot::load(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        // 3
+        loaded.contract_address = DST_ADDR;
+        let imported = MongoMerkle::import_snapshot(&loaded).unwrap();
+        assert_eq!(imported.get_root_hash(), mt.get_root_hash());
+        let (leaf, _) = imported.get_leaf_with_proof(INDEX1).unwrap();
+        assert_eq!(leaf.data, LEAF1_DATA);
+        let (leaf, _) = imported.get_leaf_with_proof(INDEX2).unwrap();
+        assert_eq!(leaf.data, LEAF2_DATA);
+
+        // 4
+        loaded.leaves[0].data[0] = 1;
+        assert!(MongoMerkle::import_snapshot(&loaded).is_err());
+    }
+
+    #[test]
+    /* Tests for leaves with multi-word data
+     * 1. Clear m tree collection. Set a leaf with three words of data.
+     * 2. Check the leaf hash is the sponge of its six 16 bytes chunks.
+     * 3. Load m tree from DB, check the leaf value and shrink it back to one word.
+     * 4. Check data that a leaf can not hold is rejected and leaves the tree untouched.
+     */
+    fn test_mongo_merkle_multi_word_leaf() {
+        const TEST_ADDR: [u8; 32] = [6; 32];
+        const INDEX1: u32 = 2_u32.pow(20) + 7;
+        const LEAF1_DATA: [u8; 32] = [
+            0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+        let data = (0..96).map(|x| x as u8).collect::<Vec<u8>>();
+
+        // 1
+        let mut mt = MongoMerkle::construct(TEST_ADDR, DEFAULT_HASH_VEC[MongoMerkle::height()]);
+        executor::block_on(drop_collection::<MerkleRecord>(
+            &mt.client,
+            MongoMerkle::get_db_name(),
+            mt.get_collection_name(),
+        ))
+        .expect("Unexpected DB Error");
+        mt.update_leaf_data_with_proof(INDEX1, &data).unwrap();
+
+        // 2
+        let (leaf, proof) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        let mut hasher = gen_hasher();
+        let chunks = data
+            .chunks(16)
+            .map(|x| {
+                let mut v = x.to_vec();
+                v.resize(32, 0);
+                Fr::from_repr(v.try_into().unwrap()).unwrap()
+            })
+            .collect::<Vec<Fr>>();
+        assert_eq!(chunks.len(), 6);
+        hasher.update(&chunks);
+        assert_eq!(leaf.hash, hasher.squeeze().to_repr());
+        assert_eq!(leaf.data, data);
+        assert_eq!(leaf.data_as_u64().len(), 12);
+        assert!(mt.verify_proof(proof).unwrap());
+
+        // 3
+        let mut mt = MongoMerkle::construct(TEST_ADDR, mt.get_root_hash());
+        let (leaf, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        assert_eq!(leaf.data, data);
+        mt.update_leaf_data_with_proof(INDEX1, &LEAF1_DATA.to_vec())
+            .unwrap();
+        let (leaf, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        assert_eq!(leaf.data, LEAF1_DATA);
+
+        // 4
+        let root = mt.get_root_hash();
+        for len in [0, 33, (MAX_LEAF_WORDS + 1) * LEAF_WORD_SIZE] {
+            match mt.update_leaf_data_with_proof(INDEX1, &vec![1; len]) {
+                Err(MerkleError::InvalidLeafData { index, len: found }) => {
+                    assert_eq!(index, INDEX1);
+                    assert_eq!(found, len);
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(mt.get_root_hash(), root);
+    }
+
+    #[test]
+    /* Tests for fsck
+     * 1. Clear m tree collection. Update two leaves and check the store is clean.
+     * 2. Check a root whose parent of the leaves has not been written.
+     */
+    fn test_mongo_merkle_fsck() {
+        const TEST_ADDR: [u8; 32] = [8; 32];
+        const INDEX1: u32 = 2_u32.pow(20) - 1;
+        const INDEX2: u32 = 2_u32.pow(20);
+        const PARENT_INDEX: u32 = 2_u32.pow(19) - 1;
+        const LEAF1_DATA: [u8; 32] = [
+            0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+
+        // 1
+        let mut mt = MongoMerkle::construct(TEST_ADDR, DEFAULT_HASH_VEC[MongoMerkle::height()]);
+        executor::block_on(drop_collection::<MerkleRecord>(
+            &mt.client,
+            MongoMerkle::get_db_name(),
+            mt.get_collection_name(),
+        ))
+        .expect("Unexpected DB Error");
+        mt.update_leaf_data_with_proof(INDEX1, &LEAF1_DATA.to_vec())
+            .unwrap();
+        mt.update_leaf_data_with_proof(INDEX2, &LEAF1_DATA.to_vec())
+            .unwrap();
+        let report = mt.fsck();
+        assert!(report.is_clean());
+        // two leaves and all their ancestors
+        assert_eq!(report.checked, 2 + MongoMerkle::height());
+        // each of them has been hashed once and is not hashed on later reads
+        assert_eq!(mt.checked.lock().unwrap().len(), report.checked);
+
+        // 2
+        let (leaf, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        let lost_parent = MongoMerkle::hash(&leaf.hash, &DEFAULT_HASH_VEC[1]);
+        let mut node = lost_parent;
+        let mut index = PARENT_INDEX;
+        while index > 0 {
+            let sibling = DEFAULT_HASH_VEC[MongoMerkle::height() - (index + 1).ilog2() as usize];
+            let parent = MongoMerkle::hash(&node, &sibling);
+            index = (index - 1) / 2;
+            mt.set_parent(index, &parent, &node, &sibling).unwrap();
+            node = parent;
+        }
+        let broken = MongoMerkle::construct(TEST_ADDR, node);
+        let report = broken.fsck();
+        assert_eq!(report.errors.len(), 1);
+        match &report.errors[0] {
+            MerkleError::MissingNode { index, hash } => {
+                assert_eq!(*index, PARENT_INDEX);
+                assert_eq!(*hash, lost_parent);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    /* Tests for errors of a corrupted store
+     * 1. Clear m tree collection. Update one leaf.
+     * 2. Read a node with an unknown hash and check it is reported as missing.
+     * 3. Store a parent whose hash does not match its children and check it is reported on read.
+     * 4. Store a leaf whose data is not a sequence of words and check it is reported on read.
+     */
+    fn test_mongo_merkle_errors() {
+        const TEST_ADDR: [u8; 32] = [7; 32];
+        const INDEX1: u32 = 2_u32.pow(20) - 1;
+        const PARENT_INDEX: u32 = 2_u32.pow(19) - 1;
+        const LEAF1_DATA: [u8; 32] = [
+            0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+
+        // 1
+        let mut mt = MongoMerkle::construct(TEST_ADDR, DEFAULT_HASH_VEC[MongoMerkle::height()]);
+        executor::block_on(drop_collection::<MerkleRecord>(
+            &mt.client,
+            MongoMerkle::get_db_name(),
+            mt.get_collection_name(),
+        ))
+        .expect("Unexpected DB Error");
+        mt.update_leaf_data_with_proof(INDEX1, &LEAF1_DATA.to_vec())
+            .unwrap();
+
+        // 2
+        let unknown = [3; 32];
+        match mt.get_node_with_hash(PARENT_INDEX, &unknown) {
+            Err(MerkleError::MissingNode { index, hash }) => {
+                assert_eq!(index, PARENT_INDEX);
+                assert_eq!(hash, unknown);
+            }
+            _ => unreachable!(),
+        }
+
+        // 3
+        let (leaf, _) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        let forged = DEFAULT_HASH_VEC[0];
+        mt.set_parent(PARENT_INDEX, &forged, &leaf.hash, &leaf.hash)
+            .unwrap();
+        match mt.get_node_with_hash(PARENT_INDEX, &forged) {
+            Err(MerkleError::HashMismatch { index, found, .. }) => {
+                assert_eq!(index, PARENT_INDEX);
+                assert_eq!(found, forged);
+            }
+            _ => unreachable!(),
+        }
+
+        // 4
+        let truncated = MerkleRecord {
+            index: INDEX1,
+            hash: [5; 32],
+            data: vec![1; 31],
+            left: [0; 32],
+            right: [0; 32],
+        };
+        mt.set_leaf(&truncated).unwrap();
+        match mt.get_node_with_hash(INDEX1, &truncated.hash) {
+            Err(MerkleError::InvalidLeafData { index, len }) => {
+                assert_eq!(index, INDEX1);
+                assert_eq!(len, 31);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_mongo_merkle_is_sync() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<MongoMerkle>();
+    }
 }
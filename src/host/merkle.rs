@@ -1,3 +1,4 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::error::Error;
 use std::fmt;
 use std::fmt::Debug;
@@ -8,51 +9,122 @@ const INTERNAL_SIG: u8 = 1u8;
 */
 
 #[derive(Debug)]
-pub enum MerkleErrorCode {
-    InvalidLeafIndex,
-    InvalidHash,
-    InvalidDepth,
-    InvalidIndex,
-}
-
-#[derive(Debug)]
-pub struct MerkleError {
-    source: [u8; 32],
-    index: u32,
-    code: MerkleErrorCode,
+pub enum MerkleError {
+    /// The node at index is neither in the store nor a default node
+    MissingNode { index: u32, hash: [u8; 32] },
+    /// The hash of a node does not match what it should be
+    HashMismatch { index: u32, expected: [u8; 32], found: [u8; 32] },
+    /// The underlying store failed while accessing the node at index
+    Storage { index: u32, source: Box<dyn Error + Send + Sync> },
+    InvalidLeafIndex(u32),
+    InvalidDepth(u32),
+    InvalidIndex(u32),
+    /// An encoded proof has an unexpected number of bytes
+    InvalidProofLength(usize),
+    /// The data of the leaf at index has a length that a leaf can not hold
+    InvalidLeafData { index: u32, len: usize },
 }
 
 impl MerkleError {
-    pub fn new(source: [u8; 32], index: u32, code: MerkleErrorCode) -> Self {
-        MerkleError {source, index, code}
+    pub fn storage<E: Error + Send + Sync + 'static>(index: u32, e: E) -> Self {
+        MerkleError::Storage { index, source: Box::new(e) }
     }
 }
 
 impl fmt::Display for MerkleError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "MerkleError {:?} {:?} {:?}", self.source, self.index, self.code)
+        match self {
+            MerkleError::MissingNode { index, hash } =>
+                write!(f, "missing node {} with hash {}", index, hex::encode(hash)),
+            MerkleError::HashMismatch { index, expected, found } =>
+                write!(f, "hash mismatch at node {}: expected {}, found {}",
+                    index, hex::encode(expected), hex::encode(found)),
+            MerkleError::Storage { index, source } =>
+                write!(f, "storage failure at node {}: {}", index, source),
+            MerkleError::InvalidLeafIndex(index) => write!(f, "index {} is not a leaf", index),
+            MerkleError::InvalidDepth(depth) => write!(f, "depth {} exceeds the tree height", depth),
+            MerkleError::InvalidIndex(index) => write!(f, "index {} is out of the tree", index),
+            MerkleError::InvalidProofLength(len) => write!(f, "proof of {} bytes has an invalid length", len),
+            MerkleError::InvalidLeafData { index, len } =>
+                write!(f, "leaf {} can not hold {} bytes of data", index, len),
+        }
     }
 }
 
 impl Error for MerkleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MerkleError::Storage { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 pub trait MerkleNode <H: Debug+Clone+PartialEq> {
     fn hash(&self) -> H;
     fn index(&self) -> u32;
-    fn set(&mut self, data: &Vec<u8>);
+    fn set(&mut self, data: &Vec<u8>) -> Result<(), MerkleError>;
     fn left(&self) -> Option<H>;  // hash of left child
     fn right(&self) -> Option<H>;  // hash of right child
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MerkleProof<H: Debug+Clone+PartialEq, const D: usize> {
     pub source:H,
     pub root:H, // last is root
+    #[serde(serialize_with = "self::serialize_array")]
+    #[serde(deserialize_with = "self::deserialize_array")]
     pub assist:[H; D],
     pub index: u32,
 }
 
+fn serialize_array<S, H: Serialize, const D: usize>(a: &[H; D], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(a.iter())
+}
+
+fn deserialize_array<'de, De, H: Deserialize<'de>, const D: usize>(deserializer: De) -> Result<[H; D], De::Error>
+where
+    De: Deserializer<'de>,
+{
+    let v = Vec::<H>::deserialize(deserializer)?;
+    let len = v.len();
+    v.try_into().map_err(|_| de::Error::invalid_length(len, &"an array of the depth of the tree"))
+}
+
+/// Compact binary encoding of proofs with 32 bytes hashes:
+/// index (u32 little endian) | source | root | assist[0] | ... | assist[D-1]
+impl<const D: usize> MerkleProof<[u8; 32], D> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.index.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.source);
+        bytes.extend_from_slice(&self.root);
+        for hash in self.assist.iter() {
+            bytes.extend_from_slice(hash);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleError> {
+        if bytes.len() != 4 + 32 * (D + 2) {
+            return Err(MerkleError::InvalidProofLength(bytes.len()));
+        }
+        let index = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let hashes = bytes[4..]
+            .chunks_exact(32)
+            .map(|x| x.try_into().unwrap())
+            .collect::<Vec<[u8; 32]>>();
+        Ok(MerkleProof {
+            source: hashes[0],
+            root: hashes[1],
+            assist: hashes[2..].to_vec().try_into().unwrap(),
+            index,
+        })
+    }
+}
+
 fn get_offset(index: u32) -> u32 {
     let height = (index+1).ilog2();
     let full = (1u32 << height) - 1;
@@ -79,7 +151,7 @@ pub trait MerkleTree<H:Debug+Clone+PartialEq, const D: usize> {
 
     fn boundary_check(&self, index: u32) -> Result<(), MerkleError> {
         if index as u32 >= (2_u32.pow(D as u32 + 1) - 1) {
-            Err(MerkleError::new([0;32], index, MerkleErrorCode::InvalidIndex))
+            Err(MerkleError::InvalidIndex(index))
         } else {
             Ok(())
         }
@@ -101,7 +173,7 @@ pub trait MerkleTree<H:Debug+Clone+PartialEq, const D: usize> {
             && (index as u32) < (2_u32.pow((D as u32) + 1) - 1){
            Ok(())
         } else {
-            Err(MerkleError::new([0; 32], index, MerkleErrorCode::InvalidLeafIndex))
+            Err(MerkleError::InvalidLeafIndex(index))
         }
     }
 
@@ -201,14 +273,24 @@ pub trait MerkleTree<H:Debug+Clone+PartialEq, const D: usize> {
 
     fn update_leaf_data_with_proof(&mut self, index: u32, data: &Vec<u8>) -> Result<MerkleProof<H, D>, MerkleError> {
         let (mut leaf, _) = self.get_leaf_with_proof(index)?;
-        leaf.set(data);
+        leaf.set(data)?;
         self.set_leaf_with_proof(&leaf)
     }
 
     fn verify_proof(&mut self, proof: MerkleProof<H, D>) -> Result <bool, MerkleError> {
-        let init = proof.source;
+        Ok(Self::check_proof(&proof))
+    }
+
+    /// Check that a proof leads from its source to its root.
+    /// It only relies on Self::hash thus can be used without a tree instance.
+    fn check_proof(proof: &MerkleProof<H, D>) -> bool {
+        if proof.index < (2_u32.pow(D as u32) - 1)
+            || proof.index >= (2_u32.pow(D as u32 + 1) - 1) {
+            return false;
+        }
+        let init = proof.source.clone();
         let mut p = get_offset(proof.index);
-        let hash = proof.assist.to_vec().iter().fold(init, |acc, x| {
+        let hash = proof.assist.iter().rev().fold(init, |acc, x| {
             let (left, right) = if p % 2 == 1 {
                 (x, &acc)
             } else {
@@ -217,7 +299,7 @@ pub trait MerkleTree<H:Debug+Clone+PartialEq, const D: usize> {
             p = p/2;
             Self::hash(left, right)
         });
-        Ok(proof.root == hash)
+        proof.root == hash
     }
 
 
@@ -225,7 +307,7 @@ pub trait MerkleTree<H:Debug+Clone+PartialEq, const D: usize> {
 
 #[cfg(test)]
 mod tests {
-    use crate::host::merkle::{MerkleNode, MerkleTree, MerkleError};
+    use crate::host::merkle::{MerkleNode, MerkleTree, MerkleError, MerkleProof};
     struct MerkleAsArray {
         data: [u64; 127] // 2^7-1 and depth = 6
     }
@@ -252,9 +334,11 @@ mod tests {
     impl MerkleNode<u64> for MerkleU64Node{
         fn index(&self) -> u32 { self.index }
         fn hash(&self) -> u64 { self.value }
-        fn set(&mut self, value: &Vec<u8>) {
-            let v:[u8; 8] = value.clone().try_into().unwrap();
+        fn set(&mut self, value: &Vec<u8>) -> Result<(), MerkleError> {
+            let v:[u8; 8] = value.clone().try_into()
+                .map_err(|_| MerkleError::InvalidLeafData { index: self.index, len: value.len() })?;
             self.value = u64::from_le_bytes(v);
+            Ok(())
         }
         fn right(&self) -> Option<u64> {
             Some(0)
@@ -326,4 +410,48 @@ mod tests {
        let root = mt.get_root_hash();
        assert_eq!(root, 6 as u64);
     }
+
+    #[test]
+    fn test_merkle_proof_serde() {
+       let mut mt = MerkleAsArray::construct("test".to_string(), "test".to_string());
+       let (mut leaf, _) = mt.get_leaf_with_proof(2_u32.pow(6) + 5).unwrap();
+       leaf.value = 7;
+       let proof = mt.set_leaf_with_proof(&leaf).unwrap();
+       assert!(MerkleAsArray::check_proof(&proof));
+
+       let json = serde_json::to_string(&proof).unwrap();
+       let decoded: MerkleProof<u64, 6> = serde_json::from_str(&json).unwrap();
+       assert_eq!(decoded, proof);
+       assert!(MerkleAsArray::check_proof(&decoded));
+
+       let mut forged = decoded.clone();
+       forged.source = 8;
+       assert!(!MerkleAsArray::check_proof(&forged));
+       forged.source = 7;
+       forged.index = 0;
+       assert!(!MerkleAsArray::check_proof(&forged));
+
+       let proof = MerkleProof::<[u8; 32], 3> {
+           source: [1; 32],
+           root: [2; 32],
+           assist: [[3; 32], [4; 32], [5; 32]],
+           index: 9,
+       };
+       let bytes = proof.to_bytes();
+       assert_eq!(bytes.len(), 4 + 32 * 5);
+       assert_eq!(MerkleProof::<[u8; 32], 3>::from_bytes(&bytes).unwrap(), proof);
+       assert!(MerkleProof::<[u8; 32], 3>::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_merkle_error() {
+       let mt = MerkleAsArray::construct("test".to_string(), "test".to_string());
+       assert!(matches!(mt.get_leaf_with_proof(2), Err(MerkleError::InvalidLeafIndex(2))));
+       assert!(matches!(mt.get_node_with_hash(127, &0), Err(MerkleError::InvalidIndex(127))));
+
+       let e = MerkleError::storage(3, std::io::Error::new(std::io::ErrorKind::Other, "disk"));
+       assert_eq!(e.to_string(), "storage failure at node 3: disk");
+       assert_eq!(std::error::Error::source(&e).unwrap().to_string(), "disk");
+       assert!(std::error::Error::source(&MerkleError::InvalidDepth(21)).is_none());
+    }
 }
@@ -5,6 +5,12 @@ pub mod rmd160;
 pub mod kvpair;
 pub mod poseidon;
 pub mod jubjub;
+pub mod eddsa;
+pub mod ecdsa;
+pub mod keccak256;
+pub mod blake2;
+pub mod sha256;
+pub mod hash_to_curve;
 
 use serde::{Deserialize, Serialize};
 use halo2_proofs::arithmetic::FieldExt;
@@ -46,11 +52,100 @@ pub enum ForeignInst {
     JubjubSumNew,
     JubjubSumPush,
     JubjubSumResult,
+    KeccakNew,
+    KeccakPush,
+    KeccakFinalize,
+    Blake2bNew,
+    Blake2bPush,
+    Blake2bFinalize,
+    Blake2sNew,
+    Blake2sPush,
+    Blake2sFinalize,
+    EddsaPush,
+    EddsaVerify,
+    Bn254MsmNew,
+    Bn254MsmG1,
+    Bn254MsmScalar,
+    Bn254MsmResult,
+    BlsMsmNew,
+    BlsMsmG1,
+    BlsMsmScalar,
+    BlsMsmResult,
+    Bn254MultiPairNew,
+    Bn254MultiPairG1,
+    Bn254MultiPairG2,
+    Bn254MultiPairGt,
+    BlsMultiPairNew,
+    BlsMultiPairG1,
+    BlsMultiPairG2,
+    BlsMultiPairGt,
+    Bn254SumG2,
+    Bn254SumG2Result,
+    BlsSumG2,
+    BlsSumG2Result,
+    BlsHashToCurveU,
+    BlsHashToCurveResult,
+    EcdsaPush,
+    EcdsaVerify,
+    EddsaCompressedPush,
+    EddsaCompressedVerify,
+    EcdsaRecoverPush,
+    EcdsaRecoverResult,
 
 }
 
 pub const MONGODB_URI:&str = "mongodb://localhost:27017";
 
+/// Host call entries of the G1, G2 and Gt of a pairing curve for the input generators of the tests:
+/// an Fq is $fq_size entries of 54 bits (see crate::adaptor::pairingadaptor::PairingOpcodes),
+/// a point is its affine coordinates followed by whether it is the identity.
+#[cfg(test)]
+#[macro_export]
+macro_rules! pairing_host_args {
+    ($fq: ty, $g1_affine: ty, $g2_affine: ty, $gt: ty, $fq_size: expr) => {
+        fn fq_to_args(f: $fq, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+            crate::adaptor::fr_to_args(f, $fq_size, 54, op)
+        }
+
+        fn identity_to_arg(is_identity: bool, op: ForeignInst) -> ExternalHostCallEntry {
+            ExternalHostCallEntry {
+                op: op as usize,
+                value: is_identity as u64,
+                is_ret: false,
+            }
+        }
+
+        fn g1_to_args(g: impl Into<$g1_affine>, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+            let g: $g1_affine = g.into();
+            let mut r = fq_to_args(g.x, op);
+            r.append(&mut fq_to_args(g.y, op));
+            r.push(identity_to_arg(bool::from(g.is_identity()), op));
+            r
+        }
+
+        fn g2_to_args(g: impl Into<$g2_affine>, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+            let g: $g2_affine = g.into();
+            let mut r = vec![];
+            for c in [g.x.c0, g.x.c1, g.y.c0, g.y.c1] {
+                r.append(&mut fq_to_args(c, op));
+            }
+            r.push(identity_to_arg(bool::from(g.is_identity()), op));
+            r
+        }
+
+        fn gt_to_args(f: $gt, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+            let mut r = vec![];
+            for c6 in [f.0.c0, f.0.c1] {
+                for c2 in [c6.c0, c6.c1, c6.c2] {
+                    r.append(&mut fq_to_args(c2.c0, op));
+                    r.append(&mut fq_to_args(c2.c1, op));
+                }
+            }
+            r
+        }
+    };
+}
+
 pub enum ReduceRule<F: FieldExt> {
     Bytes(Vec<u8>, usize),
     Field(F, usize), // F * shiftbits
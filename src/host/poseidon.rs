@@ -1,4 +1,6 @@
 use poseidon::Poseidon;
+use poseidon::Spec;
+use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::pairing::bn256::Fr;
 
 pub const T: usize = 9;
@@ -10,13 +12,200 @@ pub const PREFIX_CHALLENGE: u64 = 0u64;
 pub const PREFIX_POINT: u64 = 1u64;
 pub const PREFIX_SCALAR: u64 = 2u64;
 
+/// The largest domain tag carried by PoseidonNew, tag k selects the prefix k-1
+pub const MAX_DOMAIN_TAG: u64 = PREFIX_SCALAR + 1;
+
+/// The capacity element of a restarted sponge is 2^64 + tag,
+/// thus a tagged hash never shares its initial state with an untagged one.
+pub fn domain_capacity(tag: u64) -> Fr {
+    Fr::from_u128(1u128 << 64) + Fr::from(tag)
+}
+
+/// The domain tag of a prefix (0 for no domain separation), None for an unknown prefix
+fn domain_tag(prefix: Option<u64>) -> Option<u64> {
+    match prefix {
+        None => Some(0),
+        Some(p) if p < MAX_DOMAIN_TAG => Some(p + 1),
+        Some(_) => None,
+    }
+}
+
+/// Encode the operand of PoseidonNew: bit 0 restarts the sponge and
+/// the other bits carry the domain tag (0 for no domain separation).
+/// None if the prefix has no domain tag.
+pub fn encode_new(restart: bool, prefix: Option<u64>) -> Option<u64> {
+    domain_tag(prefix).map(|tag| (tag << 1) + restart as u64)
+}
+
+/// Named parameter sets: (T, RATE, R_F, R_P).
+/// Only the round numbers are those of the reference parameters of each width,
+/// the round constants and MDS matrices come from poseidon::Spec::new, so the
+/// hashes differ from circomlib and other implementations of the same width.
+pub const PRESET_T3: (usize, usize, usize, usize) = (3, 2, 8, 57);
+pub const PRESET_T5: (usize, usize, usize, usize) = (5, 4, 8, 60);
+pub const PRESET_T9: (usize, usize, usize, usize) = (T, RATE, R_F, R_P);
+
+/// Returns the full and partial rounds (R_F, R_P) of the preset with width t
+pub fn preset_rounds(t: usize) -> (usize, usize) {
+    let (_, _, r_f, r_p) = [PRESET_T3, PRESET_T5, PRESET_T9]
+        .into_iter()
+        .find(|(width, _, _, _)| *width == t)
+        .expect("no poseidon preset for the given width");
+    (r_f, r_p)
+}
+
+pub fn gen_hasher_with_params<const WIDTH: usize, const WIDTH_RATE: usize>(
+    r_f: usize,
+    r_p: usize,
+) -> Poseidon<Fr, WIDTH, WIDTH_RATE> {
+   assert!(WIDTH_RATE + 1 == WIDTH);
+   Poseidon::<Fr, WIDTH, WIDTH_RATE>::new(r_f, r_p)
+}
+
+/// Hasher of one of the presets selected by its width
+pub fn gen_preset_hasher<const WIDTH: usize, const WIDTH_RATE: usize>() -> Poseidon<Fr, WIDTH, WIDTH_RATE> {
+   let (r_f, r_p) = preset_rounds(WIDTH);
+   gen_hasher_with_params::<WIDTH, WIDTH_RATE>(r_f, r_p)
+}
+
 pub fn gen_hasher() -> Poseidon<Fr, T, RATE> {
-   Poseidon::<Fr, T, RATE>::new(R_F, R_P)
+   gen_hasher_with_params::<T, RATE>(R_F, R_P)
+}
+
+/// The T=9 sponge of Poseidon with the domain tag in its capacity element,
+/// without a tag it computes exactly what Poseidon::update and Poseidon::squeeze do.
+/// The state of poseidon::Poseidon can not be seeded from outside of the crate,
+/// thus the permutation is computed here and checked against the crate in test_poseidon_domain.
+pub struct DomainHasher {
+    spec: Spec<Fr, T, RATE>,
+    state: [Fr; T],
+    absorbing: Vec<Fr>,
+}
+
+impl DomainHasher {
+    /// None if the prefix has no domain tag
+    pub fn new(prefix: Option<u64>) -> Option<Self> {
+        let tag = domain_tag(prefix)?;
+        let mut state = [Fr::zero(); T];
+        state[0] = domain_capacity(tag);
+        Some(DomainHasher {
+            spec: Spec::new(R_F, R_P),
+            state,
+            absorbing: vec![],
+        })
+    }
+
+    pub fn update(&mut self, elements: &[Fr]) {
+        let mut input_elements = self.absorbing.clone();
+        input_elements.extend_from_slice(elements);
+        self.absorbing.clear();
+        for chunk in input_elements.chunks(RATE) {
+            if chunk.len() < RATE {
+                self.absorbing = chunk.to_vec();
+            } else {
+                self.permutation(chunk);
+            }
+        }
+    }
+
+    pub fn squeeze(&mut self) -> Fr {
+        let mut last_chunk = self.absorbing.clone();
+        last_chunk.push(Fr::one());
+        self.permutation(&last_chunk);
+        self.absorbing.clear();
+        self.state[1]
+    }
+
+    fn sbox_full(state: &mut [Fr; T], constants: &[Fr; T]) {
+        for (x, constant) in state.iter_mut().zip(constants.iter()) {
+            let x2 = *x * *x;
+            *x = x2 * x2 * *x + constant;
+        }
+    }
+
+    fn sbox_part(state: &mut [Fr; T], constant: &Fr) {
+        let x = state[0];
+        let x2 = x * x;
+        state[0] = x2 * x2 * x + constant;
+    }
+
+    fn apply_mds(state: &mut [Fr; T], mds: &[[Fr; T]; T]) {
+        let mut res = [Fr::zero(); T];
+        for (r, row) in res.iter_mut().zip(mds.iter()) {
+            for (x, word) in state.iter().zip(row.iter()) {
+                *r += *x * word;
+            }
+        }
+        *state = res;
+    }
+
+    /// The same rounds as the permutation of the poseidon crate (and PoseidonState::permute)
+    fn permutation(&mut self, inputs: &[Fr]) {
+        let r_f = R_F / 2;
+        let spec = &self.spec;
+        let state = &mut self.state;
+        let mds = spec.mds_matrices().mds().rows();
+
+        let constants = spec.constants().start();
+        state[0] += constants[0][0];
+        for i in 1..T {
+            let input = inputs.get(i - 1).cloned().unwrap_or(Fr::zero());
+            state[i] += input + constants[0][i];
+        }
+        for constants in constants.iter().skip(1).take(r_f - 1) {
+            Self::sbox_full(state, constants);
+            Self::apply_mds(state, &mds);
+        }
+        Self::sbox_full(state, constants.last().unwrap());
+        Self::apply_mds(state, &spec.mds_matrices().pre_sparse_mds().rows());
+
+        let sparse_matrices = spec.mds_matrices().sparse_matrices();
+        for (constant, sparse_mds) in spec.constants().partial().iter().zip(sparse_matrices.iter()) {
+            Self::sbox_part(state, constant);
+            let mut res = [Fr::zero(); T];
+            for (x, word) in state.iter().zip(sparse_mds.row().iter()) {
+                res[0] += *x * word;
+            }
+            for (i, e) in sparse_mds.col_hat().iter().enumerate() {
+                res[i + 1] = state[0] * e + state[i + 1];
+            }
+            *state = res;
+        }
+
+        for constants in spec.constants().end().iter() {
+            Self::sbox_full(state, constants);
+            Self::apply_mds(state, &mds);
+        }
+        Self::sbox_full(state, &[Fr::zero(); T]);
+        Self::apply_mds(state, &mds);
+    }
+}
+
+/// Hasher of a call started by PoseidonNew with the domain tag of the prefix,
+/// None if the prefix has no domain tag
+pub fn gen_hasher_with_prefix(prefix: u64) -> Option<DomainHasher> {
+   DomainHasher::new(Some(prefix))
+}
+
+/// The t=3 instance which hashes two field elements in one permutation
+pub fn gen_hasher_t3() -> Poseidon<Fr, 3, 2> {
+   gen_preset_hasher::<3, 2>()
 }
 
 #[cfg(test)]
 mod tests {
     use halo2_proofs::pairing::bn256::Fr;
+
+    /// Decode the operand of PoseidonNew into the restart flag and the prefix,
+    /// None if the operand carries an unknown domain tag.
+    fn decode_new(value: u64) -> Option<(bool, Option<u64>)> {
+        let tag = value >> 1;
+        if tag > super::MAX_DOMAIN_TAG {
+            return None;
+        }
+        Some((value & 1 == 1, if tag == 0 { None } else { Some(tag - 1) }))
+    }
+
     #[test]
     fn test_poseidon() {
         const ZERO_HASHER_SQUEEZE: &str = "0x03f943aabd67cd7b72a539f3de686c3280c36c572be09f2b9193f5ef78761c6b";  //force the hasher is for fr field result.
@@ -26,4 +215,62 @@ mod tests {
         println!("hash result is {:?}", result);
         assert_eq!(result.to_string(), ZERO_HASHER_SQUEEZE);
     }
+
+    #[test]
+    fn test_poseidon_domain() {
+        for restart in [true, false] {
+            for prefix in [None, Some(super::PREFIX_CHALLENGE), Some(super::PREFIX_POINT), Some(super::PREFIX_SCALAR)] {
+                assert_eq!(decode_new(super::encode_new(restart, prefix).unwrap()), Some((restart, prefix)));
+            }
+        }
+        assert_eq!(super::encode_new(true, None), Some(1));
+        assert_eq!(decode_new((super::MAX_DOMAIN_TAG + 1) << 1), None);
+        assert_eq!(super::encode_new(true, Some(super::MAX_DOMAIN_TAG)), None);
+        assert!(super::gen_hasher_with_prefix(super::MAX_DOMAIN_TAG).is_none());
+
+        // without a tag the domain hasher is the plain sponge
+        for len in [0, 1, 7, 8, 9, 17] {
+            let inputs = vec![Fr::from(3u64); len];
+            let mut hasher = super::gen_hasher();
+            hasher.update(&inputs);
+            let mut domain_hasher = super::DomainHasher::new(None).unwrap();
+            domain_hasher.update(&inputs);
+            assert_eq!(hasher.squeeze(), domain_hasher.squeeze());
+        }
+
+        // the tag is not an absorbed prefix
+        let mut hasher = super::gen_hasher_with_prefix(super::PREFIX_POINT).unwrap();
+        hasher.update(&[Fr::one()]);
+        let tagged = hasher.squeeze();
+        let mut hasher = super::gen_hasher();
+        hasher.update(&[Fr::from(super::PREFIX_POINT), Fr::one()]);
+        assert_ne!(tagged, hasher.squeeze());
+
+        let mut results = vec![];
+        for prefix in [super::PREFIX_CHALLENGE, super::PREFIX_POINT, super::PREFIX_SCALAR] {
+            let mut hasher = super::gen_hasher_with_prefix(prefix).unwrap();
+            hasher.update(&[Fr::one()]);
+            results.push(hasher.squeeze());
+        }
+        assert_ne!(results[0], results[1]);
+        assert_ne!(results[1], results[2]);
+        assert_ne!(results[0], results[2]);
+    }
+
+    #[test]
+    fn test_poseidon_presets() {
+        assert_eq!(super::preset_rounds(super::T), (super::R_F, super::R_P));
+        let mut hasher = super::gen_preset_hasher::<9, 8>();
+        hasher.update(&[Fr::zero()]);
+        let mut expected = super::gen_hasher();
+        expected.update(&[Fr::zero()]);
+        assert_eq!(hasher.squeeze(), expected.squeeze());
+
+        let mut hasher = super::gen_hasher_t3();
+        hasher.update(&[Fr::one(), Fr::zero()]);
+        let left_right = hasher.squeeze();
+        let mut hasher = super::gen_hasher_t3();
+        hasher.update(&[Fr::zero(), Fr::one()]);
+        assert_ne!(left_right, hasher.squeeze());
+    }
 }
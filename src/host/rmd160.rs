@@ -113,7 +113,6 @@ pub fn compress(w: &Vec<u32>, values: Vec<u32>) -> Vec<u32> {
             let idx = idxs[limb_index];
             rol_modifier(round, &mut rol1, values[idx], offset, shift[limb_index]);
             rol1.rotate_right(1);
-            println!("{:?}", rol1);
         }
         round += 1;
     }
@@ -123,7 +122,6 @@ pub fn compress(w: &Vec<u32>, values: Vec<u32>) -> Vec<u32> {
             let idx = idxs[limb_index];
             rol_modifier(round-1, &mut rol2, values[idx], offset, shift[limb_index]);
             rol2.rotate_right(1);
-            println!("{:?}, shift {} x {} offset {}", rol2, shift[limb_index], values[idx], offset);
         }
         round -= 1;
     }
@@ -137,6 +135,37 @@ pub fn compress(w: &Vec<u32>, values: Vec<u32>) -> Vec<u32> {
     r
 }
 
+/// MD style padding: 0x80, zeros and the bit length in little endian,
+/// the padded message is split into blocks of WORK_BUF_LEN little endian words.
+pub fn pad(message: &[u8]) -> Vec<[u32; WORK_BUF_LEN]> {
+    let mut bytes = message.to_vec();
+    bytes.push(0x80);
+    while bytes.len() % 64 != 56 {
+        bytes.push(0);
+    }
+    bytes.append(&mut ((message.len() as u64) * 8).to_le_bytes().to_vec());
+    bytes.chunks_exact(64).map(|block| {
+        block.chunks_exact(4)
+            .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }).collect()
+}
+
+pub fn rmd160(message: &[u8]) -> [u8; 20] {
+    let mut h = H0.to_vec();
+    for block in pad(message).iter() {
+        h = compress(&h, block.to_vec());
+    }
+    h.iter()
+        .map(|x| x.to_le_bytes())
+        .collect::<Vec<_>>()
+        .concat()
+        .try_into()
+        .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -441,4 +470,20 @@ mod tests {
         compress(&mut r1, &bytes);
         assert_eq!(r.to_vec(), r1);
     }
+
+    #[test]
+    fn test_rmd160() {
+        use ripemd::{Digest, Ripemd160};
+        let cases: [(&[u8], &str); 3] = [
+            (b"", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            (b"abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            (&[b'a'; 100], "fdcd0faf7faa5b59f4b5757dc8bc901091880461"),
+        ];
+        for (message, expected) in cases.iter() {
+            assert_eq!(hex::encode(super::rmd160(message)), *expected);
+            assert_eq!(super::rmd160(message).to_vec(), Ripemd160::digest(message).to_vec());
+        }
+        assert_eq!(super::pad(&[0u8; 55]).len(), 1);
+        assert_eq!(super::pad(&[0u8; 56]).len(), 2);
+    }
 }
@@ -0,0 +1,113 @@
+/* SHA-256 (FIPS 180-4) split into the padding and the compression function
+ * so that the blocks of a message can be proved one by one.
+ * Blocks and states are big endian u32 words.
+ */
+pub const STATE_WORDS: usize = 8;
+pub const BLOCK_WORDS: usize = 16;
+pub const ROUNDS: usize = 64;
+
+pub const H0: [u32; STATE_WORDS] = [
+    0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a,
+    0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19,
+];
+
+pub const K: [u32; ROUNDS] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+    0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+    0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+    0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+    0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+/// The rotations of Σ0 and Σ1
+pub const BIG_SIGMA: [[u32; 3]; 2] = [[2, 13, 22], [6, 11, 25]];
+/// The rotations of σ0 and σ1, the last one is a shift
+pub const SMALL_SIGMA: [[u32; 3]; 2] = [[7, 18, 3], [17, 19, 10]];
+
+fn big_sigma(x: u32, [r0, r1, r2]: [u32; 3]) -> u32 {
+    x.rotate_right(r0) ^ x.rotate_right(r1) ^ x.rotate_right(r2)
+}
+
+fn small_sigma(x: u32, [r0, r1, s]: [u32; 3]) -> u32 {
+    x.rotate_right(r0) ^ x.rotate_right(r1) ^ (x >> s)
+}
+
+/// The message schedule of a block
+pub fn schedule(m: &[u32; BLOCK_WORDS]) -> [u32; ROUNDS] {
+    let mut w = [0u32; ROUNDS];
+    w[..BLOCK_WORDS].copy_from_slice(m);
+    for t in BLOCK_WORDS..ROUNDS {
+        w[t] = small_sigma(w[t - 2], SMALL_SIGMA[1])
+            .wrapping_add(w[t - 7])
+            .wrapping_add(small_sigma(w[t - 15], SMALL_SIGMA[0]))
+            .wrapping_add(w[t - 16]);
+    }
+    w
+}
+
+pub fn compress(h: &mut [u32; STATE_WORDS], m: &[u32; BLOCK_WORDS]) {
+    let w = schedule(m);
+    let mut v = *h;
+    for t in 0..ROUNDS {
+        let [a, b, c, d, e, f, g, hh] = v;
+        let ch = (e & f) ^ (!e & g);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t1 = hh
+            .wrapping_add(big_sigma(e, BIG_SIGMA[1]))
+            .wrapping_add(ch)
+            .wrapping_add(K[t])
+            .wrapping_add(w[t]);
+        let t2 = big_sigma(a, BIG_SIGMA[0]).wrapping_add(maj);
+        v = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+    }
+    for i in 0..STATE_WORDS {
+        h[i] = h[i].wrapping_add(v[i]);
+    }
+}
+
+/// Pad the message and split it into blocks of big endian words
+pub fn blocks(message: &[u8]) -> Vec<[u32; BLOCK_WORDS]> {
+    let mut bytes = message.to_vec();
+    bytes.push(0x80);
+    while bytes.len() % 64 != 56 {
+        bytes.push(0);
+    }
+    bytes.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+    bytes.chunks_exact(64).map(|block| {
+        block.chunks_exact(4)
+            .map(|w| u32::from_be_bytes(w.try_into().unwrap()))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }).collect()
+}
+
+pub fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut h = H0;
+    for m in blocks(message).iter() {
+        compress(&mut h, m);
+    }
+    h.iter()
+        .map(|w| w.to_be_bytes().to_vec())
+        .collect::<Vec<_>>()
+        .concat()
+        .try_into()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_sha256() {
+        for len in [0, 3, 55, 56, 64, 200] {
+            let message = (0..len).map(|x| x as u8).collect::<Vec<_>>();
+            let expected = Sha256::new().chain_update(&message).finalize();
+            assert_eq!(super::sha256(&message).to_vec(), expected.to_vec());
+        }
+    }
+}
@@ -33,9 +33,14 @@ use std::{
 };
 
 use crate::circuits::{
-    bls::Bls381PairChip, bls::Bls381SumChip,
-    bn256::Bn256PairChip, bn256::Bn256SumChip,
+    bls::Bls381PairChip, bls::Bls381SumChip, bls::Bls381MsmChip, bls::Bls381MultiPairChip, bls::Bls381G2SumChip, bls::Bls381HashToCurveChip,
+    bn256::Bn256PairChip, bn256::Bn256SumChip, bn256::Bn256MsmChip, bn256::Bn256MultiPairChip, bn256::Bn256G2SumChip,
     poseidon::PoseidonChip,
+    eddsa::EddsaChip,
+    ecdsa::EcdsaChip,
+    keccak256::KeccakChip,
+    blake2::{Blake2bChip, Blake2sChip},
+    sha256::Sha256Chip,
     host::{
         HostOpSelector,
         HostOpChip,
@@ -43,7 +48,11 @@ use crate::circuits::{
     }
 };
 
+use crate::adaptor::eddsaadaptor::EddsaCompressedChip;
+use crate::adaptor::ecdsaadaptor::EcrecoverChip;
 use crate::utils::params::{HostCircuitInfo, Prover};
+use crate::host::kvpair::MongoMerkle;
+use crate::host::merkle::{MerkleProof, MerkleTree};
 
 use halo2_proofs::pairing::bn256::Fr;
 use halo2_proofs::pairing::bn256::Bn256;
@@ -62,9 +71,24 @@ struct ArgOpName {
 enum OpType {
     BLS381PAIR,
     BLS381SUM,
+    BLS381MSM,
+    BLS381MULTIPAIR,
+    BLS381G2SUM,
+    BLS381HASHTOCURVE,
     BN256PAIR,
     BN256SUM,
+    BN256MSM,
+    BN256MULTIPAIR,
+    BN256G2SUM,
     POSEIDONHASH,
+    KECCAKHASH,
+    BLAKE2BHASH,
+    BLAKE2SHASH,
+    SHA256HASH,
+    EDDSAVERIFY,
+    EDDSACOMPRESSEDVERIFY,
+    ECDSAVERIFY,
+    ECRECOVER,
 }
 
 
@@ -170,14 +194,102 @@ fn parse_opname(matches: &ArgMatches) -> OpType {
         .clone()
 }
 
+fn verify_proof_command<'a>() -> App<'a> {
+    App::new("verifyproof")
+        .about("Verify a merkle proof of a kvpair leaf against a given root")
+        .arg(
+            arg!(-p --proof<PROOF_FILE> "File that contains the proof")
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            arg!(-r --root<ROOT> "Expected merkle root in hex")
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            arg!(-f --format [FORMAT] "Format of the proof file: json (default) or bin")
+                .value_parser(["json", "bin"])
+        )
+}
+
+fn parse_hash(hex_str: &str) -> [u8; 32] {
+    hex::decode(hex_str.trim_start_matches("0x"))
+        .expect("hash must be in hex")
+        .try_into()
+        .expect("hash must have 32 bytes")
+}
+
+fn exec_verify_proof(matches: &ArgMatches) {
+    let proof_file = matches
+        .get_one::<PathBuf>("proof")
+        .expect("proof file is required");
+    let root = parse_hash(matches.get_one::<String>("root").expect("root is required"));
+    let format = matches
+        .get_one::<String>("format")
+        .map_or("json", |x| x.as_str());
+
+    let proof: MerkleProof<[u8; 32], 20> = if format == "bin" {
+        let bytes = std::fs::read(proof_file).expect("File does not exist");
+        MerkleProof::from_bytes(&bytes).expect("invalid proof")
+    } else {
+        let file = File::open(proof_file).expect("File does not exist");
+        serde_json::from_reader(BufReader::new(file)).expect("invalid proof")
+    };
+
+    if proof.root == root && MongoMerkle::check_proof(&proof) {
+        println!("Proof of leaf {} is valid.", proof.index);
+    } else {
+        println!("Proof of leaf {} is invalid.", proof.index);
+        std::process::exit(1);
+    }
+}
+
+fn fsck_command<'a>() -> App<'a> {
+    App::new("fsck")
+        .about("Check the consistency of the stored merkle tree of a contract")
+        .arg(
+            arg!(-a --address<ADDRESS> "Contract address in hex")
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            arg!(-r --root<ROOT> "Merkle root to check from in hex")
+                .value_parser(value_parser!(String))
+        )
+}
+
+fn exec_fsck(matches: &ArgMatches) {
+    let address = parse_hash(matches.get_one::<String>("address").expect("address is required"));
+    let root = parse_hash(matches.get_one::<String>("root").expect("root is required"));
+    let mt = MongoMerkle::construct(address, root);
+    let report = mt.fsck();
+    for e in report.errors.iter() {
+        println!("{}", e);
+    }
+    println!("{} nodes checked, {} errors found.", report.checked, report.errors.len());
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+}
+
 #[allow(clippy::many_single_char_names)]
 fn main() {
     let clap_app = App::new("hostcircuit")
         .arg(input_file())
         .arg(output_folder())
-        .arg(opname());
+        .arg(opname())
+        .subcommand(verify_proof_command())
+        .subcommand(fsck_command())
+        .subcommand_negates_reqs(true);
 
     let matches = clap_app.get_matches();
+    if let Some(sub_matches) = matches.subcommand_matches("verifyproof") {
+        exec_verify_proof(sub_matches);
+        return;
+    }
+    if let Some(sub_matches) = matches.subcommand_matches("fsck") {
+        exec_fsck(sub_matches);
+        return;
+    }
+
     let input_file = parse_input_file(&matches);
     let cache_folder = parse_output_folder(&matches);
     let opname = parse_opname(&matches);
@@ -230,6 +342,50 @@ fn main() {
             prover.mock_proof(k);
             prover.create_proof(cache_folder.as_path(), k);
         }
+        OpType::BLS381MSM => {
+            let bls381msm_circuit = HostOpCircuit::<Fr, Bls381MsmChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bls381MsmChip<Fr>>> = HostCircuitInfo::new(bls381msm_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BLS381MULTIPAIR => {
+            let bls381multipair_circuit = HostOpCircuit::<Fr, Bls381MultiPairChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bls381MultiPairChip<Fr>>> = HostCircuitInfo::new(bls381multipair_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BLS381G2SUM => {
+            let bls381g2sum_circuit = HostOpCircuit::<Fr, Bls381G2SumChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bls381G2SumChip<Fr>>> = HostCircuitInfo::new(bls381g2sum_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BLS381HASHTOCURVE => {
+            let bls381hashtocurve_circuit = HostOpCircuit::<Fr, Bls381HashToCurveChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bls381HashToCurveChip<Fr>>> = HostCircuitInfo::new(bls381hashtocurve_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
         OpType::BN256PAIR => {
             let bn256pair_circuit = HostOpCircuit::<Fr, Bn256PairChip<Fr>> {
                 shared_operands,
@@ -252,6 +408,39 @@ fn main() {
             prover.mock_proof(k);
             prover.create_proof(cache_folder.as_path(), k);
         }
+        OpType::BN256MSM => {
+            let bn256msm_circuit = HostOpCircuit::<Fr, Bn256MsmChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bn256MsmChip<Fr>>> = HostCircuitInfo::new(bn256msm_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BN256MULTIPAIR => {
+            let bn256multipair_circuit = HostOpCircuit::<Fr, Bn256MultiPairChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bn256MultiPairChip<Fr>>> = HostCircuitInfo::new(bn256multipair_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BN256G2SUM => {
+            let bn256g2sum_circuit = HostOpCircuit::<Fr, Bn256G2SumChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Bn256G2SumChip<Fr>>> = HostCircuitInfo::new(bn256g2sum_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
         OpType::POSEIDONHASH => {
             let poseidon_circuit = HostOpCircuit::<Fr, PoseidonChip<Fr>> {
                 shared_operands,
@@ -263,6 +452,94 @@ fn main() {
             prover.mock_proof(k);
             prover.create_proof(cache_folder.as_path(), k);
         }
+        OpType::KECCAKHASH => {
+            let keccak_circuit = HostOpCircuit::<Fr, KeccakChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, KeccakChip<Fr>>> = HostCircuitInfo::new(keccak_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BLAKE2BHASH => {
+            let blake2b_circuit = HostOpCircuit::<Fr, Blake2bChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Blake2bChip<Fr>>> = HostCircuitInfo::new(blake2b_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::BLAKE2SHASH => {
+            let blake2s_circuit = HostOpCircuit::<Fr, Blake2sChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Blake2sChip<Fr>>> = HostCircuitInfo::new(blake2s_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::SHA256HASH => {
+            let sha256_circuit = HostOpCircuit::<Fr, Sha256Chip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, Sha256Chip<Fr>>> = HostCircuitInfo::new(sha256_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::EDDSAVERIFY => {
+            let eddsa_circuit = HostOpCircuit::<Fr, EddsaChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, EddsaChip<Fr>>> = HostCircuitInfo::new(eddsa_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::EDDSACOMPRESSEDVERIFY => {
+            let eddsa_circuit = HostOpCircuit::<Fr, EddsaCompressedChip> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, EddsaCompressedChip>> = HostCircuitInfo::new(eddsa_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::ECDSAVERIFY => {
+            let ecdsa_circuit = HostOpCircuit::<Fr, EcdsaChip<Fr>> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, EcdsaChip<Fr>>> = HostCircuitInfo::new(ecdsa_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
+        OpType::ECRECOVER => {
+            let ecrecover_circuit = HostOpCircuit::<Fr, EcrecoverChip> {
+                shared_operands,
+                shared_opcodes,
+                shared_index,
+                _marker: PhantomData,
+            };
+            let prover: HostCircuitInfo<Bn256, HostOpCircuit<Fr, EcrecoverChip>> = HostCircuitInfo::new(ecrecover_circuit, format!("{:?}", opname), vec![]);
+            prover.mock_proof(k);
+            prover.create_proof(cache_folder.as_path(), k);
+        }
     };
 
     //circuit_info.mock_proof(k);